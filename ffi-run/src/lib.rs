@@ -4,14 +4,17 @@ mod platform;
 
 use map::tiles::shashlik_tiles_provider_v0::ShashlikTilesProviderV0;
 use map::ShashlikMap;
-use osm::source::reqwest_source::ReqwestSource;
+use map::route::guidance::GuidanceEvent;
+use map::tiles::source::MapTileSource;
 use std::sync::RwLock;
 use map::feature_processor::ShashlikFeatureProcessor;
+use map::annotation::Annotation;
+use map::style::style_sheet::{Color, StyleDef, ZoomValue};
 
 #[derive(uniffi::Object)]
 pub struct ShashlikMapApi {
     // TODO ?Can't use generic for FFI ShashlikMapApi?
-    shashlik_map: RwLock<ShashlikMap<ShashlikTilesProviderV0<ReqwestSource, ShashlikFeatureProcessor>>>,
+    shashlik_map: RwLock<ShashlikMap<ShashlikTilesProviderV0<MapTileSource, ShashlikFeatureProcessor>>>,
 }
 
 unsafe impl Sync for ShashlikMapApi {}
@@ -31,6 +34,122 @@ impl From<RouteCosting> for map::route::RouteCosting {
     }
 }
 
+#[derive(uniffi::Enum)]
+pub enum MapTheme {
+    Day, Night, HighContrast
+}
+
+impl MapTheme {
+    fn name(&self) -> &'static str {
+        match self {
+            MapTheme::Day => "day",
+            MapTheme::Night => "night",
+            MapTheme::HighContrast => "high_contrast"
+        }
+    }
+}
+
+#[derive(uniffi::Enum)]
+pub enum ManeuverKind {
+    Depart, Continue, SlightLeft, Left, SharpLeft, SlightRight, Right, SharpRight, UTurn, KeepLeft, KeepRight,
+    RampLeft, RampRight, Merge, RoundaboutEnter, RoundaboutExit, Ferry, Arrive
+}
+
+impl From<map::route::maneuver::ManeuverKind> for ManeuverKind {
+    fn from(value: map::route::maneuver::ManeuverKind) -> Self {
+        use map::route::maneuver::ManeuverKind as Kind;
+        match value {
+            Kind::Depart => ManeuverKind::Depart,
+            Kind::Continue => ManeuverKind::Continue,
+            Kind::SlightLeft => ManeuverKind::SlightLeft,
+            Kind::Left => ManeuverKind::Left,
+            Kind::SharpLeft => ManeuverKind::SharpLeft,
+            Kind::SlightRight => ManeuverKind::SlightRight,
+            Kind::Right => ManeuverKind::Right,
+            Kind::SharpRight => ManeuverKind::SharpRight,
+            Kind::UTurn => ManeuverKind::UTurn,
+            Kind::KeepLeft => ManeuverKind::KeepLeft,
+            Kind::KeepRight => ManeuverKind::KeepRight,
+            Kind::RampLeft => ManeuverKind::RampLeft,
+            Kind::RampRight => ManeuverKind::RampRight,
+            Kind::Merge => ManeuverKind::Merge,
+            Kind::RoundaboutEnter => ManeuverKind::RoundaboutEnter,
+            Kind::RoundaboutExit => ManeuverKind::RoundaboutExit,
+            Kind::Ferry => ManeuverKind::Ferry,
+            Kind::Arrive => ManeuverKind::Arrive
+        }
+    }
+}
+
+#[derive(uniffi::Record)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(uniffi::Enum)]
+pub enum FeatureKind {
+    Poi, Road, Building, Placemark, Route, Overlay
+}
+
+impl From<map::tiles::tile_data::FeatureKind> for FeatureKind {
+    fn from(value: map::tiles::tile_data::FeatureKind) -> Self {
+        use map::tiles::tile_data::FeatureKind as Kind;
+        match value {
+            Kind::Poi => FeatureKind::Poi,
+            Kind::Road => FeatureKind::Road,
+            Kind::Building => FeatureKind::Building,
+            Kind::Placemark => FeatureKind::Placemark,
+            Kind::Route => FeatureKind::Route,
+            Kind::Overlay => FeatureKind::Overlay
+        }
+    }
+}
+
+#[derive(uniffi::Record)]
+pub struct MapFeature {
+    pub kind: FeatureKind,
+    pub name: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    /// Render group of the feature, e.g. the tile
+    pub group_key: String,
+}
+
+/// Style of the annotations, colors are `#rrggbb` or `#rrggbbaa`
+#[derive(uniffi::Record)]
+pub struct AnnotationStyle {
+    pub fill: String,
+    /// Darken percent of the outline
+    pub border: Option<f32>,
+    pub opacity: Option<f32>,
+}
+
+/// HTTP header of the routing requests, e.g. `Authorization: Bearer <token>`
+#[derive(uniffi::Record)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(uniffi::Record)]
+pub struct Maneuver {
+    pub kind: ManeuverKind,
+    pub instruction: String,
+    pub street_names: Vec<String>,
+}
+
+/// Turn-by-turn events, called on the render thread, distances are in meters
+#[uniffi::export(callback_interface)]
+pub trait GuidanceListener: Send + Sync {
+    fn on_upcoming_maneuver(&self, maneuver: Maneuver, distance: f64);
+    fn on_progress(&self, distance_to_maneuver: f64, distance_remaining: f64);
+    fn on_arrival(&self);
+    fn on_off_route(&self, distance_from_route: f64);
+    fn on_reroute(&self);
+    fn on_route_error(&self, message: String);
+}
+
 #[uniffi::export]
 impl ShashlikMapApi {
     fn render(&self) {
@@ -64,18 +183,269 @@ impl ShashlikMapApi {
         shashlik_map.set_lat_lon_bearing(lat, lon, bearing);
     }
 
+    /// Speed in meters per second, the position is extrapolated with it when the GPS is lost
+    fn set_location(&self, lat: f64, lon: f64, bearing: Option<f32>, speed: Option<f32>) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.set_location(lat, lon, bearing, speed);
+    }
+
+    /// Yaw rate in degrees per second clockwise, forward acceleration in meters per second squared
+    fn push_imu_sample(&self, yaw_rate: f32, forward_acceleration: Option<f32>) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.push_imu_sample(yaw_rate, forward_acceleration);
+    }
+
+    fn set_dead_reckoning_enabled(&self, enabled: bool) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.set_dead_reckoning_enabled(enabled);
+    }
+
+    /// Records the pushed locations into a NMEA log, the winit demo replays it with `--trace`
+    fn start_trace_recording(&self, path: String) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        match shashlik_map.start_trace_recording(path) {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Can't record the trace: {err:#}");
+                false
+            }
+        }
+    }
+
+    fn stop_trace_recording(&self) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.stop_trace_recording();
+    }
+
     fn set_cam_follow_mode(&self, enabled: bool) {
         let mut shashlik_map = self.shashlik_map.write().unwrap();
         shashlik_map.set_camera_follow_mode(enabled);
     }
 
-    fn calculate_route_to_lat_lon(&self, lat: f64, lon: f64, route_costing: RouteCosting) {
-        let shashlik_map = self.shashlik_map.read().unwrap();
-        shashlik_map.create_route_to((lat, lon), route_costing.into());
+    /// Cross-fades the map colors and the background to the theme
+    fn set_theme(&self, theme: MapTheme) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        // built-in themes are always known
+        shashlik_map.set_theme_by_name(theme.name()).unwrap();
+    }
+
+    fn set_guidance_listener(&self, listener: Box<dyn GuidanceListener>) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.set_guidance_listener(Some(Box::new(move |event| match event {
+            GuidanceEvent::UpcomingManeuver { maneuver, distance } => listener.on_upcoming_maneuver(
+                Maneuver {
+                    kind: maneuver.kind.into(),
+                    instruction: maneuver.instruction.clone(),
+                    street_names: maneuver.street_names.clone(),
+                },
+                *distance,
+            ),
+            GuidanceEvent::Progress { distance_to_maneuver, distance_remaining } => {
+                listener.on_progress(*distance_to_maneuver, *distance_remaining)
+            }
+            GuidanceEvent::Arrived => listener.on_arrival(),
+            GuidanceEvent::OffRoute { distance_from_route } => listener.on_off_route(*distance_from_route),
+            GuidanceEvent::Rerouted => listener.on_reroute(),
+            GuidanceEvent::RouteFailed { message } => listener.on_route_error(message.clone()),
+        })));
+    }
+
+    fn clear_guidance_listener(&self) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.set_guidance_listener(None);
+    }
+
+    /// Routes are calculated by the Valhalla server at the URL instead of the public one,
+    /// the headers are sent with every request
+    fn set_valhalla_url(&self, url: String, headers: Vec<HttpHeader>) -> bool {
+        let headers: Vec<_> = headers.into_iter().map(|header| (header.name, header.value)).collect();
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        match shashlik_map.set_valhalla_url(&url, &headers) {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Invalid Valhalla URL {url}: {err:#}");
+                false
+            }
+        }
+    }
+
+    /// Routes are calculated by the OSRM server at the URL instead of Valhalla,
+    /// the headers are sent with every request
+    fn set_osrm_url(&self, url: String, headers: Vec<HttpHeader>) -> bool {
+        let headers: Vec<_> = headers.into_iter().map(|header| (header.name, header.value)).collect();
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        match shashlik_map.set_osrm_url(&url, &headers) {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Invalid OSRM URL {url}: {err:#}");
+                false
+            }
+        }
+    }
+
+    fn calculate_route_to_lat_lon(&self, lat: f64, lon: f64, route_costing: RouteCosting, alternatives: u32) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.create_route_to((lat, lon), route_costing.into(), alternatives as usize);
+    }
+
+    fn calculate_route(&self, point_x: f32, point_y: f32, route_costing: RouteCosting, alternatives: u32) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.create_route_to_screen_point(point_x, point_y, route_costing.into(), alternatives as usize);
     }
 
-    fn calculate_route(&self, point_x: f32, point_y: f32, route_costing: RouteCosting) {
+    /// Route through the waypoints in order, the last one is the destination
+    fn calculate_route_via(&self, waypoints: Vec<LatLon>, route_costing: RouteCosting, alternatives: u32) {
+        let waypoints = waypoints.into_iter().map(|waypoint| (waypoint.lat, waypoint.lon)).collect();
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.create_route(waypoints, route_costing.into(), alternatives as usize);
+    }
+
+    /// Selects the route under the tapped point, false if there is no route there
+    fn select_route(&self, point_x: f32, point_y: f32) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.select_route_at_screen_point(point_x, point_y)
+    }
+
+    /// Features within `tolerance` pixels of the tapped point, the nearest first
+    fn features_at(&self, point_x: f32, point_y: f32, tolerance: f32) -> Vec<MapFeature> {
         let shashlik_map = self.shashlik_map.read().unwrap();
-        shashlik_map.create_route_to_screen_point(point_x, point_y, route_costing.into());
+        shashlik_map
+            .features_at_screen_point(point_x, point_y, tolerance)
+            .into_iter()
+            .map(|feature| MapFeature {
+                kind: feature.kind.into(),
+                name: feature.name,
+                lat: feature.lat_lon.0,
+                lon: feature.lat_lon.1,
+                group_key: feature.group_key,
+            })
+            .collect()
+    }
+
+    /// Marker with the SVG icon `size` pixels wide, replaces the annotation with the same id.
+    /// The style is declared with `set_style`, the default one is used without it.
+    /// Clustered markers close on screen are drawn as one marker with their count
+    fn add_marker(
+        &self,
+        id: String,
+        position: LatLon,
+        icon: Vec<u8>,
+        size: f32,
+        style: Option<String>,
+        z_order: i32,
+        clustered: bool,
+    ) -> bool {
+        let annotation = Annotation::marker((position.lat, position.lon), icon, size).with_clustering(clustered);
+        self.add_annotation(id, annotation, style, z_order)
+    }
+
+    /// Line `width` meters wide
+    fn add_polyline(&self, id: String, points: Vec<LatLon>, width: f64, style: Option<String>, z_order: i32) -> bool {
+        let annotation = Annotation::polyline(points.into_iter().map(|p| (p.lat, p.lon)).collect(), width);
+        self.add_annotation(id, annotation, style, z_order)
+    }
+
+    fn add_polygon(&self, id: String, points: Vec<LatLon>, style: Option<String>, z_order: i32) -> bool {
+        let annotation = Annotation::polygon(points.into_iter().map(|p| (p.lat, p.lon)).collect());
+        self.add_annotation(id, annotation, style, z_order)
+    }
+
+    fn remove_annotation(&self, id: String) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.remove_annotation(&id)
+    }
+
+    fn clear_annotations(&self) {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.clear_annotations();
+    }
+
+    fn set_style(&self, id: String, style: AnnotationStyle) -> bool {
+        let fill = match style.fill.parse::<Color>() {
+            Ok(fill) => fill,
+            Err(err) => {
+                log::error!("Invalid style {id}: {err}");
+                return false;
+            }
+        };
+        let style_def = StyleDef {
+            fill: Some(ZoomValue::Fixed(fill)),
+            border: style.border,
+            opacity: style.opacity.map(ZoomValue::Fixed),
+            ..Default::default()
+        };
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.set_style(&id, style_def);
+        true
+    }
+
+    /// Adds a TrueType or OpenType font to the fallback chain of the labels, e.g. a CJK font
+    fn add_font(&self, data: Vec<u8>) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        match shashlik_map.add_font(data) {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Can't add the font: {err:#}");
+                false
+            }
+        }
+    }
+
+    /// Loads the KML or KMZ file as a named layer, replaces the layer with the same name
+    fn load_kml_layer(&self, name: String, path: String) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        match shashlik_map.load_kml_layer(&name, &path) {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Can't load the KML layer {name}: {err:#}");
+                false
+            }
+        }
+    }
+
+    fn set_kml_layer_visible(&self, name: String, visible: bool) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.set_kml_layer_visible(&name, visible)
+    }
+
+    fn remove_kml_layer(&self, name: String) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.remove_kml_layer(&name)
+    }
+
+    /// Draws the GeoJSON FeatureCollection as a named layer styled by the feature properties,
+    /// replaces the layer with the same name
+    fn set_geojson_layer(&self, name: String, geojson: String) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        match shashlik_map.set_geojson_layer(&name, &geojson) {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Can't set the GeoJSON layer {name}: {err:#}");
+                false
+            }
+        }
+    }
+
+    fn remove_geojson_layer(&self, name: String) -> bool {
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        shashlik_map.remove_geojson_layer(&name)
+    }
+}
+
+impl ShashlikMapApi {
+    fn add_annotation(&self, id: String, annotation: Annotation, style: Option<String>, z_order: i32) -> bool {
+        let annotation = match style {
+            Some(style) => annotation.with_style(&style),
+            None => annotation,
+        }
+        .with_z_order(z_order);
+        let mut shashlik_map = self.shashlik_map.write().unwrap();
+        match shashlik_map.add_annotation(&id, annotation) {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Invalid annotation {id}: {err:#}");
+                false
+            }
+        }
     }
 }
@@ -6,15 +6,17 @@ use jni::JNIEnv;
 use jni_fn::jni_fn;
 use map::ShashlikMap;
 use std::sync::{Arc, RwLock};
-use wgpu::{Device, Queue, SurfaceConfiguration, SurfaceError, SurfaceTexture};
+use wgpu::{Device, Queue, SurfaceConfiguration, SurfaceError};
 use map::tiles::shashlik_tiles_provider_v0::ShashlikTilesProviderV0;
-use osm::source::reqwest_source::ReqwestSource;
-use wgpu_canvas::wgpu_canvas::WgpuCanvas;
+use map::tiles::source::MapTileSource;
+use std::path::Path;
+use wgpu_canvas::wgpu_canvas::{CanvasTexture, WgpuCanvas};
 use jni::objects::JString;
 use app_surface::SurfaceFrame;
 use pollster::FutureExt;
 use jni::sys::jfloat;
 use map::feature_processor::ShashlikFeatureProcessor;
+use log::error;
 
 //FIXME https://github.com/gobley/gobley/issues/20
 #[uniffi::export]
@@ -39,11 +41,14 @@ impl WgpuCanvas for AndroidSurfaceAppSurface {
         &self.app_surface.device
     }
 
-    fn get_current_texture(&self) -> Result<SurfaceTexture, SurfaceError> {
-        self.app_surface.surface.get_current_texture()
+    fn get_current_texture(&self) -> Result<CanvasTexture, SurfaceError> {
+        self.app_surface
+            .surface
+            .get_current_texture()
+            .map(CanvasTexture::Surface)
     }
 
-    fn on_resize(&mut self) {
+    fn on_resize(&mut self, _width: u32, _height: u32) {
         self.app_surface.resize_surface();
     }
 
@@ -63,24 +68,34 @@ pub fn createShashlikMapApi(
     _: JClass,
     surface: jobject,
     emulator: jboolean,
-    _tiles_db: JString,
+    tiles_db: JString,
     dpi_scale: jfloat,
 ) -> jlong {
     init_logger();
     let app_surface = AppSurface::new(env, surface, emulator != 0).block_on();
     let surface = AndroidSurfaceAppSurface { app_surface };
-    // let mut env = unsafe { JNIEnv::from_raw(env as *mut *const _).unwrap() };
-    // let tiles_db: String = env.get_string(&tiles_db).unwrap().into();
-    // let tiles_sqlite_store = TilesSQLiteStore::new(tiles_db);
-    let reqwest_source = ReqwestSource::new();
+    let tiles_db = read_jstring(env, &tiles_db);
+    // offline pack if it was pushed to the device, otherwise the tile server
+    let tile_source = MapTileSource::local_or_network(tiles_db.as_deref().map(Path::new));
     let feature_processor = ShashlikFeatureProcessor::new();
-    let shashlik_map = pollster::block_on(ShashlikMap::new(Box::new(surface), ShashlikTilesProviderV0::new(reqwest_source, feature_processor, dpi_scale))).unwrap();
+    let shashlik_map = pollster::block_on(ShashlikMap::new(Box::new(surface), ShashlikTilesProviderV0::new(tile_source, feature_processor, dpi_scale))).unwrap();
     let map_api = ShashlikMapApi {
         shashlik_map: RwLock::new(shashlik_map),
     };
     Arc::into_raw(Arc::new(map_api)) as jlong
 }
 
+/// Panics can't cross the JNI boundary, the errors are logged and the string is skipped.
+fn read_jstring(env: *mut JNIEnv<'_>, string: &JString) -> Option<String> {
+    let mut env = unsafe { JNIEnv::from_raw(env as *mut *const _) }
+        .inspect_err(|err| error!("Invalid JNIEnv: {err}"))
+        .ok()?;
+    env.get_string(string)
+        .inspect_err(|err| error!("Can't read a Java string: {err}"))
+        .ok()
+        .map(Into::into)
+}
+
 fn init_logger() {
     android_logger::init_once(
         android_logger::Config::default()
@@ -1,9 +1,10 @@
 use app_surface::{AppSurface, IOSViewObj};
-use wgpu::{Device, Queue, SurfaceConfiguration, SurfaceError, SurfaceTexture};
-use wgpu_canvas::wgpu_canvas::WgpuCanvas;
+use wgpu::{Device, Queue, SurfaceConfiguration, SurfaceError};
+use wgpu_canvas::wgpu_canvas::{CanvasTexture, WgpuCanvas};
 use crate::ShashlikMapApi;
 use map::tiles::shashlik_tiles_provider_v0::ShashlikTilesProviderV0;
-use osm::source::reqwest_source::ReqwestSource;
+use map::tiles::source::MapTileSource;
+use std::path::Path;
 use map::ShashlikMap;
 use std::sync::RwLock;
 use std::ffi::c_void;
@@ -14,7 +15,7 @@ use map::feature_processor::ShashlikFeatureProcessor;
 extern "C" fn ios_callback_stub(_arg: i32) {}
 
 #[uniffi::export]
-pub fn create_shashlik_map_api_for_ios(view: u64, metal_layer: u64, maximum_frames: i32, _tiles_db: String) -> ShashlikMapApi {
+pub fn create_shashlik_map_api_for_ios(view: u64, metal_layer: u64, maximum_frames: i32, tiles_db: String) -> ShashlikMapApi {
 	let ios_view_obj = IOSViewObj {
 		view: view as *mut Object,
 		metal_layer: metal_layer as *mut c_void,
@@ -23,10 +24,12 @@ pub fn create_shashlik_map_api_for_ios(view: u64, metal_layer: u64, maximum_fram
 	};
 	let app_surface = AppSurface::new(ios_view_obj);
 	let wrapper = IOSPlatformAppSurface { app_surface };
-	let reqwest_source = ReqwestSource::new();
+	// empty path means the tile server
+	let tiles_db = Some(Path::new(&tiles_db)).filter(|path| !path.as_os_str().is_empty());
+	let tile_source = MapTileSource::local_or_network(tiles_db);
 	let feature_processor = ShashlikFeatureProcessor::new();
 	// TODO DPI from iOS
-	let shashlik_map = pollster::block_on(ShashlikMap::new(Box::new(wrapper), ShashlikTilesProviderV0::new(reqwest_source, feature_processor, 1.35))).unwrap();
+	let shashlik_map = pollster::block_on(ShashlikMap::new(Box::new(wrapper), ShashlikTilesProviderV0::new(tile_source, feature_processor, 1.35))).unwrap();
 	ShashlikMapApi { shashlik_map: RwLock::new(shashlik_map) }
 }
 
@@ -42,10 +45,10 @@ impl WgpuCanvas for IOSPlatformAppSurface {
 	fn queue(&self) -> &Queue { &self.app_surface.queue }
 	fn config(&self) -> &SurfaceConfiguration { &self.app_surface.config }
 	fn device(&self) -> &Device { &self.app_surface.device }
-	fn get_current_texture(&self) -> Result<SurfaceTexture, SurfaceError> {
-		self.app_surface.surface.get_current_texture()
+	fn get_current_texture(&self) -> Result<CanvasTexture, SurfaceError> {
+		self.app_surface.surface.get_current_texture().map(CanvasTexture::Surface)
 	}
-	fn on_resize(&mut self) {
+	fn on_resize(&mut self, _width: u32, _height: u32) {
 		self.app_surface.resize_surface();
 	}
 	fn on_pre_render(&self) {}
@@ -36,7 +36,8 @@ class MainActivity : ComponentActivity() {
                     ShashlikMapApiHolder.shashlikMapApi?.calculateRouteToLatLon(
                         coord.first,
                         coord.second,
-                        routeCosting.value
+                        routeCosting.value,
+                        ROUTE_ALTERNATIVES
                     )
                 }
             }
@@ -1,6 +1,7 @@
 package com.shashlik.demo
 
 import androidx.compose.foundation.background
+import androidx.compose.foundation.clickable
 import androidx.compose.foundation.layout.Box
 import androidx.compose.foundation.layout.Row
 import androidx.compose.foundation.layout.Spacer
@@ -20,10 +21,15 @@ import androidx.compose.ui.unit.dp
 import com.shashlik.kmp.ShashlikMap
 import com.shashlik.kmp.ShashlikMapApiHolder
 import org.jetbrains.compose.ui.tooling.preview.Preview
+import uniffi.ffi_run.MapFeature
+import uniffi.ffi_run.MapTheme
 import uniffi.ffi_run.RouteCosting
 
 var routeCosting = mutableStateOf(RouteCosting.MOTORBIKE)
 
+// alternative routes shown besides the best one, a tap selects them
+const val ROUTE_ALTERNATIVES = 2u
+
 @Composable
 @Preview
 fun App() {
@@ -31,8 +37,22 @@ fun App() {
         Box(
             modifier = Modifier.fillMaxSize()
         ) {
-            ShashlikMap { x, y->
-                ShashlikMapApiHolder.shashlikMapApi?.calculateRoute(x, y, routeCosting.value)
+            var tappedFeature by remember { mutableStateOf<MapFeature?>(null) }
+            ShashlikMap(onFeaturesTap = { tappedFeature = it.first() }) { x, y->
+                ShashlikMapApiHolder.shashlikMapApi?.calculateRoute(x, y, routeCosting.value, ROUTE_ALTERNATIVES)
+            }
+            tappedFeature?.let { feature ->
+                Text(
+                    "${feature.kind.name.lowercase()} ${feature.name ?: ""}\n" +
+                            "${feature.lat.toFloat()}, ${feature.lon.toFloat()}",
+                    color = Color.White,
+                    modifier = Modifier
+                        .align(Alignment.TopCenter)
+                        .padding(16.dp)
+                        .background(Color(0, 0, 0, 150))
+                        .clickable { tappedFeature = null }
+                        .padding(16.dp)
+                )
             }
             Row(
                 modifier = Modifier
@@ -65,6 +85,16 @@ fun App() {
                         })
                     Text("Camera Mode")
                 }
+                Spacer(modifier = Modifier.width(8.dp))
+                Row(verticalAlignment = Alignment.CenterVertically) {
+                    var nightState by remember { mutableStateOf(false) }
+                    Checkbox(
+                        nightState, onCheckedChange = {
+                            ShashlikMapApiHolder.shashlikMapApi?.setTheme(if (it) MapTheme.NIGHT else MapTheme.DAY)
+                            nightState = it
+                        })
+                    Text("Night")
+                }
             }
         }
     }
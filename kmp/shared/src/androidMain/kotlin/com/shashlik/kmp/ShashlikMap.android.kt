@@ -13,6 +13,7 @@ import com.google.accompanist.permissions.ExperimentalPermissionsApi
 import com.google.accompanist.permissions.rememberMultiplePermissionsState
 import timber.log.Timber
 import timber.log.Timber.DebugTree
+import uniffi.ffi_run.MapFeature
 
 fun shashlikMapInit() {
     Timber.plant(DebugTree())
@@ -21,7 +22,10 @@ fun shashlikMapInit() {
 @OptIn(ExperimentalPermissionsApi::class)
 @SuppressLint("MissingPermission")
 @Composable
-actual fun ShashlikMap(onLongTap: (x: Float, y: Float) -> Unit) {
+actual fun ShashlikMap(
+    onFeaturesTap: (features: List<MapFeature>) -> Unit,
+    onLongTap: (x: Float, y: Float) -> Unit
+) {
     val locationPermissionState = rememberMultiplePermissionsState(
         listOf(
             android.Manifest.permission.ACCESS_FINE_LOCATION,
@@ -30,7 +34,7 @@ actual fun ShashlikMap(onLongTap: (x: Float, y: Float) -> Unit) {
     )
 
     if (locationPermissionState.allPermissionsGranted) {
-        ShashlikMapComp(onLongTap)
+        ShashlikMapComp(onFeaturesTap, onLongTap)
     } else {
         LaunchedEffect(Unit) {
             locationPermissionState.launchMultiplePermissionRequest()
@@ -40,20 +44,30 @@ actual fun ShashlikMap(onLongTap: (x: Float, y: Float) -> Unit) {
 
 @SuppressLint("MissingPermission")
 @Composable
-private fun ShashlikMapComp(onLongTap: (x: Float, y: Float) -> Unit) {
+private fun ShashlikMapComp(
+    onFeaturesTap: (features: List<MapFeature>) -> Unit,
+    onLongTap: (x: Float, y: Float) -> Unit
+) {
     val ctx = LocalContext.current
     val locationManager = remember {
         SimpleLocationManager(ctx) {
-            ShashlikMapApiHolder.shashlikMapApi?.setLatLonBearing(it.lat, it.lon, it.bearing)
+            ShashlikMapApiHolder.shashlikMapApi?.setLocation(it.lat, it.lon, it.bearing, it.speed)
+        }
+    }
+    val imuManager = remember {
+        SimpleImuManager(ctx) { yawRate ->
+            ShashlikMapApiHolder.shashlikMapApi?.pushImuSample(yawRate, null)
         }
     }
     LifecycleStartEffect(Unit) {
         Timber.d("onStart")
         locationManager.start()
+        imuManager.start()
 
         onStopOrDispose {
             Timber.d( "onStop")
             locationManager.stop()
+            imuManager.stop()
         }
     }
 
@@ -61,6 +75,7 @@ private fun ShashlikMapComp(onLongTap: (x: Float, y: Float) -> Unit) {
         factory = { ctx ->
             WGPUTextureView(context = ctx).also {
                 it.onLongTap = onLongTap
+                it.onFeaturesTap = onFeaturesTap
             }
         },
         modifier = Modifier.fillMaxSize()
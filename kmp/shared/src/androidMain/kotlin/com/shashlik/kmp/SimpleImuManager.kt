@@ -0,0 +1,55 @@
+package com.shashlik.kmp
+
+import android.content.Context
+import android.hardware.Sensor
+import android.hardware.SensorEvent
+import android.hardware.SensorEventListener
+import android.hardware.SensorManager
+import kotlin.math.sqrt
+
+/**
+ * Yaw rate of the device in degrees per second clockwise, whatever the device is mounted like.
+ * The gyroscope rotation is projected onto the gravity, so it's the rotation around the vertical.
+ */
+class SimpleImuManager(context: Context, private val callback: (Float) -> Unit) {
+
+    private val sensorService: SensorManager =
+        context.getSystemService(Context.SENSOR_SERVICE) as SensorManager
+    private val gravity = FloatArray(3)
+    private var hasGravity = false
+
+    private val sensorListener = object : SensorEventListener {
+        override fun onSensorChanged(event: SensorEvent) {
+            when (event.sensor.type) {
+                Sensor.TYPE_GRAVITY -> {
+                    event.values.copyInto(gravity, endIndex = 3)
+                    hasGravity = true
+                }
+
+                Sensor.TYPE_GYROSCOPE -> if (hasGravity) {
+                    val norm = sqrt(gravity[0] * gravity[0] + gravity[1] * gravity[1] + gravity[2] * gravity[2])
+                    if (norm == 0f) return
+                    // counter-clockwise around the up axis, in radians per second
+                    val yawRate = (event.values[0] * gravity[0] + event.values[1] * gravity[1] +
+                            event.values[2] * gravity[2]) / norm
+                    callback(-Math.toDegrees(yawRate.toDouble()).toFloat())
+                }
+            }
+        }
+
+        override fun onAccuracyChanged(sensor: Sensor, accuracy: Int) {}
+    }
+
+    fun start() {
+        listOf(Sensor.TYPE_GRAVITY, Sensor.TYPE_GYROSCOPE).forEach { type ->
+            sensorService.getDefaultSensor(type)?.let {
+                sensorService.registerListener(sensorListener, it, SensorManager.SENSOR_DELAY_GAME)
+            }
+        }
+    }
+
+    fun stop() {
+        sensorService.unregisterListener(sensorListener)
+        hasGravity = false
+    }
+}
@@ -12,7 +12,7 @@ import kotlinx.coroutines.delay
 import kotlinx.coroutines.launch
 import timber.log.Timber
 
-data class LocationData(val lat: Double, val lon: Double, val bearing: Float?)
+data class LocationData(val lat: Double, val lon: Double, val bearing: Float?, val speed: Float?)
 class SimpleLocationManager(context: Context, private val callback: (LocationData) -> Unit) {
 
     private val scope = CoroutineScope(Dispatchers.IO + SupervisorJob())
@@ -31,7 +31,8 @@ class SimpleLocationManager(context: Context, private val callback: (LocationDat
         )
         Timber.d("hasAltitude = ${location.hasAltitude()}, altitude = ${location.altitude}")
         val bearing: Float? = if (location.hasBearing()) location.bearing else null
-        callback(LocationData(latitude, longitude, bearing))
+        val speed: Float? = if (location.hasSpeed()) location.speed else null
+        callback(LocationData(latitude, longitude, bearing, speed))
     }
 
     fun start() {
@@ -11,15 +11,21 @@ import android.view.ScaleGestureDetector
 import android.view.Surface
 import android.view.TextureView
 import timber.log.Timber
+import uniffi.ffi_run.MapFeature
 import uniffi.ffi_run.ShashlikMapApi
 import uniffi.ffi_run.toPointer
 
+// features this far from the tap in pixels are under the finger
+private const val FEATURE_TAP_TOLERANCE = 24.0f
+
 
 @SuppressLint("ClickableViewAccessibility")
 class WGPUTextureView : TextureView {
 
     var onLongTap: (x: Float, y: Float) -> Unit = { _, _ -> }
 
+    var onFeaturesTap: (features: List<MapFeature>) -> Unit = { _ -> }
+
     private val scaleListener = object : ScaleGestureDetector.SimpleOnScaleGestureListener() {
 
         override fun onScale(detector: ScaleGestureDetector): Boolean {
@@ -51,6 +57,20 @@ class WGPUTextureView : TextureView {
             return super.onScroll(e1, e2, distanceX, distanceY)
         }
 
+        override fun onSingleTapConfirmed(e: MotionEvent): Boolean {
+            val api = ShashlikMapApiHolder.shashlikMapApi ?: return false
+            // taps on the alternative routes select them
+            if (api.selectRoute(e.x, e.y)) {
+                return true
+            }
+            val features = api.featuresAt(e.x, e.y, FEATURE_TAP_TOLERANCE)
+            if (features.isEmpty()) {
+                return false
+            }
+            onFeaturesTap(features)
+            return true
+        }
+
         override fun onLongPress(e: MotionEvent) {
             super.onLongPress(e)
             onLongTap(e.x, e.y)
@@ -1,10 +1,17 @@
 package com.shashlik.kmp
 
 import androidx.compose.runtime.Composable
+import uniffi.ffi_run.MapFeature
 import uniffi.ffi_run.ShashlikMapApi
 
+/**
+ * [onFeaturesTap] gets the features under a tap, the nearest first, taps on the routes select them instead.
+ */
 @Composable
-expect fun ShashlikMap(onLongTap: (x: Float, y: Float) -> Unit)
+expect fun ShashlikMap(
+    onFeaturesTap: (features: List<MapFeature>) -> Unit = {},
+    onLongTap: (x: Float, y: Float) -> Unit
+)
 
 object ShashlikMapApiHolder {
     var shashlikMapApi: ShashlikMapApi? = null
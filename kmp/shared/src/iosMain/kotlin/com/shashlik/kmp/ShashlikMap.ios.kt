@@ -5,13 +5,17 @@ import androidx.compose.runtime.Composable
 import androidx.compose.ui.Modifier
 import androidx.compose.ui.viewinterop.UIKitViewController
 import platform.UIKit.UIViewController
+import uniffi.ffi_run.MapFeature
 
 object ShashlikMapUIViewProvider {
     lateinit var createUIViewController: () -> UIViewController
 }
 
 @Composable
-actual fun ShashlikMap(onLongTap: (x: Float, y: Float) -> Unit) {
+actual fun ShashlikMap(
+    onFeaturesTap: (features: List<MapFeature>) -> Unit,
+    onLongTap: (x: Float, y: Float) -> Unit
+) {
     // TODO Pass to iOS
     UIKitViewController(
         factory = ShashlikMapUIViewProvider.createUIViewController,
@@ -0,0 +1,144 @@
+use crate::annotation::Annotation;
+use crate::annotation::annotation_group::AnnotationGroup;
+use crate::clustering::cluster_controller::ClusterController;
+use crate::route::route_controller::LocationConverter;
+use renderer::modifier::render_modifier::SpatialData;
+use renderer::renderer_api::RendererApi;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+struct AnnotationEntry {
+    annotation: Annotation,
+    // keeps the order of the annotations with the same z-order
+    sequence: u64,
+    group_key: Option<String>,
+}
+
+/// Keeps a render group per annotation. The groups are drawn in the order they are added,
+/// so a changed annotation is added again together with all the annotations over it.
+/// Clustered markers have no group, they are the points of the cluster controller.
+pub struct AnnotationController {
+    api: Arc<RendererApi>,
+    converter: LocationConverter,
+    annotations: HashMap<String, AnnotationEntry>,
+    sequence: u64,
+    group_version: u64,
+}
+
+impl AnnotationController {
+    // the layer of the routes, over the tiles
+    const LAYER: usize = 1;
+    const CLUSTER_SOURCE_PREFIX: &'static str = "annotation:";
+
+    pub fn new(api: Arc<RendererApi>, converter: LocationConverter) -> AnnotationController {
+        AnnotationController {
+            api,
+            converter,
+            annotations: HashMap::new(),
+            sequence: 0,
+            group_version: 0,
+        }
+    }
+
+    /// Adds the annotation or replaces the one with the same id, a replaced one keeps its place
+    /// among the annotations with the same z-order.
+    pub fn add(&mut self, id: &str, annotation: Annotation, clusters: &mut ClusterController) -> anyhow::Result<()> {
+        annotation.validate()?;
+        let cluster_source = Self::cluster_source(id);
+        if let Some(item) = annotation.cluster_item(self.converter.as_ref()) {
+            self.remove_group(id);
+            clusters.set_items(&cluster_source, vec![item]);
+            return Ok(());
+        }
+        clusters.remove_items(&cluster_source);
+        let (sequence, group_key, old_order) = match self.annotations.remove(id) {
+            Some(entry) => (entry.sequence, entry.group_key, Some((entry.annotation.z_order, entry.sequence))),
+            None => {
+                self.sequence += 1;
+                (self.sequence, None, None)
+            }
+        };
+        let order = (annotation.z_order, sequence);
+        self.annotations.insert(
+            id.to_string(),
+            AnnotationEntry {
+                annotation,
+                sequence,
+                group_key,
+            },
+        );
+        self.restack(old_order.map_or(order, |old_order| old_order.min(order)));
+        Ok(())
+    }
+
+    /// Returns false if there is no annotation with the id.
+    pub fn remove(&mut self, id: &str, clusters: &mut ClusterController) -> bool {
+        let clustered = clusters.remove_items(&Self::cluster_source(id));
+        self.remove_group(id) || clustered
+    }
+
+    pub fn clear(&mut self, clusters: &mut ClusterController) {
+        let group_keys: HashSet<String> = self
+            .annotations
+            .drain()
+            .filter_map(|(_, entry)| entry.group_key)
+            .collect();
+        if !group_keys.is_empty() {
+            self.api.clear_render_groups(group_keys);
+        }
+        clusters.remove_sources(|source| source.starts_with(Self::CLUSTER_SOURCE_PREFIX));
+    }
+
+    /// Annotations with render groups, the clustered markers aren't counted.
+    pub fn len(&self) -> usize {
+        self.annotations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+
+    fn cluster_source(id: &str) -> String {
+        format!("{}{id}", Self::CLUSTER_SOURCE_PREFIX)
+    }
+
+    fn remove_group(&mut self, id: &str) -> bool {
+        let Some(entry) = self.annotations.remove(id) else {
+            return false;
+        };
+        if let Some(group_key) = entry.group_key {
+            self.api.clear_render_groups(HashSet::from([group_key]));
+        }
+        true
+    }
+
+    // adds again the annotations from the order up, the old groups are removed after the new ones are added,
+    // so there is no frame without them
+    fn restack(&mut self, from: (i32, u64)) {
+        let mut ids: Vec<((i32, u64), String)> = self
+            .annotations
+            .iter()
+            .map(|(id, entry)| ((entry.annotation.z_order, entry.sequence), id.clone()))
+            .filter(|(order, _)| *order >= from)
+            .collect();
+        ids.sort();
+
+        let mut old_keys = HashSet::new();
+        for (_, id) in ids {
+            let entry = self.annotations.get_mut(&id).unwrap();
+            let group = AnnotationGroup::new(&entry.annotation, self.converter.as_ref());
+            self.group_version += 1;
+            let group_key = format!("annotation_{}", self.group_version);
+            self.api.add_render_group(
+                group_key.clone(),
+                Self::LAYER,
+                SpatialData::transform(group.origin()),
+                Box::new(group),
+            );
+            old_keys.extend(entry.group_key.replace(group_key));
+        }
+        if !old_keys.is_empty() {
+            self.api.clear_render_groups(old_keys);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::annotation::{Annotation, AnnotationGeometry};
+use crate::tiles::mercator;
+use cgmath::Vector3;
+use geo_types::Point;
+use lyon::geom::point;
+use lyon::lyon_tessellation::{LineCap, LineJoin};
+use lyon::path::Path;
+use renderer::canvas_api::CanvasApi;
+use renderer::draw_commands::{GeometryType, PolylineOptions};
+use renderer::geometry_data::{GeometryData, ShapeData, SvgData};
+use renderer::render_group::RenderGroup;
+use renderer::styles::style_id::StyleId;
+use seahash::hash;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Draws one annotation, the geometry is relative to its first point.
+pub struct AnnotationGroup {
+    geometry: AnnotationGeometry,
+    style_id: StyleId,
+    points: Vec<Point>,
+}
+
+impl AnnotationGroup {
+    pub fn new(annotation: &Annotation, converter: &dyn Fn(&Point) -> Point) -> AnnotationGroup {
+        let lat_lons = match &annotation.geometry {
+            AnnotationGeometry::Marker { lat_lon, .. } => std::slice::from_ref(lat_lon),
+            AnnotationGeometry::Polyline { lat_lons, .. } | AnnotationGeometry::Polygon { lat_lons } => lat_lons,
+        };
+        let points = lat_lons
+            .iter()
+            .map(|(lat, lon)| converter(&Point::new(*lon, *lat)))
+            .collect();
+        AnnotationGroup {
+            geometry: annotation.geometry.clone(),
+            style_id: annotation.style_id.clone(),
+            points,
+        }
+    }
+
+    pub fn origin(&self) -> Vector3<f64> {
+        Vector3::new(self.points[0].x(), self.points[0].y(), 0.0)
+    }
+
+    fn path(&self, close: bool) -> Path {
+        let origin = self.points[0];
+        let relative = |p: &Point| point((p.x() - origin.x()) as f32, (p.y() - origin.y()) as f32);
+        let mut path_builder = Path::builder();
+        path_builder.begin(relative(&origin));
+        for p in self.points[1..].iter() {
+            path_builder.line_to(relative(p));
+        }
+        path_builder.end(close);
+        path_builder.build()
+    }
+}
+
+/// Name and SVG document of an icon, the canvas caches its mesh by the name.
+pub type Icon = (&'static str, &'static [u8]);
+
+// the canvas caches the icon meshes by name, so the name covers everything the mesh is built from
+pub(crate) fn intern_icon(icon: &[u8], size: f32, style_id: &StyleId) -> Icon {
+    static ICONS: OnceLock<Mutex<HashMap<String, Icon>>> = OnceLock::new();
+    let name = format!("annotation_{:x}_{size}_{}", hash(icon), style_id.0);
+    let mut icons = ICONS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+    *icons.entry(name).or_insert_with_key(|name| {
+        let name: &'static str = Box::leak(name.clone().into_boxed_str());
+        (name, Box::leak(icon.to_vec().into_boxed_slice()))
+    })
+}
+
+impl RenderGroup for AnnotationGroup {
+    fn content(&mut self, canvas: &mut CanvasApi) {
+        let style_id = self.style_id.clone();
+        match &self.geometry {
+            AnnotationGeometry::Marker { icon, size, .. } => {
+                canvas.geometry_data(GeometryData::Svg(SvgData {
+                    icon: intern_icon(icon, *size, &style_id),
+                    position: Vector3::new(0.0, 0.0, 0.0),
+                    size: *size,
+                    style_id,
+                    with_collision: false,
+                    rank: 0,
+                    symbol_id: None,
+                }));
+            }
+            AnnotationGeometry::Polyline { lat_lons, width } => {
+                let width = width / mercator::meters_per_world_unit(lat_lons[0].0);
+                canvas.path(ShapeData {
+                    path: self.path(false),
+                    geometry_type: GeometryType::Polyline(PolylineOptions {
+                        width: width as f32,
+                        line_join: LineJoin::Round,
+                        line_cap: LineCap::Round,
+                        tolerance: 0.05f32,
+                    }),
+                    style_id,
+                    index_layer_level: 0,
+                    is_screen: false,
+                });
+            }
+            AnnotationGeometry::Polygon { .. } => {
+                canvas.path(ShapeData {
+                    path: self.path(true),
+                    geometry_type: GeometryType::Polygon,
+                    style_id,
+                    index_layer_level: 0,
+                    is_screen: false,
+                });
+            }
+        }
+    }
+}
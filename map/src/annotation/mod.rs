@@ -0,0 +1,107 @@
+pub mod annotation_controller;
+pub mod annotation_group;
+
+use crate::annotation::annotation_group::intern_icon;
+use crate::clustering::cluster_group::ClusterItem;
+use crate::style::style_sheet::intern;
+use anyhow::bail;
+use geo_types::Point;
+use renderer::styles::style_id::StyleId;
+use std::sync::Arc;
+
+/// Shape of an annotation, positions are (lat, lon).
+#[derive(Clone, Debug)]
+pub enum AnnotationGeometry {
+    /// SVG icon `size` pixels wide, it keeps its size on screen
+    Marker { lat_lon: (f64, f64), icon: Arc<[u8]>, size: f32 },
+    /// Line `width` meters wide
+    Polyline { lat_lons: Vec<(f64, f64)>, width: f64 },
+    /// Filled area, the ring is closed automatically
+    Polygon { lat_lons: Vec<(f64, f64)> },
+}
+
+/// Content of the host app drawn over the map tiles, see `ShashlikMap::add_annotation`.
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    pub geometry: AnnotationGeometry,
+    pub style_id: StyleId,
+    /// Annotations with a bigger z-order are drawn over the others, markers are always over the shapes
+    pub z_order: i32,
+    /// Markers close on screen are merged into clusters with the KML placemarks, the z-order doesn't apply to them
+    pub clustered: bool,
+}
+
+impl Annotation {
+    pub fn marker(lat_lon: (f64, f64), icon: impl Into<Arc<[u8]>>, size: f32) -> Annotation {
+        Annotation {
+            geometry: AnnotationGeometry::Marker { lat_lon, icon: icon.into(), size },
+            style_id: StyleId("annotation_marker"),
+            z_order: 0,
+            clustered: false,
+        }
+    }
+
+    pub fn polyline(lat_lons: Vec<(f64, f64)>, width: f64) -> Annotation {
+        Annotation {
+            geometry: AnnotationGeometry::Polyline { lat_lons, width },
+            style_id: StyleId("annotation_line"),
+            z_order: 0,
+            clustered: false,
+        }
+    }
+
+    pub fn polygon(lat_lons: Vec<(f64, f64)>) -> Annotation {
+        Annotation {
+            geometry: AnnotationGeometry::Polygon { lat_lons },
+            style_id: StyleId("annotation_polygon"),
+            z_order: 0,
+            clustered: false,
+        }
+    }
+
+    /// Style of the style sheet or the one declared with `ShashlikMap::set_style`.
+    pub fn with_style(mut self, style: &str) -> Self {
+        self.style_id = StyleId(intern(style));
+        self
+    }
+
+    pub fn with_z_order(mut self, z_order: i32) -> Self {
+        self.z_order = z_order;
+        self
+    }
+
+    /// Only markers are clustered, see `ShashlikMap::add_annotation`.
+    pub fn with_clustering(mut self, clustered: bool) -> Self {
+        self.clustered = clustered;
+        self
+    }
+
+    // the clustered marker, it's drawn with the KML placemarks instead of its own group
+    pub(crate) fn cluster_item(&self, converter: &dyn Fn(&Point) -> Point) -> Option<ClusterItem> {
+        match &self.geometry {
+            AnnotationGeometry::Marker { lat_lon, icon, size } if self.clustered => {
+                let position = converter(&Point::new(lat_lon.1, lat_lon.0));
+                Some(ClusterItem {
+                    position: position.0,
+                    icon: intern_icon(icon, *size, &self.style_id),
+                    size: *size,
+                    style_id: self.style_id.clone(),
+                    label: None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        match &self.geometry {
+            AnnotationGeometry::Marker { size, .. } if *size <= 0.0 => bail!("Marker size must be positive"),
+            AnnotationGeometry::Polyline { lat_lons, .. } if lat_lons.len() < 2 => {
+                bail!("Polyline needs at least 2 points")
+            }
+            AnnotationGeometry::Polyline { width, .. } if *width <= 0.0 => bail!("Polyline width must be positive"),
+            AnnotationGeometry::Polygon { lat_lons } if lat_lons.len() < 3 => bail!("Polygon needs at least 3 points"),
+            _ => Ok(()),
+        }
+    }
+}
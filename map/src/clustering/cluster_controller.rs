@@ -0,0 +1,184 @@
+use crate::clustering::cluster_group::{ClusterGroup, ClusterItem, ClusterMarker};
+use crate::clustering::{Cluster, cluster_points};
+use geo_types::Coord;
+use renderer::modifier::render_modifier::SpatialData;
+use renderer::renderer_api::RendererApi;
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+struct ExpandAnimation {
+    start: Instant,
+    // positions of the clusters of the previous zoom level the clusters come from
+    from: Vec<Coord>,
+}
+
+/// Clusters the points of its sources, e.g. the KML placemarks, for the current zoom level.
+/// The clusters are recomputed when the zoom level changes and move from the clusters
+/// of the previous level to their places.
+pub struct ClusterController {
+    api: Arc<RendererApi>,
+    sources: BTreeMap<String, Vec<ClusterItem>>,
+    items: Vec<ClusterItem>,
+    clusters: Vec<Cluster>,
+    zoom_level: Option<i32>,
+    items_changed: bool,
+    animation: Option<ExpandAnimation>,
+    group_key: Option<String>,
+    group_version: u64,
+}
+
+impl ClusterController {
+    // the layer of the routes and the annotations, over the tiles
+    const LAYER: usize = 1;
+    /// Points closer than this on screen are clustered
+    pub const RADIUS_PX: f64 = 60.0;
+    const ANIMATION_DURATION: Duration = Duration::from_millis(300);
+
+    pub fn new(api: Arc<RendererApi>) -> ClusterController {
+        ClusterController {
+            api,
+            sources: BTreeMap::new(),
+            items: vec![],
+            clusters: vec![],
+            zoom_level: None,
+            items_changed: false,
+            animation: None,
+            group_key: None,
+            group_version: 0,
+        }
+    }
+
+    /// Replaces the points of the source.
+    pub fn set_items(&mut self, source: &str, items: Vec<ClusterItem>) {
+        self.sources.insert(source.to_string(), items);
+        self.items_changed = true;
+    }
+
+    /// Returns false if there is no source with the key.
+    pub fn remove_items(&mut self, source: &str) -> bool {
+        let removed = self.sources.remove(source).is_some();
+        self.items_changed |= removed;
+        removed
+    }
+
+    pub fn remove_sources(&mut self, predicate: impl Fn(&str) -> bool) {
+        let count = self.sources.len();
+        self.sources.retain(|source, _| !predicate(source));
+        self.items_changed |= self.sources.len() != count;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty() && self.group_key.is_none()
+    }
+
+    /// Clusters of the last update, the members are indices of the points of all sources
+    /// in the order of the source keys.
+    pub fn clusters(&self) -> &[Cluster] {
+        &self.clusters
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// `world_per_pixel` is the scale in the middle of the screen at the fractional `zoom_level`.
+    pub fn update(&mut self, zoom_level: f32, world_per_pixel: f64, now: Instant) {
+        let level = zoom_level.round() as i32;
+        if self.items_changed || self.zoom_level != Some(level) {
+            // the points of the previous clusters are not the same anymore, so they don't move
+            let previous = match (self.items_changed, self.zoom_level) {
+                (false, Some(_)) => Some(mem::take(&mut self.clusters)),
+                _ => None,
+            };
+            if self.items_changed {
+                self.items = self.sources.values().flatten().cloned().collect();
+                self.items_changed = false;
+            }
+            self.zoom_level = Some(level);
+
+            // the radius is the same within the zoom level, so the clusters don't change while zooming
+            let radius = Self::RADIUS_PX * world_per_pixel * 2f64.powf(level as f64 - zoom_level as f64);
+            let positions: Vec<Coord> = self.items.iter().map(|item| item.position).collect();
+            self.clusters = cluster_points(&positions, radius);
+            self.animation = previous.and_then(|previous| self.expand_animation(&previous, now));
+            self.draw(now);
+        } else if self.animation.is_some() {
+            self.draw(now);
+        }
+    }
+
+    fn expand_animation(&self, previous: &[Cluster], now: Instant) -> Option<ExpandAnimation> {
+        let mut previous_positions = vec![Coord::zero(); self.items.len()];
+        previous.iter().for_each(|cluster| {
+            cluster
+                .members
+                .iter()
+                .for_each(|&member| previous_positions[member] = cluster.position)
+        });
+        let from: Vec<Coord> = self
+            .clusters
+            .iter()
+            .map(|cluster| previous_positions[cluster.members[0]])
+            .collect();
+        let moves = self
+            .clusters
+            .iter()
+            .zip(&from)
+            .any(|(cluster, from)| cluster.position != *from);
+        moves.then_some(ExpandAnimation { start: now, from })
+    }
+
+    fn draw(&mut self, now: Instant) {
+        // ease out, the clusters slow down when they reach their places
+        let progress = self.animation.as_ref().map(|animation| {
+            let t = now.duration_since(animation.start).as_secs_f64() / Self::ANIMATION_DURATION.as_secs_f64();
+            t.min(1.0)
+        });
+        let eased = progress.map(|t| 1.0 - (1.0 - t).powi(3));
+        let markers: Vec<(Coord, ClusterMarker)> = self
+            .clusters
+            .iter()
+            .enumerate()
+            .map(|(i, cluster)| {
+                let position = match (&self.animation, eased) {
+                    (Some(animation), Some(eased)) => {
+                        animation.from[i] + (cluster.position - animation.from[i]) * eased
+                    }
+                    _ => cluster.position,
+                };
+                let marker = match cluster.members.as_slice() {
+                    [member] => ClusterMarker::Item(self.items[*member].clone()),
+                    members => ClusterMarker::Cluster {
+                        count: members.len(),
+                        id: members[0] as u64,
+                    },
+                };
+                (position, marker)
+            })
+            .collect();
+        if progress.is_some_and(|t| t >= 1.0) {
+            self.animation = None;
+        }
+
+        // the new group is added before the old one is removed, so there is no frame without the points
+        let old_key = if markers.is_empty() {
+            self.group_key.take()
+        } else {
+            let group = ClusterGroup::new(markers);
+            self.group_version += 1;
+            let group_key = format!("clusters_{}", self.group_version);
+            self.api.add_render_group(
+                group_key.clone(),
+                Self::LAYER,
+                SpatialData::transform(group.origin()),
+                Box::new(group),
+            );
+            self.group_key.replace(group_key)
+        };
+        if let Some(old_key) = old_key {
+            self.api.clear_render_groups(HashSet::from([old_key]));
+        }
+    }
+}
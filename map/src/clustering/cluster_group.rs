@@ -0,0 +1,111 @@
+use crate::annotation::annotation_group::Icon;
+use crate::style::style_sheet::LABEL_STYLE_ID;
+use cgmath::{Vector2, Vector3};
+use geo_types::Coord;
+use renderer::canvas_api::CanvasApi;
+use renderer::geometry_data::{GeometryData, SvgData, TextData};
+use renderer::render_group::RenderGroup;
+use renderer::styles::style_id::StyleId;
+use seahash::hash;
+
+/// Point which is drawn with its own icon until it's clustered.
+#[derive(Clone, Debug)]
+pub struct ClusterItem {
+    /// World position
+    pub position: Coord,
+    pub icon: Icon,
+    pub size: f32,
+    pub style_id: StyleId,
+    /// Drawn under the icon when the point is not clustered
+    pub label: Option<String>,
+}
+
+pub(crate) enum ClusterMarker {
+    Item(ClusterItem),
+    Cluster { count: usize, id: u64 },
+}
+
+/// Draws the clusters and the single points, the positions are relative to the first one.
+pub struct ClusterGroup {
+    markers: Vec<(Coord, ClusterMarker)>,
+}
+
+impl ClusterGroup {
+    const CLUSTER_SVG: &'static [u8] = include_bytes!("../../svg/just_circle.svg");
+    const LABEL_SIZE: f32 = 28.0;
+
+    pub(crate) fn new(markers: Vec<(Coord, ClusterMarker)>) -> ClusterGroup {
+        ClusterGroup { markers }
+    }
+
+    pub fn origin(&self) -> Vector3<f64> {
+        self.markers
+            .first()
+            .map_or(Vector3::new(0.0, 0.0, 0.0), |(position, _)| Vector3::new(position.x, position.y, 0.0))
+    }
+
+    // the canvas caches the icon meshes by name, so every size has its own name
+    fn cluster_icon(count: usize) -> (Icon, f32) {
+        match count {
+            ..10 => (("cluster_small", Self::CLUSTER_SVG), 36.0),
+            10..100 => (("cluster_medium", Self::CLUSTER_SVG), 46.0),
+            _ => (("cluster_large", Self::CLUSTER_SVG), 56.0),
+        }
+    }
+}
+
+impl RenderGroup for ClusterGroup {
+    fn content(&mut self, canvas: &mut CanvasApi) {
+        let origin = self.origin();
+        for (position, marker) in &self.markers {
+            let position = Vector3::new(position.x - origin.x, position.y - origin.y, 0.0);
+            match marker {
+                ClusterMarker::Item(item) => {
+                    canvas.geometry_data(GeometryData::Svg(SvgData {
+                        icon: item.icon,
+                        position: position.cast().unwrap(),
+                        size: item.size,
+                        style_id: item.style_id.clone(),
+                        with_collision: false,
+                        rank: 0,
+                        symbol_id: None,
+                    }));
+                    if let Some(label) = &item.label {
+                        canvas.geometry_data(GeometryData::Text(TextData {
+                            id: hash(format!("cluster_item_{label}_{}_{}", position.x, position.y).as_bytes()),
+                            text: label.clone(),
+                            screen_offset: Vector2::new(0.0, (item.size + Self::LABEL_SIZE) / 2.0),
+                            size: Self::LABEL_SIZE,
+                            positions: vec![position.cast().unwrap()],
+                            style_id: LABEL_STYLE_ID,
+                            rank: 0,
+                            symbol_id: None,
+                        }));
+                    }
+                }
+                ClusterMarker::Cluster { count, id } => {
+                    let (icon, size) = Self::cluster_icon(*count);
+                    canvas.geometry_data(GeometryData::Svg(SvgData {
+                        icon,
+                        position: position.cast().unwrap(),
+                        size,
+                        style_id: StyleId("cluster"),
+                        with_collision: false,
+                        rank: 0,
+                        symbol_id: None,
+                    }));
+                    canvas.geometry_data(GeometryData::Text(TextData {
+                        id: hash(format!("cluster_{id}_{count}").as_bytes()),
+                        text: count.to_string(),
+                        screen_offset: Vector2::new(0.0, 0.0),
+                        size: Self::LABEL_SIZE,
+                        positions: vec![position.cast().unwrap()],
+                        style_id: LABEL_STYLE_ID,
+                        rank: 0,
+                        symbol_id: None,
+                    }));
+                }
+            }
+        }
+    }
+}
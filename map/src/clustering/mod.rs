@@ -0,0 +1,54 @@
+pub mod cluster_controller;
+pub mod cluster_group;
+
+use geo_types::Coord;
+use std::collections::HashMap;
+
+/// Points merged into one marker, the position is the center of its points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cluster {
+    pub position: Coord,
+    /// Indices of the clustered points, the first one started the cluster
+    pub members: Vec<usize>,
+}
+
+/// Greedy clustering: every point which is not clustered yet takes all the free points within
+/// `radius` of it, so a point is never further than `radius` from the one which started its cluster.
+/// The grid of `radius` cells keeps it linear for evenly spread points.
+pub fn cluster_points(points: &[Coord], radius: f64) -> Vec<Cluster> {
+    let cell = |p: &Coord| ((p.x / radius).floor() as i64, (p.y / radius).floor() as i64);
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    points.iter().enumerate().for_each(|(i, p)| grid.entry(cell(p)).or_default().push(i));
+
+    let mut clustered = vec![false; points.len()];
+    let mut clusters = vec![];
+    for (i, seed) in points.iter().enumerate() {
+        if clustered[i] {
+            continue;
+        }
+        let (cell_x, cell_y) = cell(seed);
+        let mut members = vec![];
+        for x in cell_x - 1..=cell_x + 1 {
+            for y in cell_y - 1..=cell_y + 1 {
+                let Some(indices) = grid.get(&(x, y)) else {
+                    continue;
+                };
+                for &j in indices {
+                    let (dx, dy) = (points[j].x - seed.x, points[j].y - seed.y);
+                    if !clustered[j] && dx * dx + dy * dy <= radius * radius {
+                        clustered[j] = true;
+                        members.push(j);
+                    }
+                }
+            }
+        }
+        // the seed first, the others in the order of the points
+        members.sort_unstable_by_key(|&j| (j != i, j));
+        let sum = members.iter().fold(Coord::zero(), |sum, &j| sum + points[j]);
+        clusters.push(Cluster {
+            position: sum / members.len() as f64,
+            members,
+        });
+    }
+    clusters
+}
@@ -0,0 +1,252 @@
+use crate::annotation::annotation_group::Icon;
+use crate::feature_processor::ShashlikFeatureProcessor;
+use crate::kml_viewer_group::KmlGroup;
+use crate::overlay::{OverlayPainter, OverlayShape, OverlayStyle, OverlayStyles, for_each_point};
+use crate::style::style_sheet::{Color, LABEL_STYLE_ID};
+use crate::tiles::tile_data::{FeatureKind, TileFeature};
+use anyhow::{Context, bail};
+use cgmath::{Vector2, Vector3};
+use geo_types::{
+    Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon,
+};
+use renderer::canvas_api::CanvasApi;
+use renderer::geometry_data::{GeometryData, SvgData, TextData};
+use renderer::render_group::RenderGroup;
+use renderer::styles::render_style::RenderStyle;
+use renderer::styles::style_id::StyleId;
+use log::error;
+use seahash::hash;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum GeoJson {
+    FeatureCollection { features: Vec<GeoJsonFeature> },
+    Feature(GeoJsonFeature),
+}
+
+#[derive(Deserialize)]
+struct GeoJsonFeature {
+    geometry: Option<GeoJsonGeometry>,
+    #[serde(default)]
+    properties: Option<Map<String, Value>>,
+}
+
+// `[lon, lat]`, the altitude is ignored
+type Position = Vec<f64>;
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum GeoJsonGeometry {
+    Point { coordinates: Position },
+    MultiPoint { coordinates: Vec<Position> },
+    LineString { coordinates: Vec<Position> },
+    MultiLineString { coordinates: Vec<Vec<Position>> },
+    Polygon { coordinates: Vec<Vec<Position>> },
+    MultiPolygon { coordinates: Vec<Vec<Vec<Position>>> },
+    GeometryCollection { geometries: Vec<GeoJsonGeometry> },
+}
+
+#[derive(Clone, Debug)]
+struct GeoJsonPoints {
+    icon: Icon,
+    style_id: StyleId,
+}
+
+/// Features of a GeoJSON FeatureCollection styled by their properties:
+/// `stroke`, `stroke-width`, `stroke-opacity`, `fill`, `fill-opacity`, `icon` and `label` (or `name`).
+/// The icons are the built-in `circle`, `parking`, `toilets`, `train_station` and `traffic_light`.
+#[derive(Clone)]
+pub struct GeoJsonGroup {
+    layer: String,
+    painter: OverlayPainter,
+    shapes: Vec<(OverlayShape, GeoJsonPoints)>,
+    render_styles: OverlayStyles,
+}
+
+impl GeoJsonGroup {
+    const ICON_SIZE: f32 = 20.0;
+    const LABEL_SIZE: f32 = 30.0;
+
+    /// Reads the file, see `parse`.
+    pub fn load(layer: &str, path: &Path, converter: Box<dyn Fn(&Point) -> Point>) -> anyhow::Result<GeoJsonGroup> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(layer, &json, converter)
+    }
+
+    /// Parses a FeatureCollection or a single Feature. The `layer` names the labels of the features.
+    pub fn parse(layer: &str, json: &str, converter: Box<dyn Fn(&Point) -> Point>) -> anyhow::Result<GeoJsonGroup> {
+        let features = match serde_json::from_str(json).context("Invalid GeoJSON")? {
+            GeoJson::FeatureCollection { features } => features,
+            GeoJson::Feature(feature) => vec![feature],
+        };
+        let mut group = GeoJsonGroup {
+            layer: layer.to_string(),
+            painter: OverlayPainter::new(&[]),
+            shapes: vec![],
+            render_styles: OverlayStyles::default(),
+        };
+        for (index, feature) in features.into_iter().enumerate() {
+            let Some(geometry) = feature.geometry else {
+                continue;
+            };
+            let geometry =
+                Self::geometry(geometry, &*converter).with_context(|| format!("Invalid geometry of feature {index}"))?;
+            let properties = feature.properties.unwrap_or_default();
+            let name = ["label", "name"]
+                .iter()
+                .find_map(|key| properties.get(*key).and_then(Value::as_str))
+                .map(str::to_string);
+            let (style, points) = group.feature_style(&properties)?;
+            group.shapes.push((OverlayShape { name, geometry, style }, points));
+        }
+        let shapes: Vec<OverlayShape> = group.shapes.iter().map(|(shape, _)| shape.clone()).collect();
+        group.painter = OverlayPainter::new(&shapes);
+        Ok(group)
+    }
+
+    fn geometry(geometry: GeoJsonGeometry, converter: &dyn Fn(&Point) -> Point) -> anyhow::Result<Geometry> {
+        let coord = |position: &Position| -> anyhow::Result<Coord> {
+            match position[..] {
+                [lon, lat, ..] => Ok(converter(&Point::new(lon, lat)).0),
+                _ => bail!("Position with less than 2 numbers"),
+            }
+        };
+        let line = |positions: &[Position]| -> anyhow::Result<LineString> {
+            positions.iter().map(coord).collect::<anyhow::Result<Vec<_>>>().map(LineString::new)
+        };
+        let polygon = |rings: &[Vec<Position>]| -> anyhow::Result<Polygon> {
+            let mut rings = rings.iter().map(|ring| line(ring));
+            let exterior = rings.next().context("Polygon without rings")??;
+            Ok(Polygon::new(exterior, rings.collect::<anyhow::Result<_>>()?))
+        };
+        Ok(match geometry {
+            GeoJsonGeometry::Point { coordinates } => Geometry::Point(Point(coord(&coordinates)?)),
+            GeoJsonGeometry::MultiPoint { coordinates } => Geometry::MultiPoint(MultiPoint(
+                coordinates.iter().map(|position| coord(position).map(Point)).collect::<anyhow::Result<_>>()?,
+            )),
+            GeoJsonGeometry::LineString { coordinates } => Geometry::LineString(line(&coordinates)?),
+            GeoJsonGeometry::MultiLineString { coordinates } => Geometry::MultiLineString(MultiLineString(
+                coordinates.iter().map(|positions| line(positions)).collect::<anyhow::Result<_>>()?,
+            )),
+            GeoJsonGeometry::Polygon { coordinates } => Geometry::Polygon(polygon(&coordinates)?),
+            GeoJsonGeometry::MultiPolygon { coordinates } => Geometry::MultiPolygon(MultiPolygon(
+                coordinates.iter().map(|rings| polygon(rings)).collect::<anyhow::Result<_>>()?,
+            )),
+            GeoJsonGeometry::GeometryCollection { geometries } => Geometry::GeometryCollection(GeometryCollection(
+                geometries
+                    .into_iter()
+                    .map(|geometry| Self::geometry(geometry, converter))
+                    .collect::<anyhow::Result<_>>()?,
+            )),
+        })
+    }
+
+    // the defaults are `geojson_line`, `geojson_polygon` and `geojson_point` of the map style file
+    fn feature_style(&mut self, properties: &Map<String, Value>) -> anyhow::Result<(OverlayStyle, GeoJsonPoints)> {
+        let mut style = OverlayStyle::new(StyleId("geojson_line"), Some(StyleId("geojson_polygon")));
+        let mut points = GeoJsonPoints {
+            icon: ("geojson_circle", KmlGroup::CIRCLE_SVG),
+            style_id: StyleId("geojson_point"),
+        };
+        if let Some(width) = properties.get("stroke-width").and_then(Value::as_f64) {
+            style.width = width as f32;
+        }
+        if let Some(color) = Self::color(properties, "stroke", "stroke-opacity")? {
+            style.line = self.render_styles.line_style(color);
+        }
+        if let Some(color) = Self::color(properties, "fill", "fill-opacity")? {
+            let style_id = self.render_styles.fill_style(color);
+            style.fill = Some(style_id.clone());
+            points.style_id = style_id;
+        }
+        if let Some(icon) = properties.get("icon").and_then(Value::as_str) {
+            points.icon = match icon {
+                "circle" => ("geojson_circle", KmlGroup::CIRCLE_SVG),
+                "parking" => ("geojson_parking", ShashlikFeatureProcessor::PARKING_SVG),
+                "toilets" => ("geojson_toilets", ShashlikFeatureProcessor::TOILETS_SVG),
+                "train_station" => ("geojson_train_station", ShashlikFeatureProcessor::TRAIN_STATION_SVG),
+                "traffic_light" => ("geojson_traffic_light", ShashlikFeatureProcessor::TRAFFIC_LIGHT_SVG),
+                icon => {
+                    error!("Unknown GeoJSON icon {icon}");
+                    points.icon
+                }
+            };
+        }
+        Ok((style, points))
+    }
+
+    // `#rrggbb` or `#rrggbbaa`, the opacity property overrides the alpha
+    fn color(properties: &Map<String, Value>, key: &str, opacity_key: &str) -> anyhow::Result<Option<[f32; 4]>> {
+        let Some(color) = properties.get(key).and_then(Value::as_str) else {
+            return Ok(None);
+        };
+        let Color(mut color) = color.parse().map_err(anyhow::Error::msg)?;
+        if let Some(opacity) = properties.get(opacity_key).and_then(Value::as_f64) {
+            color[3] = opacity as f32;
+        }
+        Ok(Some(color))
+    }
+
+    // the features with the same color share the style
+    /// Styles of the features which have to be added to the renderer.
+    pub fn render_styles(&self) -> impl Iterator<Item = (StyleId, RenderStyle)> + '_ {
+        self.render_styles.iter()
+    }
+
+    pub fn origin(&self) -> Vector3<f64> {
+        self.painter.origin()
+    }
+
+    /// Features in world coordinates.
+    pub fn features(&self) -> Vec<TileFeature> {
+        self.shapes
+            .iter()
+            .map(|(shape, _)| TileFeature {
+                kind: FeatureKind::Overlay,
+                name: shape.name.clone(),
+                geometry: shape.geometry.clone(),
+            })
+            .collect()
+    }
+}
+
+impl RenderGroup for GeoJsonGroup {
+    fn content(&mut self, canvas: &mut CanvasApi) {
+        for (index, (shape, points)) in self.shapes.iter().enumerate() {
+            self.painter.draw_geometry(canvas, &shape.geometry, &shape.style);
+            let id = hash(format!("geojson_{}_{index}", self.layer).as_bytes());
+            if let Some(name) = &shape.name {
+                self.painter.draw_label(canvas, id, name, &shape.geometry);
+            }
+            let mut point_index = 0;
+            for_each_point(&shape.geometry, &mut |point| {
+                let position = self.painter.relative(&point.0);
+                canvas.geometry_data(GeometryData::Svg(SvgData {
+                    icon: points.icon,
+                    position: position.cast().unwrap(),
+                    size: Self::ICON_SIZE,
+                    style_id: points.style_id.clone(),
+                    with_collision: false,
+                    rank: 0,
+                    symbol_id: None,
+                }));
+                if let Some(name) = &shape.name {
+                    canvas.geometry_data(GeometryData::Text(TextData {
+                        id: id.wrapping_add(point_index),
+                        text: name.clone(),
+                        screen_offset: Vector2::new(0.0, (Self::ICON_SIZE + Self::LABEL_SIZE) / 2.0),
+                        size: Self::LABEL_SIZE,
+                        positions: vec![position],
+                        style_id: LABEL_STYLE_ID,
+                        rank: 0,
+                        symbol_id: None,
+                    }));
+                }
+                point_index += 1;
+            });
+        }
+    }
+}
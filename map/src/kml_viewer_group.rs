@@ -1,76 +1,201 @@
+use crate::clustering::cluster_group::ClusterItem;
+use crate::overlay::{OverlayPainter, OverlayShape, OverlayStyle, OverlayStyles, for_each_point};
+use crate::tiles::tile_data::{FeatureKind, TileFeature};
+use anyhow::Context;
 use cgmath::Vector3;
-use geo_types::{Geometry, GeometryCollection, Point};
-use kml::KmlReader;
+use geo::MapCoords;
+use geo_types::{Geometry, Point};
+use kml::types::Style;
+use kml::{Kml, KmlReader};
 use log::error;
 use renderer::canvas_api::CanvasApi;
-use renderer::geometry_data::{GeometryData, SvgData};
 use renderer::render_group::RenderGroup;
+use renderer::styles::render_style::RenderStyle;
 use renderer::styles::style_id::StyleId;
-use std::path::PathBuf;
+use seahash::hash;
+use std::collections::HashMap;
+use std::path::Path;
 
+// the contents of the document before the styles are resolved
+#[derive(Default)]
+struct KmlContents {
+    styles: HashMap<String, Style>,
+    // style map id to the id of its normal style
+    style_maps: HashMap<String, String>,
+    placemarks: Vec<(Option<String>, Option<String>, kml::types::Geometry)>,
+}
+
+/// Placemarks of a KML or KMZ file: the points are drawn with the clusters,
+/// the lines and the polygons with the colors and the widths of the KML styles.
+#[derive(Clone)]
 pub struct KmlGroup {
-    pub collection: GeometryCollection<f64>,
+    layer: String,
+    painter: OverlayPainter,
+    placemarks: Vec<OverlayShape>,
+    render_styles: OverlayStyles,
 }
 
 impl KmlGroup {
     pub const CIRCLE_SVG: &'static [u8] = include_bytes!("../svg/just_circle.svg");
 
-    pub fn new(path_buf: PathBuf, converter: Box<dyn Fn(&Point) -> Point>) -> KmlGroup {
-        let mut kml_reader = KmlReader::<_, f64>::from_path(path_buf).unwrap();
-        let kml_data = kml_reader.read().unwrap();
+    /// Reads the file, `.kmz` archives by the extension. The `layer` names the labels of the file.
+    pub fn load(layer: &str, path: &Path, converter: Box<dyn Fn(&Point) -> Point>) -> anyhow::Result<KmlGroup> {
+        let is_kmz = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("kmz"));
+        let kml = if is_kmz {
+            KmlReader::<_, f64>::from_kmz_path(path).and_then(|mut reader| reader.read())
+        } else {
+            KmlReader::<_, f64>::from_path(path).and_then(|mut reader| reader.read())
+        }
+        .with_context(|| format!("Failed to read {}", path.display()))?;
 
-        let mut collection: GeometryCollection<f64> = kml_data.try_into().unwrap();
-        Self::convert_collection(&mut collection, &converter);
-        KmlGroup { collection }
+        let mut contents = KmlContents::default();
+        Self::collect(kml, &mut contents);
+        let mut group = KmlGroup {
+            layer: layer.to_string(),
+            painter: OverlayPainter::new(&[]),
+            placemarks: vec![],
+            render_styles: OverlayStyles::default(),
+        };
+        for (name, style_url, geometry) in std::mem::take(&mut contents.placemarks) {
+            let geometry = match Geometry::try_from(geometry) {
+                Ok(geometry) => geometry.map_coords(|coord| converter(&Point(coord)).0),
+                Err(e) => {
+                    error!("Unsupported KML geometry: {e}");
+                    continue;
+                }
+            };
+            let style = group.placemark_style(&contents, style_url.as_deref());
+            group.placemarks.push(OverlayShape { name, geometry, style });
+        }
+        group.painter = OverlayPainter::new(&group.placemarks);
+        Ok(group)
     }
 
-    fn convert_collection(
-        collection: &mut GeometryCollection<f64>,
-        converter: &Box<dyn Fn(&Point) -> Point>,
-    ) {
-        collection.0.iter_mut().for_each(|geom| match geom {
-            Geometry::Point(point) => {
-                *point = converter(point);
+    fn collect(kml: Kml<f64>, contents: &mut KmlContents) {
+        use kml::types::Geometry as KmlGeometry;
+        let mut geometry = |geometry| contents.placemarks.push((None, None, geometry));
+        match kml {
+            Kml::Point(point) => geometry(KmlGeometry::Point(point)),
+            Kml::LineString(line) => geometry(KmlGeometry::LineString(line)),
+            Kml::LinearRing(ring) => geometry(KmlGeometry::LinearRing(ring)),
+            Kml::Polygon(polygon) => geometry(KmlGeometry::Polygon(polygon)),
+            Kml::MultiGeometry(multi_geometry) => geometry(KmlGeometry::MultiGeometry(multi_geometry)),
+            Kml::KmlDocument(document) => {
+                document.elements.into_iter().for_each(|kml| Self::collect(kml, contents));
             }
-            Geometry::GeometryCollection(collection) => {
-                Self::convert_collection(collection, converter);
+            Kml::Document { elements, .. } | Kml::Folder { elements, .. } => {
+                elements.into_iter().for_each(|kml| Self::collect(kml, contents));
             }
-            _ => {
-                println!("WARNING: KML contains invalid geometry type {:?}", geom);
+            Kml::Placemark(placemark) => {
+                if let Some(geometry) = placemark.geometry {
+                    contents.placemarks.push((placemark.name, placemark.style_url, geometry));
+                }
             }
-        });
+            Kml::Style(style) => {
+                if let Some(id) = style.id.clone() {
+                    contents.styles.insert(id, style);
+                }
+            }
+            Kml::StyleMap(style_map) => {
+                let normal = style_map.pairs.iter().find(|pair| pair.key == "normal");
+                if let (Some(id), Some(normal)) = (style_map.id.clone(), normal) {
+                    contents
+                        .style_maps
+                        .insert(id, normal.style_url.trim_start_matches('#').to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the defaults are `kml_line` and `kml_polygon` of the map style file
+    fn placemark_style(&mut self, contents: &KmlContents, style_url: Option<&str>) -> OverlayStyle {
+        let mut style = OverlayStyle::new(StyleId("kml_line"), Some(StyleId("kml_polygon")));
+        let Some(id) = style_url.map(|url| url.trim_start_matches('#')) else {
+            return style;
+        };
+        let id = contents.style_maps.get(id).map_or(id, String::as_str);
+        let Some(kml_style) = contents.styles.get(id) else {
+            return style;
+        };
+        if let Some(line) = &kml_style.line {
+            style.width = line.width as f32;
+            if let Some(color) = kml_color(&line.color) {
+                style.line = self.render_styles.line_style(color);
+            }
+        }
+        if let Some(poly) = &kml_style.poly {
+            style.outline = poly.outline;
+            style.fill = match (poly.fill, kml_color(&poly.color)) {
+                (false, _) => None,
+                (true, Some(color)) => Some(self.render_styles.fill_style(color)),
+                (true, None) => style.fill,
+            };
+        }
+        style
+    }
+
+    /// Styles of the file which have to be added to the renderer.
+    pub fn render_styles(&self) -> impl Iterator<Item = (StyleId, RenderStyle)> + '_ {
+        self.render_styles.iter()
+    }
+
+    pub fn origin(&self) -> Vector3<f64> {
+        self.painter.origin()
+    }
+
+    /// Placemarks in world coordinates.
+    pub fn features(&self) -> Vec<TileFeature> {
+        self.placemarks
+            .iter()
+            .map(|placemark| TileFeature {
+                kind: FeatureKind::Placemark,
+                name: placemark.name.clone(),
+                geometry: placemark.geometry.clone(),
+            })
+            .collect()
     }
 
-    fn populate_geometry(
-        collection: &GeometryCollection<f64>,
-        geometry_data: &mut Vec<GeometryData>,
-    ) {
-        collection.iter().for_each(|geom| match geom {
-            Geometry::Point(point) => {
-                geometry_data.push(GeometryData::Svg(SvgData {
+    /// Points of the placemarks drawn with the clusters, see `ClusterController`.
+    pub fn cluster_items(&self) -> Vec<ClusterItem> {
+        let mut items = vec![];
+        self.placemarks.iter().for_each(|placemark| {
+            for_each_point(&placemark.geometry, &mut |point| {
+                items.push(ClusterItem {
+                    position: point.0,
                     icon: ("kml", Self::CIRCLE_SVG),
-                    position: Vector3::new(point.x(), point.y(), 0.0).cast().unwrap(),
                     size: 20.0,
                     style_id: StyleId("kml_dots"),
-                    with_collision: false,
-                }));
-            }
-            Geometry::GeometryCollection(collection) => {
-                Self::populate_geometry(collection, geometry_data);
-            }
-            _ => {
-                error!("collection contains invalid geometry type {:?}", geom);
-            }
+                    label: placemark.name.clone(),
+                })
+            })
         });
+        items
     }
 }
 
+// KML colors are `aabbggrr`
+fn kml_color(color: &str) -> Option<[f32; 4]> {
+    let color = color.trim().trim_start_matches('#');
+    if color.len() != 8 {
+        return None;
+    }
+    let abgr = u32::from_str_radix(color, 16).ok()?;
+    let channel = |shift: u32| ((abgr >> shift) & 0xff) as f32 / 255.0;
+    Some([channel(0), channel(8), channel(16), channel(24)])
+}
+
 impl RenderGroup for KmlGroup {
     fn content(&mut self, canvas: &mut CanvasApi) {
-        let mut geometry_data = vec![];
-        Self::populate_geometry(&self.collection, &mut geometry_data);
-        geometry_data.into_iter().for_each(|data| {
-            canvas.geometry_data(data);
-        });
+        for (index, placemark) in self.placemarks.iter().enumerate() {
+            // the points are drawn with the clusters
+            self.painter.draw_geometry(canvas, &placemark.geometry, &placemark.style);
+            if let Some(name) = &placemark.name {
+                let id = hash(format!("kml_{}_{index}", self.layer).as_bytes());
+                self.painter.draw_label(canvas, id, name, &placemark.geometry);
+            }
+        }
     }
 }
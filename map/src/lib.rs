@@ -2,8 +2,27 @@ extern crate core;
 
 use crate::camera::{Camera, CameraController};
 use crate::route::RouteCosting;
+use crate::route::guidance::GuidanceListener;
+use crate::route::provider::RoutingProvider;
+use crate::route::provider::osrm_provider::OsrmRoutingProvider;
+use crate::route::provider::valhalla_provider::ValhallaRoutingProvider;
+use crate::geojson_group::GeoJsonGroup;
 use crate::kml_viewer_group::KmlGroup;
+use crate::location::dead_reckoning::{
+    DeadReckoning, DeadReckoningConfig, ImuSample, LocationFix,
+};
+use crate::location::recorder::TraceRecorder;
+use crate::matching::road_network::distance;
+use crate::matching::hmm_matcher::{GpsFix, HmmMatcher, MatchedPosition, MatcherConfig};
+use crate::picking::{FeatureIndex, PickedFeature};
 use crate::puck_group::SimplePuck;
+use crate::annotation::Annotation;
+use crate::annotation::annotation_controller::AnnotationController;
+use crate::clustering::cluster_controller::ClusterController;
+use crate::overlay::overlay_controller::OverlayController;
+use crate::style::style_sheet::{StyleDef, StyleSheet, intern};
+use crate::style::style_watcher::StyleWatcher;
+use crate::style::theme::{Theme, ThemeColors, ThemeFade};
 use crate::tiles::tile_data::TileData;
 use crate::tiles::tiles_provider::{TilesMessage, TilesProvider};
 use cgmath::num_traits::clamp;
@@ -13,24 +32,36 @@ use futures::{pin_mut, Stream, StreamExt};
 use geo_types::private_utils::get_bounding_rect;
 use geo_types::{coord, Coord, Point, Rect};
 use geo_types::{LineString, Polygon};
+use log::{error, info};
 use renderer::canvas_api::CanvasApi;
 use renderer::modifier::render_modifier::SpatialData;
 use renderer::render_group::RenderGroup;
 use renderer::renderer_api::RendererApi;
-use renderer::{Renderer, ShashlikRenderer};
-use route::route_controller::RouteController;
+use renderer::{Renderer, ShashlikRenderer, TextBackend, TextHalo};
+use route::route_controller::{LocationConverter, RouteController};
+use std::collections::HashMap;
 use std::mem;
-use std::path::PathBuf;
+use std::path::Path;
 use std::sync::Arc;
 use std::thread::spawn;
+use std::time::{Instant, SystemTime};
+use url::Url;
 use osm::styles::RenderStyle;
 use osm::styles::style_loader::StyleLoader;
 use renderer::styles::style_id::StyleId;
 use wgpu_canvas::wgpu_canvas::WgpuCanvas;
 
 mod camera;
+pub mod annotation;
+pub mod clustering;
 pub mod route;
-mod kml_viewer_group;
+pub mod style;
+pub mod kml_viewer_group;
+pub mod geojson_group;
+mod overlay;
+pub mod location;
+pub mod matching;
+pub mod picking;
 mod puck_group;
 pub mod tiles;
 pub mod mesh_loader;
@@ -41,6 +72,9 @@ pub struct ShashlikMap<T: TilesProvider> {
     camera_controller: CameraController,
     tiles_provider: T,
     route_controller: RouteController,
+    annotation_controller: AnnotationController,
+    cluster_controller: ClusterController,
+    overlay_controller: OverlayController,
     last_area_latlon: Rect,
     current_world_position: Vector3<f64>,
     current_bearing: f64,
@@ -48,6 +82,19 @@ pub struct ShashlikMap<T: TilesProvider> {
     pub temp_color: f32,
     cam_follow_mode: bool,
     screen_params: ScreenParam,
+    style_sheet: Arc<StyleSheet>,
+    style_watcher: Option<StyleWatcher>,
+    // fill colors of the loader and the style sheet, themes are applied on top of them
+    base_colors: HashMap<StyleId, [f32; 4]>,
+    // fill colors sent to the renderer by the themes
+    theme_style_colors: HashMap<StyleId, [f32; 4]>,
+    theme: Theme,
+    theme_fade: Option<ThemeFade>,
+    map_matcher: Option<HmmMatcher>,
+    dead_reckoning: Option<DeadReckoning>,
+    location_clock: Instant,
+    trace_recorder: Option<TraceRecorder>,
+    feature_index: Arc<FeatureIndex>,
 }
 
 struct ScreenParam {
@@ -85,6 +132,9 @@ impl<T: TilesProvider> ShashlikMap<T> {
 
         let renderer = ShashlikRenderer::new(&["puck_layer".to_string()], canvas).await?;
         let tiles_stream = tiles_provider.tiles();
+        let map_matcher = tiles_provider
+            .road_network()
+            .map(|road_network| HmmMatcher::new(road_network, MatcherConfig::default()));
 
         let initial_coord: Coord<f64> = (139.757080078125, 35.68798828125).into();
         let camera_offset = T::lat_lon_to_world(&initial_coord);
@@ -100,18 +150,28 @@ impl<T: TilesProvider> ShashlikMap<T> {
             Box::new(SimplePuck {}),
         );
 
-        Self::run_tiles(renderer.api.clone(), tiles_stream);
+        let feature_index = Arc::new(FeatureIndex::new());
+        Self::run_tiles(renderer.api.clone(), feature_index.clone(), tiles_stream);
 
         let mut camera_controller = CameraController::new();
         camera_controller.pitch = 45.0;
         camera_controller.position = camera_offset;
 
+        let mut route_controller = RouteController::new(renderer.api.clone(), Self::create_location_coord_converter());
+        route_controller.set_offline_routing_provider(tiles_provider.offline_routing_provider());
+        let annotation_controller =
+            AnnotationController::new(renderer.api.clone(), Self::create_location_coord_converter());
+        let cluster_controller = ClusterController::new(renderer.api.clone());
+        let overlay_controller = OverlayController::new(renderer.api.clone(), feature_index.clone());
         let mut map = ShashlikMap {
             renderer: Box::new(renderer),
             camera: cam,
             camera_controller,
             tiles_provider,
-            route_controller: RouteController::new(),
+            route_controller,
+            annotation_controller,
+            cluster_controller,
+            overlay_controller,
             last_area_latlon: Rect::new((0.0, 0.0), (0.0, 0.0)),
             current_world_position: camera_offset.cast().unwrap(),
             current_bearing: 0.0,
@@ -122,9 +182,23 @@ impl<T: TilesProvider> ShashlikMap<T> {
                 width: screen_size.0 as u32,
                 height: screen_size.1 as u32,
             },
+            style_sheet: Arc::new(StyleSheet::default_sheet()),
+            style_watcher: None,
+            base_colors: HashMap::new(),
+            theme_style_colors: HashMap::new(),
+            theme: Theme::day(),
+            theme_fade: None,
+            map_matcher,
+            dead_reckoning: Some(DeadReckoning::new(DeadReckoningConfig::default())),
+            location_clock: Instant::now(),
+            trace_recorder: None,
+            feature_index,
         };
-        map.set_lat_lon_bearing(initial_coord.y, initial_coord.x, Some(0f32));
+        // not a real fix, the dead reckoning doesn't start from it
+        map.move_puck(initial_coord.y, initial_coord.x, Some(0f32), false);
         map.load_styles();
+        map.apply_render_styles();
+        map.apply_theme_colors(&ThemeColors::resolve(&map.theme, &map.base_colors));
         Ok(map)
     }
 
@@ -137,6 +211,7 @@ impl<T: TilesProvider> ShashlikMap<T> {
 
     fn run_tiles(
         renderer_api: Arc<RendererApi>,
+        feature_index: Arc<FeatureIndex>,
         tiles_stream: impl Stream<Item = TilesMessage> + Send + 'static,
     ) {
         spawn(move || {
@@ -148,7 +223,12 @@ impl<T: TilesProvider> ShashlikMap<T> {
                         None => break,
                         Some(msg) => match msg {
                             TilesMessage::TilesData(data) => {
-                                data.into_iter().for_each(|item| {
+                                data.into_iter().for_each(|mut item| {
+                                    feature_index.insert(
+                                        item.key.to_string(),
+                                        coord! {x: item.position.x, y: item.position.y},
+                                        mem::take(&mut item.features),
+                                    );
                                     renderer_api.add_render_group(
                                         item.key.to_string(),
                                         0,
@@ -158,6 +238,7 @@ impl<T: TilesProvider> ShashlikMap<T> {
                                 });
                             }
                             TilesMessage::ToRemove(set) => {
+                                feature_index.remove(&set);
                                 renderer_api.clear_render_groups(set);
                             }
                         },
@@ -175,23 +256,69 @@ impl<T: TilesProvider> ShashlikMap<T> {
     }
 
     pub fn update_and_render(&mut self) {
+        self.update();
+
+        self.renderer.render().unwrap();
+    }
+
+    /// Same as `update_and_render` but reads the frame back as RGBA8 pixels,
+    /// requires a canvas backed by a texture, e.g. `TextureCanvas`.
+    pub fn update_and_render_to_rgba(&mut self) -> anyhow::Result<Vec<u8>> {
+        self.update();
+
+        self.renderer.render_to_rgba()
+    }
+
+    fn update(&mut self) {
         self.camera_controller.update_camera(&mut self.camera);
 
+        self.update_dead_reckoning();
+        self.route_controller.update();
         self.update_entities();
+        self.update_clusters();
 
+        self.renderer.set_zoom_level(self.fractional_zoom_level());
         self.renderer.update(
             self.camera.build_view_projection_matrix(),
             self.camera.offset,
         );
 
+        self.update_style_sheet();
+        self.update_theme();
+
         self.fetch_tiles();
+    }
 
-        self.renderer.render().unwrap();
+    fn update_clusters(&mut self) {
+        if self.cluster_controller.is_empty() {
+            return;
+        }
+        // world units between the screen center and the point 100 pixels to the right of it
+        let center = self.screen_params.center();
+        let world = |(lat, lon): (f64, f64)| T::lat_lon_to_world(&coord! {x: lon, y: lat});
+        let (Some(from), Some(to)) = (
+            self.screen_point_to_lat_lon(center.x, center.y),
+            self.screen_point_to_lat_lon(center.x + 100.0, center.y),
+        ) else {
+            return;
+        };
+        let world_per_pixel = distance(&world(from), &world(to)) / 100.0;
+        self.cluster_controller
+            .update(self.fractional_zoom_level(), world_per_pixel, Instant::now());
     }
 
-    fn fetch_tiles(&mut self) {
+    fn zoom_level(&self) -> i32 {
+        self.fractional_zoom_level().round() as i32
+    }
+
+    // style zoom stops are interpolated with it, so styles change smoothly while zooming
+    fn fractional_zoom_level(&self) -> f32 {
         let zoom_level = self.camera_controller.camera_z / 100.0;
-        let zoom_level = (zoom_level.log2().round() as i32).max(0);
+        (zoom_level.log2() as f32).max(0.0)
+    }
+
+    fn fetch_tiles(&mut self) {
+        let zoom_level = self.zoom_level();
         let p1 = self.clip_to_latlon(&coord! {x: -1.0, y: -1.0}).unwrap();
         let p2 = self.clip_to_latlon(&coord! {x: 1.0, y: -1.0}).unwrap();
         let p3 = self.clip_to_latlon(&coord! {x: 1.0, y: 1.0}).unwrap();
@@ -214,11 +341,19 @@ impl<T: TilesProvider> ShashlikMap<T> {
 
         let cam_zoom = self.camera_controller.forward_len / 100.0;
 
-        self.renderer
-            .api
-            .update_spatial_data("route".to_string(), move |spatial_data| {
+        if let Some(route_group_key) = self.route_controller.route_group_key() {
+            self.renderer
+                .api
+                .update_spatial_data(route_group_key.to_string(), move |spatial_data| {
+                    spatial_data.normal_scale = (cam_zoom / 2.5).max(1.0);
+                });
+        }
+        // the KML and GeoJSON lines are widened like the routes
+        self.overlay_controller.group_keys().for_each(|key| {
+            self.renderer.api.update_spatial_data(key, move |spatial_data| {
                 spatial_data.normal_scale = (cam_zoom / 2.5).max(1.0);
             });
+        });
 
         self.renderer
             .api
@@ -292,8 +427,119 @@ impl<T: TilesProvider> ShashlikMap<T> {
         }
     }
 
+    /// Moves the puck, the position is snapped to the most likely road if the tiles provider keeps roads.
     pub fn set_lat_lon_bearing(&mut self, lat: f64, lon: f64, bearing: Option<f32>) {
-        self.route_controller.set_current_lat_lon((lat, lon));
+        self.set_location(lat, lon, bearing, None);
+    }
+
+    /// Same as `set_lat_lon_bearing`, the speed in meters per second is used to extrapolate
+    /// the position when the GPS is lost. It's estimated from the previous fixes if it's `None`.
+    pub fn set_location(&mut self, lat: f64, lon: f64, bearing: Option<f32>, speed: Option<f32>) {
+        let time = self.location_time();
+        if let Some(dead_reckoning) = self.dead_reckoning.as_mut() {
+            dead_reckoning.on_fix(&LocationFix {
+                lat,
+                lon,
+                bearing,
+                speed,
+                time,
+            });
+        }
+        self.record_location(lat, lon, bearing, speed);
+        self.move_puck(lat, lon, bearing, false);
+    }
+
+    /// Writes every fix passed to `set_location` into a NMEA log until it's stopped.
+    pub fn start_trace_recording(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.trace_recorder = Some(TraceRecorder::create(path)?);
+        Ok(())
+    }
+
+    pub fn stop_trace_recording(&mut self) {
+        self.trace_recorder = None;
+    }
+
+    pub fn is_recording_trace(&self) -> bool {
+        self.trace_recorder.is_some()
+    }
+
+    fn record_location(&mut self, lat: f64, lon: f64, bearing: Option<f32>, speed: Option<f32>) {
+        let Some(recorder) = self.trace_recorder.as_mut() else {
+            return;
+        };
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let fix = LocationFix {
+            lat,
+            lon,
+            bearing,
+            speed,
+            time,
+        };
+        if let Err(err) = recorder.record(&fix) {
+            error!("Trace recording is stopped: {err:#}");
+            self.trace_recorder = None;
+        }
+    }
+
+    /// Gyroscope yaw rate in degrees per second clockwise and the optional forward acceleration
+    /// in meters per second squared, they steer the dead reckoning.
+    pub fn push_imu_sample(&mut self, yaw_rate: f32, forward_acceleration: Option<f32>) {
+        let time = self.location_time();
+        if let Some(dead_reckoning) = self.dead_reckoning.as_mut() {
+            dead_reckoning.on_imu(&ImuSample {
+                yaw_rate,
+                forward_acceleration,
+                time,
+            });
+        }
+    }
+
+    /// Dead reckoning is on by default.
+    pub fn set_dead_reckoning_enabled(&mut self, enabled: bool) {
+        self.dead_reckoning = match (enabled, self.dead_reckoning.take()) {
+            (true, None) => Some(DeadReckoning::new(DeadReckoningConfig::default())),
+            (true, dead_reckoning) => dead_reckoning,
+            (false, _) => None,
+        };
+    }
+
+    fn location_time(&self) -> f64 {
+        self.location_clock.elapsed().as_secs_f64()
+    }
+
+    // Moves the puck every frame while the GPS is lost, the road keeps the extrapolation on track
+    fn update_dead_reckoning(&mut self) {
+        let time = self.location_time();
+        let Some(estimated) = self
+            .dead_reckoning
+            .as_mut()
+            .and_then(|dead_reckoning| dead_reckoning.predict(time))
+        else {
+            return;
+        };
+        let matched = self.move_puck(estimated.lat, estimated.lon, Some(estimated.bearing), true);
+        if let (Some(dead_reckoning), Some(matched)) = (self.dead_reckoning.as_mut(), matched) {
+            dead_reckoning.constrain(matched.lat, matched.lon, matched.bearing);
+        }
+    }
+
+    // returns the road position if the location is matched to a road, the predicted locations
+    // are only snapped to the last matched road and don't change the matched fixes.
+    // The route progress follows the puck, so it keeps moving with the predictions, e.g. in tunnels
+    fn move_puck(&mut self, lat: f64, lon: f64, bearing: Option<f32>, is_predicted: bool) -> Option<MatchedPosition> {
+        let fix = GpsFix { lat, lon, bearing };
+        let matched = self.map_matcher.as_mut().and_then(|matcher| match is_predicted {
+            true => matcher.snap(&fix),
+            false => matcher.push(&fix),
+        });
+        let (lat, lon, bearing) = match &matched {
+            Some(matched) => (matched.lat, matched.lon, Some(matched.bearing)),
+            None => (lat, lon, bearing),
+        };
+        self.route_controller.set_current_lat_lon((lat, lon), bearing);
         let position = T::lat_lon_to_world(&coord! {x: lon, y: lat});
         self.current_world_position = Vector3::new(position.x, position.y, 0.0);
         if let Some(bearing) = bearing {
@@ -304,36 +550,139 @@ impl<T: TilesProvider> ShashlikMap<T> {
             }
             self.current_bearing += rot_diff % 360.0;
         }
+        matched
     }
 
-    pub fn create_route_to_from_screen_center(&self, route_costing: RouteCosting) {
+    /// Map matching is on by default when the tiles provider keeps roads.
+    pub fn set_map_matching_enabled(&mut self, enabled: bool) {
+        self.map_matcher = match (enabled, self.map_matcher.take()) {
+            (true, None) => self
+                .tiles_provider
+                .road_network()
+                .map(|road_network| HmmMatcher::new(road_network, MatcherConfig::default())),
+            (true, matcher) => matcher,
+            (false, _) => None,
+        };
+    }
+
+    pub fn create_route_to_from_screen_center(&mut self, route_costing: RouteCosting, alternatives: usize) {
         let center = self.clip_to_latlon(&coord! {x: 0.0, y: 0.0}).unwrap();
-        self.create_route_to(center.into(), route_costing);
+        self.create_route_to((center.y, center.x), route_costing, alternatives);
     }
 
     pub fn create_route_to_screen_point(
-        &self,
+        &mut self,
         point_x: f32,
         point_y: f32,
         route_costing: RouteCosting,
+        alternatives: usize,
     ) {
+        let to_lat_lon = self.screen_point_to_lat_lon(point_x, point_y).unwrap();
+        self.create_route_to(to_lat_lon, route_costing, alternatives);
+    }
+
+    /// (lat, lon) on the ground under the screen point in pixels, none above the horizon.
+    pub fn screen_point_to_lat_lon(&self, point_x: f32, point_y: f32) -> Option<(f64, f64)> {
         let clip = coord! {x: (point_x / self.screen_params.width as f32) as f64,
         y: (point_y / self.screen_params.height as f32) as f64};
         let clip = coord! { x: 2.0*(clip.x - 0.5), y: 2.0*(clip.y - 0.5) };
-        let center = self.clip_to_latlon(&clip).unwrap();
-        self.create_route_to(center.into(), route_costing);
+        let lat_lon = self.clip_to_latlon(&clip)?;
+        Some((lat_lon.y, lat_lon.x))
+    }
+
+    /// Calculates the route from the current location in the background, the turn-by-turn guidance
+    /// along it starts when it's ready. A new route is requested when the location leaves it.
+    pub fn create_route_to(&mut self, to_lat_lon: (f64, f64), route_costing: RouteCosting, alternatives: usize) {
+        self.create_route(vec![to_lat_lon], route_costing, alternatives);
+    }
+
+    /// Route from the current location through the waypoints in order, the last one is the destination.
+    /// Up to `alternatives` other routes are shown to select from with [Self::select_route_at_screen_point],
+    /// the guidance follows the best one until then.
+    pub fn create_route(&mut self, waypoints: Vec<(f64, f64)>, route_costing: RouteCosting, alternatives: usize) {
+        self.route_controller.calc_route(waypoints, route_costing, alternatives);
+    }
+
+    /// Selects the shown route under the screen point, e.g. a tapped alternative, returns false if there is none.
+    pub fn select_route_at_screen_point(&mut self, point_x: f32, point_y: f32) -> bool {
+        // routes this far from the point in pixels are under the finger
+        const TOLERANCE: f32 = 24.0;
+        let Some((lat_lon, tolerance)) = self.screen_point_area(point_x, point_y, TOLERANCE) else {
+            return false;
+        };
+        self.route_controller.select_route_near(lat_lon, tolerance)
+    }
+
+    /// Features drawn within `tolerance` pixels of the screen point, the nearest first:
+    /// POIs, named roads, buildings, KML placemarks and the shown routes.
+    pub fn features_at_screen_point(&self, point_x: f32, point_y: f32, tolerance: f32) -> Vec<PickedFeature> {
+        let Some((lat_lon, tolerance)) = self.screen_point_area(point_x, point_y, tolerance) else {
+            return vec![];
+        };
+        let mut picked = self.feature_index.pick::<T>(lat_lon, tolerance);
+        picked.extend(self.route_controller.pick_route(lat_lon, tolerance));
+        picked.sort_by(|a, b| a.0.total_cmp(&b.0));
+        picked.into_iter().map(|(_, feature)| feature).collect()
+    }
+
+    // (lat, lon) under the screen point and the tolerance in pixels converted to meters around it
+    fn screen_point_area(&self, point_x: f32, point_y: f32, tolerance: f32) -> Option<((f64, f64), f64)> {
+        let lat_lon = self.screen_point_to_lat_lon(point_x, point_y)?;
+        let edge_lat_lon = self.screen_point_to_lat_lon(point_x + tolerance, point_y)?;
+        let world = |(lat, lon): (f64, f64)| T::lat_lon_to_world(&coord! {x: lon, y: lat});
+        let tolerance = distance(&world(lat_lon), &world(edge_lat_lon)) * T::meters_per_world_unit(lat_lon.0);
+        Some((lat_lon, tolerance))
     }
 
-    pub fn create_route_to(&self, to_lat_lon: (f64, f64), route_costing: RouteCosting) {
-        self.route_controller.calc_route(
-            to_lat_lon,
-            route_costing,
-            self.create_location_coord_converter(),
-            self.renderer.api.clone(),
+    /// Routes are calculated by the Valhalla server at the URL, e.g. a local one, instead of the public one.
+    /// The headers are sent with every request, e.g. the authorization.
+    pub fn set_valhalla_url(&mut self, valhalla_url: &str, headers: &[(String, String)]) -> anyhow::Result<()> {
+        let provider = headers.iter().fold(
+            ValhallaRoutingProvider::new(Url::parse(valhalla_url)?),
+            |provider, (name, value)| provider.with_header(name, value),
         );
+        self.set_routing_provider(Arc::new(provider));
+        Ok(())
     }
 
-    fn create_location_coord_converter(&self) -> Box<dyn (Fn(&Point) -> Point) + Send> {
+    /// Routes are calculated by the OSRM server at the URL instead of Valhalla.
+    /// The headers are sent with every request, e.g. the authorization.
+    pub fn set_osrm_url(&mut self, osrm_url: &str, headers: &[(String, String)]) -> anyhow::Result<()> {
+        let provider = headers.iter().fold(
+            OsrmRoutingProvider::new(Url::parse(osrm_url)?),
+            |provider, (name, value)| provider.with_header(name, value),
+        );
+        self.set_routing_provider(Arc::new(provider));
+        Ok(())
+    }
+
+    /// Engine of the next routes, the failures come to the guidance listener.
+    pub fn set_routing_provider(&mut self, routing_provider: Arc<dyn RoutingProvider>) {
+        self.route_controller.set_routing_provider(routing_provider);
+    }
+
+    /// Receives the maneuvers, the progress, the arrival and leaving of the route.
+    pub fn set_guidance_listener(&mut self, listener: Option<GuidanceListener>) {
+        self.route_controller.set_guidance_listener(listener);
+    }
+
+    /// Draws the annotation over the map tiles, or replaces the one with the same id.
+    /// Clustered markers are drawn with the KML placemarks.
+    pub fn add_annotation(&mut self, id: &str, annotation: Annotation) -> anyhow::Result<()> {
+        self.annotation_controller
+            .add(id, annotation, &mut self.cluster_controller)
+    }
+
+    /// Returns false if there is no annotation with the id.
+    pub fn remove_annotation(&mut self, id: &str) -> bool {
+        self.annotation_controller.remove(id, &mut self.cluster_controller)
+    }
+
+    pub fn clear_annotations(&mut self) {
+        self.annotation_controller.clear(&mut self.cluster_controller);
+    }
+
+    fn create_location_coord_converter() -> LocationConverter {
         Box::new(move |p| {
             let coord: Coord<f64> = (p.x(), p.y()).into();
             let coord = T::lat_lon_to_world(&coord);
@@ -341,35 +690,218 @@ impl<T: TilesProvider> ShashlikMap<T> {
         })
     }
 
-    fn load_styles(&self) {
+    fn load_styles(&mut self) {
+        self.base_colors.clear();
 
         StyleLoader::load().into_iter().for_each(|style| {
-            let style_id = StyleId(Box::leak(style.id.into_boxed_str()));
-            let actual_render_style = match style.render_style {
+            let style_id = StyleId(intern(&style.id));
+            let (fill_color, actual_render_style) = match style.render_style {
                 RenderStyle::Fill(color) => {
-                    renderer::styles::render_style::RenderStyle::fill(color.as_array())
+                    (color.as_array(), renderer::styles::render_style::RenderStyle::fill(color.as_array()))
                 }
                 RenderStyle::Border(color, percent) => {
-                    renderer::styles::render_style::RenderStyle::border(color.as_array(), percent)
+                    (color.as_array(), renderer::styles::render_style::RenderStyle::border(color.as_array(), percent))
                 }
                 RenderStyle::Dashed(color1, color2) => {
-                    renderer::styles::render_style::RenderStyle::dashed(color1.as_array(), color2.as_array())
+                    (color1.as_array(), renderer::styles::render_style::RenderStyle::dashed(color1.as_array(), color2.as_array()))
                 }
             };
-            self.renderer.api.update_style(style_id, move |style| *style = actual_render_style);
+            self.base_colors.insert(style_id.clone(), fill_color);
+            // the route keeps its progress until the next location
+            self.renderer
+                .api
+                .update_style(style_id, move |style| *style = actual_render_style.with_progress(style.progress()));
         });
     }
 
-    pub fn load_kml_path(&self, path_buf: PathBuf) {
-        println!("Loading KML from {:?}", path_buf);
-        self.renderer.api.add_render_group(
-            "kml_data".to_string(),
-            0,
-            SpatialData::transform(Vector3::new(0.0, 0.0, 0.0)),
-            Box::new(KmlGroup::new(
-                path_buf,
-                self.create_location_coord_converter(),
-            )),
-        );
+    /// Restyles the map with a JSON or TOML style document.
+    pub fn load_style_sheet(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let style_sheet = StyleSheet::load(path)?;
+        self.set_style_sheet(style_sheet);
+        Ok(())
+    }
+
+    /// Same as `load_style_sheet`, and the document is reloaded every time the file changes.
+    pub fn watch_style_sheet(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.load_style_sheet(path.as_ref())?;
+        self.style_watcher = Some(StyleWatcher::new(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    pub fn set_style_sheet(&mut self, style_sheet: StyleSheet) {
+        self.style_sheet = Arc::new(style_sheet);
+        // zoom stops of the previous document are dropped with the base styles
+        self.load_styles();
+        self.apply_render_styles();
+        self.theme_style_colors.clear();
+        self.theme_fade = None;
+        self.apply_theme_colors(&ThemeColors::resolve(&self.theme, &self.base_colors));
+        self.tiles_provider.set_style_sheet(self.style_sheet.clone());
+    }
+
+    /// Declares a render style, e.g. for the annotations, the themes don't change its colors.
+    /// A style of the style sheet with the same id replaces it when the sheet changes.
+    pub fn set_style(&mut self, id: &str, style: StyleDef) {
+        self.renderer.api.update_style(StyleId(intern(id)), move |render_style| style.apply(render_style));
+    }
+
+    /// Adds a font to the fallback chain of the labels, e.g. a CJK font for the Japanese names,
+    /// the embedded font has only the Latin characters.
+    pub fn add_font(&mut self, data: Vec<u8>) -> anyhow::Result<()> {
+        self.renderer.add_font(data)
+    }
+
+    /// Draws the labels from a signed distance field atlas in one draw call instead of the glyph meshes.
+    pub fn set_text_backend(&mut self, backend: TextBackend) {
+        self.renderer.set_text_backend(backend);
+    }
+
+    /// Outline of the labels, e.g. to keep them readable over the busy areas, needs [TextBackend::Sdf].
+    pub fn set_text_halo(&mut self, halo: TextHalo) {
+        self.renderer.set_text_halo(halo);
+    }
+
+    /// Outlines the collision rectangles of the labels and icons, the hidden ones in red.
+    pub fn set_collision_debug(&mut self, debug: bool) {
+        self.renderer.set_collision_debug(debug);
+    }
+
+    fn update_style_sheet(&mut self) {
+        if let Some(style_sheet) = self.style_watcher.as_ref().and_then(|watcher| watcher.poll()) {
+            info!("Style sheet is reloaded");
+            self.set_style_sheet(style_sheet);
+        }
+    }
+
+    fn apply_render_styles(&mut self) {
+        self.style_sheet
+            .render_styles()
+            .into_iter()
+            .for_each(|(style_id, style_def)| {
+                // the styles with color stops keep them unless a theme recolors them
+                match style_def.fill.as_ref() {
+                    Some(fill) if !fill.is_fixed() => {
+                        self.base_colors.remove(&style_id);
+                    }
+                    Some(fill) => {
+                        self.base_colors.insert(style_id.clone(), fill.eval(0.0).unwrap().0);
+                    }
+                    None => {}
+                }
+                self.renderer
+                    .api
+                    .update_style(style_id, move |style| style_def.apply(style));
+            });
+    }
+
+    /// Switches to the theme with a cross-fade from the colors on screen.
+    pub fn set_theme(&mut self, theme: Theme) {
+        let from = match &self.theme_fade {
+            Some(fade) => fade.colors(),
+            None => ThemeColors::resolve(&self.theme, &self.base_colors),
+        };
+        let to = ThemeColors::resolve(&theme, &self.base_colors);
+        self.theme = theme;
+        self.theme_fade = Some(ThemeFade::new(from, to));
+    }
+
+    /// Switches to one of the built-in themes: `day`, `night` or `high_contrast`.
+    pub fn set_theme_by_name(&mut self, name: &str) -> anyhow::Result<()> {
+        self.set_theme(Theme::by_name(name)?);
+        Ok(())
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    fn update_theme(&mut self) {
+        let Some(fade) = &self.theme_fade else {
+            return;
+        };
+        let colors = fade.colors();
+        if fade.is_finished() {
+            self.theme_fade = None;
+        }
+        self.apply_theme_colors(&colors);
+    }
+
+    fn apply_theme_colors(&mut self, colors: &ThemeColors) {
+        self.renderer.set_clear_color(colors.background);
+        self.renderer.set_text_color(colors.text);
+        colors.styles.iter().for_each(|(style_id, color)| {
+            let color = *color;
+            if self.theme_style_colors.insert(style_id.clone(), color) != Some(color) {
+                self.renderer
+                    .api
+                    .update_style(style_id.clone(), move |style| *style = style.with_fill_color(color));
+            }
+        });
+        // the styles the theme doesn't recolor anymore get their color stops back
+        let dropped: Vec<StyleId> = self
+            .theme_style_colors
+            .keys()
+            .filter(|style_id| !colors.styles.contains_key(*style_id))
+            .cloned()
+            .collect();
+        dropped.into_iter().for_each(|style_id| {
+            self.theme_style_colors.remove(&style_id);
+            if let Some(style_def) = self.style_sheet.styles.get(style_id.0).cloned() {
+                self.renderer
+                    .api
+                    .update_style(style_id, move |style| style_def.apply(style));
+            }
+        });
+    }
+
+    /// Loads the KML or KMZ file as a layer which can be hidden, it replaces the layer with the same name.
+    /// The points are clustered when they are close on screen.
+    pub fn load_kml_layer(&mut self, name: &str, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let kml_group = KmlGroup::load(name, path.as_ref(), Self::create_location_coord_converter())?;
+        self.overlay_controller
+            .add_kml_layer(name, kml_group, &mut self.cluster_controller);
+        Ok(())
+    }
+
+    /// Returns false if there is no layer with the name.
+    pub fn set_kml_layer_visible(&mut self, name: &str, visible: bool) -> bool {
+        self.overlay_controller
+            .set_kml_layer_visible(name, visible, &mut self.cluster_controller)
+    }
+
+    /// Returns false if there is no layer with the name.
+    pub fn remove_kml_layer(&mut self, name: &str) -> bool {
+        self.overlay_controller
+            .remove_kml_layer(name, &mut self.cluster_controller)
+    }
+
+    /// Names of the loaded KML layers and whether they are visible.
+    pub fn kml_layers(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.overlay_controller.kml_layers()
+    }
+
+    /// Shows the GeoJSON FeatureCollection as a layer, it replaces the layer with the same name.
+    /// The features are styled by their properties, see `GeoJsonGroup`.
+    pub fn set_geojson_layer(&mut self, name: &str, geojson: &str) -> anyhow::Result<()> {
+        let group = GeoJsonGroup::parse(name, geojson, Self::create_location_coord_converter())?;
+        self.overlay_controller.add_geojson_layer(name, group);
+        Ok(())
+    }
+
+    /// Loads the GeoJSON file as a layer, see `set_geojson_layer`.
+    pub fn load_geojson_layer(&mut self, name: &str, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let group = GeoJsonGroup::load(name, path.as_ref(), Self::create_location_coord_converter())?;
+        self.overlay_controller.add_geojson_layer(name, group);
+        Ok(())
+    }
+
+    /// Returns false if there is no layer with the name.
+    pub fn remove_geojson_layer(&mut self, name: &str) -> bool {
+        self.overlay_controller.remove_geojson_layer(name)
+    }
+
+    /// Names of the GeoJSON layers.
+    pub fn geojson_layers(&self) -> impl Iterator<Item = &str> {
+        self.overlay_controller.geojson_layers()
     }
 }
@@ -0,0 +1,172 @@
+use crate::tiles::mercator;
+use geo_types::{Coord, coord};
+
+/// Location fix of the GPS, `time` is in seconds of any monotonic clock.
+#[derive(Clone, Copy, Debug)]
+pub struct LocationFix {
+    pub lat: f64,
+    pub lon: f64,
+    /// Degrees clockwise from the north
+    pub bearing: Option<f32>,
+    /// Meters per second, it's estimated from the previous fix when the GPS doesn't report it
+    pub speed: Option<f32>,
+    pub time: f64,
+}
+
+/// Motion sensors sample in the frame of the vehicle.
+#[derive(Clone, Copy, Debug)]
+pub struct ImuSample {
+    /// Degrees per second, clockwise like the bearing
+    pub yaw_rate: f32,
+    /// Meters per second squared along the direction of travel
+    pub forward_acceleration: Option<f32>,
+    pub time: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EstimatedLocation {
+    pub lat: f64,
+    pub lon: f64,
+    pub bearing: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeadReckoningConfig {
+    /// Extrapolation starts when there is no fix for that long, in seconds
+    pub gps_timeout: f64,
+    /// The position is frozen again when the GPS is lost for that long, in seconds
+    pub max_duration: f64,
+}
+
+impl Default for DeadReckoningConfig {
+    fn default() -> Self {
+        DeadReckoningConfig {
+            gps_timeout: 1.5,
+            max_duration: 120.0,
+        }
+    }
+}
+
+/// Extrapolates the position from the last speed and bearing while the GPS is lost, e.g. in tunnels.
+/// The yaw rate of the gyroscope turns the bearing and the accelerometer changes the speed.
+pub struct DeadReckoning {
+    config: DeadReckoningConfig,
+    position: Option<Coord>,
+    bearing: f64,
+    speed: f64,
+    last_fix_time: f64,
+    last_time: f64,
+    imu: Option<ImuSample>,
+}
+
+impl DeadReckoning {
+    // fixes closer in time give a too noisy speed
+    const MIN_SPEED_INTERVAL: f64 = 0.2;
+    // fixes further apart in time may include stops
+    const MAX_SPEED_INTERVAL: f64 = 5.0;
+    // ~250 km/h, faster estimates are jumps of the position
+    const MAX_SPEED: f64 = 70.0;
+
+    pub fn new(config: DeadReckoningConfig) -> DeadReckoning {
+        DeadReckoning {
+            config,
+            position: None,
+            bearing: 0.0,
+            speed: 0.0,
+            last_fix_time: 0.0,
+            last_time: 0.0,
+            imu: None,
+        }
+    }
+
+    pub fn on_fix(&mut self, fix: &LocationFix) {
+        let position = mercator::lat_lon_to_world(&coord! {x: fix.lon, y: fix.lat});
+        let interval = fix.time - self.last_fix_time;
+        let moved = self.position.map(|last| {
+            let delta = position - last;
+            (delta, delta.x.hypot(delta.y) * mercator::meters_per_world_unit(fix.lat))
+        });
+
+        self.speed = match (fix.speed, moved) {
+            (Some(speed), _) => speed as f64,
+            (None, Some((_, distance)))
+                if (Self::MIN_SPEED_INTERVAL..Self::MAX_SPEED_INTERVAL).contains(&interval) =>
+            {
+                (distance / interval).min(Self::MAX_SPEED)
+            }
+            (None, _) => self.speed,
+        };
+        self.bearing = match (fix.bearing, moved) {
+            (Some(bearing), _) => bearing as f64,
+            (None, Some((delta, distance))) if distance > 1.0 => bearing_of(&delta),
+            (None, _) => self.bearing,
+        };
+        self.position = Some(position);
+        self.last_fix_time = fix.time;
+        self.last_time = fix.time;
+    }
+
+    pub fn on_imu(&mut self, sample: &ImuSample) {
+        if self.is_active(sample.time) {
+            self.advance(sample.time);
+        }
+        self.imu = Some(*sample);
+    }
+
+    /// The extrapolated location, `None` while the GPS is fine, when it's lost for too long
+    /// or when the vehicle stands still.
+    pub fn predict(&mut self, time: f64) -> Option<EstimatedLocation> {
+        if !self.is_active(time) || (self.speed <= 0.0 && self.imu.is_none()) {
+            return None;
+        }
+        self.advance(time);
+        let position = self.position?;
+        let lat_lon = mercator::world_to_lat_lon(&position);
+        Some(EstimatedLocation {
+            lat: lat_lon.y,
+            lon: lat_lon.x,
+            bearing: self.bearing.rem_euclid(360.0) as f32,
+        })
+    }
+
+    /// Corrects the extrapolated location, e.g. with the road it's matched to.
+    pub fn constrain(&mut self, lat: f64, lon: f64, bearing: f32) {
+        if self.position.is_some() {
+            self.position = Some(mercator::lat_lon_to_world(&coord! {x: lon, y: lat}));
+            self.bearing = bearing as f64;
+        }
+    }
+
+    fn is_active(&self, time: f64) -> bool {
+        let lost_for = time - self.last_fix_time;
+        self.position.is_some()
+            && lost_for > self.config.gps_timeout
+            && lost_for < self.config.max_duration
+    }
+
+    fn advance(&mut self, time: f64) {
+        let Some(position) = self.position else {
+            return;
+        };
+        let dt = time - self.last_time;
+        if dt <= 0.0 {
+            return;
+        }
+        if let Some(imu) = self.imu {
+            self.bearing += imu.yaw_rate as f64 * dt;
+            if let Some(acceleration) = imu.forward_acceleration {
+                self.speed = (self.speed + acceleration as f64 * dt).max(0.0);
+            }
+        }
+        let lat = mercator::world_to_lat_lon(&position).y;
+        let distance = self.speed * dt / mercator::meters_per_world_unit(lat);
+        let (sin, cos) = self.bearing.to_radians().sin_cos();
+        // world y grows to the south
+        self.position = Some(position + coord! {x: sin * distance, y: -cos * distance});
+        self.last_time = time;
+    }
+}
+
+fn bearing_of(delta: &Coord) -> f64 {
+    delta.x.atan2(-delta.y).to_degrees()
+}
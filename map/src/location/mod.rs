@@ -0,0 +1,13 @@
+pub mod dead_reckoning;
+pub mod recorder;
+pub mod replay;
+pub mod trace;
+
+use crate::location::dead_reckoning::LocationFix;
+use std::time::Instant;
+
+/// Source of the position which is polled every frame, e.g. a replayed trace.
+pub trait LocationSource {
+    /// The next fix to show, `None` when there is no new one.
+    fn poll(&mut self, now: Instant) -> Option<LocationFix>;
+}
@@ -0,0 +1,65 @@
+use crate::location::dead_reckoning::LocationFix;
+use crate::location::trace::{civil_from_days, nmea_checksum};
+use anyhow::Context;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const METERS_PER_SECOND_TO_KNOTS: f64 = 3600.0 / 1852.0;
+
+/// Writes fixes as NMEA RMC sentences, `Trace::load` replays the file.
+/// Every sentence is flushed, the log survives a crash of the app.
+pub struct TraceRecorder {
+    writer: BufWriter<File>,
+}
+
+impl TraceRecorder {
+    pub fn create(path: impl AsRef<Path>) -> anyhow::Result<TraceRecorder> {
+        let path = path.as_ref();
+        let file = File::create(path).with_context(|| format!("Can't create {:?}", path))?;
+        Ok(TraceRecorder {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// `time` of the fix is in seconds since the Unix epoch.
+    pub fn record(&mut self, fix: &LocationFix) -> anyhow::Result<()> {
+        let sentence = rmc_sentence(fix);
+        writeln!(self.writer, "${sentence}*{:02X}", nmea_checksum(&sentence))?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn rmc_sentence(fix: &LocationFix) -> String {
+    let days = fix.time.div_euclid(86400.0);
+    let seconds = fix.time - days * 86400.0;
+    let (year, month, day) = civil_from_days(days as i64);
+    let hours = (seconds / 3600.0).floor();
+    let minutes = ((seconds - hours * 3600.0) / 60.0).floor();
+    let seconds = seconds - hours * 3600.0 - minutes * 60.0;
+    format!(
+        "GPRMC,{:02}{:02}{:05.2},A,{},{},{},{},{},{},{:02}{:02}{:02},,,A",
+        hours as u32,
+        minutes as u32,
+        seconds,
+        nmea_coordinate(fix.lat.abs(), 2),
+        if fix.lat < 0.0 { "S" } else { "N" },
+        nmea_coordinate(fix.lon.abs(), 3),
+        if fix.lon < 0.0 { "W" } else { "E" },
+        fix.speed
+            .map(|speed| format!("{:.2}", speed as f64 * METERS_PER_SECOND_TO_KNOTS))
+            .unwrap_or_default(),
+        fix.bearing.map(|bearing| format!("{bearing:.1}")).unwrap_or_default(),
+        day,
+        month,
+        year % 100,
+    )
+}
+
+// ddmm.mmmmmm, ~0.2m precision
+fn nmea_coordinate(value: f64, degree_digits: usize) -> String {
+    let degrees = value.trunc();
+    let minutes = (value - degrees) * 60.0;
+    format!("{:0degree_digits$}{:09.6}", degrees as u32, minutes)
+}
@@ -0,0 +1,94 @@
+use crate::location::LocationSource;
+use crate::location::dead_reckoning::LocationFix;
+use crate::location::trace::Trace;
+use std::time::{Duration, Instant};
+
+/// Plays a trace back in real time or faster, positions between the fixes are interpolated.
+/// While it's paused or over, the last position keeps being reported with zero speed,
+/// so the dead reckoning doesn't move the puck.
+pub struct TraceReplayer {
+    trace: Trace,
+    speed: f64,
+    paused: bool,
+    position: f64,
+    last_poll: Option<Instant>,
+    next_fix: Option<Instant>,
+}
+
+impl TraceReplayer {
+    /// Roughly the rate of a good GPS, the puck animates in between
+    const FIX_INTERVAL: Duration = Duration::from_millis(200);
+    const MAX_SPEED: f64 = 64.0;
+
+    pub fn new(trace: Trace) -> TraceReplayer {
+        TraceReplayer {
+            trace,
+            speed: 1.0,
+            paused: false,
+            position: 0.0,
+            last_poll: None,
+            next_fix: None,
+        }
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// Playback speed multiplier, 1 is real time.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(1.0 / Self::MAX_SPEED, Self::MAX_SPEED);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Seconds from the start of the trace.
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// Jumps to the time of the trace, the position is reported on the next poll.
+    pub fn seek(&mut self, position: f64) {
+        self.position = position.clamp(0.0, self.trace.duration());
+        self.next_fix = None;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.trace.duration()
+    }
+}
+
+impl LocationSource for TraceReplayer {
+    fn poll(&mut self, now: Instant) -> Option<LocationFix> {
+        if let Some(last_poll) = self.last_poll
+            && !self.paused
+        {
+            let elapsed = now.saturating_duration_since(last_poll).as_secs_f64();
+            self.position = (self.position + elapsed * self.speed).min(self.trace.duration());
+        }
+        self.last_poll = Some(now);
+
+        if self.next_fix.is_some_and(|next_fix| now < next_fix) {
+            return None;
+        }
+        self.next_fix = Some(now + Self::FIX_INTERVAL);
+
+        let mut fix = self.trace.interpolate(self.position);
+        if self.paused || self.is_finished() {
+            fix.speed = Some(0.0);
+        } else {
+            fix.speed = fix.speed.map(|speed| speed * self.speed as f32);
+        }
+        Some(fix)
+    }
+}
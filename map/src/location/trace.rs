@@ -0,0 +1,279 @@
+use crate::location::dead_reckoning::LocationFix;
+use crate::tiles::mercator;
+use anyhow::{Context, anyhow, bail};
+use geo_types::coord;
+use std::path::Path;
+
+const KNOTS_TO_METERS_PER_SECOND: f64 = 1852.0 / 3600.0;
+
+/// Recorded drive, `time` of the fixes is in seconds from the first fix.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    fixes: Vec<LocationFix>,
+}
+
+impl Trace {
+    /// Fixes are sorted by time, fixes without time are one second apart.
+    pub fn new(mut fixes: Vec<LocationFix>) -> anyhow::Result<Trace> {
+        if fixes.is_empty() {
+            bail!("The trace has no fixes");
+        }
+        fixes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        fixes.dedup_by(|next, fix| next.time <= fix.time);
+        let start = fixes[0].time;
+        fixes.iter_mut().for_each(|fix| fix.time -= start);
+        Ok(Trace { fixes })
+    }
+
+    /// GPX or NMEA, the format is picked by the extension.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Trace> {
+        let path = path.as_ref();
+        let document = std::fs::read_to_string(path).with_context(|| format!("Can't read {:?}", path))?;
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        match extension.map(|extension| extension.to_ascii_lowercase()).as_deref() {
+            Some("gpx") => Trace::from_gpx(&document),
+            Some("nmea" | "txt" | "log") => Trace::from_nmea(&document),
+            _ => bail!("Unknown trace format {:?}, gpx or nmea is expected", path),
+        }
+        .with_context(|| format!("Can't parse {:?}", path))
+    }
+
+    /// Track and route points, `course` and `speed` of GPX 1.0 are kept.
+    pub fn from_gpx(document: &str) -> anyhow::Result<Trace> {
+        let document = roxmltree::Document::parse(document)?;
+        let mut fixes = vec![];
+        for (index, point) in document
+            .descendants()
+            .filter(|node| node.has_tag_name("trkpt") || node.has_tag_name("rtept"))
+            .enumerate()
+        {
+            let attribute = |name: &str| -> anyhow::Result<f64> {
+                let value = point.attribute(name).ok_or_else(|| anyhow!("Point without {name}"))?;
+                Ok(value.parse()?)
+            };
+            let child = |name: &str| {
+                point
+                    .children()
+                    .find(|child| child.has_tag_name(name))
+                    .and_then(|child| child.text())
+                    .map(str::trim)
+            };
+            let time = match child("time") {
+                Some(time) => parse_iso8601(time)?,
+                None => index as f64,
+            };
+            fixes.push(LocationFix {
+                lat: attribute("lat")?,
+                lon: attribute("lon")?,
+                bearing: child("course").and_then(|course| course.parse().ok()),
+                speed: child("speed").and_then(|speed| speed.parse().ok()),
+                time,
+            });
+        }
+        Trace::new(fixes)
+    }
+
+    /// RMC sentences, GGA sentences are used when the log has no RMC.
+    pub fn from_nmea(document: &str) -> anyhow::Result<Trace> {
+        let sentences: Vec<Vec<&str>> = document.lines().filter_map(nmea_fields).collect();
+        let rmc: Vec<LocationFix> = sentences
+            .iter()
+            .filter(|fields| fields[0].ends_with("RMC"))
+            .filter_map(|fields| parse_rmc(fields))
+            .collect();
+        let fixes = if rmc.is_empty() {
+            sentences
+                .iter()
+                .filter(|fields| fields[0].ends_with("GGA"))
+                .filter_map(|fields| parse_gga(fields))
+                .collect()
+        } else {
+            rmc
+        };
+        Trace::new(fixes)
+    }
+
+    pub fn fixes(&self) -> &[LocationFix] {
+        &self.fixes
+    }
+
+    /// Seconds from the first to the last fix.
+    pub fn duration(&self) -> f64 {
+        self.fixes.last().map_or(0.0, |fix| fix.time)
+    }
+
+    /// Position between the fixes around `time`, the bearing and the speed come from the movement
+    /// between them when the fixes don't have it.
+    pub fn interpolate(&self, time: f64) -> LocationFix {
+        let next = self.fixes.partition_point(|fix| fix.time <= time);
+        if next == 0 {
+            return self.fixes[0];
+        }
+        if next == self.fixes.len() {
+            return LocationFix {
+                time,
+                ..self.fixes[next - 1]
+            };
+        }
+        let from = &self.fixes[next - 1];
+        let to = &self.fixes[next];
+        let t = (time - from.time) / (to.time - from.time);
+
+        let from_world = mercator::lat_lon_to_world(&coord! {x: from.lon, y: from.lat});
+        let to_world = mercator::lat_lon_to_world(&coord! {x: to.lon, y: to.lat});
+        let delta = to_world - from_world;
+        let lat_lon = mercator::world_to_lat_lon(&(from_world + delta * t));
+        let moved = delta.x.hypot(delta.y) * mercator::meters_per_world_unit(from.lat);
+
+        let bearing = match (from.bearing, to.bearing) {
+            (Some(from), Some(to)) => {
+                let turn = (to - from + 540.0).rem_euclid(360.0) - 180.0;
+                Some((from + turn * t as f32).rem_euclid(360.0))
+            }
+            // world y grows to the south
+            _ if moved > 0.0 => Some(delta.x.atan2(-delta.y).to_degrees().rem_euclid(360.0) as f32),
+            _ => from.bearing,
+        };
+        let speed = match (from.speed, to.speed) {
+            (Some(from), Some(to)) => Some(from + (to - from) * t as f32),
+            _ => Some((moved / (to.time - from.time)) as f32),
+        };
+        LocationFix {
+            lat: lat_lon.y,
+            lon: lat_lon.x,
+            bearing,
+            speed,
+            time,
+        }
+    }
+}
+
+// fields of a sentence with a valid checksum, the first one is the talker and the type, e.g. GPRMC
+fn nmea_fields(line: &str) -> Option<Vec<&str>> {
+    let sentence = line.trim().strip_prefix('$')?;
+    let sentence = match sentence.split_once('*') {
+        Some((sentence, checksum)) => {
+            (u8::from_str_radix(checksum.trim(), 16).ok()? == nmea_checksum(sentence)).then_some(sentence)?
+        }
+        None => sentence,
+    };
+    Some(sentence.split(',').collect())
+}
+
+pub(crate) fn nmea_checksum(sentence: &str) -> u8 {
+    sentence.bytes().fold(0, |checksum, byte| checksum ^ byte)
+}
+
+// $GPRMC,hhmmss.ss,A,ddmm.mm,N,dddmm.mm,E,knots,course,ddmmyy,...
+fn parse_rmc(fields: &[&str]) -> Option<LocationFix> {
+    if fields.len() < 10 || fields[2] != "A" {
+        return None;
+    }
+    let date = fields[9];
+    if date.len() != 6 {
+        return None;
+    }
+    // lines without a checksum might be corrupted, `get` skips them instead of splitting a character
+    let day = date.get(0..2)?.parse().ok()?;
+    let month = date.get(2..4)?.parse().ok()?;
+    let year = 2000 + date.get(4..6)?.parse::<i64>().ok()?;
+    Some(LocationFix {
+        lat: parse_nmea_coordinate(fields[3], fields[4])?,
+        lon: parse_nmea_coordinate(fields[5], fields[6])?,
+        bearing: fields[8].parse().ok(),
+        speed: fields[7]
+            .parse::<f64>()
+            .ok()
+            .map(|knots| (knots * KNOTS_TO_METERS_PER_SECOND) as f32),
+        time: days_from_civil(year, month, day) as f64 * 86400.0 + parse_nmea_time(fields[1])?,
+    })
+}
+
+// $GPGGA,hhmmss.ss,ddmm.mm,N,dddmm.mm,E,quality,...
+fn parse_gga(fields: &[&str]) -> Option<LocationFix> {
+    if fields.len() < 7 || fields[6] == "0" {
+        return None;
+    }
+    Some(LocationFix {
+        lat: parse_nmea_coordinate(fields[2], fields[3])?,
+        lon: parse_nmea_coordinate(fields[4], fields[5])?,
+        bearing: None,
+        speed: None,
+        time: parse_nmea_time(fields[1])?,
+    })
+}
+
+fn parse_nmea_time(time: &str) -> Option<f64> {
+    if time.len() < 6 {
+        return None;
+    }
+    let hours: f64 = time.get(0..2)?.parse().ok()?;
+    let minutes: f64 = time.get(2..4)?.parse().ok()?;
+    let seconds: f64 = time.get(4..)?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+// ddmm.mmmm or dddmm.mmmm
+fn parse_nmea_coordinate(value: &str, hemisphere: &str) -> Option<f64> {
+    let value: f64 = value.parse().ok()?;
+    let degrees = (value / 100.0).trunc();
+    let coordinate = degrees + (value - degrees * 100.0) / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(coordinate),
+        "S" | "W" => Some(-coordinate),
+        _ => None,
+    }
+}
+
+// 2024-05-01T10:00:00.5Z or with an offset, e.g. +09:00
+fn parse_iso8601(time: &str) -> anyhow::Result<f64> {
+    let error = || anyhow!("Invalid time {time:?}");
+    let (date, clock) = time.split_once('T').ok_or_else(error)?;
+    let mut date = date.split('-').map(str::parse::<i64>);
+    let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) = (date.next(), date.next(), date.next()) else {
+        return Err(error());
+    };
+
+    let (clock, offset) = match clock.find(['Z', '+', '-']) {
+        Some(position) => clock.split_at(position),
+        None => (clock, ""),
+    };
+    let mut clock = clock.split(':').map(str::parse::<f64>);
+    let (Some(Ok(hours)), Some(Ok(minutes)), Some(Ok(seconds))) = (clock.next(), clock.next(), clock.next())
+    else {
+        return Err(error());
+    };
+    let offset = match offset.split_at_checked(1) {
+        None | Some(("Z", "")) => 0.0,
+        Some((sign, offset)) => {
+            let (offset_hours, offset_minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+            let offset = offset_hours.parse::<f64>()? * 3600.0 + offset_minutes.parse::<f64>()? * 60.0;
+            if sign == "-" { -offset } else { offset }
+        }
+    };
+    Ok(days_from_civil(year, month, day) as f64 * 86400.0 + hours * 3600.0 + minutes * 60.0 + seconds - offset)
+}
+
+// Days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Inverse of `days_from_civil`, (year, month, day).
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,278 @@
+use crate::matching::road_network::{Candidate, RoadIndex, RoadNetwork, distance};
+use crate::tiles::mercator;
+use geo_types::{Coord, coord};
+use std::sync::Arc;
+
+/// GPS fix, `bearing` is in degrees clockwise from the north.
+#[derive(Clone, Copy, Debug)]
+pub struct GpsFix {
+    pub lat: f64,
+    pub lon: f64,
+    pub bearing: Option<f32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchedPosition {
+    pub lat: f64,
+    pub lon: f64,
+    /// Direction of the road in the direction of travel, degrees clockwise from the north
+    pub bearing: f32,
+    pub road_name: Option<String>,
+    pub road_class: &'static str,
+    /// Distance from the fix to the road in meters
+    pub offset: f64,
+}
+
+/// Parameters of the HMM, see "Hidden Markov Map Matching Through Noise and Sparseness"(Newson, Krumm).
+#[derive(Clone, Copy, Debug)]
+pub struct MatcherConfig {
+    /// Standard deviation of the GPS noise in meters
+    pub gps_sigma: f64,
+    /// Scale of the difference between the road and the great circle distances in meters,
+    /// the smaller it is the less detours between fixes are allowed
+    pub beta: f64,
+    /// Standard deviation of the GPS bearing in degrees, roads across the bearing are less likely
+    pub heading_sigma: f64,
+    /// Roads further than it from a fix aren't considered, in meters
+    pub search_radius: f64,
+    pub max_candidates: usize,
+}
+
+impl Default for MatcherConfig {
+    fn default() -> Self {
+        MatcherConfig {
+            gps_sigma: 10.0,
+            beta: 5.0,
+            heading_sigma: 30.0,
+            search_radius: 50.0,
+            max_candidates: 8,
+        }
+    }
+}
+
+struct State {
+    candidate: Candidate,
+    log_probability: f64,
+}
+
+/// Online Viterbi matcher, every fix is snapped to the end of the most likely road path so far.
+/// There is no look-ahead, the matched position is available right away for the puck.
+pub struct HmmMatcher {
+    road_network: Arc<RoadNetwork>,
+    config: MatcherConfig,
+    // the segments of the state candidates are the ones of this index, it's rebuilt when tiles are loaded
+    index: Option<Arc<RoadIndex>>,
+    states: Vec<State>,
+    last_fix: Option<Coord>,
+    last_match: Option<Candidate>,
+}
+
+impl HmmMatcher {
+    // in world units, the same position in the rebuilt index is only off by the rounding
+    const MOVED_STATE_RADIUS: f64 = 1e-6;
+
+    pub fn new(road_network: Arc<RoadNetwork>, config: MatcherConfig) -> HmmMatcher {
+        HmmMatcher {
+            road_network,
+            config,
+            index: None,
+            states: vec![],
+            last_fix: None,
+            last_match: None,
+        }
+    }
+
+    /// Forgets the previous fixes, e.g. after a teleport of the position.
+    pub fn reset(&mut self) {
+        self.index = None;
+        self.states.clear();
+        self.last_fix = None;
+        self.last_match = None;
+    }
+
+    /// Snaps the fix onto the most likely road, `None` when there are no roads around.
+    pub fn push(&mut self, fix: &GpsFix) -> Option<MatchedPosition> {
+        let index = self.road_network.index();
+        if let Some(states_index) = &self.index
+            && !Arc::ptr_eq(states_index, &index)
+        {
+            self.move_states(&index);
+        }
+        let world = mercator::lat_lon_to_world(&coord! {x: fix.lon, y: fix.lat});
+        let meters_per_unit = mercator::meters_per_world_unit(fix.lat);
+        let candidates = index.candidates(
+            &world,
+            self.config.search_radius / meters_per_unit,
+            self.config.max_candidates,
+        );
+        if candidates.is_empty() {
+            self.reset();
+            return None;
+        }
+
+        let emissions: Vec<f64> = candidates
+            .iter()
+            .map(|candidate| self.emission(&index, candidate, fix, meters_per_unit))
+            .collect();
+        let mut states = match self.last_fix {
+            Some(last_fix) if !self.states.is_empty() => {
+                let fix_distance = distance(&last_fix, &world);
+                self.transit(&index, &candidates, &emissions, fix_distance, meters_per_unit)
+            }
+            _ => vec![],
+        };
+        // no path connects the previous candidates with the new ones, the HMM is broken and starts over
+        if states.is_empty() {
+            states = candidates
+                .iter()
+                .zip(emissions)
+                .map(|(candidate, log_probability)| State {
+                    candidate: *candidate,
+                    log_probability,
+                })
+                .collect();
+        }
+
+        let best = states
+            .iter()
+            .max_by(|a, b| a.log_probability.total_cmp(&b.log_probability))
+            .map(|state| state.candidate)
+            .unwrap();
+        let bearing = self.bearing(&index, &best, fix);
+        // keeps the numbers in range on long traces
+        let max_log_probability = states.iter().map(|state| state.log_probability).fold(f64::MIN, f64::max);
+        states.iter_mut().for_each(|state| state.log_probability -= max_log_probability);
+
+        self.states = states;
+        self.index = Some(index.clone());
+        self.last_fix = Some(world);
+        self.last_match = Some(best);
+
+        Some(Self::matched_position(&index, &best, bearing, meters_per_unit))
+    }
+
+    /// Projects a predicted position, e.g. of the dead reckoning, onto the road of the last match.
+    /// The fixes matched before aren't changed by it, `None` when the road isn't around the position.
+    pub fn snap(&self, fix: &GpsFix) -> Option<MatchedPosition> {
+        let (index, last_match) = (self.index.as_ref()?, self.last_match?);
+        let road = index.segments[last_match.segment].road;
+        let world = mercator::lat_lon_to_world(&coord! {x: fix.lon, y: fix.lat});
+        let meters_per_unit = mercator::meters_per_world_unit(fix.lat);
+        let candidate = index
+            .candidates(&world, self.config.search_radius / meters_per_unit, usize::MAX)
+            .into_iter()
+            .find(|candidate| index.segments[candidate.segment].road == road)?;
+        let bearing = self.bearing(index, &candidate, fix);
+        Some(Self::matched_position(index, &candidate, bearing, meters_per_unit))
+    }
+
+    fn matched_position(
+        index: &RoadIndex,
+        candidate: &Candidate,
+        bearing: f32,
+        meters_per_unit: f64,
+    ) -> MatchedPosition {
+        let road = &index.roads[index.segments[candidate.segment].road];
+        let lat_lon = mercator::world_to_lat_lon(&candidate.position);
+        MatchedPosition {
+            lat: lat_lon.y,
+            lon: lat_lon.x,
+            bearing,
+            road_name: road.name.clone(),
+            road_class: road.class,
+            offset: candidate.distance * meters_per_unit,
+        }
+    }
+
+    // Finds the candidates of the states in the rebuilt index by their positions, the states whose roads
+    // are gone are dropped
+    fn move_states(&mut self, index: &Arc<RoadIndex>) {
+        self.states = std::mem::take(&mut self.states)
+            .into_iter()
+            .filter_map(|state| {
+                let candidate = index
+                    .candidates(&state.candidate.position, Self::MOVED_STATE_RADIUS, 1)
+                    .into_iter()
+                    .next()?;
+                Some(State {
+                    candidate: Candidate {
+                        distance: state.candidate.distance,
+                        ..candidate
+                    },
+                    log_probability: state.log_probability,
+                })
+            })
+            .collect();
+        self.index = Some(index.clone());
+    }
+
+    fn emission(&self, index: &RoadIndex, candidate: &Candidate, fix: &GpsFix, meters_per_unit: f64) -> f64 {
+        let z = candidate.distance * meters_per_unit / self.config.gps_sigma;
+        let heading = fix.bearing.map_or(0.0, |bearing| {
+            let segment = &index.segments[candidate.segment];
+            // roads are two-way
+            let difference = angle_difference(bearing as f64, bearing_of(&segment.start, &segment.end));
+            difference.min(180.0 - difference) / self.config.heading_sigma
+        });
+        -0.5 * (z * z + heading * heading)
+    }
+
+    fn transit(
+        &self,
+        index: &RoadIndex,
+        candidates: &[Candidate],
+        emissions: &[f64],
+        fix_distance: f64,
+        meters_per_unit: f64,
+    ) -> Vec<State> {
+        // detours longer than that are impossible between two fixes
+        let max_route_distance = fix_distance * 3.0 + self.config.search_radius * 2.0 / meters_per_unit;
+        candidates
+            .iter()
+            .zip(emissions)
+            .filter_map(|(candidate, emission)| {
+                self.states
+                    .iter()
+                    .filter_map(|state| {
+                        let route_distance =
+                            index.route_distance(&state.candidate, candidate, max_route_distance)?;
+                        let difference = (route_distance - fix_distance).abs() * meters_per_unit;
+                        Some(state.log_probability - difference / self.config.beta)
+                    })
+                    .max_by(f64::total_cmp)
+                    .map(|log_probability| State {
+                        candidate: *candidate,
+                        log_probability: log_probability + emission,
+                    })
+            })
+            .collect()
+    }
+
+    // Road direction which agrees with the GPS bearing, or with the movement since the last match
+    fn bearing(&self, index: &RoadIndex, candidate: &Candidate, fix: &GpsFix) -> f32 {
+        let segment = &index.segments[candidate.segment];
+        let forward = bearing_of(&segment.start, &segment.end);
+        let heading = match (fix.bearing, self.last_match) {
+            (Some(bearing), _) => Some(bearing as f64),
+            (None, Some(last)) if distance(&last.position, &candidate.position) > 0.0 => {
+                Some(bearing_of(&last.position, &candidate.position))
+            }
+            _ => None,
+        };
+        let bearing = match heading {
+            Some(heading) if angle_difference(heading, forward) > 90.0 => forward + 180.0,
+            _ => forward,
+        };
+        bearing.rem_euclid(360.0) as f32
+    }
+}
+
+// world y grows to the south
+fn bearing_of(from: &Coord, to: &Coord) -> f64 {
+    (to.x - from.x).atan2(from.y - to.y).to_degrees()
+}
+
+fn angle_difference(a: f64, b: f64) -> f64 {
+    let difference = (a - b).rem_euclid(360.0);
+    difference.min(360.0 - difference)
+}
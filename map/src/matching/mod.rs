@@ -0,0 +1,2 @@
+pub mod hmm_matcher;
+pub mod road_network;
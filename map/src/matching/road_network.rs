@@ -0,0 +1,307 @@
+use geo_types::{Coord, LineString};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Road of a tile in world coordinates.
+#[derive(Clone, Debug)]
+pub struct Road {
+    pub name: Option<String>,
+    pub class: &'static str,
+    pub line: LineString,
+}
+
+/// Road geometry of the recently loaded tiles, the map matcher snaps fixes onto it.
+/// Tiles are built on the loader threads, the search index is rebuilt lazily when it's requested.
+pub struct RoadNetwork {
+    state: Mutex<NetworkState>,
+}
+
+struct NetworkState {
+    tiles: VecDeque<(String, Arc<Vec<Road>>)>,
+    index: Option<Arc<RoadIndex>>,
+}
+
+impl RoadNetwork {
+    /// Older tiles are dropped, it's enough to cover the area around the puck
+    const MAX_TILES: usize = 64;
+
+    pub fn new() -> RoadNetwork {
+        RoadNetwork {
+            state: Mutex::new(NetworkState {
+                tiles: VecDeque::new(),
+                index: None,
+            }),
+        }
+    }
+
+    /// Replaces the roads of the tile if it's already known.
+    pub fn insert_tile(&self, tile_key: String, roads: Vec<Road>) {
+        let mut state = self.state.lock().unwrap();
+        let tiles_count = state.tiles.len();
+        state.tiles.retain(|(key, _)| *key != tile_key);
+        if roads.is_empty() {
+            if state.tiles.len() != tiles_count {
+                state.index = None;
+            }
+            return;
+        }
+        state.tiles.push_back((tile_key, Arc::new(roads)));
+        while state.tiles.len() > Self::MAX_TILES {
+            state.tiles.pop_front();
+        }
+        state.index = None;
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.tiles.clear();
+        state.index = None;
+    }
+
+    pub(crate) fn index(&self) -> Arc<RoadIndex> {
+        let mut state = self.state.lock().unwrap();
+        if state.index.is_none() {
+            let tiles: Vec<_> = state.tiles.iter().map(|(_, roads)| roads.clone()).collect();
+            state.index = Some(Arc::new(RoadIndex::new(&tiles)));
+        }
+        state.index.clone().unwrap()
+    }
+}
+
+impl Default for RoadNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Segment {
+    pub start: Coord,
+    pub end: Coord,
+    pub road: usize,
+}
+
+impl Segment {
+    pub fn length(&self) -> f64 {
+        distance(&self.start, &self.end)
+    }
+
+    pub fn point_at(&self, t: f64) -> Coord {
+        self.start + (self.end - self.start) * t
+    }
+}
+
+/// Projection of a point onto a segment.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Candidate {
+    pub segment: usize,
+    /// Position on the segment, 0 is its start and 1 is its end
+    pub t: f64,
+    pub position: Coord,
+    pub distance: f64,
+}
+
+pub(crate) type NodeKey = (i64, i64);
+
+/// Grid of road segments and their connectivity, road ends which share a coordinate are connected.
+pub(crate) struct RoadIndex {
+    pub roads: Vec<Road>,
+    pub segments: Vec<Segment>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    node_segments: HashMap<NodeKey, Vec<usize>>,
+}
+
+impl RoadIndex {
+    // ~100m, world units are ~8m in mid latitudes
+    const CELL_SIZE: f64 = 16.0;
+    // coordinates closer than that are the same road node
+    const NODE_PRECISION: f64 = 100.0;
+
+    fn new(tiles: &[Arc<Vec<Road>>]) -> RoadIndex {
+        let mut index = RoadIndex {
+            roads: vec![],
+            segments: vec![],
+            cells: HashMap::new(),
+            node_segments: HashMap::new(),
+        };
+        tiles.iter().flat_map(|roads| roads.iter()).for_each(|road| {
+            let road_index = index.roads.len();
+            index.roads.push(road.clone());
+            road.line.lines().for_each(|line| {
+                if line.start == line.end {
+                    return;
+                }
+                index.add_segment(Segment {
+                    start: line.start,
+                    end: line.end,
+                    road: road_index,
+                });
+            });
+        });
+        index
+    }
+
+    fn add_segment(&mut self, segment: Segment) {
+        let segment_index = self.segments.len();
+        self.segments.push(segment);
+
+        let (min_x, max_x) = min_max(segment.start.x, segment.end.x);
+        let (min_y, max_y) = min_max(segment.start.y, segment.end.y);
+        for cx in Self::cell(min_x)..=Self::cell(max_x) {
+            for cy in Self::cell(min_y)..=Self::cell(max_y) {
+                self.cells.entry((cx, cy)).or_default().push(segment_index);
+            }
+        }
+        for node in [Self::node_key(&segment.start), Self::node_key(&segment.end)] {
+            self.node_segments.entry(node).or_default().push(segment_index);
+        }
+    }
+
+    fn cell(value: f64) -> i64 {
+        (value / Self::CELL_SIZE).floor() as i64
+    }
+
+    pub(crate) fn node_key(coord: &Coord) -> NodeKey {
+        (
+            (coord.x * Self::NODE_PRECISION).round() as i64,
+            (coord.y * Self::NODE_PRECISION).round() as i64,
+        )
+    }
+
+    /// The nearest projections within `radius`, at most `limit` of them.
+    pub fn candidates(&self, point: &Coord, radius: f64, limit: usize) -> Vec<Candidate> {
+        let mut segments: Vec<usize> = vec![];
+        for cx in Self::cell(point.x - radius)..=Self::cell(point.x + radius) {
+            for cy in Self::cell(point.y - radius)..=Self::cell(point.y + radius) {
+                if let Some(cell) = self.cells.get(&(cx, cy)) {
+                    segments.extend(cell);
+                }
+            }
+        }
+        segments.sort_unstable();
+        segments.dedup();
+
+        let mut candidates: Vec<Candidate> = segments
+            .into_iter()
+            .map(|segment_index| {
+                let segment = &self.segments[segment_index];
+                let direction = segment.end - segment.start;
+                let length2 = direction.x * direction.x + direction.y * direction.y;
+                let to_point = *point - segment.start;
+                let t = ((to_point.x * direction.x + to_point.y * direction.y) / length2).clamp(0.0, 1.0);
+                let position = segment.point_at(t);
+                Candidate {
+                    segment: segment_index,
+                    t,
+                    position,
+                    distance: distance(&position, point),
+                }
+            })
+            .filter(|candidate| candidate.distance <= radius)
+            .collect();
+        candidates.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        candidates.truncate(limit);
+        candidates
+    }
+
+    /// Shortest distance along the roads, `None` if it's longer than `max_distance`.
+    /// Roads are treated as two-way.
+    pub fn route_distance(&self, from: &Candidate, to: &Candidate, max_distance: f64) -> Option<f64> {
+        let from_segment = &self.segments[from.segment];
+        let to_segment = &self.segments[to.segment];
+        let mut best = if from.segment == to.segment {
+            (to.t - from.t).abs() * from_segment.length()
+        } else {
+            f64::INFINITY
+        };
+
+        let mut distances: HashMap<NodeKey, f64> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        let from_length = from_segment.length();
+        for (node, node_distance) in [
+            (from_segment.start, from.t * from_length),
+            (from_segment.end, (1.0 - from.t) * from_length),
+        ] {
+            let key = Self::node_key(&node);
+            if node_distance < *distances.get(&key).unwrap_or(&f64::INFINITY) {
+                distances.insert(key, node_distance);
+                heap.push(QueueItem { distance: node_distance, node });
+            }
+        }
+
+        let to_length = to_segment.length();
+        let targets = [
+            (Self::node_key(&to_segment.start), to.t * to_length),
+            (Self::node_key(&to_segment.end), (1.0 - to.t) * to_length),
+        ];
+
+        while let Some(QueueItem { distance: node_distance, node }) = heap.pop() {
+            if node_distance >= best.min(max_distance) {
+                break;
+            }
+            let key = Self::node_key(&node);
+            if node_distance > *distances.get(&key).unwrap_or(&f64::INFINITY) {
+                continue;
+            }
+            targets
+                .iter()
+                .filter(|(target, _)| *target == key)
+                .for_each(|(_, rest)| best = best.min(node_distance + rest));
+
+            for segment_index in self.node_segments.get(&key).into_iter().flatten() {
+                let segment = &self.segments[*segment_index];
+                let next = if Self::node_key(&segment.start) == key {
+                    segment.end
+                } else {
+                    segment.start
+                };
+                let next_key = Self::node_key(&next);
+                let next_distance = node_distance + segment.length();
+                if next_distance < *distances.get(&next_key).unwrap_or(&f64::INFINITY) {
+                    distances.insert(next_key, next_distance);
+                    heap.push(QueueItem {
+                        distance: next_distance,
+                        node: next,
+                    });
+                }
+            }
+        }
+
+        (best <= max_distance).then_some(best)
+    }
+}
+
+struct QueueItem {
+    distance: f64,
+    node: Coord,
+}
+
+// min-heap by distance
+impl Ord for QueueItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+impl PartialOrd for QueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueueItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl Eq for QueueItem {}
+
+fn min_max(a: f64, b: f64) -> (f64, f64) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+pub(crate) fn distance(a: &Coord, b: &Coord) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
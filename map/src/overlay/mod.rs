@@ -0,0 +1,216 @@
+pub(crate) mod overlay_controller;
+
+use crate::style::style_sheet::{LABEL_STYLE_ID, intern};
+use cgmath::{Vector2, Vector3};
+use geo::{Centroid, CoordsIter};
+use geo_types::{Coord, Geometry, LineString, Point, Polygon};
+use lyon::geom::point;
+use lyon::lyon_tessellation::{LineCap, LineJoin};
+use lyon::path::Path;
+use renderer::canvas_api::CanvasApi;
+use renderer::draw_commands::{GeometryType, PolylineOptions};
+use renderer::geometry_data::{GeometryData, ShapeData, TextData};
+use renderer::styles::render_style::RenderStyle;
+use renderer::styles::style_id::StyleId;
+use std::collections::HashMap;
+
+/// Render styles of the lines and the polygons of an overlay shape.
+#[derive(Clone, Debug)]
+pub(crate) struct OverlayStyle {
+    pub line: StyleId,
+    /// Line width in pixels
+    pub width: f32,
+    pub fill: Option<StyleId>,
+    pub outline: bool,
+}
+
+impl OverlayStyle {
+    pub fn new(line: StyleId, fill: Option<StyleId>) -> OverlayStyle {
+        OverlayStyle {
+            line,
+            width: 2.0,
+            fill,
+            outline: true,
+        }
+    }
+}
+
+/// Shape of a KML or GeoJSON file in world coordinates.
+#[derive(Clone, Debug)]
+pub(crate) struct OverlayShape {
+    pub name: Option<String>,
+    pub geometry: Geometry,
+    pub style: OverlayStyle,
+}
+
+/// Styles of the colors of an overlay layer, they are named after the color only,
+/// so the layers share them and the number of the interned style ids stays bounded.
+#[derive(Clone, Default)]
+pub(crate) struct OverlayStyles {
+    render_styles: HashMap<StyleId, RenderStyle>,
+}
+
+impl OverlayStyles {
+    /// The alpha of the color becomes the opacity.
+    pub fn line_style(&mut self, color: [f32; 4]) -> StyleId {
+        self.color_style("line", color)
+    }
+
+    /// The alpha of the color becomes the opacity.
+    pub fn fill_style(&mut self, color: [f32; 4]) -> StyleId {
+        self.color_style("fill", color)
+    }
+
+    // `overlay_line_rrggbbaa`, the color is rounded to the name, so the same id has the same style
+    fn color_style(&mut self, kind: &str, color: [f32; 4]) -> StyleId {
+        let channels = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let name: String = channels.iter().map(|channel| format!("{channel:02x}")).collect();
+        let style_id = StyleId(intern(&format!("overlay_{kind}_{name}")));
+        let [r, g, b, a] = channels.map(|channel| channel as f32 / 255.0);
+        let render_style = RenderStyle::fill([r, g, b, 1.0]).with_opacity_stops(&[(0.0, a)]);
+        self.render_styles.insert(style_id.clone(), render_style);
+        style_id
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (StyleId, RenderStyle)> + '_ {
+        self.render_styles.iter().map(|(id, style)| (id.clone(), *style))
+    }
+}
+
+/// Draws the lines and the polygons relative to the first coordinate of the shapes.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OverlayPainter {
+    pub origin: Coord,
+}
+
+impl OverlayPainter {
+    // the width of the lines at the closest zoom, like the routes they are widened when zooming out
+    const WORLD_UNITS_PER_PIXEL: f32 = 0.5;
+    const LABEL_SIZE: f32 = 30.0;
+
+    pub fn new(shapes: &[OverlayShape]) -> OverlayPainter {
+        let origin = shapes
+            .iter()
+            .find_map(|shape| shape.geometry.coords_iter().next())
+            .unwrap_or(Coord::zero());
+        OverlayPainter { origin }
+    }
+
+    pub fn origin(&self) -> Vector3<f64> {
+        Vector3::new(self.origin.x, self.origin.y, 0.0)
+    }
+
+    pub fn relative(&self, coord: &Coord) -> Vector3<f32> {
+        Vector3::new((coord.x - self.origin.x) as f32, (coord.y - self.origin.y) as f32, 0.0)
+    }
+
+    fn path_point(&self, coord: &Coord) -> lyon::math::Point {
+        let relative = self.relative(coord);
+        point(relative.x, relative.y)
+    }
+
+    /// Lines and polygons of the geometry, the points are left to the caller.
+    pub fn draw_geometry(&self, canvas: &mut CanvasApi, geometry: &Geometry, style: &OverlayStyle) {
+        match geometry {
+            Geometry::Line(line) => self.draw_line(canvas, &LineString::from(*line), style),
+            Geometry::LineString(line) => self.draw_line(canvas, line, style),
+            Geometry::MultiLineString(lines) => lines.iter().for_each(|line| self.draw_line(canvas, line, style)),
+            Geometry::Polygon(polygon) => self.draw_polygon(canvas, polygon, style),
+            Geometry::MultiPolygon(polygons) => {
+                polygons.iter().for_each(|polygon| self.draw_polygon(canvas, polygon, style))
+            }
+            Geometry::GeometryCollection(collection) => collection
+                .iter()
+                .for_each(|geometry| self.draw_geometry(canvas, geometry, style)),
+            _ => {}
+        }
+    }
+
+    fn draw_line(&self, canvas: &mut CanvasApi, line: &LineString, style: &OverlayStyle) {
+        if line.0.len() < 2 {
+            return;
+        }
+        let mut path_builder = Path::builder();
+        path_builder.begin(self.path_point(&line.0[0]));
+        line.0[1..].iter().for_each(|coord| {
+            path_builder.line_to(self.path_point(coord));
+        });
+        path_builder.end(false);
+        canvas.path(ShapeData {
+            path: path_builder.build(),
+            geometry_type: GeometryType::Polyline(PolylineOptions {
+                width: style.width * Self::WORLD_UNITS_PER_PIXEL,
+                line_join: LineJoin::Round,
+                line_cap: LineCap::Round,
+                tolerance: 0.05,
+            }),
+            style_id: style.line.clone(),
+            index_layer_level: 1,
+            is_screen: false,
+        });
+    }
+
+    fn draw_polygon(&self, canvas: &mut CanvasApi, polygon: &Polygon, style: &OverlayStyle) {
+        if let Some(fill) = &style.fill {
+            // the holes are sub paths, the even-odd fill leaves them out
+            let mut path_builder = Path::builder();
+            std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .filter(|ring| ring.0.len() > 2)
+                .for_each(|ring| {
+                    path_builder.begin(self.path_point(&ring.0[0]));
+                    ring.0[1..].iter().for_each(|coord| {
+                        path_builder.line_to(self.path_point(coord));
+                    });
+                    path_builder.end(true);
+                });
+            canvas.path(ShapeData {
+                path: path_builder.build(),
+                geometry_type: GeometryType::Polygon,
+                style_id: fill.clone(),
+                index_layer_level: 0,
+                is_screen: false,
+            });
+        }
+        if style.outline {
+            std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .for_each(|ring| self.draw_line(canvas, ring, style));
+        }
+    }
+
+    /// Label along the line or in the middle of the other shapes, the points are left to the caller.
+    pub fn draw_label(&self, canvas: &mut CanvasApi, id: u64, text: &str, geometry: &Geometry) {
+        let positions = match geometry {
+            Geometry::Point(_) | Geometry::MultiPoint(_) => return,
+            // FIXME TextRenderer has a bug for only 2 coords line
+            Geometry::LineString(line) if line.0.len() > 2 => line.0.iter().map(|c| self.relative(c)).collect(),
+            geometry => match geometry.centroid() {
+                Some(centroid) => vec![self.relative(&centroid.0)],
+                None => return,
+            },
+        };
+        canvas.geometry_data(GeometryData::Text(TextData {
+            id,
+            text: text.to_string(),
+            screen_offset: Vector2::new(0.0, 0.0),
+            size: Self::LABEL_SIZE,
+            positions,
+            style_id: LABEL_STYLE_ID,
+            rank: 0,
+            symbol_id: None,
+        }));
+    }
+}
+
+/// Calls `f` for the points of the geometry.
+pub(crate) fn for_each_point(geometry: &Geometry, f: &mut impl FnMut(&Point)) {
+    match geometry {
+        Geometry::Point(point) => f(point),
+        Geometry::MultiPoint(points) => points.iter().for_each(f),
+        Geometry::GeometryCollection(collection) => {
+            collection.iter().for_each(|geometry| for_each_point(geometry, f))
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,137 @@
+use crate::clustering::cluster_controller::ClusterController;
+use crate::geojson_group::GeoJsonGroup;
+use crate::kml_viewer_group::KmlGroup;
+use crate::picking::FeatureIndex;
+use geo_types::coord;
+use renderer::modifier::render_modifier::SpatialData;
+use renderer::renderer_api::RendererApi;
+use renderer::styles::render_style::RenderStyle;
+use renderer::styles::style_id::StyleId;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Arc;
+
+/// Keeps the render groups of the KML and GeoJSON layers and their features for the picking.
+/// The points of the visible KML layers are clustered.
+pub(crate) struct OverlayController {
+    api: Arc<RendererApi>,
+    feature_index: Arc<FeatureIndex>,
+    // the KML files by the layer names and whether they are visible
+    kml_layers: BTreeMap<String, (KmlGroup, bool)>,
+    geojson_layers: BTreeSet<String>,
+}
+
+impl OverlayController {
+    // the layer of the routes and the annotations, over the tiles
+    const LAYER: usize = 1;
+
+    pub fn new(api: Arc<RendererApi>, feature_index: Arc<FeatureIndex>) -> OverlayController {
+        OverlayController {
+            api,
+            feature_index,
+            kml_layers: BTreeMap::new(),
+            geojson_layers: BTreeSet::new(),
+        }
+    }
+
+    /// Shows the KML layer, it replaces the layer with the same name.
+    pub fn add_kml_layer(&mut self, name: &str, kml_group: KmlGroup, clusters: &mut ClusterController) {
+        self.update_styles(kml_group.render_styles());
+        self.remove_kml_layer(name, clusters);
+        self.kml_layers.insert(name.to_string(), (kml_group, false));
+        self.set_kml_layer_visible(name, true, clusters);
+    }
+
+    /// Returns false if there is no layer with the name.
+    pub fn set_kml_layer_visible(&mut self, name: &str, visible: bool, clusters: &mut ClusterController) -> bool {
+        let Some((kml_group, layer_visible)) = self.kml_layers.get_mut(name) else {
+            return false;
+        };
+        if *layer_visible == visible {
+            return true;
+        }
+        *layer_visible = visible;
+        let key = Self::kml_layer_key(name);
+        if visible {
+            self.feature_index
+                .insert(key.clone(), coord! {x: 0.0, y: 0.0}, kml_group.features());
+            clusters.set_items(&key, kml_group.cluster_items());
+            self.api.add_render_group(
+                key,
+                Self::LAYER,
+                SpatialData::transform(kml_group.origin()),
+                Box::new(kml_group.clone()),
+            );
+        } else {
+            let keys = HashSet::from([key.clone()]);
+            self.feature_index.remove(&keys);
+            clusters.remove_items(&key);
+            self.api.clear_render_groups(keys);
+        }
+        true
+    }
+
+    /// Returns false if there is no layer with the name.
+    pub fn remove_kml_layer(&mut self, name: &str, clusters: &mut ClusterController) -> bool {
+        self.set_kml_layer_visible(name, false, clusters);
+        self.kml_layers.remove(name).is_some()
+    }
+
+    /// Names of the KML layers and whether they are visible.
+    pub fn kml_layers(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.kml_layers
+            .iter()
+            .map(|(name, (_, visible))| (name.as_str(), *visible))
+    }
+
+    /// Shows the GeoJSON layer, it replaces the layer with the same name.
+    pub fn add_geojson_layer(&mut self, name: &str, group: GeoJsonGroup) {
+        self.update_styles(group.render_styles());
+        self.remove_geojson_layer(name);
+        let key = Self::geojson_layer_key(name);
+        self.feature_index
+            .insert(key.clone(), coord! {x: 0.0, y: 0.0}, group.features());
+        self.api
+            .add_render_group(key, Self::LAYER, SpatialData::transform(group.origin()), Box::new(group));
+        self.geojson_layers.insert(name.to_string());
+    }
+
+    /// Returns false if there is no layer with the name.
+    pub fn remove_geojson_layer(&mut self, name: &str) -> bool {
+        if !self.geojson_layers.remove(name) {
+            return false;
+        }
+        let keys = HashSet::from([Self::geojson_layer_key(name)]);
+        self.feature_index.remove(&keys);
+        self.api.clear_render_groups(keys);
+        true
+    }
+
+    pub fn geojson_layers(&self) -> impl Iterator<Item = &str> {
+        self.geojson_layers.iter().map(String::as_str)
+    }
+
+    /// Render groups of the shown layers, their lines are widened like the routes.
+    pub fn group_keys(&self) -> impl Iterator<Item = String> + '_ {
+        let kml_keys = self
+            .kml_layers
+            .iter()
+            .filter(|(_, (_, visible))| *visible)
+            .map(|(name, _)| Self::kml_layer_key(name));
+        let geojson_keys = self.geojson_layers.iter().map(|name| Self::geojson_layer_key(name));
+        kml_keys.chain(geojson_keys)
+    }
+
+    fn update_styles(&self, render_styles: impl Iterator<Item = (StyleId, RenderStyle)>) {
+        render_styles.for_each(|(style_id, render_style)| {
+            self.api.update_style(style_id, move |style| *style = render_style);
+        });
+    }
+
+    fn kml_layer_key(name: &str) -> String {
+        format!("kml_{name}")
+    }
+
+    fn geojson_layer_key(name: &str) -> String {
+        format!("geojson_{name}")
+    }
+}
@@ -0,0 +1,113 @@
+use crate::tiles::tile_data::{FeatureKind, TileFeature};
+use crate::tiles::tiles_provider::TilesProvider;
+use geo::{Centroid, Closest, ClosestPoint};
+use geo_types::{Coord, Geometry, Point, coord};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Feature found on the map by `ShashlikMap::features_at_screen_point`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PickedFeature {
+    pub kind: FeatureKind,
+    pub name: Option<String>,
+    /// (lat, lon) of a point, the nearest point of a line or the center of a polygon
+    pub lat_lon: (f64, f64),
+    /// Render group which draws the feature, e.g. the tile
+    pub group_key: String,
+}
+
+/// Pickable features of the render groups on the map.
+/// Tiles are added on the loader thread and removed together with their render groups.
+pub struct FeatureIndex {
+    groups: Mutex<HashMap<String, FeatureGroup>>,
+}
+
+#[derive(Clone)]
+struct FeatureGroup {
+    origin: Coord,
+    features: Arc<Vec<TileFeature>>,
+}
+
+impl FeatureIndex {
+    pub fn new() -> FeatureIndex {
+        FeatureIndex {
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces the features of the group, their geometry is relative to the world `origin`.
+    pub fn insert(&self, group_key: String, origin: Coord, features: Vec<TileFeature>) {
+        let mut groups = self.groups.lock().unwrap();
+        if features.is_empty() {
+            groups.remove(&group_key);
+        } else {
+            let features = Arc::new(features);
+            groups.insert(group_key, FeatureGroup { origin, features });
+        }
+    }
+
+    pub fn remove(&self, group_keys: &HashSet<String>) {
+        self.groups
+            .lock()
+            .unwrap()
+            .retain(|group_key, _| !group_keys.contains(group_key));
+    }
+
+    /// Features within `tolerance` meters of the (lat, lon) with the distance to them, the nearest first.
+    /// Named features cut by the tiles are found once, the origins are in the world of the tiles provider `T`.
+    pub fn pick<T: TilesProvider>(&self, lat_lon: (f64, f64), tolerance: f64) -> Vec<(f64, PickedFeature)> {
+        let point = T::lat_lon_to_world(&coord! {x: lat_lon.1, y: lat_lon.0});
+        let meters_per_world_unit = T::meters_per_world_unit(lat_lon.0);
+        let groups: Vec<_> = self
+            .groups
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(group_key, group)| (group_key.clone(), group.clone()))
+            .collect();
+
+        let mut picked = vec![];
+        for (group_key, FeatureGroup { origin, features }) in groups {
+            let local = Point(point - origin);
+            for feature in features.iter() {
+                let nearest = match feature.geometry.closest_point(&local) {
+                    Closest::Intersection(nearest) | Closest::SinglePoint(nearest) => nearest,
+                    Closest::Indeterminate => continue,
+                };
+                let offset = nearest.0 - local.0;
+                let distance = offset.x.hypot(offset.y) * meters_per_world_unit;
+                if distance > tolerance {
+                    continue;
+                }
+                let position = match &feature.geometry {
+                    Geometry::Polygon(polygon) => polygon.centroid().unwrap_or(nearest),
+                    _ => nearest,
+                };
+                let position = T::world_to_lat_lon(&(origin + position.0));
+                picked.push((
+                    distance,
+                    PickedFeature {
+                        kind: feature.kind,
+                        name: feature.name.clone(),
+                        lat_lon: (position.y, position.x),
+                        group_key: group_key.clone(),
+                    },
+                ));
+            }
+        }
+        picked.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut named = HashSet::new();
+        picked.retain(|(_, feature)| match &feature.name {
+            Some(name) => named.insert((feature.kind, name.clone())),
+            None => true,
+        });
+        picked
+    }
+}
+
+impl Default for FeatureIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
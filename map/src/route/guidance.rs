@@ -0,0 +1,245 @@
+use crate::matching::road_network::distance;
+use crate::route::maneuver::{Maneuver, Route};
+use crate::tiles::mercator;
+use geo_types::{Coord, coord};
+
+#[derive(Clone, Debug)]
+pub enum GuidanceEvent {
+    /// Sent when the maneuver becomes the next one and when it passes an announcement distance
+    UpcomingManeuver { maneuver: Maneuver, distance: f64 },
+    /// Distances in meters along the route, sent on every location update
+    Progress {
+        distance_to_maneuver: f64,
+        distance_remaining: f64,
+    },
+    Arrived,
+    /// Sent once when the location leaves the route, too far from it or heading the other way
+    OffRoute { distance_from_route: f64 },
+    /// A new route from the current location replaced the one which was left
+    Rerouted,
+    /// The routing provider couldn't calculate the requested route
+    RouteFailed { message: String },
+}
+
+pub type GuidanceListener = Box<dyn Fn(&GuidanceEvent) + Send>;
+
+#[derive(Clone, Debug)]
+pub struct GuidanceConfig {
+    /// Locations further than it from the route are off the route, in meters
+    pub off_route_distance: f64,
+    /// Off the route location has to come that close to be on the route again, in meters
+    pub on_route_distance: f64,
+    /// Bearing difference with the route which is off the route, in degrees
+    pub off_route_heading: f64,
+    /// Bearing difference to be on the route again, in degrees
+    pub on_route_heading: f64,
+    /// Off the route locations in a row before `OffRoute`, single GPS outliers are ignored
+    pub off_route_fixes: usize,
+    /// Distance to the end of the route which counts as the arrival, in meters
+    pub arrival_distance: f64,
+    /// The upcoming maneuver is announced again at these distances, in meters, the longest first
+    pub announcement_distances: Vec<f64>,
+}
+
+impl Default for GuidanceConfig {
+    fn default() -> Self {
+        GuidanceConfig {
+            off_route_distance: 40.0,
+            on_route_distance: 20.0,
+            off_route_heading: 90.0,
+            on_route_heading: 45.0,
+            off_route_fixes: 3,
+            arrival_distance: 20.0,
+            announcement_distances: vec![1000.0, 300.0, 50.0],
+        }
+    }
+}
+
+/// Tracks the progress along a route from location updates and tells what's next.
+pub struct Guidance {
+    route: Route,
+    config: GuidanceConfig,
+    // shape of the route in world coordinates
+    points: Vec<Coord>,
+    // distance along the route to every shape point, in meters
+    distances: Vec<f64>,
+    segment: usize,
+    progress: f64,
+    next_maneuver: Option<usize>,
+    announced: usize,
+    off_route_fixes: usize,
+    off_route: bool,
+    arrived: bool,
+}
+
+impl Guidance {
+    // the progress doesn't jump further ahead than that between updates, e.g. to a later part of a loop
+    const LOOKAHEAD_DISTANCE: f64 = 500.0;
+    // GPS noise moves the location back and forth along the route by a few meters
+    const BACKWARD_TOLERANCE: f64 = 20.0;
+
+    /// The route shape has at least 2 points and the maneuvers start on it like the ones of the routing providers.
+    pub fn new(route: Route, config: GuidanceConfig) -> Guidance {
+        if let Err(err) = route.check() {
+            panic!("{err}");
+        }
+        let points: Vec<Coord> = route.shape.iter().map(mercator::lat_lon_to_world).collect();
+        let mut distances = vec![0.0];
+        route.shape.windows(2).zip(points.windows(2)).for_each(|(lat_lon, world)| {
+            let length = distance(&world[0], &world[1]) * mercator::meters_per_world_unit(lat_lon[0].y);
+            distances.push(distances.last().unwrap() + length);
+        });
+        Guidance {
+            route,
+            config,
+            points,
+            distances,
+            segment: 0,
+            progress: 0.0,
+            next_maneuver: None,
+            announced: 0,
+            off_route_fixes: 0,
+            off_route: false,
+            arrived: false,
+        }
+    }
+
+    pub fn route(&self) -> &Route {
+        &self.route
+    }
+
+    /// Distance along the route to the last location on it, in meters.
+    pub fn progress(&self) -> f64 {
+        self.progress
+    }
+
+    /// Shape segment of the last location on the route and the part of the segment behind it.
+    pub fn position(&self) -> (usize, f64) {
+        let length = self.distances[self.segment + 1] - self.distances[self.segment];
+        let part = if length > 0.0 { (self.progress - self.distances[self.segment]) / length } else { 0.0 };
+        (self.segment, part.clamp(0.0, 1.0))
+    }
+
+    /// Index of the next maneuver in the route maneuvers, none before the first location on the route.
+    pub fn next_maneuver(&self) -> Option<usize> {
+        self.next_maneuver
+    }
+
+    pub fn length(&self) -> f64 {
+        *self.distances.last().unwrap()
+    }
+
+    pub fn is_off_route(&self) -> bool {
+        self.off_route
+    }
+
+    pub fn has_arrived(&self) -> bool {
+        self.arrived
+    }
+
+    /// `bearing` is in degrees clockwise from the north, when the location has it.
+    pub fn update(&mut self, lat: f64, lon: f64, bearing: Option<f32>) -> Vec<GuidanceEvent> {
+        let mut events = vec![];
+        if self.arrived {
+            return events;
+        }
+        let world = mercator::lat_lon_to_world(&coord! {x: lon, y: lat});
+        let meters_per_unit = mercator::meters_per_world_unit(lat);
+        let (segment, progress, distance_from_route) = self.project(&world, meters_per_unit);
+
+        let heading_difference = bearing.map(|bearing| {
+            let start = self.points[segment];
+            let end = self.points[segment + 1];
+            // world y grows to the south
+            let segment_bearing = (end.x - start.x).atan2(start.y - end.y).to_degrees();
+            let difference = (bearing as f64 - segment_bearing).rem_euclid(360.0);
+            difference.min(360.0 - difference)
+        });
+        // the location has to come closer to get back on the route than to leave it
+        let (max_distance, max_heading) = if self.off_route {
+            (self.config.on_route_distance, self.config.on_route_heading)
+        } else {
+            (self.config.off_route_distance, self.config.off_route_heading)
+        };
+        if distance_from_route > max_distance || heading_difference.is_some_and(|difference| difference > max_heading) {
+            self.off_route_fixes += 1;
+            if self.off_route_fixes >= self.config.off_route_fixes && !self.off_route {
+                self.off_route = true;
+                events.push(GuidanceEvent::OffRoute { distance_from_route });
+            }
+            return events;
+        }
+        self.off_route_fixes = 0;
+        self.off_route = false;
+        if progress >= self.progress || self.progress - progress > Self::BACKWARD_TOLERANCE {
+            self.segment = segment;
+            self.progress = progress;
+        }
+        let progress = self.progress;
+
+        let next_maneuver = self
+            .route
+            .maneuvers
+            .iter()
+            .position(|maneuver| self.distances[maneuver.begin_shape_index] > progress);
+        if let Some(next_maneuver) = next_maneuver {
+            let maneuver = &self.route.maneuvers[next_maneuver];
+            let distance = self.distances[maneuver.begin_shape_index] - progress;
+            let crossed = self
+                .config
+                .announcement_distances
+                .iter()
+                .filter(|announcement| distance <= **announcement)
+                .count();
+            if self.next_maneuver != Some(next_maneuver) || crossed > self.announced {
+                events.push(GuidanceEvent::UpcomingManeuver {
+                    maneuver: maneuver.clone(),
+                    distance,
+                });
+            }
+            self.next_maneuver = Some(next_maneuver);
+            self.announced = crossed;
+        }
+
+        let distance_remaining = self.length() - progress;
+        let distance_to_maneuver = self
+            .next_maneuver
+            .map_or(distance_remaining, |next_maneuver| {
+                self.distances[self.route.maneuvers[next_maneuver].begin_shape_index] - progress
+            })
+            .max(0.0);
+        events.push(GuidanceEvent::Progress {
+            distance_to_maneuver,
+            distance_remaining,
+        });
+        if distance_remaining <= self.config.arrival_distance {
+            self.arrived = true;
+            events.push(GuidanceEvent::Arrived);
+        }
+        events
+    }
+
+    // (segment, distance along the route, distance from the route) of the nearest point ahead
+    fn project(&self, world: &Coord, meters_per_unit: f64) -> (usize, f64, f64) {
+        let first = self.segment.saturating_sub(1);
+        let max_progress = self.progress + Self::LOOKAHEAD_DISTANCE;
+        (first..self.points.len() - 1)
+            .take_while(|segment| self.distances[*segment] <= max_progress)
+            .map(|segment| {
+                let start = self.points[segment];
+                let direction = self.points[segment + 1] - start;
+                let length2 = direction.x * direction.x + direction.y * direction.y;
+                let to_point = *world - start;
+                let t = if length2 > 0.0 {
+                    ((to_point.x * direction.x + to_point.y * direction.y) / length2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let along = self.distances[segment] + (self.distances[segment + 1] - self.distances[segment]) * t;
+                let offset = distance(&(start + direction * t), world) * meters_per_unit;
+                (segment, along, offset)
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .unwrap()
+    }
+}
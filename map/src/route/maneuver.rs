@@ -0,0 +1,226 @@
+use crate::matching::road_network::distance;
+use crate::route::RouteCosting;
+use crate::tiles::mercator;
+use anyhow::anyhow;
+use geo_types::{Coord, coord};
+use valhalla_client::route::{ManeuverType, Trip};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManeuverKind {
+    Depart,
+    Continue,
+    SlightLeft,
+    Left,
+    SharpLeft,
+    SlightRight,
+    Right,
+    SharpRight,
+    UTurn,
+    KeepLeft,
+    KeepRight,
+    RampLeft,
+    RampRight,
+    Merge,
+    RoundaboutEnter,
+    RoundaboutExit,
+    Ferry,
+    Arrive,
+}
+
+#[derive(Clone, Debug)]
+pub struct Maneuver {
+    pub kind: ManeuverKind,
+    pub instruction: String,
+    pub street_names: Vec<String>,
+    /// Index of the route shape point where the maneuver starts
+    pub begin_shape_index: usize,
+}
+
+impl Maneuver {
+    /// Maneuver with an instruction made of its kind and street, for the engines which have no instructions.
+    pub(crate) fn new(kind: ManeuverKind, street_name: Option<String>, begin_shape_index: usize) -> Maneuver {
+        let action = match kind {
+            ManeuverKind::Depart => "Depart",
+            ManeuverKind::Continue => "Continue",
+            ManeuverKind::SlightLeft => "Bear left",
+            ManeuverKind::Left => "Turn left",
+            ManeuverKind::SharpLeft => "Make a sharp left",
+            ManeuverKind::SlightRight => "Bear right",
+            ManeuverKind::Right => "Turn right",
+            ManeuverKind::SharpRight => "Make a sharp right",
+            ManeuverKind::UTurn => "Make a U-turn",
+            ManeuverKind::KeepLeft => "Keep left",
+            ManeuverKind::KeepRight => "Keep right",
+            ManeuverKind::RampLeft => "Take the ramp on the left",
+            ManeuverKind::RampRight => "Take the ramp on the right",
+            ManeuverKind::Merge => "Merge",
+            ManeuverKind::RoundaboutEnter => "Enter the roundabout",
+            ManeuverKind::RoundaboutExit => "Exit the roundabout",
+            ManeuverKind::Ferry => "Take the ferry",
+            ManeuverKind::Arrive => "Arrive",
+        };
+        let instruction = match (kind, &street_name) {
+            (ManeuverKind::Arrive, _) => "You have arrived at your destination.".to_string(),
+            (_, Some(street_name)) => format!("{action} onto {street_name}."),
+            (_, None) => format!("{action}."),
+        };
+        Maneuver {
+            kind,
+            instruction,
+            street_names: street_name.into_iter().collect(),
+            begin_shape_index,
+        }
+    }
+}
+
+/// Route with its turn-by-turn maneuvers, the shape is in lat/lon(x is lon).
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub shape: Vec<Coord>,
+    pub maneuvers: Vec<Maneuver>,
+    pub costing: RouteCosting,
+}
+
+impl Route {
+    /// Legs of the trip are joined into one route, the arrivals of the intermediate legs are dropped.
+    pub fn from_trip(trip: &Trip, costing: RouteCosting) -> anyhow::Result<Route> {
+        let mut shape: Vec<Coord> = vec![];
+        let mut maneuvers: Vec<Maneuver> = vec![];
+        for (leg_index, leg) in trip.legs.iter().enumerate() {
+            // legs share the via point
+            let offset = shape.len().saturating_sub(1);
+            let is_last_leg = leg_index + 1 == trip.legs.len();
+            maneuvers.extend(
+                leg.maneuvers
+                    .iter()
+                    .map(|maneuver| Maneuver {
+                        kind: ManeuverKind::from(&maneuver.type_),
+                        instruction: maneuver.instruction.clone(),
+                        street_names: maneuver.street_names.clone().unwrap_or_default(),
+                        begin_shape_index: offset + maneuver.begin_shape_index,
+                    })
+                    .filter(|maneuver| {
+                        (leg_index == 0 || maneuver.kind != ManeuverKind::Depart)
+                            && (is_last_leg || maneuver.kind != ManeuverKind::Arrive)
+                    }),
+            );
+            let points = leg.shape.iter().map(|p| coord! {x: p.lon, y: p.lat});
+            shape.extend(points.skip(if leg_index == 0 { 0 } else { 1 }));
+        }
+        let route = Route {
+            shape,
+            maneuvers,
+            costing,
+        };
+        route.check()?;
+        Ok(route)
+    }
+
+    /// Route through the waypoints of the consecutive legs, the arrivals of the intermediate legs are dropped.
+    pub fn join(legs: Vec<Route>) -> anyhow::Result<Route> {
+        let costing = legs.first().ok_or_else(|| anyhow!("The route has no legs"))?.costing;
+        let mut shape: Vec<Coord> = vec![];
+        let mut maneuvers: Vec<Maneuver> = vec![];
+        let leg_count = legs.len();
+        for (leg_index, leg) in legs.into_iter().enumerate() {
+            // legs share the waypoint
+            let offset = shape.len().saturating_sub(1);
+            let is_last_leg = leg_index + 1 == leg_count;
+            maneuvers.extend(
+                leg.maneuvers
+                    .into_iter()
+                    .filter(|maneuver| {
+                        (leg_index == 0 || maneuver.kind != ManeuverKind::Depart)
+                            && (is_last_leg || maneuver.kind != ManeuverKind::Arrive)
+                    })
+                    .map(|maneuver| Maneuver {
+                        begin_shape_index: offset + maneuver.begin_shape_index,
+                        ..maneuver
+                    }),
+            );
+            shape.extend(leg.shape.into_iter().skip(if leg_index == 0 { 0 } else { 1 }));
+        }
+        let route = Route {
+            shape,
+            maneuvers,
+            costing,
+        };
+        route.check()?;
+        Ok(route)
+    }
+
+    /// The route has a segment and its maneuvers start on the shape, the indices come from the server.
+    pub(crate) fn check(&self) -> anyhow::Result<()> {
+        if self.shape.len() < 2 {
+            return Err(anyhow!("The route has no shape"));
+        }
+        if let Some(maneuver) = self
+            .maneuvers
+            .iter()
+            .find(|maneuver| maneuver.begin_shape_index >= self.shape.len())
+        {
+            return Err(anyhow!(
+                "The maneuver {:?} starts at {} outside of the route shape",
+                maneuver.instruction,
+                maneuver.begin_shape_index
+            ));
+        }
+        Ok(())
+    }
+
+    /// Distance from the (lat, lon) to the nearest point of the route, in meters.
+    pub fn distance_from(&self, lat_lon: (f64, f64)) -> f64 {
+        self.nearest_point(lat_lon).map_or(f64::INFINITY, |(_, distance)| distance)
+    }
+
+    /// The (lat, lon) of the route nearest to the (lat, lon) and the distance to it in meters,
+    /// none for a route without segments.
+    pub fn nearest_point(&self, lat_lon: (f64, f64)) -> Option<((f64, f64), f64)> {
+        let point = mercator::lat_lon_to_world(&coord! {x: lat_lon.1, y: lat_lon.0});
+        let world: Vec<Coord> = self.shape.iter().map(mercator::lat_lon_to_world).collect();
+        let nearest = world
+            .windows(2)
+            .map(|segment| {
+                let direction = segment[1] - segment[0];
+                let to_point = point - segment[0];
+                let length2 = direction.x * direction.x + direction.y * direction.y;
+                let t = if length2 > 0.0 {
+                    ((to_point.x * direction.x + to_point.y * direction.y) / length2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                segment[0] + direction * t
+            })
+            .min_by(|a, b| distance(a, &point).total_cmp(&distance(b, &point)))?;
+        let nearest_lat_lon = mercator::world_to_lat_lon(&nearest);
+        let meters = distance(&nearest, &point) * mercator::meters_per_world_unit(lat_lon.0);
+        Some(((nearest_lat_lon.y, nearest_lat_lon.x), meters))
+    }
+}
+
+impl From<&ManeuverType> for ManeuverKind {
+    fn from(value: &ManeuverType) -> Self {
+        match value {
+            ManeuverType::Start | ManeuverType::StartRight | ManeuverType::StartLeft => ManeuverKind::Depart,
+            ManeuverType::Destination | ManeuverType::DestinationRight | ManeuverType::DestinationLeft => {
+                ManeuverKind::Arrive
+            }
+            ManeuverType::SlightLeft => ManeuverKind::SlightLeft,
+            ManeuverType::Left => ManeuverKind::Left,
+            ManeuverType::SharpLeft => ManeuverKind::SharpLeft,
+            ManeuverType::SlightRight => ManeuverKind::SlightRight,
+            ManeuverType::Right => ManeuverKind::Right,
+            ManeuverType::SharpRight => ManeuverKind::SharpRight,
+            ManeuverType::UturnLeft | ManeuverType::UturnRight => ManeuverKind::UTurn,
+            ManeuverType::StayLeft => ManeuverKind::KeepLeft,
+            ManeuverType::StayRight => ManeuverKind::KeepRight,
+            ManeuverType::RampLeft | ManeuverType::ExitLeft => ManeuverKind::RampLeft,
+            ManeuverType::RampRight | ManeuverType::ExitRight => ManeuverKind::RampRight,
+            ManeuverType::Merge | ManeuverType::MergeLeft | ManeuverType::MergeRight => ManeuverKind::Merge,
+            ManeuverType::RoundaboutEnter => ManeuverKind::RoundaboutEnter,
+            ManeuverType::RoundaboutExit => ManeuverKind::RoundaboutExit,
+            ManeuverType::FerryEnter | ManeuverType::FerryExit => ManeuverKind::Ferry,
+            _ => ManeuverKind::Continue,
+        }
+    }
+}
@@ -1,6 +1,12 @@
+pub mod guidance;
+pub mod maneuver;
+pub mod provider;
+pub mod reroute;
+pub mod road_graph;
 pub(crate) mod route_controller;
 pub(crate) mod route_group;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RouteCosting {
     Pedestrian, Motorbike
 }
@@ -0,0 +1,41 @@
+use crate::route::RouteCosting;
+use crate::route::maneuver::Route;
+use crate::route::provider::valhalla_provider::ValhallaResponse;
+use crate::route::provider::{RouteRequest, RoutingProvider};
+use anyhow::Context;
+use std::path::Path;
+
+/// Answers every request with the same routes, for the tests and the demos without a network.
+pub struct FixtureRoutingProvider {
+    routes: Vec<Route>,
+}
+
+impl FixtureRoutingProvider {
+    /// The first route is the best one, the others are the alternatives.
+    pub fn new(routes: Vec<Route>) -> Self {
+        FixtureRoutingProvider { routes }
+    }
+
+    /// A saved response of the Valhalla route API, with its alternates.
+    pub fn load(path: impl AsRef<Path>, costing: RouteCosting) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).with_context(|| format!("Can't read {:?}", path))?;
+        let response: ValhallaResponse =
+            serde_json::from_str(&json).with_context(|| format!("Not a Valhalla route {:?}", path))?;
+        Ok(Self::new(response.into_routes(costing)?))
+    }
+}
+
+impl RoutingProvider for FixtureRoutingProvider {
+    fn route(&self, request: &RouteRequest) -> anyhow::Result<Vec<Route>> {
+        Ok(self
+            .routes
+            .iter()
+            .take(request.alternatives + 1)
+            .map(|route| Route {
+                costing: request.costing,
+                ..route.clone()
+            })
+            .collect())
+    }
+}
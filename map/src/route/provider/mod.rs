@@ -0,0 +1,37 @@
+use crate::route::RouteCosting;
+use crate::route::maneuver::Route;
+
+pub mod fixture_provider;
+pub mod offline_provider;
+pub mod osrm_provider;
+pub mod valhalla_provider;
+
+/// Route through the (lat, lon) locations in order, from the start to the destination.
+#[derive(Clone, Debug)]
+pub struct RouteRequest {
+    pub locations: Vec<(f64, f64)>,
+    pub costing: RouteCosting,
+    /// Alternative routes wanted besides the best one, providers may find fewer
+    pub alternatives: usize,
+}
+
+impl RouteRequest {
+    pub fn new(locations: Vec<(f64, f64)>, costing: RouteCosting) -> RouteRequest {
+        RouteRequest {
+            locations,
+            costing,
+            alternatives: 0,
+        }
+    }
+
+    pub fn with_alternatives(mut self, alternatives: usize) -> Self {
+        self.alternatives = alternatives;
+        self
+    }
+}
+
+/// Routing engine which calculates the routes, called on a background thread.
+pub trait RoutingProvider: Send + Sync {
+    /// Routes with the maneuvers, the best one first and then the alternatives.
+    fn route(&self, request: &RouteRequest) -> anyhow::Result<Vec<Route>>;
+}
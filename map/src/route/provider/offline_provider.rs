@@ -0,0 +1,96 @@
+use crate::matching::road_network::Road;
+use crate::route::maneuver::Route;
+use crate::route::provider::{RouteRequest, RoutingProvider};
+use crate::route::road_graph::RoadGraph;
+use crate::tiles::shashlik_tiles_provider_v0::{ROADS_MAX_ZOOM_LEVEL, highway_road};
+use crate::tiles::source::pack_export::tile_keys;
+use anyhow::anyhow;
+use geo_types::{Rect, coord};
+use osm::map::MapGeometry;
+use osm::source::TileSource;
+use osm::tiles::{TileKey, TileStore};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Routes over the highways of the tiles around the locations of the request, so there is a route
+/// without a network when the tiles are local.
+pub struct OfflineRoutingProvider<S: TileSource> {
+    tile_store: Arc<TileStore<S>>,
+    // roads of the loaded tiles, reroutes need mostly the same ones
+    tiles: Mutex<HashMap<TileKey, Arc<Vec<Road>>>>,
+}
+
+impl<S: TileSource> OfflineRoutingProvider<S> {
+    // roads this far around the locations are routable, in degrees(~1km)
+    const MARGIN: f64 = 0.01;
+    const MAX_TILES: usize = 256;
+
+    pub fn new(tile_store: Arc<TileStore<S>>) -> Self {
+        OfflineRoutingProvider {
+            tile_store,
+            tiles: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn roads(&self, tile_keys: &[TileKey]) -> Vec<Road> {
+        let mut roads: Vec<Arc<Vec<Road>>> = vec![];
+        let mut missing: Vec<TileKey> = vec![];
+        {
+            let tiles = self.tiles.lock().unwrap();
+            tile_keys.iter().for_each(|key| match tiles.get(key) {
+                Some(tile_roads) => roads.push(tile_roads.clone()),
+                None => missing.push(*key),
+            });
+        }
+        let loaded: Vec<(TileKey, Arc<Vec<Road>>)> = missing
+            .par_iter()
+            .map(|key| {
+                let roads = self
+                    .tile_store
+                    .load_geometries(key)
+                    .into_iter()
+                    .filter_map(|(obj, geometry)| match geometry {
+                        MapGeometry::Line(line) => highway_road(&obj.kind, &line),
+                        _ => None,
+                    })
+                    .collect();
+                (*key, Arc::new(roads))
+            })
+            .collect();
+
+        let mut tiles = self.tiles.lock().unwrap();
+        if tiles.len() + loaded.len() > Self::MAX_TILES {
+            tiles.clear();
+        }
+        loaded.into_iter().for_each(|(key, tile_roads)| {
+            roads.push(tile_roads.clone());
+            tiles.insert(key, tile_roads);
+        });
+        roads.iter().flat_map(|tile_roads| tile_roads.iter().cloned()).collect()
+    }
+}
+
+impl<S: TileSource> RoutingProvider for OfflineRoutingProvider<S> {
+    fn route(&self, request: &RouteRequest) -> anyhow::Result<Vec<Route>> {
+        let mut locations = request.locations.iter().map(|(lat, lon)| coord! {x: *lon, y: *lat});
+        let first = locations
+            .next()
+            .ok_or_else(|| anyhow!("The route needs a start and a destination"))?;
+        let area_latlon = locations.fold(Rect::new(first, first), |area, location| {
+            Rect::new(
+                coord! {x: area.min().x.min(location.x), y: area.min().y.min(location.y)},
+                coord! {x: area.max().x.max(location.x), y: area.max().y.max(location.y)},
+            )
+        });
+        let area_latlon = Rect::new(
+            area_latlon.min() - coord! {x: Self::MARGIN, y: Self::MARGIN},
+            area_latlon.max() + coord! {x: Self::MARGIN, y: Self::MARGIN},
+        );
+        let tile_keys = tile_keys(&area_latlon, ROADS_MAX_ZOOM_LEVEL..=ROADS_MAX_ZOOM_LEVEL);
+        if tile_keys.len() > Self::MAX_TILES {
+            return Err(anyhow!("The destination is too far for the offline routing"));
+        }
+        RoadGraph::new(self.roads(&tile_keys)).routes(request)
+    }
+}
@@ -0,0 +1,204 @@
+use crate::route::RouteCosting;
+use crate::route::maneuver::{Maneuver, ManeuverKind, Route};
+use crate::route::provider::{RouteRequest, RoutingProvider};
+use anyhow::anyhow;
+use geo_types::{Coord, coord};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Deserialize)]
+struct OsrmResponse {
+    code: String,
+    message: Option<String>,
+    #[serde(default)]
+    routes: Vec<OsrmRoute>,
+}
+
+#[derive(Deserialize)]
+struct OsrmRoute {
+    legs: Vec<OsrmLeg>,
+}
+
+#[derive(Deserialize)]
+struct OsrmLeg {
+    steps: Vec<OsrmStep>,
+}
+
+#[derive(Deserialize)]
+struct OsrmStep {
+    name: String,
+    geometry: OsrmGeometry,
+    maneuver: OsrmManeuver,
+}
+
+#[derive(Deserialize)]
+struct OsrmGeometry {
+    // (lon, lat)
+    coordinates: Vec<[f64; 2]>,
+}
+
+#[derive(Deserialize)]
+struct OsrmManeuver {
+    #[serde(rename = "type")]
+    type_: String,
+    modifier: Option<String>,
+}
+
+/// Routes of an OSRM server, the public demo one by default.
+/// OSRM has no instructions, they are made of the maneuvers and the street names.
+pub struct OsrmRoutingProvider {
+    base_url: Url,
+    client: Client,
+    headers: Vec<(String, String)>,
+}
+
+impl OsrmRoutingProvider {
+    const DEFAULT_URL: &'static str = "https://router.project-osrm.org/";
+
+    pub fn new(base_url: Url) -> Self {
+        OsrmRoutingProvider {
+            base_url,
+            client: Client::new(),
+            headers: vec![],
+        }
+    }
+
+    /// Header sent with every request, e.g. the authorization of a private server
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn profile(costing: RouteCosting) -> &'static str {
+        match costing {
+            RouteCosting::Pedestrian => "foot",
+            RouteCosting::Motorbike => "driving",
+        }
+    }
+
+    fn routes_from_response(response: OsrmResponse, costing: RouteCosting) -> anyhow::Result<Vec<Route>> {
+        if response.code != "Ok" {
+            return Err(anyhow!(
+                "Error calculating route: {} {}",
+                response.code,
+                response.message.unwrap_or_default()
+            ));
+        }
+        if response.routes.is_empty() {
+            return Err(anyhow!("No route"));
+        }
+        response
+            .routes
+            .into_iter()
+            .map(|route| Self::route_from_legs(route.legs, costing))
+            .collect()
+    }
+
+    fn route_from_legs(legs: Vec<OsrmLeg>, costing: RouteCosting) -> anyhow::Result<Route> {
+        let mut shape: Vec<Coord> = vec![];
+        let mut maneuvers: Vec<Maneuver> = vec![];
+        for (leg_index, leg) in legs.iter().enumerate() {
+            let is_last_leg = leg_index + 1 == legs.len();
+            for step in &leg.steps {
+                let mut points = step.geometry.coordinates.iter().map(|[lon, lat]| coord! {x: *lon, y: *lat});
+                let Some(first) = points.next() else {
+                    continue;
+                };
+                // steps start where the previous one ends
+                if shape.last() != Some(&first) {
+                    shape.push(first);
+                }
+                let begin_shape_index = shape.len() - 1;
+                points.for_each(|point| {
+                    if shape.last() != Some(&point) {
+                        shape.push(point);
+                    }
+                });
+
+                let kind = Self::maneuver_kind(&step.maneuver);
+                // the legs are joined into one route like the Valhalla ones
+                if (leg_index > 0 && kind == ManeuverKind::Depart) || (!is_last_leg && kind == ManeuverKind::Arrive) {
+                    continue;
+                }
+                let street_name = Some(step.name.clone()).filter(|name| !name.is_empty());
+                maneuvers.push(Maneuver::new(kind, street_name, begin_shape_index));
+            }
+        }
+        if shape.len() < 2 {
+            return Err(anyhow!("The route has no shape"));
+        }
+        Ok(Route {
+            shape,
+            maneuvers,
+            costing,
+        })
+    }
+
+    fn maneuver_kind(maneuver: &OsrmManeuver) -> ManeuverKind {
+        let modifier = maneuver.modifier.as_deref().unwrap_or("straight");
+        let is_left = modifier.contains("left");
+        match maneuver.type_.as_str() {
+            "depart" => ManeuverKind::Depart,
+            "arrive" => ManeuverKind::Arrive,
+            "roundabout" | "rotary" | "roundabout turn" => ManeuverKind::RoundaboutEnter,
+            "exit roundabout" | "exit rotary" => ManeuverKind::RoundaboutExit,
+            "merge" => ManeuverKind::Merge,
+            "on ramp" | "off ramp" if is_left => ManeuverKind::RampLeft,
+            "on ramp" | "off ramp" => ManeuverKind::RampRight,
+            "fork" if is_left => ManeuverKind::KeepLeft,
+            "fork" => ManeuverKind::KeepRight,
+            // turn, new name, continue, end of road
+            _ => match modifier {
+                "uturn" => ManeuverKind::UTurn,
+                "sharp right" => ManeuverKind::SharpRight,
+                "right" => ManeuverKind::Right,
+                "slight right" => ManeuverKind::SlightRight,
+                "slight left" => ManeuverKind::SlightLeft,
+                "left" => ManeuverKind::Left,
+                "sharp left" => ManeuverKind::SharpLeft,
+                _ => ManeuverKind::Continue,
+            },
+        }
+    }
+}
+
+impl Default for OsrmRoutingProvider {
+    fn default() -> Self {
+        Self::new(Url::parse(Self::DEFAULT_URL).unwrap())
+    }
+}
+
+impl RoutingProvider for OsrmRoutingProvider {
+    fn route(&self, request: &RouteRequest) -> anyhow::Result<Vec<Route>> {
+        let coordinates: Vec<String> = request
+            .locations
+            .iter()
+            .map(|(lat, lon)| format!("{lon},{lat}"))
+            .collect();
+        let mut url = format!(
+            "{}/route/v1/{}/{}?steps=true&geometries=geojson&overview=false",
+            self.base_url.as_str().trim_end_matches('/'),
+            Self::profile(request.costing),
+            coordinates.join(";")
+        );
+        // OSRM finds the alternatives only between two locations
+        if request.alternatives > 0 {
+            url.push_str(&format!("&alternatives={}", request.alternatives));
+        }
+        let http_request = self
+            .headers
+            .iter()
+            .fold(self.client.get(&url), |http_request, (name, value)| http_request.header(name, value));
+        // errors like "NoRoute" come with a 400 and are described in the body
+        let body = http_request
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|err| anyhow!("Error calculating route: {err}"))?;
+        let response: OsrmResponse =
+            serde_json::from_str(&body).map_err(|err| anyhow!("Not an OSRM route: {err}"))?;
+        let mut routes = Self::routes_from_response(response, request.costing)?;
+        routes.truncate(request.alternatives + 1);
+        Ok(routes)
+    }
+}
@@ -0,0 +1,109 @@
+use crate::route::RouteCosting;
+use crate::route::maneuver::Route;
+use crate::route::provider::{RouteRequest, RoutingProvider};
+use anyhow::anyhow;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::json;
+use url::Url;
+use valhalla_client::route::Trip;
+
+/// Response of the Valhalla route API, the best trip and the alternates or the error.
+#[derive(Deserialize)]
+pub(crate) struct ValhallaResponse {
+    trip: Option<Trip>,
+    #[serde(default)]
+    alternates: Vec<ValhallaAlternate>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ValhallaAlternate {
+    trip: Trip,
+}
+
+impl ValhallaResponse {
+    pub(crate) fn into_routes(self, costing: RouteCosting) -> anyhow::Result<Vec<Route>> {
+        let Some(trip) = self.trip else {
+            return Err(anyhow!("Error calculating route: {}", self.error.unwrap_or_default()));
+        };
+        std::iter::once(trip)
+            .chain(self.alternates.into_iter().map(|alternate| alternate.trip))
+            .map(|trip| Route::from_trip(&trip, costing))
+            .collect()
+    }
+}
+
+/// Routes of a Valhalla server, the public one by default.
+/// Valhalla finds the alternatives only between two locations.
+pub struct ValhallaRoutingProvider {
+    base_url: Url,
+    client: Client,
+    headers: Vec<(String, String)>,
+}
+
+impl ValhallaRoutingProvider {
+    const DEFAULT_URL: &'static str = "https://valhalla1.openstreetmap.de/";
+
+    pub fn new(base_url: Url) -> Self {
+        ValhallaRoutingProvider {
+            base_url,
+            client: Client::new(),
+            headers: vec![],
+        }
+    }
+
+    /// Header sent with every request, e.g. the authorization of a private server
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn costing(costing: RouteCosting) -> &'static str {
+        match costing {
+            RouteCosting::Pedestrian => "pedestrian",
+            RouteCosting::Motorbike => "motorcycle",
+        }
+    }
+}
+
+impl Default for ValhallaRoutingProvider {
+    fn default() -> Self {
+        Self::new(Url::parse(Self::DEFAULT_URL).unwrap())
+    }
+}
+
+impl RoutingProvider for ValhallaRoutingProvider {
+    fn route(&self, request: &RouteRequest) -> anyhow::Result<Vec<Route>> {
+        let locations: Vec<_> = request
+            .locations
+            .iter()
+            .map(|(lat, lon)| json!({"lat": lat, "lon": lon}))
+            .collect();
+        let alternates = if request.locations.len() == 2 { request.alternatives } else { 0 };
+        let manifest = json!({
+            "locations": locations,
+            "costing": Self::costing(request.costing),
+            "directions_type": "instructions",
+            "alternates": alternates,
+        });
+        let url = Url::parse_with_params(
+            &format!("{}/route", self.base_url.as_str().trim_end_matches('/')),
+            &[("json", manifest.to_string())],
+        )?;
+        let http_request = self
+            .headers
+            .iter()
+            .fold(self.client.get(url.as_str()), |http_request, (name, value)| http_request.header(name, value));
+        // errors like the locations without roads come with a 400 and are described in the body
+        let body = http_request
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|err| anyhow!("Error calculating route: {err}"))?;
+        let response: ValhallaResponse =
+            serde_json::from_str(&body).map_err(|err| anyhow!("Not a Valhalla route: {err}"))?;
+        let mut routes = response.into_routes(request.costing)?;
+        routes.truncate(request.alternatives + 1);
+        Ok(routes)
+    }
+}
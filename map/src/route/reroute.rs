@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+
+/// Limits the route requests after leaving the route, a detour or a bad GPS shouldn't flood
+/// the routing server. One request is in flight at a time.
+pub struct RerouteThrottle {
+    min_interval: Duration,
+    last_request: Option<Instant>,
+    in_flight: bool,
+}
+
+impl RerouteThrottle {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
+    pub fn new(min_interval: Duration) -> RerouteThrottle {
+        RerouteThrottle {
+            min_interval,
+            last_request: None,
+            in_flight: false,
+        }
+    }
+
+    pub fn can_request(&self, now: Instant) -> bool {
+        !self.in_flight
+            && self
+                .last_request
+                .is_none_or(|last_request| now.saturating_duration_since(last_request) >= self.min_interval)
+    }
+
+    pub fn on_request(&mut self, now: Instant) {
+        self.last_request = Some(now);
+        self.in_flight = true;
+    }
+
+    /// The route or the failure came back.
+    pub fn on_response(&mut self) {
+        self.in_flight = false;
+    }
+}
+
+impl Default for RerouteThrottle {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL)
+    }
+}
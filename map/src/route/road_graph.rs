@@ -0,0 +1,446 @@
+use crate::matching::road_network::{NodeKey, Road, RoadIndex, distance};
+use crate::route::RouteCosting;
+use crate::route::maneuver::{Maneuver, ManeuverKind, Route};
+use crate::route::provider::RouteRequest;
+use crate::tiles::mercator;
+use anyhow::anyhow;
+use geo_types::Coord;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+struct Edge {
+    to: usize,
+    road: usize,
+    // meters
+    length: f64,
+}
+
+// Projection of a location onto the nearest open road, `start` < `end`
+struct Snap {
+    start: usize,
+    end: usize,
+    road: usize,
+    t: f64,
+    position: Coord,
+    length: f64,
+}
+
+// Points of a found route, the graph nodes among them and the roads between them
+struct Path {
+    points: Vec<Coord>,
+    nodes: Vec<Option<usize>>,
+    roads: Vec<usize>,
+}
+
+/// Routable graph of roads in world coordinates, road lines which share a point are connected there.
+/// Roads are two-way, the tiles have no one-way information.
+pub struct RoadGraph {
+    roads: Vec<Road>,
+    nodes: Vec<Coord>,
+    edges: Vec<Vec<Edge>>,
+}
+
+impl RoadGraph {
+    // locations further than it from any open road can't be routed, in meters
+    const MAX_SNAP_DISTANCE: f64 = 200.0;
+    // turns sharper than it at an intersection are maneuvers even on the same street, in degrees
+    const TURN_ANGLE: f64 = 45.0;
+    // cost factor of the roads of the found routes, so the next search finds another way
+    const ALTERNATIVE_PENALTY: f64 = 2.0;
+    // alternatives may be this much slower than the best route
+    const MAX_ALTERNATIVE_COST: f64 = 1.5;
+    // alternatives can share this part of their length with the other routes
+    const MAX_ALTERNATIVE_OVERLAP: f64 = 0.7;
+
+    pub fn new(roads: impl IntoIterator<Item = Road>) -> RoadGraph {
+        let mut graph = RoadGraph {
+            roads: vec![],
+            nodes: vec![],
+            edges: vec![],
+        };
+        let mut node_keys: HashMap<NodeKey, usize> = HashMap::new();
+        roads.into_iter().for_each(|road| {
+            let road_index = graph.roads.len();
+            let nodes: Vec<usize> = road
+                .line
+                .coords()
+                .map(|coord| {
+                    *node_keys.entry(RoadIndex::node_key(coord)).or_insert_with(|| {
+                        graph.nodes.push(*coord);
+                        graph.edges.push(vec![]);
+                        graph.nodes.len() - 1
+                    })
+                })
+                .collect();
+            nodes.windows(2).filter(|pair| pair[0] != pair[1]).for_each(|pair| {
+                let length = graph.meters(&graph.nodes[pair[0]], &graph.nodes[pair[1]]);
+                graph.edges[pair[0]].push(Edge {
+                    to: pair[1],
+                    road: road_index,
+                    length,
+                });
+                graph.edges[pair[1]].push(Edge {
+                    to: pair[0],
+                    road: road_index,
+                    length,
+                });
+            });
+            graph.roads.push(road);
+        });
+        graph
+    }
+
+    /// The fastest route for the costing between two (lat, lon), found with A*.
+    pub fn route(&self, from_lat_lon: (f64, f64), to_lat_lon: (f64, f64), costing: RouteCosting) -> anyhow::Result<Route> {
+        let mut routes = self.routes(&RouteRequest::new(vec![from_lat_lon, to_lat_lon], costing))?;
+        Ok(routes.remove(0))
+    }
+
+    /// The fastest route through the locations of the request, then the alternatives which are
+    /// different enough and not much slower. There are alternatives only between two locations.
+    pub fn routes(&self, request: &RouteRequest) -> anyhow::Result<Vec<Route>> {
+        let costing = request.costing;
+        if request.locations.len() < 2 {
+            return Err(anyhow!("The route needs a start and a destination"));
+        }
+        let last = request.locations.len() - 1;
+        let snaps = request
+            .locations
+            .iter()
+            .enumerate()
+            .map(|(index, lat_lon)| {
+                self.snap(&Self::world(*lat_lon), costing).ok_or_else(|| match index {
+                    0 => anyhow!("No road near the start"),
+                    index if index == last => anyhow!("No road near the destination"),
+                    index => anyhow!("No road near the waypoint {index}"),
+                })
+            })
+            .collect::<anyhow::Result<Vec<Snap>>>()?;
+
+        let no_penalties = HashMap::new();
+        if snaps.len() > 2 {
+            let legs = snaps
+                .windows(2)
+                .map(|pair| {
+                    self.search(&pair[0], &pair[1], costing, &no_penalties)
+                        .map(|path| self.to_route(path, costing))
+                        .ok_or_else(|| anyhow!("No offline route"))
+                })
+                .collect::<anyhow::Result<Vec<Route>>>()?;
+            return Ok(vec![Route::join(legs)?]);
+        }
+
+        let (from, to) = (&snaps[0], &snaps[1]);
+        let best = self
+            .search(from, to, costing, &no_penalties)
+            .ok_or_else(|| anyhow!("No offline route"))?;
+        let max_cost = self.path_cost(&best, costing) * Self::MAX_ALTERNATIVE_COST;
+        let mut penalties: HashMap<(usize, usize), f64> = HashMap::new();
+        let mut last_path_edges = Self::path_edges(&best);
+        let mut paths = vec![best];
+        // a few more searches than the alternatives, some of the found paths are too similar
+        for _ in 0..request.alternatives * 3 {
+            if paths.len() > request.alternatives {
+                break;
+            }
+            last_path_edges.iter().for_each(|edge| {
+                *penalties.entry(*edge).or_insert(1.0) *= Self::ALTERNATIVE_PENALTY;
+            });
+            let Some(candidate) = self.search(from, to, costing, &penalties) else {
+                break;
+            };
+            last_path_edges = Self::path_edges(&candidate);
+            let is_different = paths
+                .iter()
+                .all(|path| self.overlap(&candidate, &Self::segments(path)) <= Self::MAX_ALTERNATIVE_OVERLAP);
+            if is_different && self.path_cost(&candidate, costing) <= max_cost {
+                paths.push(candidate);
+            }
+        }
+        Ok(paths.into_iter().map(|path| self.to_route(path, costing)).collect())
+    }
+
+    fn world(lat_lon: (f64, f64)) -> Coord {
+        mercator::lat_lon_to_world(&(lat_lon.1, lat_lon.0).into())
+    }
+
+    fn meters(&self, a: &Coord, b: &Coord) -> f64 {
+        distance(a, b) * mercator::meters_per_world_unit(mercator::world_to_lat_lon(a).y)
+    }
+
+    // seconds to drive the part of the road
+    fn cost(&self, road: usize, length: f64, costing: RouteCosting) -> Option<f64> {
+        speed(costing, self.roads[road].class).map(|speed| length / speed)
+    }
+
+    fn snap(&self, point: &Coord, costing: RouteCosting) -> Option<Snap> {
+        self.edges
+            .iter()
+            .enumerate()
+            .flat_map(|(start, edges)| edges.iter().map(move |edge| (start, edge)))
+            .filter(|(start, edge)| *start < edge.to && speed(costing, self.roads[edge.road].class).is_some())
+            .map(|(start, edge)| {
+                let start_coord = self.nodes[start];
+                let direction = self.nodes[edge.to] - start_coord;
+                let to_point = *point - start_coord;
+                let length2 = direction.x * direction.x + direction.y * direction.y;
+                let t = ((to_point.x * direction.x + to_point.y * direction.y) / length2).clamp(0.0, 1.0);
+                Snap {
+                    start,
+                    end: edge.to,
+                    road: edge.road,
+                    t,
+                    position: start_coord + direction * t,
+                    length: edge.length,
+                }
+            })
+            .map(|snap| (self.meters(&snap.position, point), snap))
+            .filter(|(offset, _)| *offset <= Self::MAX_SNAP_DISTANCE)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, snap)| snap)
+    }
+
+    // edges between the graph nodes of the path, by their (smaller, bigger) node
+    fn path_edges(path: &Path) -> HashSet<(usize, usize)> {
+        path.nodes
+            .windows(2)
+            .filter_map(|pair| Some(edge_key(pair[0]?, pair[1]?)))
+            .collect()
+    }
+
+    // seconds to drive the path
+    fn path_cost(&self, path: &Path, costing: RouteCosting) -> f64 {
+        path.points
+            .windows(2)
+            .zip(&path.roads)
+            .filter_map(|(pair, road)| self.cost(*road, self.meters(&pair[0], &pair[1]), costing))
+            .sum()
+    }
+
+    // segments of the path by their ends, the parts of the snapped roads too
+    fn segments(path: &Path) -> HashSet<(NodeKey, NodeKey)> {
+        path.points.windows(2).map(|pair| segment_key(&pair[0], &pair[1])).collect()
+    }
+
+    // part of the path length on the segments
+    fn overlap(&self, path: &Path, segments: &HashSet<(NodeKey, NodeKey)>) -> f64 {
+        let (shared, total) = path.points.windows(2).fold((0.0, 0.0), |(shared, total), pair| {
+            let length = self.meters(&pair[0], &pair[1]);
+            let is_shared = segments.contains(&segment_key(&pair[0], &pair[1]));
+            (if is_shared { shared + length } else { shared }, total + length)
+        });
+        if total > 0.0 { shared / total } else { 1.0 }
+    }
+
+    // `penalties` multiply the cost of the edges, they are at least 1 so the heuristic stays below the cost
+    fn search(
+        &self,
+        from: &Snap,
+        to: &Snap,
+        costing: RouteCosting,
+        penalties: &HashMap<(usize, usize), f64>,
+    ) -> Option<Path> {
+        let max_speed = max_speed(costing);
+        let heuristic = |node: usize| self.meters(&self.nodes[node], &to.position) / max_speed;
+
+        let mut costs = vec![f64::INFINITY; self.nodes.len()];
+        let mut previous: Vec<Option<(usize, usize)>> = vec![None; self.nodes.len()];
+        let mut closed = vec![false; self.nodes.len()];
+        let mut heap = BinaryHeap::new();
+        for (node, length) in [(from.start, from.t * from.length), (from.end, (1.0 - from.t) * from.length)] {
+            let cost = self.cost(from.road, length, costing)?;
+            if cost < costs[node] {
+                costs[node] = cost;
+                heap.push(QueueItem {
+                    priority: cost + heuristic(node),
+                    node,
+                });
+            }
+        }
+        let targets = [(to.start, to.t * to.length), (to.end, (1.0 - to.t) * to.length)];
+
+        // both ends on the same road part
+        let mut best: Option<(f64, Option<usize>)> = (from.start == to.start && from.end == to.end)
+            .then(|| self.cost(from.road, (to.t - from.t).abs() * from.length, costing))
+            .flatten()
+            .map(|cost| (cost, None));
+
+        while let Some(QueueItem { priority, node }) = heap.pop() {
+            if best.is_some_and(|(best_cost, _)| priority >= best_cost) {
+                break;
+            }
+            if closed[node] {
+                continue;
+            }
+            closed[node] = true;
+            for (target, length) in targets {
+                if target == node
+                    && let Some(rest) = self.cost(to.road, length, costing)
+                    && best.is_none_or(|(best_cost, _)| costs[node] + rest < best_cost)
+                {
+                    best = Some((costs[node] + rest, Some(node)));
+                }
+            }
+            for edge in &self.edges[node] {
+                let Some(cost) = self.cost(edge.road, edge.length, costing) else {
+                    continue;
+                };
+                let cost = cost * penalties.get(&edge_key(node, edge.to)).unwrap_or(&1.0);
+                let next_cost = costs[node] + cost;
+                if !closed[edge.to] && next_cost < costs[edge.to] {
+                    costs[edge.to] = next_cost;
+                    previous[edge.to] = Some((node, edge.road));
+                    heap.push(QueueItem {
+                        priority: next_cost + heuristic(edge.to),
+                        node: edge.to,
+                    });
+                }
+            }
+        }
+
+        let (_, last_node) = best?;
+        let mut nodes = vec![];
+        let mut roads = vec![];
+        let mut node = last_node;
+        while let Some(current) = node {
+            nodes.push(current);
+            node = previous[current].map(|(previous_node, road)| {
+                roads.push(road);
+                previous_node
+            });
+        }
+        nodes.reverse();
+        roads.reverse();
+
+        let mut path = Path {
+            points: vec![from.position],
+            nodes: vec![None],
+            roads: vec![],
+        };
+        let steps = nodes
+            .into_iter()
+            .map(|node| (self.nodes[node], Some(node)))
+            .chain([(to.position, None)]);
+        let step_roads = [from.road].into_iter().chain(roads).chain([to.road]);
+        steps.zip(step_roads).for_each(|((point, node), road)| {
+            // the location can be right at a node
+            if distance(path.points.last().unwrap(), &point) <= f64::EPSILON {
+                let last = path.nodes.last_mut().unwrap();
+                *last = last.or(node);
+                return;
+            }
+            path.points.push(point);
+            path.nodes.push(node);
+            path.roads.push(road);
+        });
+        (path.points.len() >= 2).then_some(path)
+    }
+
+    fn to_route(&self, path: Path, costing: RouteCosting) -> Route {
+        let Path { points, nodes, roads } = path;
+        let road_name = |road: usize| self.roads[road].name.clone();
+        let mut maneuvers = vec![Maneuver::new(ManeuverKind::Depart, road_name(roads[0]), 0)];
+        for index in 1..points.len() - 1 {
+            let (in_road, out_road) = (roads[index - 1], roads[index]);
+            let turn = turn_angle(&points[index - 1], &points[index], &points[index + 1]);
+            let is_intersection = nodes[index].is_some_and(|node| self.edges[node].len() > 2);
+            if self.roads[in_road].name != self.roads[out_road].name
+                || (is_intersection && turn.abs() >= Self::TURN_ANGLE)
+            {
+                maneuvers.push(Maneuver::new(turn_kind(turn), road_name(out_road), index));
+            }
+        }
+        maneuvers.push(Maneuver::new(ManeuverKind::Arrive, None, points.len() - 1));
+        Route {
+            shape: points.iter().map(mercator::world_to_lat_lon).collect(),
+            maneuvers,
+            costing,
+        }
+    }
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn segment_key(a: &Coord, b: &Coord) -> (NodeKey, NodeKey) {
+    let (a, b) = (RoadIndex::node_key(a), RoadIndex::node_key(b));
+    (a.min(b), a.max(b))
+}
+
+/// Travel speed on the road class in meters per second, `None` if the road is closed for the costing.
+fn speed(costing: RouteCosting, class: &str) -> Option<f64> {
+    let km_h = match costing {
+        RouteCosting::Pedestrian => match class {
+            "motorway" | "motorway_link" | "trunk" | "trunk_link" => return None,
+            _ => 5.0,
+        },
+        RouteCosting::Motorbike => match class {
+            "motorway" => 100.0,
+            "trunk" => 80.0,
+            "primary" => 60.0,
+            "secondary" => 50.0,
+            "motorway_link" | "trunk_link" | "tertiary" => 40.0,
+            "primary_link" | "secondary_link" | "tertiary_link" | "unclassified" => 30.0,
+            "residential" => 25.0,
+            "footway" => return None,
+            _ => 20.0,
+        },
+    };
+    Some(km_h / 3.6)
+}
+
+// keeps the A* heuristic below the real cost
+fn max_speed(costing: RouteCosting) -> f64 {
+    match costing {
+        RouteCosting::Pedestrian => 5.0 / 3.6,
+        RouteCosting::Motorbike => 100.0 / 3.6,
+    }
+}
+
+// degrees, positive to the right
+fn turn_angle(previous: &Coord, point: &Coord, next: &Coord) -> f64 {
+    // world y grows to the south
+    let bearing = |a: &Coord, b: &Coord| (b.x - a.x).atan2(a.y - b.y).to_degrees();
+    (bearing(point, next) - bearing(previous, point) + 540.0).rem_euclid(360.0) - 180.0
+}
+
+fn turn_kind(turn: f64) -> ManeuverKind {
+    let is_right = turn > 0.0;
+    match turn.abs() {
+        angle if angle < 30.0 => ManeuverKind::Continue,
+        angle if angle < 60.0 && is_right => ManeuverKind::SlightRight,
+        angle if angle < 60.0 => ManeuverKind::SlightLeft,
+        angle if angle < 135.0 && is_right => ManeuverKind::Right,
+        angle if angle < 135.0 => ManeuverKind::Left,
+        angle if angle < 170.0 && is_right => ManeuverKind::SharpRight,
+        angle if angle < 170.0 => ManeuverKind::SharpLeft,
+        _ => ManeuverKind::UTurn,
+    }
+}
+
+struct QueueItem {
+    priority: f64,
+    node: usize,
+}
+
+// min-heap by priority
+impl Ord for QueueItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+impl PartialOrd for QueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueueItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for QueueItem {}
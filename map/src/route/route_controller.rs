@@ -1,78 +1,339 @@
 use crate::route::RouteCosting;
+use crate::route::guidance::{Guidance, GuidanceConfig, GuidanceEvent, GuidanceListener};
+use crate::route::maneuver::{ManeuverKind, Route};
+use crate::matching::road_network::distance;
+use crate::picking::PickedFeature;
+use crate::route::provider::{RouteRequest, RoutingProvider};
+use crate::route::provider::valhalla_provider::ValhallaRoutingProvider;
+use crate::route::reroute::RerouteThrottle;
 use crate::route::route_group::RouteGroup;
-use cgmath::Vector3;
-use geo_types::{Point, point};
+use crate::tiles::mercator;
+use crate::tiles::tile_data::FeatureKind;
+use anyhow::anyhow;
+use geo_types::{Point, coord, point};
 use log::error;
 use renderer::modifier::render_modifier::SpatialData;
 use renderer::renderer_api::RendererApi;
+use renderer::styles::style_id::StyleId;
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender, channel};
 use std::thread::spawn;
-use valhalla_client::blocking::Valhalla;
-use valhalla_client::costing::Costing;
-use valhalla_client::route::{DirectionsType, Location, Manifest};
+use std::time::Instant;
+
+pub type LocationConverter = Box<dyn (Fn(&Point) -> Point) + Send>;
+
+struct RouteResponse {
+    generation: u64,
+    routes: anyhow::Result<Vec<Route>>,
+}
 
 pub struct RouteController {
+    api: Arc<RendererApi>,
+    converter: LocationConverter,
     current_lat_lon: Option<(f64, f64)>,
+    current_bearing: Option<f32>,
+    routing_provider: Arc<dyn RoutingProvider>,
+    offline_routing_provider: Option<Arc<dyn RoutingProvider>>,
+    route_sender: Sender<RouteResponse>,
+    route_receiver: Receiver<RouteResponse>,
+    // only the response to the latest request is shown
+    generation: u64,
+    is_rerouting: bool,
+    // the waypoints which are not reached yet and the destination, the request starts at the current location
+    route_request: Option<RouteRequest>,
+    reroute_throttle: RerouteThrottle,
+    // the best route first, then the alternatives
+    routes: Vec<Route>,
+    selected_route: usize,
+    route_group_version: u64,
+    route_group_key: Option<String>,
+    // distance along the line of the guided route to its shape points, for the progress of its styles
+    route_lengths: Vec<f64>,
+    // maneuvers from it on have arrows, none until the routes are shown
+    first_arrow_maneuver: Option<usize>,
+    guidance: Option<Guidance>,
+    guidance_listener: Option<GuidanceListener>,
 }
 
 impl RouteController {
-    pub fn new() -> RouteController {
+    // a waypoint this close to the location is reached, in meters
+    const WAYPOINT_REACHED_DISTANCE: f64 = 30.0;
+
+    pub fn new(api: Arc<RendererApi>, converter: LocationConverter) -> RouteController {
+        let (route_sender, route_receiver) = channel();
         RouteController {
+            api,
+            converter,
             current_lat_lon: None,
+            current_bearing: None,
+            routing_provider: Arc::new(ValhallaRoutingProvider::default()),
+            offline_routing_provider: None,
+            route_sender,
+            route_receiver,
+            generation: 0,
+            is_rerouting: false,
+            route_request: None,
+            reroute_throttle: RerouteThrottle::default(),
+            routes: vec![],
+            selected_route: 0,
+            route_group_version: 0,
+            route_group_key: None,
+            route_lengths: vec![],
+            first_arrow_maneuver: None,
+            guidance: None,
+            guidance_listener: None,
         }
     }
-    pub fn set_current_lat_lon(&mut self, lat_lon: (f64, f64)) {
+    pub fn set_current_lat_lon(&mut self, lat_lon: (f64, f64), bearing: Option<f32>) {
         self.current_lat_lon = Some(lat_lon);
+        self.current_bearing = bearing;
+        self.drop_reached_waypoint(lat_lon);
+        self.update_guidance();
+        self.update_route_progress();
+        self.reroute_if_off_route();
+    }
+
+    /// The public Valhalla server is used by default.
+    pub fn set_routing_provider(&mut self, routing_provider: Arc<dyn RoutingProvider>) {
+        self.routing_provider = routing_provider;
+    }
+
+    /// Routes when the routing provider fails, e.g. without a network.
+    pub fn set_offline_routing_provider(&mut self, offline_routing_provider: Option<Arc<dyn RoutingProvider>>) {
+        self.offline_routing_provider = offline_routing_provider;
+    }
+
+    pub fn set_guidance_listener(&mut self, listener: Option<GuidanceListener>) {
+        self.guidance_listener = listener;
+    }
+
+    /// Render group of the shown route, it changes with every new route.
+    pub fn route_group_key(&self) -> Option<&str> {
+        self.route_group_key.as_deref()
+    }
+
+    /// Selects the route nearest to the (lat, lon) within the tolerance in meters and guides along it,
+    /// returns false if there is no route there.
+    pub fn select_route_near(&mut self, lat_lon: (f64, f64), tolerance: f64) -> bool {
+        let Some((index, ..)) = self.route_near(lat_lon, tolerance) else {
+            return false;
+        };
+        if index != self.selected_route {
+            self.guide(index);
+            self.update_guidance();
+            self.update_route_progress();
+        }
+        true
+    }
+
+    /// The shown route nearest to the (lat, lon) within the tolerance in meters with the distance to it.
+    pub fn pick_route(&self, lat_lon: (f64, f64), tolerance: f64) -> Option<(f64, PickedFeature)> {
+        let group_key = self.route_group_key.clone()?;
+        let (_, nearest, distance) = self.route_near(lat_lon, tolerance)?;
+        let route = PickedFeature {
+            kind: FeatureKind::Route,
+            name: None,
+            lat_lon: nearest,
+            group_key,
+        };
+        Some((distance, route))
+    }
+
+    // index of the route, its point nearest to the (lat, lon) and the distance to it
+    fn route_near(&self, lat_lon: (f64, f64), tolerance: f64) -> Option<(usize, (f64, f64), f64)> {
+        self.routes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, route)| {
+                let (nearest, distance) = route.nearest_point(lat_lon)?;
+                Some((index, nearest, distance))
+            })
+            .filter(|(.., distance)| *distance <= tolerance)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+    }
+
+    /// Shows the routes calculated in the background and starts the guidance along the best one, if there are new ones.
+    pub fn update(&mut self) {
+        let Some(response) = self
+            .route_receiver
+            .try_iter()
+            .filter(|response| response.generation == self.generation)
+            .last()
+        else {
+            return;
+        };
+        self.reroute_throttle.on_response();
+        // the guidance needs a segment, providers outside of the crate may return any shape
+        let routes = response
+            .routes
+            .map(|routes| routes.into_iter().filter(|route| route.check().is_ok()).collect::<Vec<_>>());
+        let routes = match routes {
+            Ok(routes) if routes.is_empty() => Err(anyhow!("No route")),
+            routes => routes,
+        };
+        let routes = match routes {
+            Ok(routes) => routes,
+            Err(err) => {
+                error!("{:#}", err);
+                self.notify(&[GuidanceEvent::RouteFailed {
+                    message: format!("{:#}", err),
+                }]);
+                return;
+            }
+        };
+
+        self.routes = routes;
+        self.guide(0);
+        if self.is_rerouting {
+            self.notify(&[GuidanceEvent::Rerouted]);
+        }
+        self.update_guidance();
+        self.update_route_progress();
+    }
+
+    // the guidance and the progress of the route line follow the route, it's shown again with its arrows
+    fn guide(&mut self, route_index: usize) {
+        let route = self.routes[route_index].clone();
+        let line: Vec<Point> = route
+            .shape
+            .iter()
+            .map(|p| (self.converter)(&point! { x: p.x, y: p.y }))
+            .collect();
+        self.route_lengths = RouteGroup::route_lengths(&line);
+        self.selected_route = route_index;
+        self.guidance = Some(Guidance::new(route, GuidanceConfig::default()));
+        self.first_arrow_maneuver = None;
+    }
+
+    // the passed maneuvers lose their arrows and the traveled part of the route line ends at the location
+    fn update_route_progress(&mut self) {
+        let Some(next_maneuver) = self.guidance.as_ref().map(|guidance| guidance.next_maneuver().unwrap_or(0)) else {
+            return;
+        };
+        if self.first_arrow_maneuver != Some(next_maneuver) {
+            self.show_routes(next_maneuver);
+        }
+        let Some(guidance) = self.guidance.as_ref() else {
+            return;
+        };
+        let (segment, part) = guidance.position();
+        let lengths = &self.route_lengths;
+        let progress = (lengths[segment] + (lengths[segment + 1] - lengths[segment]) * part) as f32;
+        for style_id in [RouteGroup::style_id(guidance.route().costing), StyleId("route_chevron")] {
+            self.api
+                .update_style(style_id, move |style| *style = style.with_progress(progress));
+        }
+    }
+
+    fn show_routes(&mut self, first_arrow_maneuver: usize) {
+        let routes: Vec<Vec<Point>> = self
+            .routes
+            .iter()
+            .map(|route| route.shape.iter().map(|p| point! { x: p.x, y: p.y }).collect())
+            .collect();
+        let route = &self.routes[self.selected_route];
+        self.first_arrow_maneuver = Some(first_arrow_maneuver);
+        let maneuver_points = route.maneuvers[first_arrow_maneuver..]
+            .iter()
+            .filter(|maneuver| !matches!(maneuver.kind, ManeuverKind::Depart | ManeuverKind::Arrive))
+            .map(|maneuver| maneuver.begin_shape_index)
+            .collect();
+        let route_group = Box::new(RouteGroup::new(
+            routes,
+            self.selected_route,
+            route.costing,
+            maneuver_points,
+            self.converter.as_ref(),
+        ));
+        let spatial_data = SpatialData::transform(route_group.first_route_point());
+        // the new routes are added before the old ones are removed, so there is no frame without a route
+        self.route_group_version += 1;
+        let route_group_key = format!("route_{}", self.route_group_version);
+        self.api
+            .add_render_group(route_group_key.clone(), 1, spatial_data, route_group);
+        if let Some(old_key) = self.route_group_key.replace(route_group_key) {
+            self.api.clear_render_groups(HashSet::from([old_key]));
+        }
+    }
+
+    fn drop_reached_waypoint(&mut self, lat_lon: (f64, f64)) {
+        let Some(request) = self.route_request.as_mut() else {
+            return;
+        };
+        let world = |(lat, lon): (f64, f64)| mercator::lat_lon_to_world(&coord! {x: lon, y: lat});
+        let is_reached = |waypoint: (f64, f64)| {
+            distance(&world(waypoint), &world(lat_lon)) * mercator::meters_per_world_unit(lat_lon.0)
+                <= Self::WAYPOINT_REACHED_DISTANCE
+        };
+        // the destination stays for the reroutes
+        if request.locations.len() > 1 && is_reached(request.locations[0]) {
+            request.locations.remove(0);
+        }
+    }
+
+    fn update_guidance(&mut self) {
+        let (Some(guidance), Some((lat, lon))) = (self.guidance.as_mut(), self.current_lat_lon) else {
+            return;
+        };
+        let events = guidance.update(lat, lon, self.current_bearing);
+        self.notify(&events);
+    }
+
+    fn notify(&self, events: &[GuidanceEvent]) {
+        if let Some(listener) = &self.guidance_listener {
+            events.iter().for_each(listener);
+        }
+    }
+
+    fn reroute_if_off_route(&mut self) {
+        let is_off_route = self.guidance.as_ref().is_some_and(|guidance| guidance.is_off_route());
+        if let Some(route_request) = &self.route_request
+            && is_off_route
+            && self.reroute_throttle.can_request(Instant::now())
+        {
+            // only the best route on the way
+            let route_request = route_request.clone().with_alternatives(0);
+            self.request(route_request, true);
+        }
+    }
+
+    /// Routes from the current location through the waypoints to the last one, with up to `alternatives`
+    /// other routes to select from.
+    pub fn calc_route(&mut self, waypoints: Vec<(f64, f64)>, route_costing: RouteCosting, alternatives: usize) {
+        if waypoints.is_empty() {
+            return;
+        }
+        let route_request = RouteRequest::new(waypoints, route_costing).with_alternatives(alternatives);
+        self.route_request = Some(route_request.clone());
+        self.request(route_request, false);
     }
 
-    pub fn calc_route(
-        &self,
-        to_lat_lon: (f64, f64),
-        route_costing: RouteCosting,
-        converter: Box<dyn (Fn(&Point) -> Point) + Send>,
-        api: Arc<RendererApi>,
-    ) {
-        if let Some((lat, lon)) = self.current_lat_lon {
-            spawn(move || {
-                let valhalla = Valhalla::default();
-
-                let source_loc = Location::new(lon as f32, lat as f32);
-                let destination_loc = Location::new(to_lat_lon.0 as f32, to_lat_lon.1 as f32);
-                let costing = match route_costing {
-                    RouteCosting::Pedestrian => Costing::Pedestrian(Default::default()),
-                    RouteCosting::Motorbike => Costing::Motorcycle(Default::default()),
+    fn request(&mut self, mut route_request: RouteRequest, is_rerouting: bool) {
+        let Some(from_lat_lon) = self.current_lat_lon else {
+            return;
+        };
+        self.generation += 1;
+        self.is_rerouting = is_rerouting;
+        self.reroute_throttle.on_request(Instant::now());
+
+        let generation = self.generation;
+        let route_sender = self.route_sender.clone();
+        let routing_provider = self.routing_provider.clone();
+        let offline_routing_provider = self.offline_routing_provider.clone();
+        route_request.locations.insert(0, from_lat_lon);
+        spawn(move || {
+            let routes = routing_provider.route(&route_request).or_else(|err| {
+                let Some(offline_routing_provider) = offline_routing_provider else {
+                    return Err(err);
                 };
-                let manifest = Manifest::builder()
-                    .locations([source_loc, destination_loc])
-                    .directions_type(DirectionsType::None)
-                    .costing(costing);
-
-                api.clear_render_groups(HashSet::from_iter(vec!["route".to_string()]));
-                match valhalla.route(manifest) {
-                    Ok(trip) => {
-                        println!("Route calculated: {:?}", trip);
-                        if let Some(leg) = trip.legs.first() {
-                            let route: Vec<Point> = leg
-                                .shape
-                                .iter()
-                                .map(|p| {
-                                    point! { x: p.lon, y: p.lat }
-                                })
-                                .collect();
-
-                            let route = Box::new(RouteGroup::new(route, route_costing, converter));
-                            let spatial_data = SpatialData::transform(route.first_route_point());
-                            api.add_render_group("route".to_string(), 1, spatial_data, route);
-                        } else {
-                            error!("No legs found in route!");
-                        }
-                    }
-                    Err(err) => {
-                        error!("Error calculating route: {:?}", err);
-                    }
-                }
+                error!("{:#}, routing offline", err);
+                offline_routing_provider
+                    .route(&route_request)
+                    .map_err(|offline_err| anyhow!("{:#}, offline: {:#}", err, offline_err))
             });
-        }
+            // the map is gone if the receiver is
+            let _ = route_sender.send(RouteResponse { generation, routes });
+        });
     }
 }
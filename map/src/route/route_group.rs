@@ -11,52 +11,170 @@ use renderer::styles::style_id::StyleId;
 use crate::route::RouteCosting;
 
 pub struct RouteGroup {
-    route: Vec<Point>,
-    route_costing: RouteCosting
+    routes: Vec<Vec<Point>>,
+    selected_route: usize,
+    route_costing: RouteCosting,
+    maneuver_points: Vec<usize>,
 }
 
 impl RouteGroup {
-    pub fn new(route: Vec<Point>, route_costing: RouteCosting, converter: Box<dyn Fn(&Point) -> Point>) -> RouteGroup {
-        let route: Vec<Point> = route.iter().map(|p| converter(p)).collect();
-        RouteGroup { route, route_costing }
+    // maneuver arrows in world units, the route line is 1 wide
+    const ARROW_LENGTH_BEFORE: f64 = 4.0;
+    const ARROW_LENGTH_AFTER: f64 = 2.5;
+    const ARROW_WIDTH: f32 = 0.5;
+    const ARROW_HEAD_LENGTH: f64 = 1.2;
+    const ARROW_HEAD_WIDTH: f64 = 1.4;
+
+    /// The selected route is drawn with the costing style over the alternatives, with the chevrons
+    /// and an arrow at each of its `maneuver_points`(shape indices).
+    pub fn new(
+        routes: Vec<Vec<Point>>,
+        selected_route: usize,
+        route_costing: RouteCosting,
+        maneuver_points: Vec<usize>,
+        converter: &dyn Fn(&Point) -> Point,
+    ) -> RouteGroup {
+        let routes: Vec<Vec<Point>> = routes
+            .iter()
+            .map(|route| route.iter().map(converter).collect())
+            .collect();
+        RouteGroup { routes, selected_route, route_costing, maneuver_points }
+    }
+
+    /// Style of the selected route line.
+    pub fn style_id(route_costing: RouteCosting) -> StyleId {
+        match route_costing {
+            RouteCosting::Pedestrian =>  StyleId("route_pedestrian"),
+            RouteCosting::Motorbike =>  StyleId("route_motorbike")
+        }
     }
 
     pub fn first_route_point(&self) -> Vector3<f64> {
-        Vector3::new(self.route[0].x(), self.route[0].y(), 0.0)
+        let first_route_point = self.routes[self.selected_route][0];
+        Vector3::new(first_route_point.x(), first_route_point.y(), 0.0)
     }
-}
 
-impl RenderGroup for RouteGroup {
-    fn content(&mut self, canvas: &mut CanvasApi) {
+    /// Distance along the converted route line to every shape point, in the units of the line distances
+    /// of the shaders, e.g. for the route progress.
+    pub fn route_lengths(route: &[Point]) -> Vec<f64> {
+        let mut lengths = vec![0.0];
+        route.windows(2).for_each(|pair| {
+            lengths.push(lengths.last().unwrap() + distance(pair[0], pair[1]));
+        });
+        lengths
+    }
+
+    fn relative(&self, p: &Point) -> lyon::math::Point {
+        let first_route_point = self.routes[self.selected_route][0];
+        point((p.x() - first_route_point.x()) as f32,
+              (p.y() - first_route_point.y()) as f32)
+    }
+
+    fn line(&self, line: &[Point]) -> Path {
         let mut path_builder = Path::builder();
-        let first_route_point = self.route[0];
-        path_builder.begin(point(0.0f32, 0.0f32));
+        path_builder.begin(self.relative(&line[0]));
 
         // TODO Should relative coords calc for the route be the route responsibility?
-        for &p in self.route[1..].iter() {
-            path_builder.line_to(point((p.x() - first_route_point.x()) as f32,
-                                       (p.y() - first_route_point.y()) as f32));
+        for p in line[1..].iter() {
+            path_builder.line_to(self.relative(p));
         }
         path_builder.end(false);
+        path_builder.build()
+    }
 
+    fn draw_line(canvas: &mut CanvasApi, path: Path, width: f32, style_id: StyleId) {
         let options = PolylineOptions {
-            width: 1f32,
+            width,
             line_join: LineJoin::Round,
             line_cap: LineCap::Round,
             tolerance: 0.05f32,
         };
-        
-        let style_id = match self.route_costing {
-            RouteCosting::Pedestrian =>  StyleId("route_pedestrian"),
-            RouteCosting::Motorbike =>  StyleId("route_motorbike")
-        };
-        
+
         canvas.path(ShapeData {
-            path: path_builder.build(),
+            path,
             geometry_type: GeometryType::Polyline(options),
             style_id,
             index_layer_level: 0,
             is_screen: false,
         });
     }
+
+    // the part of the route around the maneuver and a head at its end
+    fn draw_arrow(&self, canvas: &mut CanvasApi, index: usize) {
+        let route = &self.routes[self.selected_route];
+        let mut arrow = walk(route[..=index].iter().rev(), Self::ARROW_LENGTH_BEFORE);
+        arrow.reverse();
+        let after = walk(route[index..].iter(), Self::ARROW_LENGTH_AFTER);
+        arrow.extend(after.into_iter().skip(1));
+        if arrow.len() < 2 {
+            return;
+        }
+        Self::draw_line(canvas, self.line(&arrow), Self::ARROW_WIDTH, StyleId("route_maneuver_arrow"));
+
+        let end = arrow[arrow.len() - 1];
+        let length = distance(arrow[arrow.len() - 2], end);
+        if length <= 0.0 {
+            return;
+        }
+        let direction = (end - arrow[arrow.len() - 2]) / length;
+        let normal = Point::new(-direction.y(), direction.x());
+        let mut path_builder = Path::builder();
+        path_builder.begin(self.relative(&(end + direction * Self::ARROW_HEAD_LENGTH)));
+        path_builder.line_to(self.relative(&(end + normal * (Self::ARROW_HEAD_WIDTH / 2.0))));
+        path_builder.line_to(self.relative(&(end - normal * (Self::ARROW_HEAD_WIDTH / 2.0))));
+        path_builder.end(true);
+        canvas.path(ShapeData {
+            path: path_builder.build(),
+            geometry_type: GeometryType::Polygon,
+            style_id: StyleId("route_maneuver_arrow"),
+            index_layer_level: 0,
+            is_screen: false,
+        });
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.x() - a.x()).hypot(b.y() - a.y())
+}
+
+// the points from the first one until the line is `max_length` long
+fn walk<'a>(mut points: impl Iterator<Item = &'a Point>, max_length: f64) -> Vec<Point> {
+    let Some(first) = points.next() else {
+        return vec![];
+    };
+    let mut line = vec![*first];
+    let mut length = 0.0;
+    for next in points {
+        let last = *line.last().unwrap();
+        let step = distance(last, *next);
+        if length + step >= max_length {
+            if step > 0.0 {
+                line.push(last + (*next - last) * ((max_length - length) / step));
+            }
+            break;
+        }
+        length += step;
+        line.push(*next);
+    }
+    line
+}
+
+impl RenderGroup for RouteGroup {
+    fn content(&mut self, canvas: &mut CanvasApi) {
+        for (index, route) in self.routes.iter().enumerate() {
+            if index != self.selected_route {
+                Self::draw_line(canvas, self.line(route), 1f32, StyleId("route_alternative"));
+            }
+        }
+
+        let style_id = Self::style_id(self.route_costing);
+        // the chevrons share the line distances of the route, so they are hidden on its traveled part too
+        let route = self.line(&self.routes[self.selected_route]);
+        Self::draw_line(canvas, route.clone(), 1f32, style_id);
+        Self::draw_line(canvas, route, 1f32, StyleId("route_chevron"));
+
+        for index in &self.maneuver_points {
+            self.draw_arrow(canvas, *index);
+        }
+    }
 }
@@ -0,0 +1,220 @@
+use crate::feature_processor::ShashlikFeatureProcessor;
+use crate::style::style_sheet::{LayerRule, LayerType, StyleSheet};
+use cgmath::{Vector2, Vector3};
+use geo_types::{Coord, LineString, Polygon};
+use lyon::geom::point;
+use lyon::path::Path;
+use rand::Rng;
+use renderer::draw_commands::{GeometryType, PolylineOptions};
+use renderer::geometry_data::{ExtrudedPolygonData, GeometryData, ShapeData, SvgData, TextData};
+use seahash::hash;
+use std::collections::HashMap;
+
+/// Geometry of a feature in world units relative to the tile position.
+pub enum FeatureGeometry<'a> {
+    Points(&'a [Coord]),
+    Lines(&'a [LineString]),
+    Polygons(&'a [Polygon]),
+}
+
+pub struct StyleContext<'a> {
+    /// Map zoom level, 0 is the closest
+    pub zoom_level: i32,
+    pub dpi_scale: f32,
+    pub line_text_map: &'a mut HashMap<String, i32>,
+}
+
+fn builtin_icon(name: &str) -> Option<(&'static str, &'static [u8])> {
+    let icon = match name {
+        "train_station" => ("train_station", ShashlikFeatureProcessor::TRAIN_STATION_SVG),
+        "railway_station" => ("railway_station", ShashlikFeatureProcessor::TRAIN_STATION_SVG),
+        "traffic_light" => ("traffic_light", ShashlikFeatureProcessor::TRAFFIC_LIGHT_SVG),
+        "toilets" => ("toilets", ShashlikFeatureProcessor::TOILETS_SVG),
+        "parking" => ("parking", ShashlikFeatureProcessor::PARKING_SVG),
+        _ => return None,
+    };
+    Some(icon)
+}
+
+fn line_path(line: &LineString) -> Path {
+    let mut path_builder = Path::builder();
+    path_builder.begin(point(line[0].x as f32, line[0].y as f32));
+    for p in line.0[1..].iter() {
+        path_builder.line_to(point(p.x as f32, p.y as f32));
+    }
+    path_builder.end(false);
+    path_builder.build()
+}
+
+// exterior and interior rings, holes are cut by the fill rule
+fn polygon_path(polygon: &Polygon) -> Path {
+    let mut path_builder = Path::builder();
+    for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+        if ring.0.is_empty() {
+            continue;
+        }
+        path_builder.begin(point(ring[0].x as f32, ring[0].y as f32));
+        for p in ring.0[1..].iter() {
+            path_builder.line_to(point(p.x as f32, p.y as f32));
+        }
+        path_builder.end(true);
+    }
+    path_builder.build()
+}
+
+/// Emits the geometry of every style sheet rule matching the feature `kind` and `tags`,
+/// returns false if there is no such rule.
+pub fn style_feature(
+    style_sheet: &StyleSheet,
+    geometry_data: &mut Vec<GeometryData>,
+    kind: &str,
+    tags: &HashMap<&str, String>,
+    geometry: FeatureGeometry,
+    context: &mut StyleContext,
+) -> bool {
+    let mut is_styled = false;
+    let layer_level = tags
+        .get("layer")
+        .and_then(|layer| layer.parse::<i8>().ok())
+        .unwrap_or(0);
+    for rule in style_sheet.matching_layers(kind, tags, context.zoom_level) {
+        is_styled = true;
+        match (rule.layer_type, &geometry) {
+            (LayerType::Line, FeatureGeometry::Lines(lines)) => {
+                let width = rule
+                    .width
+                    .as_ref()
+                    .and_then(|width| width.eval(context.zoom_level as f32))
+                    .unwrap_or(1.0);
+                lines.iter().filter(|line| line.0.len() >= 2).for_each(|line| {
+                    geometry_data.push(GeometryData::Shape(ShapeData {
+                        path: line_path(line),
+                        geometry_type: GeometryType::Polyline(PolylineOptions {
+                            width,
+                            ..Default::default()
+                        }),
+                        style_id: rule.style_id().unwrap(),
+                        index_layer_level: rule.layer_level.unwrap_or(layer_level),
+                        is_screen: false,
+                    }));
+                });
+            }
+            (LayerType::Fill, FeatureGeometry::Polygons(polygons)) => {
+                polygons.iter().for_each(|polygon| {
+                    geometry_data.push(GeometryData::Shape(ShapeData {
+                        path: polygon_path(polygon),
+                        geometry_type: GeometryType::Polygon,
+                        style_id: rule.style_id().unwrap(),
+                        index_layer_level: rule.layer_level.unwrap_or(layer_level),
+                        is_screen: false,
+                    }));
+                });
+            }
+            (LayerType::Extrusion, FeatureGeometry::Polygons(polygons)) => {
+                let levels = match tags.get("levels").and_then(|levels| levels.parse::<u32>().ok()) {
+                    Some(levels) if levels > 0 => levels,
+                    _ => rand::rng().random_range(2..=3),
+                };
+                polygons.iter().for_each(|polygon| {
+                    geometry_data.push(GeometryData::ExtrudedPolygon(ExtrudedPolygonData {
+                        path: polygon_path(polygon),
+                        height: levels as f32 * rule.height.unwrap_or(0.5),
+                    }));
+                });
+            }
+            (LayerType::Symbol, FeatureGeometry::Points(points)) => {
+                points.iter().for_each(|position| {
+                    symbol(geometry_data, rule, tags, position, context);
+                });
+            }
+            (LayerType::Symbol, FeatureGeometry::Lines(lines)) => {
+                line_label(geometry_data, rule, tags, lines, context);
+            }
+            _ => {}
+        }
+    }
+    is_styled
+}
+
+fn rule_text<'a>(rule: &LayerRule, tags: &'a HashMap<&str, String>) -> Option<&'a String> {
+    let text = rule.text.as_ref()?;
+    tags.get(text.field.as_str()).filter(|text| !text.is_empty())
+}
+
+fn symbol(
+    geometry_data: &mut Vec<GeometryData>,
+    rule: &LayerRule,
+    tags: &HashMap<&str, String>,
+    coord: &Coord,
+    context: &StyleContext,
+) {
+    let position = Vector3::new(coord.x as f32, coord.y as f32, 0.0);
+    let icon = rule.icon.as_deref().and_then(builtin_icon);
+    let text = rule.text.as_ref().zip(rule_text(rule, tags));
+    let rank = rule.rank.unwrap_or(0);
+    // the icon and the label are placed together
+    let text_id = text.map(|(_, text)| hash(format!("{:?}{}{}", text, coord.x, coord.y).as_bytes()));
+    let symbol_id = icon.and(text_id);
+    if let (Some(icon), Some(style_id)) = (icon, rule.style_id()) {
+        geometry_data.push(GeometryData::Svg(SvgData {
+            icon,
+            position: position.cast().unwrap(),
+            size: rule.icon_size.unwrap_or(40.0) * context.dpi_scale,
+            style_id,
+            with_collision: true,
+            rank,
+            symbol_id,
+        }));
+    }
+
+    if let (Some((text_rule, text)), Some(id)) = (text, text_id) {
+        let y_offset = if icon.is_some() { text_rule.offset } else { 0.0 };
+        geometry_data.push(GeometryData::Text(TextData {
+            id,
+            text: text.clone(),
+            screen_offset: Vector2::new(0.0, y_offset * context.dpi_scale),
+            size: text_rule.size * context.dpi_scale,
+            positions: vec![position],
+            style_id: text_rule.style_id(),
+            rank,
+            symbol_id,
+        }));
+    }
+}
+
+fn line_label(
+    geometry_data: &mut Vec<GeometryData>,
+    rule: &LayerRule,
+    tags: &HashMap<&str, String>,
+    lines: &[LineString],
+    context: &mut StyleContext,
+) {
+    let (Some(text_rule), Some(name)) = (&rule.text, rule_text(rule, tags)) else {
+        return;
+    };
+    let repeat = text_rule.repeat.unwrap_or(1).max(1);
+    for line in lines {
+        // There might be more than 500 lines with the same name in a tile, only every n-th one is labeled
+        let name_count = context
+            .line_text_map
+            .entry(name.clone())
+            .and_modify(|entry| *entry += 1)
+            .or_insert(0);
+        // FIXME TextRenderer has a bug for only 2 coords line, let's skip it for now
+        if *name_count % repeat == 0 && line.0.len() > 2 {
+            geometry_data.push(GeometryData::Text(TextData {
+                id: hash(name.as_bytes()),
+                text: name.clone(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: text_rule.size * context.dpi_scale,
+                positions: line
+                    .coords()
+                    .map(|item| Vector3::new(item.x as f32, item.y as f32, 0.0))
+                    .collect(),
+                style_id: text_rule.style_id(),
+                rank: rule.rank.unwrap_or(0),
+                symbol_id: None,
+            }));
+        }
+    }
+}
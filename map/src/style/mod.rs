@@ -0,0 +1,4 @@
+pub mod feature_styler;
+pub mod style_sheet;
+pub mod style_watcher;
+pub mod theme;
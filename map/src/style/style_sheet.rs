@@ -0,0 +1,405 @@
+use anyhow::{Context, bail};
+use renderer::styles::render_style::RenderStyle;
+use renderer::styles::style_id::StyleId;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+const DEFAULT_STYLE_SHEET: &str = include_str!("../../styles/default.toml");
+
+/// Text style of the labels whose rule doesn't name one, e.g. the overlay and cluster labels.
+pub const LABEL_STYLE_ID: StyleId = StyleId("label");
+
+/// Style document which declares render styles and the rules mapping map features onto them.
+/// Zoom in the document is the map zoom level, 0 is the closest one.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StyleSheet {
+    #[serde(default)]
+    pub styles: BTreeMap<String, StyleDef>,
+    #[serde(default)]
+    pub layers: Vec<LayerRule>,
+}
+
+/// Render style, interpolated over zoom on the GPU every frame.
+/// Without `fill` the zoom stops are applied on top of the existing style with the same id.
+#[derive(Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StyleDef {
+    pub fill: Option<ZoomValue<Color>>,
+    /// Darken percent of the outline
+    pub border: Option<f32>,
+    pub dash: Option<Color>,
+    /// Line width factor, lines are widened from the width they are built with
+    pub width: Option<ZoomValue<f32>>,
+    pub opacity: Option<ZoomValue<f32>>,
+    /// Length of a dash and a gap in world units, the distance between the chevrons in line widths
+    pub dash_length: Option<ZoomValue<f32>>,
+    /// Only chevrons along the line are drawn in the fill color
+    #[serde(default)]
+    pub chevrons: bool,
+    /// Color of the traveled part of the routes, transparent hides it
+    pub traveled: Option<Color>,
+    /// Label style, `fill` is the text color, without it the labels keep the theme text color
+    #[serde(default)]
+    pub text: bool,
+    /// Outline of the labels, drawn only by the SDF text backend
+    pub halo: Option<Color>,
+    /// In screen pixels
+    pub halo_width: Option<f32>,
+    /// Index of the font the labels are shaped with first, 0 is the embedded one, the added fonts follow it
+    pub font: Option<usize>,
+    /// Extra space after every glyph in ems
+    pub letter_spacing: Option<f32>,
+    /// Point labels wider than it in ems are wrapped at the spaces
+    pub max_width: Option<f32>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerType {
+    Line,
+    Fill,
+    Extrusion,
+    Symbol,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerRule {
+    pub kind: String,
+    /// Tag name to the accepted values, `!` prefix of the tag name negates the match
+    #[serde(default)]
+    pub filter: HashMap<String, FilterValue>,
+    #[serde(rename = "type")]
+    pub layer_type: LayerType,
+    pub style: Option<String>,
+    pub min_zoom: Option<i32>,
+    pub max_zoom: Option<i32>,
+    /// Line width in world units
+    pub width: Option<ZoomValue<f32>>,
+    /// Draw order of lines and fills, the feature layer is used if it's not set
+    pub layer_level: Option<i8>,
+    /// Extrusion height per building level
+    pub height: Option<f32>,
+    pub icon: Option<String>,
+    pub icon_size: Option<f32>,
+    pub text: Option<TextRule>,
+    /// Icons and labels of the symbols with a higher rank are placed before the colliding ones
+    pub rank: Option<i32>,
+    // `style` interned when the document is loaded
+    #[serde(skip)]
+    style_id: Option<StyleId>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextRule {
+    #[serde(default = "TextRule::default_field")]
+    pub field: String,
+    #[serde(default = "TextRule::default_size")]
+    pub size: f32,
+    /// Screen offset in pixels, only applied when the rule also has an icon
+    #[serde(default)]
+    pub offset: f32,
+    /// Only every n-th line label with the same text is shown
+    pub repeat: Option<i32>,
+    /// Text style of the labels, `label` by default
+    pub style: Option<String>,
+    #[serde(skip)]
+    style_id: Option<StyleId>,
+}
+
+impl TextRule {
+    fn default_field() -> String {
+        "name".to_string()
+    }
+
+    fn default_size() -> f32 {
+        40.0
+    }
+
+    pub fn style_id(&self) -> StyleId {
+        self.style_id.clone().unwrap_or(LABEL_STYLE_ID)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum FilterValue {
+    One(String),
+    Any(Vec<String>),
+}
+
+impl FilterValue {
+    fn matches(&self, value: Option<&str>) -> bool {
+        match (self, value) {
+            (_, None) => false,
+            (FilterValue::One(expected), Some(value)) => expected == "*" || expected == value,
+            (FilterValue::Any(expected), Some(value)) => expected.iter().any(|item| item == value),
+        }
+    }
+}
+
+/// A fixed value or linearly interpolated stops over the zoom level.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ZoomValue<T> {
+    Fixed(T),
+    Stops(Vec<(f32, T)>),
+}
+
+pub trait Interpolate: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<T: Interpolate> ZoomValue<T> {
+    pub fn eval(&self, zoom: f32) -> Option<T> {
+        match self {
+            ZoomValue::Fixed(value) => Some(*value),
+            ZoomValue::Stops(stops) => {
+                let first = stops.first()?;
+                if zoom <= first.0 {
+                    return Some(first.1);
+                }
+                for pair in stops.windows(2) {
+                    let ((z1, v1), (z2, v2)) = (pair[0], pair[1]);
+                    if zoom <= z2 {
+                        let t = if z2 > z1 { (zoom - z1) / (z2 - z1) } else { 1.0 };
+                        return Some(v1.lerp(v2, t));
+                    }
+                }
+                stops.last().map(|stop| stop.1)
+            }
+        }
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        matches!(self, ZoomValue::Fixed(_))
+    }
+
+    /// A fixed value is a single stop
+    pub fn stops(&self) -> Vec<(f32, T)> {
+        match self {
+            ZoomValue::Fixed(value) => vec![(0.0, *value)],
+            ZoomValue::Stops(stops) => stops.clone(),
+        }
+    }
+}
+
+/// RGBA color, `"#rrggbb"`, `"#rrggbbaa"` or `[r, g, b, a]` with 0..1 components.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "ColorRepr")]
+pub struct Color(pub [f32; 4]);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorRepr {
+    Hex(String),
+    Rgba([f32; 4]),
+}
+
+impl TryFrom<ColorRepr> for Color {
+    type Error = String;
+
+    fn try_from(value: ColorRepr) -> Result<Self, Self::Error> {
+        match value {
+            ColorRepr::Rgba(rgba) => Ok(Color(rgba)),
+            ColorRepr::Hex(hex) => hex.parse(),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !(digits.len() == 6 || digits.len() == 8) || !digits.is_ascii() {
+            return Err(format!("Invalid color {hex}"));
+        }
+        let mut rgba = [1.0f32; 4];
+        for (i, chunk) in digits.as_bytes().chunks(2).enumerate() {
+            let chunk = std::str::from_utf8(chunk).unwrap();
+            let value = u8::from_str_radix(chunk, 16).map_err(|_| format!("Invalid color {hex}"))?;
+            rgba[i] = value as f32 / 255.0;
+        }
+        Ok(Color(rgba))
+    }
+}
+
+impl Interpolate for Color {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let mut rgba = self.0;
+        rgba.iter_mut()
+            .zip(other.0)
+            .for_each(|(value, other)| *value = value.lerp(other, t));
+        Color(rgba)
+    }
+}
+
+impl StyleDef {
+    /// The progress and the traveled color of a route are kept, e.g. when the style sheet is reloaded.
+    pub fn apply(&self, style: &mut RenderStyle) {
+        if self.text {
+            self.apply_text(style);
+            return;
+        }
+        let progress = style.progress();
+        let traveled_color = style.traveled_color();
+        if let Some(fill) = &self.fill {
+            let color = fill.eval(0.0).unwrap_or(Color([1.0; 4])).0;
+            *style = match (self.chevrons, self.dash, self.border) {
+                (true, _, _) => RenderStyle::chevrons(color),
+                (false, Some(dash), _) => RenderStyle::dashed(color, dash.0),
+                (false, None, Some(border)) => RenderStyle::border(color, border),
+                (false, None, None) => RenderStyle::fill(color),
+            };
+        }
+        if let Some(traveled) = self.traveled.map(|traveled| traveled.0).or(traveled_color) {
+            *style = style.with_traveled_color(traveled);
+        }
+        let color_stops: Vec<(f32, [f32; 4])> = match &self.fill {
+            Some(fill) if !fill.is_fixed() => {
+                fill.stops().into_iter().map(|(zoom, color)| (zoom, color.0)).collect()
+            }
+            _ => vec![],
+        };
+        let scalar_stops =
+            |value: &Option<ZoomValue<f32>>| value.as_ref().map(|value| value.stops()).unwrap_or_default();
+        *style = style
+            .with_color_stops(&color_stops)
+            .with_width_stops(&scalar_stops(&self.width))
+            .with_opacity_stops(&scalar_stops(&self.opacity))
+            .with_dash_stops(&scalar_stops(&self.dash_length))
+            .with_progress(progress);
+    }
+
+    fn apply_text(&self, style: &mut RenderStyle) {
+        let color = self.fill.as_ref().and_then(|fill| fill.eval(0.0)).map(|color| color.0);
+        let mut text_style = RenderStyle::text(color)
+            .with_font(self.font.unwrap_or(0))
+            .with_letter_spacing(self.letter_spacing.unwrap_or(0.0))
+            .with_max_width(self.max_width.unwrap_or(0.0));
+        if let Some(halo) = self.halo {
+            text_style = text_style.with_halo(halo.0, self.halo_width.unwrap_or(1.0));
+        }
+        *style = text_style;
+    }
+}
+
+impl LayerRule {
+    pub fn matches(&self, kind: &str, tags: &HashMap<&str, String>, zoom_level: i32) -> bool {
+        self.kind == kind
+            && self.min_zoom.is_none_or(|min_zoom| zoom_level >= min_zoom)
+            && self.max_zoom.is_none_or(|max_zoom| zoom_level <= max_zoom)
+            && self.filter.iter().all(|(key, expected)| match key.strip_prefix('!') {
+                Some(key) => !expected.matches(tags.get(key).map(|v| v.as_str())),
+                None => expected.matches(tags.get(key.as_str()).map(|v| v.as_str())),
+            })
+    }
+
+    pub fn style_id(&self) -> Option<StyleId> {
+        self.style_id.clone()
+    }
+}
+
+impl StyleSheet {
+    pub fn from_toml(document: &str) -> anyhow::Result<StyleSheet> {
+        let mut style_sheet: StyleSheet = toml::from_str(document)?;
+        style_sheet.validate()?;
+        style_sheet.intern_style_ids();
+        Ok(style_sheet)
+    }
+
+    pub fn from_json(document: &str) -> anyhow::Result<StyleSheet> {
+        let mut style_sheet: StyleSheet = serde_json::from_str(document)?;
+        style_sheet.validate()?;
+        style_sheet.intern_style_ids();
+        Ok(style_sheet)
+    }
+
+    /// Parses a `.json` or `.toml` style document.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<StyleSheet> {
+        let path = path.as_ref();
+        let document = std::fs::read_to_string(path)
+            .with_context(|| format!("Can't read style sheet {:?}", path))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&document),
+            _ => Self::from_toml(&document),
+        }
+        .with_context(|| format!("Invalid style sheet {:?}", path))
+    }
+
+    /// Rules of the built-in document, they reproduce the original hardcoded styling.
+    pub fn default_sheet() -> StyleSheet {
+        Self::from_toml(DEFAULT_STYLE_SHEET).expect("Default style sheet is invalid")
+    }
+
+    pub fn matching_layers<'a>(
+        &'a self,
+        kind: &'a str,
+        tags: &'a HashMap<&str, String>,
+        zoom_level: i32,
+    ) -> impl Iterator<Item = &'a LayerRule> + 'a {
+        self.layers
+            .iter()
+            .filter(move |layer| layer.matches(kind, tags, zoom_level))
+    }
+
+    pub fn render_styles(&self) -> Vec<(StyleId, StyleDef)> {
+        self.styles
+            .iter()
+            .map(|(id, style)| (StyleId(intern(id)), style.clone()))
+            .collect()
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        for layer in &self.layers {
+            let needs_style = matches!(layer.layer_type, LayerType::Line | LayerType::Fill);
+            if needs_style && layer.style.is_none() {
+                bail!("Layer of kind {:?} has no style", layer.kind);
+            }
+            if layer.layer_type == LayerType::Symbol && layer.icon.is_none() && layer.text.is_none()
+            {
+                bail!("Symbol layer of kind {:?} has neither icon nor text", layer.kind);
+            }
+            if layer.icon.is_some() && layer.style.is_none() {
+                bail!("Layer of kind {:?} has an icon but no style", layer.kind);
+            }
+        }
+        Ok(())
+    }
+
+    // the rules are matched per feature, the ids are resolved once instead of locking the interned ones every time
+    fn intern_style_ids(&mut self) {
+        for layer in &mut self.layers {
+            layer.style_id = layer.style.as_deref().map(|style| StyleId(intern(style)));
+            if let Some(text) = layer.text.as_mut() {
+                text.style_id = text.style.as_deref().map(|style| StyleId(intern(style)));
+            }
+        }
+    }
+}
+
+/// `StyleId` needs a static string, every id is leaked once no matter how many times the document is reloaded.
+pub(crate) fn intern(id: &str) -> &'static str {
+    static IDS: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut ids = IDS.get_or_init(|| Mutex::new(HashSet::new())).lock().unwrap();
+    match ids.get(id) {
+        Some(id) => id,
+        None => {
+            let id: &'static str = Box::leak(id.to_string().into_boxed_str());
+            ids.insert(id);
+            id
+        }
+    }
+}
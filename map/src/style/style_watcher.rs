@@ -0,0 +1,57 @@
+use crate::style::style_sheet::StyleSheet;
+use log::error;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, channel};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, SystemTime};
+
+/// Polls the modification time of a style document and parses it again when it changes.
+/// Invalid documents are logged and skipped, so a typo doesn't break the running map.
+pub struct StyleWatcher {
+    receiver: Receiver<StyleSheet>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl StyleWatcher {
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new(path: PathBuf) -> StyleWatcher {
+        let (sender, receiver) = channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        spawn(move || {
+            let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            let mut last_modified: Option<SystemTime> = modified(&path);
+            while !thread_stopped.load(Ordering::Relaxed) {
+                sleep(Self::POLL_INTERVAL);
+                let current = modified(&path);
+                if current.is_none() || current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                match StyleSheet::load(&path) {
+                    Ok(style_sheet) => {
+                        if sender.send(style_sheet).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => error!("Style sheet is not reloaded: {err:#}"),
+                }
+            }
+        });
+        StyleWatcher { receiver, stopped }
+    }
+
+    /// The latest reloaded document, if any
+    pub fn poll(&self) -> Option<StyleSheet> {
+        self.receiver.try_iter().last()
+    }
+}
+
+impl Drop for StyleWatcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
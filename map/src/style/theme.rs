@@ -0,0 +1,147 @@
+use crate::style::style_sheet::{Color, Interpolate, intern};
+use anyhow::{Context, bail};
+use renderer::styles::style_id::StyleId;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const BUILTIN_THEMES: [(&str, &str); 3] = [
+    ("day", include_str!("../../styles/themes/day.toml")),
+    ("night", include_str!("../../styles/themes/night.toml")),
+    ("high_contrast", include_str!("../../styles/themes/high_contrast.toml")),
+];
+
+/// Named set of colors applied on top of the style sheet, e.g. for a night mode.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub name: String,
+    /// Clear color of the map
+    pub background: Color,
+    #[serde(default = "Theme::default_text")]
+    pub text: Color,
+    /// Style id to its fill color, styles which aren't listed keep the color of the style sheet
+    #[serde(default)]
+    pub colors: BTreeMap<String, Color>,
+}
+
+impl Theme {
+    fn default_text() -> Color {
+        Color([0.0, 0.0, 0.0, 1.0])
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_THEMES.iter().map(|(name, _)| *name)
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        BUILTIN_THEMES
+            .iter()
+            .find(|(theme_name, _)| *theme_name == name)
+            .map(|(_, document)| Self::from_toml(document).expect("Built-in theme is invalid"))
+    }
+
+    pub fn day() -> Theme {
+        Self::builtin("day").unwrap()
+    }
+
+    pub fn from_toml(document: &str) -> anyhow::Result<Theme> {
+        Ok(toml::from_str(document)?)
+    }
+
+    pub fn from_json(document: &str) -> anyhow::Result<Theme> {
+        Ok(serde_json::from_str(document)?)
+    }
+
+    /// Parses a `.json` or `.toml` theme document.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Theme> {
+        let path = path.as_ref();
+        let document =
+            std::fs::read_to_string(path).with_context(|| format!("Can't read theme {:?}", path))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&document),
+            _ => Self::from_toml(&document),
+        }
+        .with_context(|| format!("Invalid theme {:?}", path))
+    }
+
+    pub fn by_name(name: &str) -> anyhow::Result<Theme> {
+        match Self::builtin(name) {
+            Some(theme) => Ok(theme),
+            None => bail!(
+                "Unknown theme {name:?}, built-in themes are {:?}",
+                Self::builtin_names().collect::<Vec<_>>()
+            ),
+        }
+    }
+}
+
+/// Theme colors resolved against the base colors of the styles, so every style has a target color.
+#[derive(Clone)]
+pub(crate) struct ThemeColors {
+    pub background: [f32; 4],
+    pub text: [f32; 4],
+    pub styles: HashMap<StyleId, [f32; 4]>,
+}
+
+impl ThemeColors {
+    pub fn resolve(theme: &Theme, base_colors: &HashMap<StyleId, [f32; 4]>) -> ThemeColors {
+        let mut styles = base_colors.clone();
+        theme.colors.iter().for_each(|(style_id, color)| {
+            styles.insert(StyleId(intern(style_id)), color.0);
+        });
+        ThemeColors {
+            background: theme.background.0,
+            text: theme.text.0,
+            styles,
+        }
+    }
+
+    fn lerp(&self, other: &ThemeColors, t: f32) -> ThemeColors {
+        let lerp = |from: [f32; 4], to: [f32; 4]| Color(from).lerp(Color(to), t).0;
+        ThemeColors {
+            background: lerp(self.background, other.background),
+            text: lerp(self.text, other.text),
+            styles: other
+                .styles
+                .iter()
+                .map(|(style_id, to)| {
+                    let from = self.styles.get(style_id).copied().unwrap_or(*to);
+                    (style_id.clone(), lerp(from, *to))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Cross-fade from the colors on screen to the colors of a new theme.
+pub(crate) struct ThemeFade {
+    from: ThemeColors,
+    to: ThemeColors,
+    started: Instant,
+}
+
+impl ThemeFade {
+    const DURATION: Duration = Duration::from_millis(600);
+
+    pub fn new(from: ThemeColors, to: ThemeColors) -> ThemeFade {
+        ThemeFade {
+            from,
+            to,
+            started: Instant::now(),
+        }
+    }
+
+    fn progress(&self) -> f32 {
+        (self.started.elapsed().as_secs_f32() / Self::DURATION.as_secs_f32()).min(1.0)
+    }
+
+    pub fn colors(&self) -> ThemeColors {
+        self.from.lerp(&self.to, self.progress())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
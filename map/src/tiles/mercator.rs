@@ -0,0 +1,32 @@
+use geo_types::Coord;
+use googleprojection::Mercator;
+
+/// World coordinates are Mercator pixels at this zoom with 1px tiles, y grows to the south.
+pub const WORLD_ZOOM: usize = 22;
+
+pub fn lat_lon_to_world(lat_lon: &Coord<f64>) -> Coord<f64> {
+    let lat_lon: (f64, f64) = (*lat_lon).into();
+    Mercator::with_size(1)
+        .from_ll_to_subpixel(&lat_lon, WORLD_ZOOM)
+        .unwrap()
+        .into()
+}
+
+pub fn world_to_lat_lon(world: &Coord<f64>) -> Coord<f64> {
+    let world: (f64, f64) = (*world).into();
+    Mercator::with_size(1)
+        .from_pixel_to_ll(&world, WORLD_ZOOM)
+        .unwrap()
+        .into()
+}
+
+/// Size of an XYZ(slippy map) tile of `zoom` in world units, the tiles deeper than `WORLD_ZOOM` are smaller than one.
+pub fn tile_world_size(zoom: u8) -> f64 {
+    2f64.powi(WORLD_ZOOM as i32 - zoom as i32)
+}
+
+/// Length of one world unit in meters at the latitude, Mercator stretches it towards the poles.
+pub fn meters_per_world_unit(lat: f64) -> f64 {
+    const EARTH_CIRCUMFERENCE: f64 = 40_075_016.686;
+    EARTH_CIRCUMFERENCE * lat.to_radians().cos() / (1u64 << WORLD_ZOOM) as f64
+}
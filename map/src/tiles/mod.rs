@@ -1,3 +1,7 @@
 pub mod tile_data;
 pub mod tiles_provider;
 pub mod shashlik_tiles_provider_v0;
+pub mod source;
+pub mod mercator;
+pub mod mvt;
+mod tile_loader;
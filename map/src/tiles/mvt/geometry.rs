@@ -0,0 +1,120 @@
+use crate::tiles::mvt::vector_tile::GeomType;
+use geo_types::{Coord, LineString, Polygon};
+
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+pub enum MvtGeometry {
+    Points(Vec<Coord>),
+    Lines(Vec<LineString>),
+    Polygons(Vec<Polygon>),
+}
+
+fn zigzag(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+struct Part {
+    coords: Vec<(i32, i32)>,
+    closed: bool,
+}
+
+// Malformed command streams are cut at the first invalid command instead of failing the whole tile
+fn decode_parts(commands: &[u32]) -> Vec<Part> {
+    let mut parts: Vec<Part> = vec![];
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut i = 0;
+    while i < commands.len() {
+        let command = commands[i] & 0x7;
+        let count = (commands[i] >> 3) as usize;
+        i += 1;
+        match command {
+            MOVE_TO | LINE_TO => {
+                if i + count * 2 > commands.len() {
+                    break;
+                }
+                for n in 0..count {
+                    x = x.wrapping_add(zigzag(commands[i + n * 2]));
+                    y = y.wrapping_add(zigzag(commands[i + n * 2 + 1]));
+                    if command == MOVE_TO {
+                        parts.push(Part {
+                            coords: vec![(x, y)],
+                            closed: false,
+                        });
+                    } else if let Some(part) = parts.last_mut() {
+                        part.coords.push((x, y));
+                    }
+                }
+                i += count * 2;
+            }
+            CLOSE_PATH => {
+                if let Some(part) = parts.last_mut() {
+                    part.closed = true;
+                }
+            }
+            _ => break,
+        }
+    }
+    parts
+}
+
+// surveyor's formula in tile coordinates, exterior rings are positive
+fn signed_area(coords: &[(i32, i32)]) -> i64 {
+    let mut area = 0i64;
+    for i in 0..coords.len() {
+        let (x1, y1) = coords[i];
+        let (x2, y2) = coords[(i + 1) % coords.len()];
+        area += x1 as i64 * y2 as i64 - x2 as i64 * y1 as i64;
+    }
+    area
+}
+
+/// Decodes the command stream of a feature, `to_local` converts tile extent coordinates.
+pub fn decode_geometry(
+    geom_type: GeomType,
+    commands: &[u32],
+    to_local: impl Fn(i32, i32) -> Coord,
+) -> Option<MvtGeometry> {
+    let parts = decode_parts(commands);
+    let to_line = |part: &Part| -> LineString {
+        part.coords.iter().map(|(x, y)| to_local(*x, *y)).collect()
+    };
+
+    let geometry = match geom_type {
+        GeomType::Point => MvtGeometry::Points(
+            parts
+                .iter()
+                .flat_map(|part| part.coords.iter().map(|(x, y)| to_local(*x, *y)))
+                .collect(),
+        ),
+        GeomType::Linestring => MvtGeometry::Lines(
+            parts
+                .iter()
+                .filter(|part| part.coords.len() >= 2)
+                .map(to_line)
+                .collect(),
+        ),
+        GeomType::Polygon => {
+            let mut polygons: Vec<(LineString, Vec<LineString>)> = vec![];
+            for part in parts.iter().filter(|part| part.closed && part.coords.len() >= 3) {
+                let area = signed_area(&part.coords);
+                if area == 0 {
+                    continue;
+                }
+                match polygons.last_mut() {
+                    Some((_, interiors)) if area < 0 => interiors.push(to_line(part)),
+                    _ => polygons.push((to_line(part), vec![])),
+                }
+            }
+            MvtGeometry::Polygons(
+                polygons
+                    .into_iter()
+                    .map(|(exterior, interiors)| Polygon::new(exterior, interiors))
+                    .collect(),
+            )
+        }
+        GeomType::Unknown => return None,
+    };
+    Some(geometry)
+}
@@ -0,0 +1,104 @@
+use crate::tiles::mvt::vector_tile::{Layer, Tile, Value};
+use crate::tiles::tile_loader::LoaderTileKey;
+use flate2::read::GzDecoder;
+use prost::Message;
+use std::collections::HashMap;
+use std::io::Read;
+
+pub mod geometry;
+pub mod mvt_tiles_provider;
+pub mod openmaptiles_processor;
+pub mod source;
+pub mod vector_tile;
+
+/// XYZ(slippy map) tile key, y grows to the south.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MvtTileKey {
+    pub x: u32,
+    pub y: u32,
+    pub zoom: u8,
+}
+
+impl MvtTileKey {
+    pub fn as_string_key(&self) -> String {
+        format!("mvt_{}_{}_{}", self.zoom, self.x, self.y)
+    }
+}
+
+impl LoaderTileKey for MvtTileKey {
+    fn zoom(&self) -> i32 {
+        self.zoom as i32
+    }
+
+    fn as_string_key(&self) -> String {
+        MvtTileKey::as_string_key(self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MvtValue {
+    String(String),
+    Float(f32),
+    Double(f64),
+    Int(i64),
+    UInt(u64),
+    Bool(bool),
+}
+
+impl MvtValue {
+    fn from_value(value: &Value) -> Option<MvtValue> {
+        if let Some(v) = &value.string_value {
+            Some(MvtValue::String(v.clone()))
+        } else if let Some(v) = value.float_value {
+            Some(MvtValue::Float(v))
+        } else if let Some(v) = value.double_value {
+            Some(MvtValue::Double(v))
+        } else if let Some(v) = value.int_value.or(value.sint_value) {
+            Some(MvtValue::Int(v))
+        } else if let Some(v) = value.uint_value {
+            Some(MvtValue::UInt(v))
+        } else {
+            value.bool_value.map(MvtValue::Bool)
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MvtValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            MvtValue::Float(v) => Some(*v as f64),
+            MvtValue::Double(v) => Some(*v),
+            MvtValue::Int(v) => Some(*v as f64),
+            MvtValue::UInt(v) => Some(*v as f64),
+            MvtValue::String(v) => v.parse().ok(),
+            MvtValue::Bool(_) => None,
+        }
+    }
+}
+
+/// Decodes a tile protobuf, gzipped tiles(e.g. from MBTiles) are inflated first.
+pub fn decode_tile(data: &[u8]) -> anyhow::Result<Tile> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut inflated = vec![];
+        GzDecoder::new(data).read_to_end(&mut inflated)?;
+        Ok(Tile::decode(inflated.as_slice())?)
+    } else {
+        Ok(Tile::decode(data)?)
+    }
+}
+
+/// Resolves the `tags` of a feature against the keys and values of its layer.
+pub fn feature_properties(layer: &Layer, tags: &[u32]) -> HashMap<String, MvtValue> {
+    tags.chunks_exact(2)
+        .filter_map(|tag| {
+            let key = layer.keys.get(tag[0] as usize)?;
+            let value = MvtValue::from_value(layer.values.get(tag[1] as usize)?)?;
+            Some((key.clone(), value))
+        })
+        .collect()
+}
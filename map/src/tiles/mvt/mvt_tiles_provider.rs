@@ -0,0 +1,252 @@
+use crate::matching::road_network::{Road, RoadNetwork};
+use crate::style::style_sheet::StyleSheet;
+use crate::tiles::mercator;
+use crate::tiles::mvt::geometry::{MvtGeometry, decode_geometry};
+use crate::tiles::mvt::source::MvtSource;
+use crate::tiles::shashlik_tiles_provider_v0::ROADS_MAX_ZOOM_LEVEL;
+use crate::tiles::mvt::{MvtTileKey, MvtValue, decode_tile, feature_properties};
+use crate::tiles::tile_data::{TileData, TileFeature};
+use crate::tiles::tile_loader::TileLoader;
+use crate::tiles::tiles_provider::{TilesMessage, TilesProvider};
+use futures::Stream;
+use geo::Intersects;
+use geo_types::{Coord, Polygon, Rect, coord};
+use log::{error, warn};
+use renderer::geometry_data::GeometryData;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+pub struct MvtFeature {
+    pub id: Option<u64>,
+    pub properties: HashMap<String, MvtValue>,
+    /// In world units relative to the tile position
+    pub geometry: MvtGeometry,
+}
+
+impl MvtFeature {
+    pub fn str(&self, key: &str) -> Option<&str> {
+        self.properties.get(key)?.as_str()
+    }
+
+    pub fn f64(&self, key: &str) -> Option<f64> {
+        self.properties.get(key)?.as_f64()
+    }
+}
+
+pub struct MvtTileContext {
+    /// Zoom of the tile
+    pub zoom: u8,
+    /// Map zoom level, as `FeatureProcessor` gets it, 0 is the closest
+    pub zoom_level: i32,
+    pub dpi_scale: f32,
+    pub line_text_map: HashMap<String, i32>,
+}
+
+/// Converts MVT features into renderer geometry, the layer names and properties depend on the tiles schema.
+pub trait MvtFeatureProcessor: Send + Sync {
+    /// Drawn features which can be picked go to `features`
+    fn process_feature(
+        &self,
+        geometry_data: &mut Vec<GeometryData>,
+        features: &mut Vec<TileFeature>,
+        layer: &str,
+        feature: MvtFeature,
+        context: &mut MvtTileContext,
+    );
+
+    /// Replaces the rules for the tiles built after this call
+    fn set_style_sheet(&self, _style_sheet: Arc<StyleSheet>) {}
+
+    /// Roads of the feature the map matcher snaps to, in the coordinates of the feature geometry
+    fn roads(&self, _layer: &str, _feature: &MvtFeature) -> Vec<Road> {
+        vec![]
+    }
+}
+
+pub struct MvtTilesProvider<S: MvtSource, FP: MvtFeatureProcessor> {
+    tile_loader: TileLoader<MvtTileKey>,
+    source: Arc<S>,
+    dpi_scale: f32,
+    feature_processor: Arc<FP>,
+    road_network: Arc<RoadNetwork>,
+}
+
+impl<S: MvtSource, FP: MvtFeatureProcessor + 'static> MvtTilesProvider<S, FP> {
+    // the tile zoom which gives a few tiles per screen for the map zoom level 0
+    const ZOOM_LEVEL_OFFSET: i32 = 17;
+    // protects from loading the whole world when the source has no low zoom tiles
+    const MAX_VISIBLE_TILES: usize = 256;
+    // the tile indices of deeper zooms don't fit u32
+    const MAX_TILE_ZOOM: i32 = 30;
+
+    pub fn new(source: S, feature_processor: FP, dpi_scale: f32) -> MvtTilesProvider<S, FP> {
+        Self {
+            tile_loader: TileLoader::new(),
+            source: Arc::new(source),
+            dpi_scale,
+            feature_processor: Arc::new(feature_processor),
+            road_network: Arc::new(RoadNetwork::new()),
+        }
+    }
+
+    fn tile_zoom(&self, zoom_level: i32) -> u8 {
+        let min_zoom = (self.source.min_zoom() as i32).min(Self::MAX_TILE_ZOOM);
+        let max_zoom = (self.source.max_zoom() as i32).clamp(min_zoom, Self::MAX_TILE_ZOOM);
+        (Self::ZOOM_LEVEL_OFFSET - zoom_level).clamp(min_zoom, max_zoom) as u8
+    }
+
+    fn visible_tiles(area_poly: &Polygon<f64>, zoom: u8) -> HashSet<MvtTileKey> {
+        let world_poly: Polygon<f64> = Polygon::new(
+            area_poly
+                .exterior()
+                .coords()
+                .map(mercator::lat_lon_to_world)
+                .collect(),
+            vec![],
+        );
+        let Some(bbox) = geo_types::private_utils::get_bounding_rect(world_poly.exterior()) else {
+            return HashSet::new();
+        };
+
+        let tile_size = mercator::tile_world_size(zoom);
+        let max_index = (1u32 << zoom) - 1;
+        let to_index = |v: f64| ((v / tile_size).floor().max(0.0) as u32).min(max_index);
+
+        let mut tiles = HashSet::new();
+        for x in to_index(bbox.min().x)..=to_index(bbox.max().x) {
+            for y in to_index(bbox.min().y)..=to_index(bbox.max().y) {
+                let tile_rect = Rect::new(
+                    coord! {x: x as f64 * tile_size, y: y as f64 * tile_size},
+                    coord! {x: (x + 1) as f64 * tile_size, y: (y + 1) as f64 * tile_size},
+                );
+                if world_poly.intersects(&tile_rect) {
+                    tiles.insert(MvtTileKey { x, y, zoom });
+                }
+            }
+        }
+        tiles
+    }
+
+    fn get_tile_key_data(
+        source: &S,
+        feature_processor: &FP,
+        road_network: &RoadNetwork,
+        tile_key: &MvtTileKey,
+        zoom_level: i32,
+        dpi_scale: f32,
+    ) -> Option<TileData> {
+        let data = source.load(tile_key)?;
+        let tile = decode_tile(&data)
+            .map_err(|err| error!("Can't decode tile {:?}: {err}", tile_key))
+            .ok()?;
+
+        let tile_size = mercator::tile_world_size(tile_key.zoom);
+        // the position is the south-west corner, like tiles of ShashlikTilesProviderV0
+        let tile_origin = coord! {
+            x: tile_key.x as f64 * tile_size,
+            y: (tile_key.y + 1) as f64 * tile_size,
+        };
+        let tile_position = [tile_origin.x, tile_origin.y, 0.0].into();
+
+        let mut context = MvtTileContext {
+            zoom: tile_key.zoom,
+            zoom_level,
+            dpi_scale,
+            line_text_map: HashMap::new(),
+        };
+        let mut geometry_data: Vec<GeometryData> = vec![];
+        let mut features = vec![];
+        let mut roads = vec![];
+        for layer in &tile.layers {
+            let extent = layer.extent().max(1) as f64;
+            let to_local = |x: i32, y: i32| -> Coord {
+                coord! {
+                    x: x as f64 / extent * tile_size,
+                    y: (y as f64 / extent - 1.0) * tile_size,
+                }
+            };
+            for feature in &layer.features {
+                let Some(geometry) = decode_geometry(feature.r#type(), &feature.geometry, to_local)
+                else {
+                    continue;
+                };
+                let feature = MvtFeature {
+                    id: feature.id,
+                    properties: feature_properties(layer, &feature.tags),
+                    geometry,
+                };
+                if zoom_level <= ROADS_MAX_ZOOM_LEVEL {
+                    roads.extend(feature_processor.roads(&layer.name, &feature).into_iter().map(
+                        |mut road| {
+                            road.line.coords_mut().for_each(|coord| *coord = *coord + tile_origin);
+                            road
+                        },
+                    ));
+                }
+                feature_processor.process_feature(
+                    &mut geometry_data,
+                    &mut features,
+                    &layer.name,
+                    feature,
+                    &mut context,
+                );
+            }
+        }
+        road_network.insert_tile(tile_key.as_string_key(), roads);
+
+        Some(TileData {
+            key: tile_key.as_string_key(),
+            position: tile_position,
+            size: (tile_size, tile_size),
+            geometry_data,
+            features,
+        })
+    }
+}
+
+impl<S: MvtSource, FP: MvtFeatureProcessor + 'static> TilesProvider for MvtTilesProvider<S, FP> {
+    fn load(&mut self, _area_latlon: Rect, area_poly: Polygon<f64>, zoom_level: i32) {
+        let zoom = self.tile_zoom(zoom_level);
+        let current_visible_tiles = Self::visible_tiles(&area_poly, zoom);
+        if current_visible_tiles.len() > Self::MAX_VISIBLE_TILES {
+            warn!(
+                "Skipping {} tiles of zoom {zoom}, the source has no tiles for this zoom level",
+                current_visible_tiles.len()
+            );
+            return;
+        }
+
+        let source = self.source.clone();
+        let feature_processor = self.feature_processor.clone();
+        let road_network = self.road_network.clone();
+        let dpi_scale = self.dpi_scale;
+        self.tile_loader.load(current_visible_tiles, zoom as i32, move |key| {
+            Self::get_tile_key_data(&source, &feature_processor, &road_network, key, zoom_level, dpi_scale)
+        });
+    }
+
+    fn tiles(&mut self) -> impl Stream<Item = TilesMessage> + Send + 'static {
+        self.tile_loader.tiles()
+    }
+
+    fn set_style_sheet(&mut self, style_sheet: Arc<StyleSheet>) {
+        self.feature_processor.set_style_sheet(style_sheet);
+        self.tile_loader.reload();
+    }
+
+    fn road_network(&self) -> Option<Arc<RoadNetwork>> {
+        Some(self.road_network.clone())
+    }
+
+    fn lat_lon_to_world(lat_lon: &Coord<f64>) -> Coord<f64> {
+        mercator::lat_lon_to_world(lat_lon)
+    }
+
+    fn world_to_lat_lon(lat_lon: &Coord<f64>) -> Coord<f64> {
+        mercator::world_to_lat_lon(lat_lon)
+    }
+
+    fn meters_per_world_unit(lat: f64) -> f64 {
+        mercator::meters_per_world_unit(lat)
+    }
+}
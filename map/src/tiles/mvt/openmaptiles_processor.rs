@@ -0,0 +1,219 @@
+use crate::feature_processor::ShashlikFeatureProcessor;
+use crate::matching::road_network::Road;
+use crate::style::feature_styler::{FeatureGeometry, StyleContext, style_feature};
+use crate::style::style_sheet::StyleSheet;
+use crate::tiles::mvt::geometry::MvtGeometry;
+use crate::tiles::mvt::mvt_tiles_provider::{MvtFeature, MvtFeatureProcessor, MvtTileContext};
+use crate::tiles::tile_data::{FeatureKind, TileFeature};
+use geo::Winding;
+use geo_types::{Geometry, Point};
+use osm::map::HighwayKind;
+use renderer::geometry_data::GeometryData;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Maps the [OpenMapTiles](https://openmaptiles.org/schema/) schema onto the style sheet kinds and tags,
+/// so MVT tiles look like the ones of `ShashlikTilesProviderV0`.
+pub struct OpenMapTilesFeatureProcessor {
+    style_sheet: RwLock<Arc<StyleSheet>>,
+}
+
+impl OpenMapTilesFeatureProcessor {
+    pub fn new() -> Self {
+        Self::with_style_sheet(Arc::new(StyleSheet::default_sheet()))
+    }
+
+    pub fn with_style_sheet(style_sheet: Arc<StyleSheet>) -> Self {
+        OpenMapTilesFeatureProcessor {
+            style_sheet: RwLock::new(style_sheet),
+        }
+    }
+
+    fn highway_kind(class: &str, is_ramp: bool) -> Option<HighwayKind> {
+        let kind = match (class, is_ramp) {
+            ("motorway", false) => HighwayKind::Motorway,
+            ("motorway", true) => HighwayKind::MotorwayLink,
+            ("trunk", false) => HighwayKind::Trunk,
+            ("trunk", true) => HighwayKind::TrunkLink,
+            ("primary", false) => HighwayKind::Primary,
+            ("primary", true) => HighwayKind::PrimaryLink,
+            ("secondary", false) => HighwayKind::Secondary,
+            ("secondary", true) => HighwayKind::SecondaryLink,
+            ("tertiary", false) => HighwayKind::Tertiary,
+            ("tertiary", true) => HighwayKind::TertiaryLink,
+            ("minor", _) => HighwayKind::Residential,
+            ("service", _) => HighwayKind::Unclassified,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn highway_class(feature: &MvtFeature) -> Option<&'static str> {
+        let class = feature.str("class").unwrap_or_default();
+        let is_ramp = feature.f64("ramp").unwrap_or(0.0) > 0.0;
+        Self::highway_kind(class, is_ramp).map(|kind| ShashlikFeatureProcessor::highway_class(&kind))
+    }
+
+    fn nature_class(layer: &str, feature: &MvtFeature) -> &'static str {
+        match (layer, feature.str("class").unwrap_or_default()) {
+            ("water", _) => "water",
+            ("park", _) => "park",
+            (_, "wood") => "forest",
+            (_, "grass" | "farmland" | "wetland") => "park",
+            _ => "ground",
+        }
+    }
+
+    fn poi_class(feature: &MvtFeature) -> &'static str {
+        match (
+            feature.str("class").unwrap_or_default(),
+            feature.str("subclass").unwrap_or_default(),
+        ) {
+            ("railway", "station") => "train_station",
+            ("railway", _) => "railway_station",
+            ("parking", _) => "parking",
+            ("toilets", _) => "toilets",
+            _ => "other",
+        }
+    }
+
+    fn name(feature: &MvtFeature) -> Option<&str> {
+        feature
+            .str("name:en")
+            .or(feature.str("name_en"))
+            .or(feature.str("name"))
+            .filter(|name| !name.is_empty())
+    }
+
+    // returns the style sheet kind and tags of the feature, None for features which aren't drawn
+    fn kind_and_tags(layer: &str, feature: &MvtFeature) -> Option<(&'static str, HashMap<&'static str, String>)> {
+        let name = || Self::name(feature).unwrap_or_default().to_string();
+        let layer_level = || (feature.f64("layer").unwrap_or(0.0) as i8).to_string();
+        let kind_and_tags = match layer {
+            "water" | "landcover" | "park" => (
+                "nature",
+                HashMap::from([("class", Self::nature_class(layer, feature).to_string())]),
+            ),
+            "building" => {
+                // same scale as the building levels of ShashlikTilesProviderV0, ~3m per level
+                let levels = (feature.f64("render_height").unwrap_or(6.0) / 3.0).round().max(1.0);
+                ("building", HashMap::from([("levels", (levels as u32).to_string())]))
+            }
+            "transportation" => match feature.str("class").unwrap_or_default() {
+                "rail" | "transit" => {
+                    let mut tags = HashMap::from([("layer", layer_level())]);
+                    if feature.str("brunnel") == Some("tunnel") {
+                        tags.insert("tunnel", "yes".to_string());
+                    }
+                    ("railway", tags)
+                }
+                _ => (
+                    "highway",
+                    HashMap::from([
+                        ("class", Self::highway_class(feature)?.to_string()),
+                        ("layer", layer_level()),
+                    ]),
+                ),
+            },
+            // OpenMapTiles keeps road names in a separate layer
+            "transportation_name" => (
+                "road_name",
+                HashMap::from([
+                    ("class", Self::highway_class(feature).unwrap_or("other").to_string()),
+                    ("name", name()),
+                ]),
+            ),
+            "boundary" if feature.f64("admin_level").is_some_and(|level| level <= 4.0) => {
+                ("admin_line", HashMap::new())
+            }
+            "poi" => (
+                "poi",
+                HashMap::from([("class", Self::poi_class(feature).to_string()), ("name", name())]),
+            ),
+            "place" => ("place", HashMap::from([("name", name())])),
+            _ => return None,
+        };
+        Some(kind_and_tags)
+    }
+}
+
+impl Default for OpenMapTilesFeatureProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MvtFeatureProcessor for OpenMapTilesFeatureProcessor {
+    fn process_feature(
+        &self,
+        geometry_data: &mut Vec<GeometryData>,
+        features: &mut Vec<TileFeature>,
+        layer: &str,
+        mut feature: MvtFeature,
+        context: &mut MvtTileContext,
+    ) {
+        let Some((kind, tags)) = Self::kind_and_tags(layer, &feature) else {
+            return;
+        };
+        if let ("building", MvtGeometry::Polygons(polygons)) = (kind, &mut feature.geometry) {
+            polygons
+                .iter_mut()
+                .for_each(|polygon| polygon.exterior_mut(|exterior| exterior.make_ccw_winding()));
+        }
+        let geometry = match &feature.geometry {
+            MvtGeometry::Points(points) => FeatureGeometry::Points(points),
+            MvtGeometry::Lines(lines) => FeatureGeometry::Lines(lines),
+            MvtGeometry::Polygons(polygons) => FeatureGeometry::Polygons(polygons),
+        };
+        let style_sheet = self.style_sheet.read().unwrap().clone();
+        let mut style_context = StyleContext {
+            zoom_level: context.zoom_level,
+            dpi_scale: context.dpi_scale,
+            line_text_map: &mut context.line_text_map,
+        };
+        if !style_feature(&style_sheet, geometry_data, kind, &tags, geometry, &mut style_context) {
+            return;
+        }
+
+        // the roads are unnamed, the names come with the road_name lines
+        let feature_kind = match kind {
+            "poi" => FeatureKind::Poi,
+            "road_name" => FeatureKind::Road,
+            "building" => FeatureKind::Building,
+            _ => return,
+        };
+        let name = Self::name(&feature).map(str::to_string);
+        let geometries: Vec<Geometry> = match feature.geometry {
+            MvtGeometry::Points(points) => points.into_iter().map(|point| Geometry::Point(Point(point))).collect(),
+            MvtGeometry::Lines(lines) => lines.into_iter().map(Geometry::LineString).collect(),
+            MvtGeometry::Polygons(polygons) => polygons.into_iter().map(Geometry::Polygon).collect(),
+        };
+        features.extend(geometries.into_iter().map(|geometry| TileFeature {
+            kind: feature_kind,
+            name: name.clone(),
+            geometry,
+        }));
+    }
+
+    fn set_style_sheet(&self, style_sheet: Arc<StyleSheet>) {
+        *self.style_sheet.write().unwrap() = style_sheet;
+    }
+
+    // the transportation lines are usually unnamed, the matched positions have no road name then
+    fn roads(&self, layer: &str, feature: &MvtFeature) -> Vec<Road> {
+        let (Some(class), MvtGeometry::Lines(lines)) = (
+            (layer == "transportation").then(|| Self::highway_class(feature)).flatten(),
+            &feature.geometry,
+        ) else {
+            return vec![];
+        };
+        lines
+            .iter()
+            .map(|line| Road {
+                name: Self::name(feature).map(str::to_string),
+                class,
+                line: line.clone(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::tiles::mvt::MvtTileKey;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// OpenMapTiles schema stops at 14, deeper zoom levels reuse its tiles
+const DEFAULT_MAX_ZOOM: u8 = 14;
+
+pub trait MvtSource: Send + Sync + 'static {
+    /// Raw tile protobuf, might be gzipped
+    fn load(&self, tile_key: &MvtTileKey) -> Option<Vec<u8>>;
+
+    fn min_zoom(&self) -> u8 {
+        0
+    }
+
+    fn max_zoom(&self) -> u8 {
+        DEFAULT_MAX_ZOOM
+    }
+}
+
+/// Reads tiles from a directory laid out as `{root}/{z}/{x}/{y}.pbf` or `.mvt`.
+pub struct MvtDirSource {
+    root: PathBuf,
+    max_zoom: u8,
+}
+
+impl MvtDirSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        MvtDirSource {
+            root: root.into(),
+            max_zoom: DEFAULT_MAX_ZOOM,
+        }
+    }
+
+    pub fn with_max_zoom(mut self, max_zoom: u8) -> Self {
+        self.max_zoom = max_zoom;
+        self
+    }
+}
+
+impl MvtSource for MvtDirSource {
+    fn load(&self, tile_key: &MvtTileKey) -> Option<Vec<u8>> {
+        let dir = self
+            .root
+            .join(tile_key.zoom.to_string())
+            .join(tile_key.x.to_string());
+        ["pbf", "mvt"]
+            .iter()
+            .find_map(|ext| fs::read(dir.join(format!("{}.{ext}", tile_key.y))).ok())
+    }
+
+    fn max_zoom(&self) -> u8 {
+        self.max_zoom
+    }
+}
+
+/// Reads tiles from an MBTiles SQLite file, zoom levels are taken from its metadata.
+pub struct MbTilesSource {
+    connection: Mutex<Connection>,
+    min_zoom: u8,
+    max_zoom: u8,
+}
+
+impl MbTilesSource {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        let metadata = |name: &str| -> anyhow::Result<Option<u8>> {
+            let value: Option<String> = connection
+                .query_row(
+                    "SELECT value FROM metadata WHERE name = ?1",
+                    [name],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(value.and_then(|value| value.trim().parse().ok()))
+        };
+        let min_zoom = metadata("minzoom")?.unwrap_or(0);
+        let max_zoom = metadata("maxzoom")?.unwrap_or(DEFAULT_MAX_ZOOM);
+
+        Ok(MbTilesSource {
+            connection: Mutex::new(connection),
+            min_zoom,
+            max_zoom,
+        })
+    }
+}
+
+impl MvtSource for MbTilesSource {
+    fn load(&self, tile_key: &MvtTileKey) -> Option<Vec<u8>> {
+        // MBTiles rows follow TMS, y grows to the north
+        let tms_y = (1u32 << tile_key.zoom) - 1 - tile_key.y;
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT tile_data FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                (tile_key.zoom, tile_key.x, tms_y),
+                |row| row.get(0),
+            )
+            .ok()
+    }
+
+    fn min_zoom(&self) -> u8 {
+        self.min_zoom
+    }
+
+    fn max_zoom(&self) -> u8 {
+        self.max_zoom
+    }
+}
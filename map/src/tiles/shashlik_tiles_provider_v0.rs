@@ -1,70 +1,99 @@
-use crate::tiles::tile_data::TileData;
+use crate::feature_processor::ShashlikFeatureProcessor;
+use crate::matching::road_network::{Road, RoadNetwork};
+use crate::route::provider::RoutingProvider;
+use crate::route::provider::offline_provider::OfflineRoutingProvider;
+use crate::style::style_sheet::StyleSheet;
+use crate::tiles::mercator;
+use crate::tiles::tile_data::{TileData, TileFeature};
 use crate::tiles::tiles_provider::{TilesMessage, TilesProvider};
+use crate::tiles::tile_loader::{LoaderTileKey, TileLoader};
 use futures::Stream;
-use futures::channel::mpsc::{UnboundedSender, unbounded};
 use geo::Intersects;
 use geo::Winding;
 use geo_types::{LineString, Rect};
-use googleprojection::Mercator;
-use log::error;
 use osm::map::{
-    MapGeomObjectKind, MapGeometry, MapPointInfo,
+    LineKind, MapGeomObjectKind, MapGeometry, MapPointInfo,
 };
 use osm::source::TileSource;
 use osm::tiles::{TILES_COUNT, TileKey, TileStore, calc_tile_ranges};
-use rayon::iter::IntoParallelRefIterator;
-use rayon::iter::ParallelIterator;
 use renderer::geometry_data::{GeometryData};
 use std::collections::{HashMap, HashSet};
-use std::sync::atomic::{AtomicI32, Ordering};
-use std::sync::{Arc, RwLock};
-use std::thread::spawn;
-use std::time::SystemTime;
+use std::sync::Arc;
 
 pub trait FeatureProcessor: Send + Sync {
+    /// Drawn features which can be picked go to `features`
     fn process_poi(
         &self,
         geometry_data: &mut Vec<GeometryData>,
+        features: &mut Vec<TileFeature>,
         poi: &MapPointInfo,
         local_position: &geo::Coord,
+        zoom_level: i32,
         dpi_scale: f32,
     );
 
     fn process_line(
         &self,
         geometry_data: &mut Vec<GeometryData>,
+        features: &mut Vec<TileFeature>,
         line: LineString,
         kind: MapGeomObjectKind,
         line_text_map: &mut HashMap<String, i32>,
         zoom_level: i32,
         dpi_scale: f32,
     );
+
+    /// Replaces the rules for the tiles built after this call
+    fn set_style_sheet(&self, _style_sheet: Arc<StyleSheet>) {}
+}
+
+/// Road in world coordinates if the line(x is lon) is a highway.
+pub(crate) fn highway_road(kind: &MapGeomObjectKind, line: &LineString) -> Option<Road> {
+    if let MapGeomObjectKind::Way(info) = kind
+        && let LineKind::Highway { kind } = &info.line_kind
+    {
+        Some(Road {
+            name: info.name_en.clone(),
+            class: ShashlikFeatureProcessor::highway_class(kind),
+            line: line.coords().map(mercator::lat_lon_to_world).collect(),
+        })
+    } else {
+        None
+    }
 }
 
 pub struct ShashlikTilesProviderV0<S: TileSource, FP: FeatureProcessor> {
-    sender: Option<UnboundedSender<TilesMessage>>,
+    tile_loader: TileLoader<TileKey>,
     tile_store: Arc<TileStore<S>>,
-    per_frame_cache: HashSet<TileKey>,
-    actual_cache: Arc<RwLock<HashSet<TileKey>>>,
-    last_loaded_zoom_level: Arc<AtomicI32>,
-    current_zoom_level: Arc<AtomicI32>,
-    loading_map: Arc<RwLock<HashMap<i32, i32>>>,
     dpi_scale: f32,
     feature_processor: Arc<FP>,
+    road_network: Arc<RoadNetwork>,
+    offline_routing_provider: Arc<OfflineRoutingProvider<S>>,
+}
+
+impl LoaderTileKey for TileKey {
+    fn zoom(&self) -> i32 {
+        self.zoom_level
+    }
+
+    fn as_string_key(&self) -> String {
+        TileKey::as_string_key(self)
+    }
 }
 
+/// Roads of far zoom levels are simplified, they aren't used for map matching and routing
+pub(crate) const ROADS_MAX_ZOOM_LEVEL: i32 = 2;
+
 impl<S: TileSource, FP: FeatureProcessor + 'static> ShashlikTilesProviderV0<S, FP> {
     pub fn new(source: S, feature_processor: FP, dpi_scale: f32) -> ShashlikTilesProviderV0<S, FP> {
+        let tile_store = Arc::new(TileStore::new(source));
         Self {
-            sender: None,
-            tile_store: Arc::new(TileStore::new(source)),
-            per_frame_cache: HashSet::new(),
-            actual_cache: Arc::new(RwLock::new(HashSet::new())),
-            last_loaded_zoom_level: Arc::new(AtomicI32::new(1)),
-            current_zoom_level: Arc::new(AtomicI32::new(1)),
-            loading_map: Arc::new(RwLock::new(HashMap::new())),
+            tile_loader: TileLoader::new(),
+            offline_routing_provider: Arc::new(OfflineRoutingProvider::new(tile_store.clone())),
+            tile_store,
             dpi_scale,
             feature_processor: Arc::new(feature_processor),
+            road_network: Arc::new(RoadNetwork::new()),
         }
     }
 
@@ -78,6 +107,7 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> ShashlikTilesProviderV0<S, F
     fn get_tile_key_data(
         tile_store: Arc<TileStore<S>>,
         feature_processor: Arc<FP>,
+        road_network: &RoadNetwork,
         tile_key: &TileKey,
         dpi_scale: f32,
     ) -> TileData {
@@ -93,7 +123,9 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> ShashlikTilesProviderV0<S, F
         let tile_position = [tile_rect_origin.x, tile_rect_origin.y, 0.0].into();
 
         let mut geometry_data: Vec<GeometryData> = vec![];
+        let mut features = vec![];
         let mut line_text_map = HashMap::new();
+        let mut roads = vec![];
         geom.into_iter()
             .for_each(|(obj_type, geometry)| match geometry {
                 MapGeometry::Coord(coord) => {
@@ -102,8 +134,10 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> ShashlikTilesProviderV0<S, F
                         MapGeomObjectKind::Poi(poi) => {
                             feature_processor.process_poi(
                                 &mut geometry_data,
+                                &mut features,
                                 poi,
                                 &local_position,
+                                zoom_level,
                                 dpi_scale,
                             );
                         }
@@ -111,8 +145,14 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> ShashlikTilesProviderV0<S, F
                     }
                 }
                 MapGeometry::Line(line) => {
+                    if zoom_level <= ROADS_MAX_ZOOM_LEVEL
+                        && let Some(road) = highway_road(&obj_type.kind, &line)
+                    {
+                        roads.push(road);
+                    }
                     feature_processor.process_line(
                         &mut geometry_data,
+                        &mut features,
                         Self::convert_line_coords(line, tile_rect_origin),
                         obj_type.kind,
                         &mut line_text_map,
@@ -127,6 +167,7 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> ShashlikTilesProviderV0<S, F
                     }
                     feature_processor.process_line(
                         &mut geometry_data,
+                        &mut features,
                         Self::convert_line_coords(line, tile_rect_origin),
                         obj_type.kind,
                         &mut line_text_map,
@@ -136,12 +177,15 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> ShashlikTilesProviderV0<S, F
                 }
             });
 
+        road_network.insert_tile(tile_key.as_string_key(), roads);
+
         let tile_data = TileData {
             key: tile_key.as_string_key(),
             position: tile_position,
             // can be negative
             size: (tile_rect_size.x.abs(), tile_rect_size.y.abs()),
             geometry_data,
+            features,
         };
 
         tile_data
@@ -154,9 +198,6 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> TilesProvider
     fn load(&mut self, area_latlon: Rect, area_poly: geo_types::Polygon<f64>, zoom_level: i32) {
         let ranges = calc_tile_ranges(TILES_COUNT, zoom_level, &area_latlon);
         let mut current_visible_tiles: HashSet<TileKey> = HashSet::new();
-        let mut to_load: HashSet<TileKey> = HashSet::new();
-
-        self.current_zoom_level.store(zoom_level, Ordering::Relaxed);
 
         for tx in ranges.min_x..=ranges.max_x {
             for ty in ranges.min_y..=ranges.max_y {
@@ -170,124 +211,51 @@ impl<S: TileSource, FP: FeatureProcessor + 'static> TilesProvider
                 let tile_rect = tile_key.calc_tile_boundary(1.0);
                 if area_poly.intersects(&tile_rect) {
                     current_visible_tiles.insert(tile_key);
-                    if self.per_frame_cache.insert(tile_key) {
-                        to_load.insert(tile_key);
-                    }
                 }
             }
         }
 
-        if let Ok(mut actual_cache) = self.actual_cache.try_write() {
-            let sender = self.sender.clone().unwrap();
-
-            let last_loaded_zoom_level = self.last_loaded_zoom_level.load(Ordering::Relaxed);
-
-            let removed: HashSet<TileKey> = actual_cache
-                .extract_if(|key| {
-                    (key.zoom_level == zoom_level && !current_visible_tiles.contains(&key))
-                        || (key.zoom_level != last_loaded_zoom_level
-                            && last_loaded_zoom_level == zoom_level)
-                })
-                .collect();
-
-            if !removed.is_empty() {
-                sender
-                    .unbounded_send(TilesMessage::ToRemove(
-                        removed.iter().map(|item| item.as_string_key()).collect(),
-                    ))
-                    .unwrap();
-            }
-        }
-
-        let removed: HashSet<TileKey> = self
-            .per_frame_cache
-            .extract_if(|key| !current_visible_tiles.contains(&key))
-            .collect();
-
-        if !removed.is_empty() || !to_load.is_empty() {
-            let ts = SystemTime::now();
-            let tile_store = self.tile_store.clone();
-            let current_zoom_level = self.current_zoom_level.clone();
-            let actual_cache = self.actual_cache.clone();
-            let last_loaded_zoom_level = self.last_loaded_zoom_level.clone();
-            let loading_map = self.loading_map.clone();
-            let sender = self.sender.clone().unwrap();
-            let feature_processor = self.feature_processor.clone();
-            let dpi_scale = self.dpi_scale;
-            spawn(move || {
-                let loading_count = *loading_map
-                    .write()
-                    .unwrap()
-                    .entry(zoom_level)
-                    .and_modify(|v| *v = *v + 1)
-                    .or_insert(1);
-                let data: Vec<(TileKey, TileData)> = to_load
-                    .par_iter()
-                    .filter_map(|key| {
-                        if current_zoom_level.load(Ordering::Relaxed) == zoom_level {
-                            let tile_data = Self::get_tile_key_data(
-                                tile_store.clone(),
-                                feature_processor.clone(),
-                                key,
-                                dpi_scale,
-                            );
-                            Some((key.clone(), tile_data))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                if !data.is_empty() && zoom_level == current_zoom_level.load(Ordering::Relaxed) {
-                    if loading_count == 1 {
-                        last_loaded_zoom_level.store(zoom_level, Ordering::Relaxed);
-                    }
-
-                    actual_cache
-                        .write()
-                        .unwrap()
-                        .extend(data.iter().map(|item| item.0.clone()));
+        let tile_store = self.tile_store.clone();
+        let feature_processor = self.feature_processor.clone();
+        let road_network = self.road_network.clone();
+        let dpi_scale = self.dpi_scale;
+        self.tile_loader.load(current_visible_tiles, zoom_level, move |key| {
+            Some(Self::get_tile_key_data(
+                tile_store.clone(),
+                feature_processor.clone(),
+                &road_network,
+                key,
+                dpi_scale,
+            ))
+        });
+    }
 
-                    error!(
-                        "Tiles batch is ready: {:?}",
-                        SystemTime::now().duration_since(ts)
-                    );
-                    sender
-                        .unbounded_send(TilesMessage::TilesData(
-                            data.into_iter().map(|(_, data)| data).collect(),
-                        ))
-                        .unwrap();
-                }
+    fn tiles(&mut self) -> impl Stream<Item = TilesMessage> + Send + 'static {
+        self.tile_loader.tiles()
+    }
 
-                loading_map
-                    .write()
-                    .unwrap()
-                    .entry(zoom_level)
-                    .and_modify(|v| *v = (*v - 1).max(0))
-                    .or_insert(0);
-            });
-        }
+    fn set_style_sheet(&mut self, style_sheet: Arc<StyleSheet>) {
+        self.feature_processor.set_style_sheet(style_sheet);
+        self.tile_loader.reload();
     }
 
-    fn tiles(&mut self) -> impl Stream<Item = TilesMessage> + Send + 'static {
-        let (sender, receiver) = unbounded();
-        self.sender = Some(sender);
+    fn road_network(&self) -> Option<Arc<RoadNetwork>> {
+        Some(self.road_network.clone())
+    }
 
-        receiver
+    fn offline_routing_provider(&self) -> Option<Arc<dyn RoutingProvider>> {
+        Some(self.offline_routing_provider.clone())
     }
 
     fn lat_lon_to_world(lat_lon: &geo_types::Coord<f64>) -> geo_types::Coord<f64> {
-        let lat_lon: (f64, f64) = (*lat_lon).into();
-        Mercator::with_size(1)
-            .from_ll_to_subpixel(&lat_lon, 22)
-            .unwrap()
-            .into()
+        mercator::lat_lon_to_world(lat_lon)
     }
 
     fn world_to_lat_lon(lat_lon: &geo_types::Coord<f64>) -> geo_types::Coord<f64> {
-        let lat_lon: (f64, f64) = (*lat_lon).into();
-        Mercator::with_size(1)
-            .from_pixel_to_ll(&lat_lon, 22)
-            .unwrap()
-            .into()
+        mercator::world_to_lat_lon(lat_lon)
+    }
+
+    fn meters_per_world_unit(lat: f64) -> f64 {
+        mercator::meters_per_world_unit(lat)
     }
 }
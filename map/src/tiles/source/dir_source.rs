@@ -0,0 +1,28 @@
+use osm::source::TileSource;
+use osm::tiles::TileKey;
+use std::fs;
+use std::path::PathBuf;
+
+/// Reads pre-generated tiles from a directory laid out as `{root}/{zoom}/{x}/{y}.tile`.
+pub struct DirTileSource {
+    root: PathBuf,
+}
+
+impl DirTileSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirTileSource { root: root.into() }
+    }
+
+    pub fn tile_path(&self, tile_key: &TileKey) -> PathBuf {
+        self.root
+            .join(tile_key.zoom_level.to_string())
+            .join(tile_key.tile_x.to_string())
+            .join(format!("{}.tile", tile_key.tile_y))
+    }
+}
+
+impl TileSource for DirTileSource {
+    fn load(&self, tile_key: &TileKey) -> Option<Vec<u8>> {
+        fs::read(self.tile_path(tile_key)).ok()
+    }
+}
@@ -0,0 +1,56 @@
+use crate::tiles::source::dir_source::DirTileSource;
+use crate::tiles::source::pack_source::PackTileSource;
+use log::error;
+use osm::source::TileSource;
+use osm::source::reqwest_source::ReqwestSource;
+use osm::tiles::TileKey;
+use std::path::Path;
+
+pub mod dir_source;
+pub mod pack_export;
+pub mod pack_source;
+
+/// Tile source which is picked at runtime, so the binaries and the FFI can keep
+/// a single `ShashlikMap` type for both online and offline maps.
+pub enum MapTileSource {
+    Network(ReqwestSource),
+    Dir(DirTileSource),
+    Pack(PackTileSource),
+}
+
+impl MapTileSource {
+    pub fn network() -> Self {
+        MapTileSource::Network(ReqwestSource::new())
+    }
+
+    /// A tiles directory or a pack file, depending on what `path` points to.
+    pub fn local(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            Ok(MapTileSource::Dir(DirTileSource::new(path)))
+        } else {
+            Ok(MapTileSource::Pack(PackTileSource::open(path)?))
+        }
+    }
+
+    /// Local tiles if `path` is set and readable, otherwise the tile server.
+    pub fn local_or_network(path: Option<&Path>) -> Self {
+        let Some(path) = path.filter(|path| path.exists()) else {
+            return Self::network();
+        };
+        Self::local(path).unwrap_or_else(|err| {
+            error!("Can't open local tiles {:?}, fallback to network: {err}", path);
+            Self::network()
+        })
+    }
+}
+
+impl TileSource for MapTileSource {
+    fn load(&self, tile_key: &TileKey) -> Option<Vec<u8>> {
+        match self {
+            MapTileSource::Network(source) => source.load(tile_key),
+            MapTileSource::Dir(source) => source.load(tile_key),
+            MapTileSource::Pack(source) => source.load(tile_key),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::tiles::source::pack_source::TilePackWriter;
+use geo_types::Rect;
+use log::info;
+use osm::source::TileSource;
+use osm::tiles::{TILES_COUNT, TileKey, calc_tile_ranges};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+// tiles are fetched in parallel, but only a batch is kept in memory before it's written
+const BATCH_SIZE: usize = 256;
+
+pub struct ExportSummary {
+    pub exported: usize,
+    /// Tiles the source had no data for
+    pub missing: usize,
+}
+
+/// All the tile keys covering `area_latlon`(x is lon, y is lat) for the given zoom levels.
+pub fn tile_keys(area_latlon: &Rect, zoom_levels: RangeInclusive<i32>) -> Vec<TileKey> {
+    zoom_levels
+        .flat_map(|zoom_level| {
+            let ranges = calc_tile_ranges(TILES_COUNT, zoom_level, area_latlon);
+            (ranges.min_x..=ranges.max_x).flat_map(move |tx| {
+                (ranges.min_y..=ranges.max_y).map(move |ty| TileKey {
+                    tile_x: tx as i32,
+                    tile_y: ty as i32,
+                    zoom_level,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Copies every tile of `area_latlon` and `zoom_levels` from `source` into a new pack at `path`.
+pub fn export_pack<S: TileSource>(
+    source: &S,
+    area_latlon: &Rect,
+    zoom_levels: RangeInclusive<i32>,
+    path: impl AsRef<Path>,
+) -> anyhow::Result<ExportSummary> {
+    let keys = tile_keys(area_latlon, zoom_levels);
+    let mut writer = TilePackWriter::create(path)?;
+    let mut summary = ExportSummary {
+        exported: 0,
+        missing: 0,
+    };
+
+    for batch in keys.chunks(BATCH_SIZE) {
+        let tiles: Vec<(&TileKey, Option<Vec<u8>>)> = batch
+            .par_iter()
+            .map(|key| (key, source.load(key)))
+            .collect();
+        for (key, data) in tiles {
+            match data {
+                Some(data) => {
+                    writer.add(key, &data)?;
+                    summary.exported += 1;
+                }
+                None => summary.missing += 1,
+            }
+        }
+        info!(
+            "Exported {}/{} tiles",
+            summary.exported + summary.missing,
+            keys.len()
+        );
+    }
+
+    writer.finish()?;
+    Ok(summary)
+}
@@ -0,0 +1,146 @@
+use anyhow::{bail, ensure};
+use osm::source::TileSource;
+use osm::tiles::TileKey;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+// Pack layout, all numbers are little-endian:
+// [MAGIC][VERSION: u32][tile blobs...][index entries...][index offset: u64][entries count: u32]
+// index entry: [zoom: i32][x: i32][y: i32][blob offset: u64][blob len: u32]
+const MAGIC: &[u8; 4] = b"SHPK";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 8;
+const FOOTER_LEN: u64 = 12;
+const INDEX_ENTRY_LEN: usize = 24;
+
+/// Reads tiles from a single pack file written by [`TilePackWriter`].
+/// Only the index is kept in memory, tiles are read from the file on demand.
+pub struct PackTileSource {
+    file: Mutex<File>,
+    index: HashMap<TileKey, (u64, u32)>,
+}
+
+impl PackTileSource {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut file = File::open(path)?;
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_exact(&mut header)?;
+        ensure!(&header[..4] == MAGIC, "Not a tile pack");
+        let version = u32::from_le_bytes(header[4..8].try_into()?);
+        if version != VERSION {
+            bail!("Unsupported tile pack version {version}");
+        }
+
+        let file_len = file.metadata()?.len();
+        ensure!(file_len >= HEADER_LEN + FOOTER_LEN, "Tile pack is truncated");
+        file.seek(SeekFrom::Start(file_len - FOOTER_LEN))?;
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        file.read_exact(&mut footer)?;
+        let index_offset = u64::from_le_bytes(footer[..8].try_into()?);
+        let count = u32::from_le_bytes(footer[8..].try_into()?) as usize;
+        let index_len = count
+            .checked_mul(INDEX_ENTRY_LEN)
+            .and_then(|len| u64::try_from(len).ok());
+        let index_end = index_len
+            .and_then(|len| index_offset.checked_add(len))
+            .and_then(|end| end.checked_add(FOOTER_LEN));
+        ensure!(index_end == Some(file_len), "Tile pack index is corrupted");
+
+        file.seek(SeekFrom::Start(index_offset))?;
+        let mut entries = vec![0u8; count * INDEX_ENTRY_LEN];
+        file.read_exact(&mut entries)?;
+
+        let mut index = HashMap::with_capacity(count);
+        for entry in entries.chunks_exact(INDEX_ENTRY_LEN) {
+            let tile_key = TileKey {
+                zoom_level: i32::from_le_bytes(entry[0..4].try_into()?),
+                tile_x: i32::from_le_bytes(entry[4..8].try_into()?),
+                tile_y: i32::from_le_bytes(entry[8..12].try_into()?),
+            };
+            let offset = u64::from_le_bytes(entry[12..20].try_into()?);
+            let len = u32::from_le_bytes(entry[20..24].try_into()?);
+            let end = offset.checked_add(len as u64);
+            ensure!(
+                offset >= HEADER_LEN && end.is_some_and(|end| end <= index_offset),
+                "Tile pack entry is out of bounds"
+            );
+            index.insert(tile_key, (offset, len));
+        }
+
+        Ok(PackTileSource {
+            file: Mutex::new(file),
+            index,
+        })
+    }
+
+    pub fn tiles_count(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn contains(&self, tile_key: &TileKey) -> bool {
+        self.index.contains_key(tile_key)
+    }
+
+    fn read(&self, offset: u64, len: u32) -> std::io::Result<Vec<u8>> {
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0u8; len as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+impl TileSource for PackTileSource {
+    fn load(&self, tile_key: &TileKey) -> Option<Vec<u8>> {
+        let (offset, len) = *self.index.get(tile_key)?;
+        self.read(offset, len).ok()
+    }
+}
+
+/// Writes tiles into a single pack file, see [`PackTileSource`].
+/// The pack is only readable after [`TilePackWriter::finish`].
+pub struct TilePackWriter {
+    writer: BufWriter<File>,
+    offset: u64,
+    index: Vec<(TileKey, u64, u32)>,
+}
+
+impl TilePackWriter {
+    pub fn create(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(TilePackWriter {
+            writer,
+            offset: HEADER_LEN,
+            index: vec![],
+        })
+    }
+
+    pub fn add(&mut self, tile_key: &TileKey, data: &[u8]) -> anyhow::Result<()> {
+        let len = u32::try_from(data.len())?;
+        self.writer.write_all(data)?;
+        self.index.push((*tile_key, self.offset, len));
+        self.offset += len as u64;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        let count = u32::try_from(self.index.len())?;
+        for (tile_key, offset, len) in &self.index {
+            self.writer.write_all(&tile_key.zoom_level.to_le_bytes())?;
+            self.writer.write_all(&tile_key.tile_x.to_le_bytes())?;
+            self.writer.write_all(&tile_key.tile_y.to_le_bytes())?;
+            self.writer.write_all(&offset.to_le_bytes())?;
+            self.writer.write_all(&len.to_le_bytes())?;
+        }
+        self.writer.write_all(&self.offset.to_le_bytes())?;
+        self.writer.write_all(&count.to_le_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
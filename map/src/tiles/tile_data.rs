@@ -1,4 +1,5 @@
 use cgmath::Vector3;
+use geo_types::Geometry;
 use renderer::geometry_data::GeometryData;
 
 pub struct TileData {
@@ -6,4 +7,25 @@ pub struct TileData {
     pub position: Vector3<f64>,
     pub size: (f64, f64),
     pub geometry_data: Vec<GeometryData>,
+    /// Features which can be picked on the map, see `ShashlikMap::features_at_screen_point`
+    pub features: Vec<TileFeature>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FeatureKind {
+    Poi,
+    Road,
+    Building,
+    Placemark,
+    Route,
+    Overlay,
+}
+
+/// Metadata of a drawn feature, the geometry is in world units relative to the tile position
+/// like its geometry data.
+#[derive(Clone, Debug)]
+pub struct TileFeature {
+    pub kind: FeatureKind,
+    pub name: Option<String>,
+    pub geometry: Geometry,
 }
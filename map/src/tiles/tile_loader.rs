@@ -0,0 +1,173 @@
+use crate::tiles::tile_data::TileData;
+use crate::tiles::tiles_provider::TilesMessage;
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded};
+use log::info;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::ParallelIterator;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::spawn;
+use std::time::SystemTime;
+
+pub(crate) trait LoaderTileKey: Copy + Eq + Hash + Send + Sync + 'static {
+    /// Tiles of the same zoom replace each other, the ones of the other zooms are kept until it's loaded
+    fn zoom(&self) -> i32;
+    fn as_string_key(&self) -> String;
+}
+
+/// Tracks the visible and the loaded tiles of a provider, builds the new ones on the rayon threads and
+/// sends them with the tiles to remove. The tiles of the previous zoom are kept until the new zoom is loaded
+/// to avoid blinking.
+pub(crate) struct TileLoader<K: LoaderTileKey> {
+    sender: Option<UnboundedSender<TilesMessage>>,
+    per_frame_cache: HashSet<K>,
+    // the render group key of every loaded tile
+    actual_cache: Arc<RwLock<HashMap<K, String>>>,
+    // tiles of a reload get new render group keys to be shown next to the old ones until they replace them
+    generation: Arc<AtomicU32>,
+    last_loaded_zoom: Arc<AtomicI32>,
+    current_zoom: Arc<AtomicI32>,
+    loading_map: Arc<RwLock<HashMap<i32, i32>>>,
+}
+
+impl<K: LoaderTileKey> TileLoader<K> {
+    pub fn new() -> Self {
+        TileLoader {
+            sender: None,
+            per_frame_cache: HashSet::new(),
+            actual_cache: Arc::new(RwLock::new(HashMap::new())),
+            generation: Arc::new(AtomicU32::new(0)),
+            last_loaded_zoom: Arc::new(AtomicI32::new(-1)),
+            current_zoom: Arc::new(AtomicI32::new(-1)),
+            loading_map: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn tiles(&mut self) -> UnboundedReceiver<TilesMessage> {
+        let (sender, receiver) = unbounded();
+        self.sender = Some(sender);
+
+        receiver
+    }
+
+    /// Removes the tiles which aren't visible anymore and builds the new visible ones with `build_tile`,
+    /// the tiles without data are skipped.
+    pub fn load<F>(&mut self, visible_tiles: HashSet<K>, zoom: i32, build_tile: F)
+    where
+        F: Fn(&K) -> Option<TileData> + Send + Sync + 'static,
+    {
+        let Some(sender) = self.sender.clone() else {
+            return;
+        };
+        let to_load: HashSet<K> = visible_tiles
+            .iter()
+            .filter(|key| self.per_frame_cache.insert(**key))
+            .copied()
+            .collect();
+
+        self.current_zoom.store(zoom, Ordering::Relaxed);
+
+        if let Ok(mut actual_cache) = self.actual_cache.try_write() {
+            let last_loaded_zoom = self.last_loaded_zoom.load(Ordering::Relaxed);
+
+            let removed: HashSet<String> = actual_cache
+                .extract_if(|key, _| {
+                    (key.zoom() == zoom && !visible_tiles.contains(key))
+                        || (key.zoom() != last_loaded_zoom && last_loaded_zoom == zoom)
+                })
+                .map(|(_, string_key)| string_key)
+                .collect();
+
+            if !removed.is_empty() {
+                sender.unbounded_send(TilesMessage::ToRemove(removed)).unwrap();
+            }
+        }
+
+        self.per_frame_cache.retain(|key| visible_tiles.contains(key));
+
+        if to_load.is_empty() {
+            return;
+        }
+        let ts = SystemTime::now();
+        let current_zoom = self.current_zoom.clone();
+        let actual_cache = self.actual_cache.clone();
+        let last_loaded_zoom = self.last_loaded_zoom.clone();
+        let loading_map = self.loading_map.clone();
+        let generation = self.generation.clone();
+        let tiles_generation = generation.load(Ordering::Relaxed);
+        spawn(move || {
+            let loading_count = *loading_map
+                .write()
+                .unwrap()
+                .entry(zoom)
+                .and_modify(|v| *v += 1)
+                .or_insert(1);
+            let data: Vec<(K, TileData)> = to_load
+                .par_iter()
+                .filter(|_| current_zoom.load(Ordering::Relaxed) == zoom)
+                .filter_map(|key| {
+                    let mut tile_data = build_tile(key)?;
+                    tile_data.key = Self::string_key(key, tiles_generation);
+                    Some((*key, tile_data))
+                })
+                .collect();
+            // the tiles built before a reload are dropped, the reload builds them again
+            if !data.is_empty()
+                && zoom == current_zoom.load(Ordering::Relaxed)
+                && tiles_generation == generation.load(Ordering::Relaxed)
+            {
+                if loading_count == 1 {
+                    last_loaded_zoom.store(zoom, Ordering::Relaxed);
+                }
+
+                let replaced: HashSet<String> = {
+                    let mut actual_cache = actual_cache.write().unwrap();
+                    data.iter()
+                        .filter_map(|(key, tile_data)| {
+                            actual_cache
+                                .insert(*key, tile_data.key.clone())
+                                .filter(|string_key| *string_key != tile_data.key)
+                        })
+                        .collect()
+                };
+
+                info!(
+                    "Tiles batch is ready: {:?}",
+                    SystemTime::now().duration_since(ts)
+                );
+                sender
+                    .unbounded_send(TilesMessage::TilesData(
+                        data.into_iter().map(|(_, data)| data).collect(),
+                    ))
+                    .unwrap();
+                // the replaced tiles are removed once the new ones are shown
+                if !replaced.is_empty() {
+                    sender.unbounded_send(TilesMessage::ToRemove(replaced)).unwrap();
+                }
+            }
+
+            loading_map
+                .write()
+                .unwrap()
+                .entry(zoom)
+                .and_modify(|v| *v = (*v - 1).max(0))
+                .or_insert(0);
+        });
+    }
+
+    /// Builds the visible tiles again on the next load, e.g. with a new style sheet.
+    /// The loaded tiles stay on the map until the rebuilt ones replace them.
+    pub fn reload(&mut self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.per_frame_cache.clear();
+    }
+
+    fn string_key(key: &K, generation: u32) -> String {
+        match generation {
+            0 => key.as_string_key(),
+            generation => format!("{}_{generation}", key.as_string_key()),
+        }
+    }
+}
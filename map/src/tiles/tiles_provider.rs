@@ -1,4 +1,8 @@
 use std::collections::HashSet;
+use std::sync::Arc;
+use crate::matching::road_network::RoadNetwork;
+use crate::route::provider::RoutingProvider;
+use crate::style::style_sheet::StyleSheet;
 use crate::tiles::tile_data::TileData;
 use futures::Stream;
 use geo_types::{Coord, Polygon, Rect};
@@ -13,6 +17,19 @@ pub trait TilesProvider {
     fn load(&mut self, area_latlon: Rect, area_poly: Polygon<f64>, zoom_level: i32);
     
     fn tiles(&mut self) -> impl Stream<Item = TilesMessage> + Send + 'static;
+
+    /// Restyles the map, providers which build tiles from style rules reload the visible tiles
+    fn set_style_sheet(&mut self, _style_sheet: Arc<StyleSheet>) {}
+
+    /// Roads of the loaded tiles for map matching, `None` if the provider doesn't keep them
+    fn road_network(&self) -> Option<Arc<RoadNetwork>> {
+        None
+    }
+
+    /// Router over the roads of the tiles for the routes without a network, `None` if the provider has no roads
+    fn offline_routing_provider(&self) -> Option<Arc<dyn RoutingProvider>> {
+        None
+    }
     
     fn lat_lon_to_world(_lat_lon: &Coord<f64>) -> Coord<f64> {
         (0.0, 0.0).into()
@@ -20,5 +37,10 @@ pub trait TilesProvider {
     fn world_to_lat_lon(_lat_lon: &Coord<f64>) -> Coord<f64> {
         (0.0, 0.0).into()
     }
+
+    /// Meters of one world unit at the latitude, e.g. to pick the features within a distance
+    fn meters_per_world_unit(_lat: f64) -> f64 {
+        1.0
+    }
 }
 
@@ -0,0 +1,114 @@
+//! Follows the render groups of the annotations through the renderer messages.
+
+use geo_types::Point;
+use map::annotation::Annotation;
+use map::annotation::annotation_controller::AnnotationController;
+use map::clustering::cluster_controller::ClusterController;
+use renderer::messages::RendererApiMsg;
+use renderer::renderer_api::RendererApi;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, channel};
+
+const ICON: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><circle cx="5" cy="5" r="5"/></svg>"#;
+
+fn controller() -> (AnnotationController, ClusterController, Receiver<RendererApiMsg>) {
+    let (tx, rx) = channel();
+    let api = Arc::new(RendererApi::new(tx));
+    // the world x is the longitude, so the groups tell which annotation they draw
+    let controller = AnnotationController::new(api.clone(), Box::new(|p: &Point| *p));
+    (controller, ClusterController::new(api), rx)
+}
+
+// longitudes of the drawn annotations from the bottom up, after the messages so far
+fn stack(rx: &Receiver<RendererApiMsg>, groups: &mut Vec<(String, f64)>) -> Vec<f64> {
+    rx.try_iter().for_each(|msg| match msg {
+        RendererApiMsg::RenderGroup((key, layer, spatial_data, _)) => {
+            assert_eq!(layer, 1);
+            assert!(groups.iter().all(|(old_key, _)| *old_key != key));
+            groups.push((key, spatial_data.transform.x));
+        }
+        RendererApiMsg::ClearGroups(keys) => groups.retain(|(key, _)| !keys.contains(key)),
+        _ => {}
+    });
+    groups.iter().map(|(_, lon)| *lon).collect()
+}
+
+#[test]
+fn z_order_decides_the_stacking() {
+    let (mut controller, mut clusters, rx) = controller();
+    let mut groups = vec![];
+    controller.add("a", Annotation::marker((0.0, 1.0), ICON, 24.0), &mut clusters).unwrap();
+    controller
+        .add("b", Annotation::polyline(vec![(0.0, 2.0), (1.0, 2.0)], 5.0).with_z_order(1), &mut clusters)
+        .unwrap();
+    controller.add("c", Annotation::polygon(vec![(0.0, 3.0), (1.0, 3.0), (1.0, 4.0)]), &mut clusters).unwrap();
+    // "c" goes under "b" with a bigger z-order
+    assert_eq!(stack(&rx, &mut groups), [1.0, 3.0, 2.0]);
+
+    // a replaced annotation keeps its place
+    controller.add("c", Annotation::polygon(vec![(0.0, 3.0), (1.0, 3.0), (1.0, 4.0)]), &mut clusters).unwrap();
+    assert_eq!(stack(&rx, &mut groups), [1.0, 3.0, 2.0]);
+
+    controller.add("a", Annotation::marker((0.0, 1.0), ICON, 24.0).with_z_order(2), &mut clusters).unwrap();
+    assert_eq!(stack(&rx, &mut groups), [3.0, 2.0, 1.0]);
+
+    controller.add("a", Annotation::marker((0.0, 5.0), ICON, 24.0), &mut clusters).unwrap();
+    assert_eq!(stack(&rx, &mut groups), [5.0, 3.0, 2.0]);
+}
+
+#[test]
+fn removed_annotations_are_not_drawn() {
+    let (mut controller, mut clusters, rx) = controller();
+    let mut groups = vec![];
+    controller.add("a", Annotation::marker((0.0, 1.0), ICON, 24.0), &mut clusters).unwrap();
+    controller.add("b", Annotation::marker((0.0, 2.0), ICON, 24.0), &mut clusters).unwrap();
+
+    assert!(controller.remove("a", &mut clusters));
+    assert!(!controller.remove("a", &mut clusters));
+    assert_eq!(stack(&rx, &mut groups), [2.0]);
+    assert_eq!(controller.len(), 1);
+
+    controller.clear(&mut clusters);
+    assert!(stack(&rx, &mut groups).is_empty());
+    assert!(controller.is_empty());
+}
+
+#[test]
+fn invalid_annotations_are_rejected() {
+    let (mut controller, mut clusters, rx) = controller();
+    assert!(controller.add("line", Annotation::polyline(vec![(0.0, 0.0)], 5.0), &mut clusters).is_err());
+    assert!(controller.add("line", Annotation::polyline(vec![(0.0, 0.0), (1.0, 0.0)], 0.0), &mut clusters).is_err());
+    assert!(controller.add("area", Annotation::polygon(vec![(0.0, 0.0), (1.0, 0.0)]), &mut clusters).is_err());
+    assert!(controller.add("marker", Annotation::marker((0.0, 0.0), ICON, 0.0), &mut clusters).is_err());
+    assert!(controller.is_empty());
+    assert_eq!(rx.try_iter().count(), 0);
+}
+
+#[test]
+fn clustered_markers_are_cluster_points() {
+    let (mut controller, mut clusters, rx) = controller();
+    let mut groups = vec![];
+    controller.add("a", Annotation::marker((0.0, 1.0), ICON, 24.0), &mut clusters).unwrap();
+    controller
+        .add("b", Annotation::marker((0.0, 2.0), ICON, 24.0).with_clustering(true), &mut clusters)
+        .unwrap();
+    assert_eq!(stack(&rx, &mut groups), [1.0]);
+    assert!(!clusters.is_empty());
+
+    // the clustered marker gets its own group and the other one leaves it
+    controller.add("b", Annotation::marker((0.0, 2.0), ICON, 24.0), &mut clusters).unwrap();
+    controller
+        .add("a", Annotation::marker((0.0, 1.0), ICON, 24.0).with_clustering(true), &mut clusters)
+        .unwrap();
+    assert_eq!(stack(&rx, &mut groups), [2.0]);
+    assert_eq!(controller.len(), 1);
+
+    assert!(controller.remove("a", &mut clusters));
+    assert!(clusters.is_empty());
+    controller
+        .add("c", Annotation::marker((0.0, 3.0), ICON, 24.0).with_clustering(true), &mut clusters)
+        .unwrap();
+    controller.clear(&mut clusters);
+    assert!(clusters.is_empty());
+    assert!(stack(&rx, &mut groups).is_empty());
+}
@@ -0,0 +1,123 @@
+//! Clusters points on a grid of world units and follows the cluster groups through the renderer messages.
+
+use geo_types::{Coord, coord};
+use map::clustering::cluster_controller::ClusterController;
+use map::clustering::cluster_group::ClusterItem;
+use map::clustering::cluster_points;
+use renderer::messages::RendererApiMsg;
+use renderer::renderer_api::RendererApi;
+use renderer::styles::style_id::StyleId;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, channel};
+use std::time::{Duration, Instant};
+
+const ICON: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><circle cx="5" cy="5" r="5"/></svg>"#;
+
+// two groups of three points 10 units wide, 100 units apart
+fn points() -> Vec<Coord> {
+    vec![
+        coord! {x: 0.0, y: 0.0},
+        coord! {x: 100.0, y: 0.0},
+        coord! {x: 5.0, y: 5.0},
+        coord! {x: 105.0, y: 5.0},
+        coord! {x: 10.0, y: 0.0},
+        coord! {x: 110.0, y: 0.0},
+    ]
+}
+
+#[test]
+fn close_points_are_clustered() {
+    let clusters = cluster_points(&points(), 20.0);
+    assert_eq!(clusters.len(), 2);
+    assert_eq!(clusters[0].members, [0, 2, 4]);
+    assert_eq!(clusters[1].members, [1, 3, 5]);
+    assert_eq!(clusters[0].position, coord! {x: 5.0, y: 5.0 / 3.0});
+
+    let clusters = cluster_points(&points(), 8.0);
+    let members: Vec<Vec<usize>> = clusters.into_iter().map(|cluster| cluster.members).collect();
+    assert_eq!(members, [vec![0, 2], vec![1, 3], vec![4], vec![5]]);
+
+    assert_eq!(cluster_points(&points(), 1000.0).len(), 1);
+    assert!(cluster_points(&[], 10.0).is_empty());
+}
+
+#[test]
+fn points_across_grid_cells_are_clustered() {
+    let points = [coord! {x: 19.0, y: 19.0}, coord! {x: 21.0, y: 21.0}, coord! {x: 41.0, y: 19.0}];
+    let clusters = cluster_points(&points, 20.0);
+    assert_eq!(clusters.len(), 2);
+    assert_eq!(clusters[0].members, [0, 1]);
+}
+
+fn controller() -> (ClusterController, Receiver<RendererApiMsg>) {
+    let (tx, rx) = channel();
+    let mut controller = ClusterController::new(Arc::new(RendererApi::new(tx)));
+    let items = points()
+        .into_iter()
+        .map(|position| ClusterItem {
+            position,
+            icon: ("test", ICON),
+            size: 20.0,
+            style_id: StyleId("test"),
+            label: None,
+        })
+        .collect();
+    controller.set_items("test", items);
+    (controller, rx)
+}
+
+// keys of the groups added and removed since the last call
+fn group_changes(rx: &Receiver<RendererApiMsg>) -> (Vec<String>, Vec<String>) {
+    let (mut added, mut removed) = (vec![], vec![]);
+    rx.try_iter().for_each(|msg| match msg {
+        RendererApiMsg::RenderGroup((key, ..)) => added.push(key),
+        RendererApiMsg::ClearGroups(keys) => removed.extend(keys),
+        _ => {}
+    });
+    (added, removed)
+}
+
+#[test]
+fn clusters_expand_when_zooming_in() {
+    let (mut controller, rx) = controller();
+    let now = Instant::now();
+    // 60 pixels are 60 units at zoom 3
+    controller.update(3.0, 1.0, now);
+    assert_eq!(controller.clusters().len(), 2);
+    assert!(!controller.is_animating());
+    assert_eq!(group_changes(&rx), (vec!["clusters_1".to_string()], vec![]));
+
+    // the same zoom level keeps the clusters
+    controller.update(3.3, 1.2, now);
+    assert!(group_changes(&rx).0.is_empty());
+
+    // 60 pixels are 7.5 units at zoom 0
+    controller.update(0.0, 0.125, now);
+    assert_eq!(controller.clusters().len(), 4);
+    assert!(controller.is_animating());
+    controller.update(0.0, 0.125, now + Duration::from_millis(150));
+    assert!(controller.is_animating());
+    controller.update(0.0, 0.125, now + Duration::from_millis(300));
+    assert!(!controller.is_animating());
+    let (added, removed) = group_changes(&rx);
+    assert_eq!(added, ["clusters_2", "clusters_3", "clusters_4"]);
+    assert_eq!(removed, ["clusters_1", "clusters_2", "clusters_3"]);
+
+    controller.update(0.0, 0.125, now + Duration::from_millis(400));
+    assert!(group_changes(&rx).0.is_empty());
+}
+
+#[test]
+fn removed_points_are_not_drawn() {
+    let (mut controller, rx) = controller();
+    controller.update(3.0, 1.0, Instant::now());
+    group_changes(&rx);
+
+    assert!(controller.remove_items("test"));
+    assert!(!controller.remove_items("test"));
+    controller.update(3.0, 1.0, Instant::now());
+    assert!(controller.clusters().is_empty());
+    assert!(!controller.is_animating());
+    assert_eq!(group_changes(&rx), (vec![], vec!["clusters_1".to_string()]));
+    assert!(controller.is_empty());
+}
@@ -0,0 +1,70 @@
+//! Fixtures shared by the routing tests, every test crate uses a part of them.
+#![allow(dead_code)]
+
+use map::route::maneuver::{ManeuverKind, Route};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, channel};
+use std::thread::spawn;
+
+/// Ends of the fixture route along "Main Avenue" with a left turn onto "Cross 2".
+pub const ORIGIN: (f64, f64) = (35.688, 139.753);
+pub const DESTINATION: (f64, f64) = (35.6902, 139.757);
+
+pub fn fixture(dir: &str, name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(dir)
+        .join(name)
+}
+
+/// Answers a single HTTP request with the body, the request head is sent back.
+pub fn serve_once(status: &str, body: String) -> (SocketAddr, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let status = status.to_string();
+    let (head_sender, head_receiver) = channel();
+    spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut head = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                content_length = value.trim().parse().unwrap();
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+            head.push_str(&line);
+        }
+        reader.read_exact(&mut vec![0; content_length]).unwrap();
+        let mut stream = reader.into_inner();
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        head_sender.send(head).unwrap();
+    });
+    (address, head_receiver)
+}
+
+pub fn assert_cross_2_route(route: &Route) {
+    let kinds: Vec<_> = route.maneuvers.iter().map(|maneuver| maneuver.kind).collect();
+    assert_eq!(kinds, [ManeuverKind::Depart, ManeuverKind::Left, ManeuverKind::Arrive]);
+    assert_eq!(route.maneuvers[1].instruction, "Turn left onto Cross 2.");
+    assert_eq!(route.maneuvers[1].street_names, ["Cross 2"]);
+    assert_eq!(route.maneuvers[1].begin_shape_index, 2);
+    assert_eq!(route.shape.len(), 5, "{:?}", route.shape);
+    assert!((route.shape[0].x - ORIGIN.1).abs() < 1e-6 && (route.shape[0].y - ORIGIN.0).abs() < 1e-6);
+    assert!((route.shape[4].y - DESTINATION.0).abs() < 1e-6);
+}
@@ -0,0 +1,130 @@
+//! Extrapolation of the position while the GPS is lost, the fixes are around Tokyo station.
+
+use geo_types::{Coord, coord};
+use map::location::dead_reckoning::{DeadReckoning, DeadReckoningConfig, EstimatedLocation, ImuSample, LocationFix};
+use map::tiles::mercator;
+
+const LAT: f64 = 35.681;
+const LON: f64 = 139.767;
+
+fn world(lat: f64, lon: f64) -> Coord {
+    mercator::lat_lon_to_world(&coord! {x: lon, y: lat})
+}
+
+// the fix `north` and `east` meters away from the start
+fn fix(north: f64, east: f64, speed: Option<f32>, time: f64) -> LocationFix {
+    let meters_per_unit = mercator::meters_per_world_unit(LAT);
+    let position = world(LAT, LON) + coord! {x: east / meters_per_unit, y: -north / meters_per_unit};
+    let lat_lon = mercator::world_to_lat_lon(&position);
+    LocationFix {
+        lat: lat_lon.y,
+        lon: lat_lon.x,
+        bearing: None,
+        speed,
+        time,
+    }
+}
+
+// meters to the north and to the east of the start
+fn offset(location: &EstimatedLocation) -> (f64, f64) {
+    let delta = world(location.lat, location.lon) - world(LAT, LON);
+    let meters_per_unit = mercator::meters_per_world_unit(LAT);
+    (-delta.y * meters_per_unit, delta.x * meters_per_unit)
+}
+
+fn assert_offset(location: &EstimatedLocation, north: f64, east: f64) {
+    let (actual_north, actual_east) = offset(location);
+    assert!(
+        (actual_north - north).abs() < 0.1 && (actual_east - east).abs() < 0.1,
+        "Offset ({actual_north}, {actual_east}) isn't ({north}, {east})"
+    );
+}
+
+#[test]
+fn speed_and_bearing_are_estimated_from_the_fixes() {
+    let mut dead_reckoning = DeadReckoning::new(DeadReckoningConfig::default());
+    dead_reckoning.on_fix(&fix(0.0, 0.0, None, 0.0));
+    dead_reckoning.on_fix(&fix(10.0, 0.0, None, 1.0));
+
+    // 10 m/s to the north for 2 seconds after the last fix
+    let location = dead_reckoning.predict(3.0).unwrap();
+    assert_offset(&location, 30.0, 0.0);
+    assert!(location.bearing.min(360.0 - location.bearing) < 0.1, "Bearing {}", location.bearing);
+}
+
+#[test]
+fn speed_of_distant_or_jumping_fixes_is_not_trusted() {
+    // the fixes are 10 seconds apart, the reported speed of the first one is kept
+    let mut dead_reckoning = DeadReckoning::new(DeadReckoningConfig::default());
+    dead_reckoning.on_fix(&fix(0.0, 0.0, Some(5.0), 0.0));
+    dead_reckoning.on_fix(&fix(0.0, 200.0, None, 10.0));
+    assert_offset(&dead_reckoning.predict(12.0).unwrap(), 0.0, 210.0);
+
+    // a jump of 1 km in a second is clamped to 70 m/s
+    let mut dead_reckoning = DeadReckoning::new(DeadReckoningConfig::default());
+    dead_reckoning.on_fix(&fix(0.0, 0.0, None, 0.0));
+    dead_reckoning.on_fix(&fix(0.0, 1000.0, None, 1.0));
+    assert_offset(&dead_reckoning.predict(3.0).unwrap(), 0.0, 1140.0);
+}
+
+#[test]
+fn prediction_is_active_only_while_the_gps_is_lost() {
+    let mut dead_reckoning = DeadReckoning::new(DeadReckoningConfig::default());
+    assert!(dead_reckoning.predict(10.0).is_none());
+
+    dead_reckoning.on_fix(&fix(0.0, 0.0, Some(10.0), 0.0));
+    // the next fix is still expected
+    assert!(dead_reckoning.predict(1.0).is_none());
+    assert_offset(&dead_reckoning.predict(2.0).unwrap(), 20.0, 0.0);
+    // the position is too uncertain
+    assert!(dead_reckoning.predict(120.0).is_none());
+
+    // a new fix restarts the windows
+    dead_reckoning.on_fix(&fix(0.0, 0.0, Some(0.0), 130.0));
+    // standing still
+    assert!(dead_reckoning.predict(132.0).is_none());
+}
+
+#[test]
+fn motion_sensors_turn_and_accelerate() {
+    let mut dead_reckoning = DeadReckoning::new(DeadReckoningConfig::default());
+    dead_reckoning.on_fix(&fix(0.0, 0.0, Some(10.0), 0.0));
+    dead_reckoning.on_imu(&ImuSample {
+        yaw_rate: 45.0,
+        forward_acceleration: Some(1.0),
+        time: 1.0,
+    });
+
+    // the sample applies to the whole interval since the fix, the bearing turns by 90 degrees at once
+    // and the speed is 12 m/s
+    let location = dead_reckoning.predict(2.0).unwrap();
+    assert!((location.bearing - 90.0).abs() < 0.1, "Bearing {}", location.bearing);
+    assert_offset(&location, 0.0, 24.0);
+
+    // braking stops the vehicle, it doesn't go backwards
+    let mut dead_reckoning = DeadReckoning::new(DeadReckoningConfig::default());
+    dead_reckoning.on_fix(&fix(0.0, 0.0, Some(10.0), 0.0));
+    dead_reckoning.on_imu(&ImuSample {
+        yaw_rate: 0.0,
+        forward_acceleration: Some(-20.0),
+        time: 0.5,
+    });
+    assert_offset(&dead_reckoning.predict(2.0).unwrap(), 0.0, 0.0);
+}
+
+#[test]
+fn constrained_location_is_extrapolated_further() {
+    let mut dead_reckoning = DeadReckoning::new(DeadReckoningConfig::default());
+    // nothing to correct before the first fix
+    dead_reckoning.constrain(LAT, LON, 0.0);
+    assert!(dead_reckoning.predict(10.0).is_none());
+
+    dead_reckoning.on_fix(&fix(0.0, 0.0, Some(10.0), 0.0));
+    dead_reckoning.predict(2.0).unwrap();
+    // matched to a road to the east
+    let road = fix(15.0, 5.0, None, 2.0);
+    dead_reckoning.constrain(road.lat, road.lon, 90.0);
+    let location = dead_reckoning.predict(3.0).unwrap();
+    assert!((location.bearing - 90.0).abs() < 0.1, "Bearing {}", location.bearing);
+    assert_offset(&location, 15.0, 15.0);
+}
@@ -0,0 +1,86 @@
+//! Parses GeoJSON overlays with an identity converter, so the world coordinates are the longitudes and latitudes.
+
+use geo_types::{Geometry, Point};
+use map::geojson_group::GeoJsonGroup;
+use map::tiles::tile_data::FeatureKind;
+
+const ZONES: &str = r##"{
+  "type": "FeatureCollection",
+  "features": [
+    {
+      "type": "Feature",
+      "properties": {"name": "Zone A", "stroke": "#ff0000", "stroke-width": 4, "fill": "#00ff00", "fill-opacity": 0.5},
+      "geometry": {
+        "type": "Polygon",
+        "coordinates": [
+          [[10.0, 20.0], [14.0, 20.0], [14.0, 24.0], [10.0, 24.0], [10.0, 20.0]],
+          [[11.0, 21.0], [12.0, 21.0], [12.0, 22.0], [11.0, 21.0]]
+        ]
+      }
+    },
+    {
+      "type": "Feature",
+      "properties": {"label": "Parking", "icon": "parking"},
+      "geometry": {"type": "MultiPoint", "coordinates": [[10.5, 20.5, 30.0], [11.5, 20.5]]}
+    },
+    {
+      "type": "Feature",
+      "properties": {"stroke": "#ff0000"},
+      "geometry": {"type": "LineString", "coordinates": [[10.0, 20.0], [15.0, 25.0]]}
+    },
+    {"type": "Feature", "properties": null, "geometry": null}
+  ]
+}"##;
+
+fn parse(json: &str) -> anyhow::Result<GeoJsonGroup> {
+    GeoJsonGroup::parse("zones", json, Box::new(|p: &Point| *p))
+}
+
+#[test]
+fn features_are_parsed_with_their_names() {
+    let group = parse(ZONES).unwrap();
+    let features = group.features();
+    assert_eq!(features.len(), 3);
+    assert!(features.iter().all(|feature| feature.kind == FeatureKind::Overlay));
+
+    let names: Vec<Option<&str>> = features.iter().map(|feature| feature.name.as_deref()).collect();
+    assert_eq!(names, [Some("Zone A"), Some("Parking"), None]);
+
+    let Geometry::Polygon(polygon) = &features[0].geometry else {
+        panic!("Not a polygon: {:?}", features[0].geometry);
+    };
+    assert_eq!(polygon.interiors().len(), 1);
+    let Geometry::MultiPoint(points) = &features[1].geometry else {
+        panic!("Not points: {:?}", features[1].geometry);
+    };
+    assert_eq!(points.0[0], Point::new(10.5, 20.5));
+    assert_eq!(group.origin().x, 10.0);
+    assert_eq!(group.origin().y, 20.0);
+}
+
+#[test]
+fn features_with_the_same_colors_share_the_styles() {
+    let group = parse(ZONES).unwrap();
+    let mut styles: Vec<&str> = group.render_styles().map(|(style_id, _)| style_id.0).collect();
+    styles.sort();
+    assert_eq!(styles, ["overlay_fill_00ff0080", "overlay_line_ff0000ff"]);
+}
+
+#[test]
+fn single_feature_is_a_layer() {
+    let json = r#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1.0, 2.0]}}"#;
+    let features = parse(json).unwrap().features();
+    assert_eq!(features.len(), 1);
+    assert_eq!(features[0].geometry, Geometry::Point(Point::new(1.0, 2.0)));
+}
+
+#[test]
+fn invalid_geojson_is_an_error() {
+    assert!(parse("{").is_err());
+    assert!(parse(r#"{"type": "Topology"}"#).is_err());
+    let position = r#"{"type": "Feature", "geometry": {"type": "Point", "coordinates": [1.0]}}"#;
+    assert!(parse(position).is_err());
+    let color = r#"{"type": "Feature", "properties": {"stroke": "red"},
+        "geometry": {"type": "Point", "coordinates": [1.0, 2.0]}}"#;
+    assert!(parse(color).is_err());
+}
@@ -0,0 +1,168 @@
+//! Turn-by-turn guidance along a fixture route, driven by a recorded trace.
+//!
+//! `fixtures/guidance/route.json` is the Valhalla response for a route along "Main Avenue" of the
+//! map-matching fixtures with a left turn onto "Cross 2", `drive.gpx` is `trace_turn.csv` recorded
+//! once a second.
+
+mod common;
+
+use common::{DESTINATION, ORIGIN, assert_cross_2_route, fixture};
+use map::location::trace::Trace;
+use map::route::RouteCosting;
+use map::route::guidance::{Guidance, GuidanceConfig, GuidanceEvent};
+use map::route::maneuver::{ManeuverKind, Route};
+use map::route::provider::{RouteRequest, RoutingProvider};
+use map::route::provider::fixture_provider::FixtureRoutingProvider;
+use map::route::reroute::RerouteThrottle;
+use std::time::{Duration, Instant};
+
+fn fixture_route() -> Route {
+    FixtureRoutingProvider::load(fixture("guidance", "route.json"), RouteCosting::Motorbike)
+        .unwrap()
+        .route(&RouteRequest::new(vec![ORIGIN, DESTINATION], RouteCosting::Motorbike))
+        .unwrap()
+        .remove(0)
+}
+
+fn upcoming_maneuvers(events: &[GuidanceEvent]) -> Vec<(ManeuverKind, f64)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            GuidanceEvent::UpcomingManeuver { maneuver, distance } => Some((maneuver.kind, *distance)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn route_keeps_the_maneuvers() {
+    let provider = FixtureRoutingProvider::load(fixture("guidance", "route.json"), RouteCosting::Motorbike).unwrap();
+    let routes = provider
+        .route(&RouteRequest::new(vec![ORIGIN, DESTINATION], RouteCosting::Pedestrian).with_alternatives(2))
+        .unwrap();
+    // the saved response has no alternatives, the costing is the requested one
+    assert_eq!(routes.len(), 1);
+    assert_cross_2_route(&routes[0]);
+    assert_eq!(routes[0].costing, RouteCosting::Pedestrian);
+}
+
+#[test]
+fn recorded_drive_is_guided_to_the_destination() {
+    let mut guidance = Guidance::new(fixture_route(), GuidanceConfig::default());
+    let trace = Trace::load(fixture("guidance", "drive.gpx")).unwrap();
+    let events: Vec<GuidanceEvent> = trace
+        .fixes()
+        .iter()
+        .flat_map(|fix| guidance.update(fix.lat, fix.lon, fix.bearing))
+        .collect();
+
+    let upcoming = upcoming_maneuvers(&events);
+    let turns: Vec<_> = upcoming.iter().filter(|(kind, _)| *kind == ManeuverKind::Left).collect();
+    // the turn becomes the next maneuver ~300m ahead, it's announced again at 300m and 50m
+    assert_eq!(turns.len(), 3, "{upcoming:?}");
+    assert!(turns[1].1 <= 300.0 && turns[2].1 <= 50.0, "{upcoming:?}");
+    assert_eq!(upcoming.last().unwrap().0, ManeuverKind::Arrive);
+
+    let remaining: Vec<f64> = events
+        .iter()
+        .filter_map(|event| match event {
+            GuidanceEvent::Progress { distance_remaining, .. } => Some(*distance_remaining),
+            _ => None,
+        })
+        .collect();
+    assert!(remaining.windows(2).all(|pair| pair[1] <= pair[0]), "{remaining:?}");
+
+    assert!(!events.iter().any(|event| matches!(event, GuidanceEvent::OffRoute { .. })));
+    assert!(matches!(events.last(), Some(GuidanceEvent::Arrived)));
+    assert!(guidance.has_arrived());
+    assert!(guidance.update(DESTINATION.0, DESTINATION.1, None).is_empty());
+}
+
+#[test]
+fn leaving_the_route_is_reported_once() {
+    let mut guidance = Guidance::new(fixture_route(), GuidanceConfig::default());
+    assert!(!guidance.update(35.688, 139.7535, Some(90.0)).is_empty());
+
+    // ~110m north of Main Avenue
+    let off_route: Vec<GuidanceEvent> = (0..5)
+        .flat_map(|i| guidance.update(35.689, 139.7536 + i as f64 * 0.0001, Some(90.0)))
+        .collect();
+    assert_eq!(off_route.len(), 1, "{off_route:?}");
+    let GuidanceEvent::OffRoute { distance_from_route } = off_route[0] else {
+        panic!("{off_route:?}");
+    };
+    assert!((distance_from_route - 111.0).abs() < 5.0, "{distance_from_route}");
+    assert!(guidance.is_off_route());
+
+    // ~30m from the route, close enough to stay on it but not to get back
+    assert!(guidance.update(35.68827, 139.7545, Some(90.0)).is_empty());
+    assert!(guidance.is_off_route());
+
+    // back on the route
+    let events = guidance.update(35.688, 139.7546, Some(90.0));
+    assert!(events.iter().any(|event| matches!(event, GuidanceEvent::Progress { .. })));
+    assert!(!guidance.is_off_route());
+}
+
+#[test]
+fn driving_the_route_backwards_is_off_route() {
+    let mut guidance = Guidance::new(fixture_route(), GuidanceConfig::default());
+    let events: Vec<GuidanceEvent> = (0..4)
+        .flat_map(|i| guidance.update(35.688, 139.7560 - i as f64 * 0.0001, Some(270.0)))
+        .collect();
+    let off_route: Vec<_> = events
+        .iter()
+        .filter(|event| matches!(event, GuidanceEvent::OffRoute { .. }))
+        .collect();
+    assert_eq!(off_route.len(), 1, "{events:?}");
+    assert!(guidance.is_off_route());
+
+    // turned around, the bearing has to agree with the route again
+    guidance.update(35.688, 139.7557, Some(30.0));
+    assert!(guidance.is_off_route());
+    guidance.update(35.688, 139.7558, Some(85.0));
+    assert!(!guidance.is_off_route());
+}
+
+#[test]
+fn reroute_requests_are_throttled() {
+    let mut throttle = RerouteThrottle::new(Duration::from_secs(10));
+    let start = Instant::now();
+    assert!(throttle.can_request(start));
+    throttle.on_request(start);
+    // one request at a time
+    assert!(!throttle.can_request(start + Duration::from_secs(20)));
+    throttle.on_response();
+    assert!(!throttle.can_request(start + Duration::from_secs(5)));
+    assert!(throttle.can_request(start + Duration::from_secs(10)));
+}
+
+#[test]
+fn routes_without_segments_are_rejected() {
+    let route = fixture_route();
+    let point = Route {
+        shape: route.shape[..1].to_vec(),
+        maneuvers: vec![],
+        costing: route.costing,
+    };
+    assert!(Route::join(vec![point.clone(), point.clone()]).is_err());
+    assert!(Route::join(vec![]).is_err());
+    assert_eq!(Route::join(vec![point, route.clone()]).unwrap().shape, route.shape);
+
+    let mut outside = route.clone();
+    outside.maneuvers.last_mut().unwrap().begin_shape_index = route.shape.len();
+    assert!(Route::join(vec![outside]).is_err());
+}
+
+#[test]
+#[should_panic(expected = "The route has no shape")]
+fn guidance_needs_a_route_segment() {
+    let route = fixture_route();
+    Guidance::new(
+        Route {
+            shape: route.shape[..1].to_vec(),
+            ..route
+        },
+        GuidanceConfig::default(),
+    );
+}
@@ -0,0 +1,108 @@
+//! Reads KML and KMZ overlays with an identity converter, so the world coordinates are the longitudes and latitudes.
+//!
+//! `fixtures/kml/zones.kml` has two pickup points in a folder, two zones sharing a style through a style map,
+//! a courier route of a line and a point, and a line with a missing style. `depot.kmz` is an archive of a point
+//! and a styled line.
+
+use geo_types::{Coord, Geometry, Point, coord};
+use map::kml_viewer_group::KmlGroup;
+use map::tiles::tile_data::FeatureKind;
+use std::fs;
+use std::path::PathBuf;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("kml")
+        .join(name)
+}
+
+fn load(layer: &str, path: PathBuf) -> anyhow::Result<KmlGroup> {
+    KmlGroup::load(layer, &path, Box::new(|p: &Point| *p))
+}
+
+fn style_ids(group: &KmlGroup) -> Vec<&'static str> {
+    let mut style_ids: Vec<&str> = group.render_styles().map(|(style_id, _)| style_id.0).collect();
+    style_ids.sort();
+    style_ids
+}
+
+#[test]
+fn placemarks_of_the_folders_are_read() {
+    let group = load("zones", fixture("zones.kml")).unwrap();
+    let features = group.features();
+    assert!(features.iter().all(|feature| feature.kind == FeatureKind::Placemark));
+    let names: Vec<Option<&str>> = features.iter().map(|feature| feature.name.as_deref()).collect();
+    assert_eq!(
+        names,
+        [Some("Pickup A"), Some("Pickup B"), Some("Zone A"), Some("Zone B"), Some("Courier route"), None]
+    );
+
+    assert_eq!(features[0].geometry, Geometry::Point(Point::new(139.755, 35.685)));
+    let Geometry::Polygon(zone) = &features[2].geometry else {
+        panic!("Not a polygon: {:?}", features[2].geometry);
+    };
+    assert_eq!(zone.exterior().0.len(), 5);
+    assert_eq!(zone.interiors().len(), 1);
+    let Geometry::GeometryCollection(route) = &features[4].geometry else {
+        panic!("Not a multi geometry: {:?}", features[4].geometry);
+    };
+    assert!(matches!(route.0[..], [Geometry::LineString(_), Geometry::Point(_)]));
+    assert_eq!(group.origin().x, 139.755);
+    assert_eq!(group.origin().y, 35.685);
+}
+
+#[test]
+fn points_are_clustered_with_their_names() {
+    let group = load("zones", fixture("zones.kml")).unwrap();
+    let items: Vec<(Coord, Option<String>)> = group
+        .cluster_items()
+        .into_iter()
+        .map(|item| (item.position, item.label))
+        .collect();
+    assert_eq!(
+        items,
+        [
+            (coord! {x: 139.755, y: 35.685}, Some("Pickup A".to_string())),
+            (coord! {x: 139.765, y: 35.690}, Some("Pickup B".to_string())),
+            (coord! {x: 139.760, y: 35.688}, Some("Courier route".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn shared_styles_are_resolved_once() {
+    let group = load("zones", fixture("zones.kml")).unwrap();
+    // both zones use the normal style of the style map, the highlight and the missing styles aren't used
+    assert_eq!(
+        style_ids(&group),
+        ["overlay_fill_00ff0080", "overlay_line_0000ffff", "overlay_line_ff0000ff"]
+    );
+    // the styles are named after the colors, so the layers share them
+    let other_layer = load("other", fixture("zones.kml")).unwrap();
+    assert_eq!(style_ids(&other_layer), style_ids(&group));
+}
+
+#[test]
+fn kmz_archive_is_extracted() {
+    let group = load("depot", fixture("depot.kmz")).unwrap();
+    let names: Vec<Option<String>> = group.features().into_iter().map(|feature| feature.name).collect();
+    assert_eq!(names, [Some("Depot".to_string()), Some("Border".to_string())]);
+    assert_eq!(style_ids(&group), ["overlay_line_ffff00ff"]);
+    assert_eq!(group.cluster_items().len(), 1);
+}
+
+#[test]
+fn malformed_files_are_errors() {
+    let err = load("broken", fixture("broken.kml")).err().unwrap();
+    assert!(format!("{err:#}").contains("broken.kml"), "{err:#}");
+    assert!(load("missing", fixture("missing.kml")).is_err());
+
+    // a KML file isn't an archive
+    let dir = std::env::temp_dir().join(format!("shashlik_kml_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let not_kmz = dir.join("zones.kmz");
+    fs::copy(fixture("zones.kml"), &not_kmz).unwrap();
+    assert!(load("zones", not_kmz).is_err());
+}
@@ -0,0 +1,241 @@
+//! Replays recorded GPS traces against the roads of fixture tiles.
+//!
+//! `fixtures/matching/roads.json` is a grid of streets split into two tiles: "Main Avenue" runs
+//! west to east with a parallel "Side Street" 35m to the north, and "Cross 1..3" go north to south.
+//! Every trace line is `lat,lon,bearing,expected_road`, `*` marks fixes near an intersection
+//! where either road is fine.
+
+use geo_types::{Coord, LineString, coord};
+use map::matching::hmm_matcher::{GpsFix, HmmMatcher, MatchedPosition, MatcherConfig};
+use map::matching::road_network::{Road, RoadNetwork};
+use map::tiles::mercator;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+struct FixtureRoads {
+    tiles: BTreeMap<String, Vec<FixtureRoad>>,
+}
+
+#[derive(Deserialize)]
+struct FixtureRoad {
+    name: String,
+    class: String,
+    coords: Vec<(f64, f64)>,
+}
+
+struct TraceFix {
+    fix: GpsFix,
+    expected_road: String,
+}
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("matching")
+}
+
+fn fixture_tiles() -> Vec<(String, Vec<Road>)> {
+    let document = std::fs::read_to_string(fixtures_dir().join("roads.json")).unwrap();
+    let fixture: FixtureRoads = serde_json::from_str(&document).unwrap();
+    fixture
+        .tiles
+        .into_iter()
+        .map(|(tile_key, roads)| {
+            let roads = roads
+                .into_iter()
+                .map(|road| Road {
+                    name: Some(road.name),
+                    // the classes of the tile roads are static strings of the feature processor
+                    class: Box::leak(road.class.into_boxed_str()),
+                    line: LineString::from_iter(
+                        road.coords
+                            .iter()
+                            .map(|(lon, lat)| mercator::lat_lon_to_world(&coord! {x: *lon, y: *lat})),
+                    ),
+                })
+                .collect();
+            (tile_key, roads)
+        })
+        .collect()
+}
+
+fn road_network() -> Arc<RoadNetwork> {
+    let road_network = RoadNetwork::new();
+    fixture_tiles()
+        .into_iter()
+        .for_each(|(tile_key, roads)| road_network.insert_tile(tile_key, roads));
+    Arc::new(road_network)
+}
+
+fn trace(name: &str) -> Vec<TraceFix> {
+    std::fs::read_to_string(fixtures_dir().join(name))
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let columns: Vec<&str> = line.split(',').collect();
+            TraceFix {
+                fix: GpsFix {
+                    lat: columns[0].parse().unwrap(),
+                    lon: columns[1].parse().unwrap(),
+                    bearing: columns[2].parse().ok(),
+                },
+                expected_road: columns[3].to_string(),
+            }
+        })
+        .collect()
+}
+
+fn replay(matcher: &mut HmmMatcher, trace: &[TraceFix]) -> Vec<MatchedPosition> {
+    trace
+        .iter()
+        .map(|fix| matcher.push(&fix.fix).expect("Every fix of the trace is near a road"))
+        .collect()
+}
+
+fn assert_roads(trace: &[TraceFix], matched: &[MatchedPosition]) {
+    trace.iter().zip(matched).enumerate().for_each(|(i, (fix, matched))| {
+        if fix.expected_road != "*" {
+            assert_eq!(
+                matched.road_name.as_deref(),
+                Some(fix.expected_road.as_str()),
+                "Fix {i} is matched to a wrong road"
+            );
+        }
+    });
+}
+
+fn angle_difference(a: f32, b: f32) -> f32 {
+    let difference = (a - b).rem_euclid(360.0);
+    difference.min(360.0 - difference)
+}
+
+fn world(lat: f64, lon: f64) -> Coord {
+    mercator::lat_lon_to_world(&coord! {x: lon, y: lat})
+}
+
+#[test]
+fn noisy_trace_stays_on_the_avenue() {
+    let road_network = road_network();
+    let trace = trace("trace_avenue.csv");
+
+    // the fixes drift north, snapping every fix to the nearest road jumps to the side street
+    let nearest_roads: Vec<_> = trace
+        .iter()
+        .map(|fix| {
+            let mut matcher = HmmMatcher::new(road_network.clone(), MatcherConfig::default());
+            matcher.push(&fix.fix).unwrap().road_name
+        })
+        .collect();
+    assert!(nearest_roads.iter().any(|road| road.as_deref() == Some("Side Street")));
+
+    let mut matcher = HmmMatcher::new(road_network, MatcherConfig::default());
+    let matched = replay(&mut matcher, &trace);
+    assert_roads(&trace, &matched);
+    matched.iter().for_each(|matched| {
+        assert!(angle_difference(matched.bearing, 90.0) < 1.0, "Bearing {}", matched.bearing);
+        assert_eq!(matched.road_class, "primary");
+    });
+}
+
+#[test]
+fn turn_follows_the_cross_street() {
+    let mut matcher = HmmMatcher::new(road_network(), MatcherConfig::default());
+    let trace = trace("trace_turn.csv");
+    let matched = replay(&mut matcher, &trace);
+    assert_roads(&trace, &matched);
+
+    let last = matched.last().unwrap();
+    assert!(angle_difference(last.bearing, 0.0) < 1.0, "Bearing {}", last.bearing);
+}
+
+#[test]
+fn matched_positions_are_on_the_road() {
+    let road_network = road_network();
+    let mut matcher = HmmMatcher::new(road_network, MatcherConfig::default());
+    let trace = trace("trace_avenue.csv");
+    let avenue_y = world(35.6880, 139.7570).y;
+    replay(&mut matcher, &trace).iter().for_each(|matched| {
+        let position = world(matched.lat, matched.lon);
+        assert!((position.y - avenue_y).abs() < 1e-3);
+    });
+}
+
+#[test]
+fn fix_without_roads_is_not_matched() {
+    let mut matcher = HmmMatcher::new(road_network(), MatcherConfig::default());
+    let trace = trace("trace_avenue.csv");
+    assert!(matcher.push(&trace[0].fix).is_some());
+    // ~1km north of the fixture roads
+    let far_fix = GpsFix {
+        lat: 35.697,
+        lon: 139.757,
+        bearing: None,
+    };
+    assert!(matcher.push(&far_fix).is_none());
+    // the matcher starts over after the gap
+    let matched = matcher.push(&trace[10].fix).unwrap();
+    assert_eq!(matched.road_name.as_deref(), Some("Main Avenue"));
+}
+
+#[test]
+fn tiles_loaded_during_a_trace_keep_the_match() {
+    let road_network = road_network();
+    let mut matcher = HmmMatcher::new(road_network.clone(), MatcherConfig::default());
+    let trace = trace("trace_turn.csv");
+    let (first, second) = trace.split_at(trace.len() / 2);
+    let mut matched = replay(&mut matcher, first);
+
+    // the trace is in the west tile, its roads are indexed after the east ones until the east tile is gone,
+    // then a tile far away from the trace is loaded
+    road_network.insert_tile("fixture_east".to_string(), vec![]);
+    matched.push(matcher.push(&second[0].fix).unwrap());
+    road_network.insert_tile(
+        "far_away".to_string(),
+        vec![Road {
+            name: Some("Far Road".to_string()),
+            class: "residential",
+            line: LineString::from(vec![world(35.70, 139.70), world(35.70, 139.71)]),
+        }],
+    );
+    matched.extend(replay(&mut matcher, &second[1..]));
+    assert_roads(&trace, &matched);
+
+    // the tile of the matched roads is gone
+    road_network.insert_tile("fixture_west".to_string(), vec![]);
+    assert!(matcher.push(second.last().map(|fix| &fix.fix).unwrap()).is_none());
+}
+
+#[test]
+fn predicted_positions_stay_on_the_matched_road() {
+    let mut matcher = HmmMatcher::new(road_network(), MatcherConfig::default());
+    let predicted = GpsFix {
+        lat: 35.6883,
+        lon: 139.7600,
+        bearing: Some(90.0),
+    };
+    // nothing is matched yet
+    assert!(matcher.snap(&predicted).is_none());
+
+    let trace = trace("trace_avenue.csv");
+    replay(&mut matcher, &trace);
+    // the side street is closer to the prediction
+    let snapped = matcher.snap(&predicted).unwrap();
+    assert_eq!(snapped.road_name.as_deref(), Some("Main Avenue"));
+    assert!((world(snapped.lat, snapped.lon).y - world(35.688, 139.76).y).abs() < 1e-3);
+    assert!(angle_difference(snapped.bearing, 90.0) < 1.0, "Bearing {}", snapped.bearing);
+
+    let far_away = GpsFix {
+        lat: 35.697,
+        lon: 139.757,
+        bearing: None,
+    };
+    assert!(matcher.snap(&far_away).is_none());
+    // the snapped positions don't break the matched trace
+    let matched = matcher.push(&trace.last().unwrap().fix).unwrap();
+    assert_eq!(matched.road_name.as_deref(), Some("Main Avenue"));
+}
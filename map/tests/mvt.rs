@@ -0,0 +1,191 @@
+//! Mapbox Vector Tiles: the geometry command streams, the ring winding and the MBTiles rows.
+
+use geo_types::{Coord, coord};
+use map::tiles::mvt::geometry::{MvtGeometry, decode_geometry};
+use map::tiles::mvt::source::{MbTilesSource, MvtSource};
+use map::tiles::mvt::vector_tile::{Feature, GeomType, Layer, Tile, Value};
+use map::tiles::mvt::{MvtTileKey, MvtValue, decode_tile, feature_properties};
+use prost::Message;
+use rusqlite::Connection;
+use std::fs;
+
+const CLOSE_PATH: u32 = 7 | (1 << 3);
+
+fn command(id: u32, count: u32) -> u32 {
+    id | (count << 3)
+}
+
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+// every part is MoveTo its first point and LineTo the rest, the cursor carries over the parts,
+// the points are absolute and encoded as deltas
+fn encode(parts: &[(&[(i32, i32)], bool)]) -> Vec<u32> {
+    let mut commands = vec![];
+    let mut cursor = (0, 0);
+    for (points, close) in parts {
+        for (i, point) in points.iter().enumerate() {
+            if i == 0 {
+                commands.push(command(1, 1));
+            } else if i == 1 {
+                commands.push(command(2, points.len() as u32 - 1));
+            }
+            commands.push(zigzag(point.0 - cursor.0));
+            commands.push(zigzag(point.1 - cursor.1));
+            cursor = *point;
+        }
+        if *close {
+            commands.push(CLOSE_PATH);
+        }
+    }
+    commands
+}
+
+fn identity(x: i32, y: i32) -> Coord {
+    coord! {x: x as f64, y: y as f64}
+}
+
+fn coords(line: &geo_types::LineString) -> Vec<(f64, f64)> {
+    line.coords().map(|c| (c.x, c.y)).collect()
+}
+
+#[test]
+fn lines_are_decoded_from_deltas() {
+    let commands = encode(&[(&[(2, 3), (4, 3), (4, -1)], false), (&[(10, 12), (12, 14)], false)]);
+    let Some(MvtGeometry::Lines(lines)) = decode_geometry(GeomType::Linestring, &commands, identity)
+    else {
+        panic!("lines are expected");
+    };
+    assert_eq!(lines.len(), 2);
+    assert_eq!(coords(&lines[0]), vec![(2.0, 3.0), (4.0, 3.0), (4.0, -1.0)]);
+    assert_eq!(coords(&lines[1]), vec![(10.0, 12.0), (12.0, 14.0)]);
+}
+
+#[test]
+fn points_and_to_local_are_applied() {
+    let commands = [command(1, 2), zigzag(5), zigzag(5), zigzag(-2), zigzag(3)];
+    let Some(MvtGeometry::Points(points)) =
+        decode_geometry(GeomType::Point, &commands, |x, y| coord! {x: x as f64 / 2.0, y: -y as f64})
+    else {
+        panic!("points are expected");
+    };
+    assert_eq!(points, vec![coord! {x: 2.5, y: -5.0}, coord! {x: 1.5, y: -8.0}]);
+    assert!(decode_geometry(GeomType::Unknown, &commands, identity).is_none());
+}
+
+#[test]
+fn malformed_commands_are_cut() {
+    // the LineTo promises two points but has one, the valid part before it is kept
+    let mut commands = encode(&[(&[(0, 0), (5, 0)], false)]);
+    commands.extend([command(1, 1), zigzag(1), zigzag(1), command(2, 2), zigzag(1), zigzag(1)]);
+    let Some(MvtGeometry::Lines(lines)) = decode_geometry(GeomType::Linestring, &commands, identity)
+    else {
+        panic!("lines are expected");
+    };
+    assert_eq!(lines.len(), 1);
+    assert_eq!(coords(&lines[0]), vec![(0.0, 0.0), (5.0, 0.0)]);
+
+    let unknown_command = [command(5, 1), zigzag(1), zigzag(1)];
+    let Some(MvtGeometry::Lines(lines)) =
+        decode_geometry(GeomType::Linestring, &unknown_command, identity)
+    else {
+        panic!("lines are expected");
+    };
+    assert!(lines.is_empty());
+}
+
+#[test]
+fn polygon_rings_are_split_by_winding() {
+    // y grows down in the tile, so the exterior rings are clockwise on the screen
+    // not closed and degenerated rings are dropped
+    let commands = encode(&[
+        (&[(0, 0), (10, 0), (10, 10), (0, 10)], true),
+        (&[(2, 2), (2, 8), (8, 8), (8, 2)], true),
+        (&[(20, 0), (30, 0), (30, 10)], true),
+        (&[(40, 0), (50, 0), (50, 10)], false),
+        (&[(60, 0), (70, 0), (80, 0)], true),
+    ]);
+
+    let Some(MvtGeometry::Polygons(polygons)) =
+        decode_geometry(GeomType::Polygon, &commands, identity)
+    else {
+        panic!("polygons are expected");
+    };
+    assert_eq!(polygons.len(), 2);
+    assert_eq!(polygons[0].interiors().len(), 1);
+    assert_eq!(
+        coords(&polygons[0].interiors()[0])[..4],
+        [(2.0, 2.0), (2.0, 8.0), (8.0, 8.0), (8.0, 2.0)]
+    );
+    assert!(polygons[1].interiors().is_empty());
+    assert_eq!(coords(polygons[1].exterior())[0], (20.0, 0.0));
+}
+
+#[test]
+fn tile_layers_and_properties_are_decoded() {
+    let layer = Layer {
+        version: 2,
+        name: "transportation".to_string(),
+        features: vec![Feature {
+            id: Some(7),
+            tags: vec![0, 0, 1, 1, 5, 0],
+            r#type: Some(GeomType::Linestring as i32),
+            geometry: encode(&[(&[(0, 0), (100, 0)], false)]),
+        }],
+        keys: vec!["class".to_string(), "layer".to_string()],
+        values: vec![
+            Value {
+                string_value: Some("primary".to_string()),
+                ..Default::default()
+            },
+            Value {
+                int_value: Some(1),
+                ..Default::default()
+            },
+        ],
+        extent: Some(4096),
+    };
+    let data = Tile { layers: vec![layer] }.encode_to_vec();
+
+    let tile = decode_tile(&data).unwrap();
+    assert_eq!(tile.layers.len(), 1);
+    let layer = &tile.layers[0];
+    assert_eq!(layer.name, "transportation");
+    assert_eq!(layer.extent(), 4096);
+    // the tag with the missing key is skipped
+    let properties = feature_properties(layer, &layer.features[0].tags);
+    assert_eq!(properties.len(), 2);
+    assert_eq!(properties.get("class"), Some(&MvtValue::String("primary".to_string())));
+    assert_eq!(properties.get("layer").and_then(|value| value.as_f64()), Some(1.0));
+
+    assert!(decode_tile(&[0xff, 0xff, 0xff]).is_err());
+}
+
+#[test]
+fn mbtiles_rows_are_flipped_to_xyz() {
+    let dir = std::env::temp_dir().join(format!("shashlik_mbtiles_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tiles.mbtiles");
+    let connection = Connection::open(&path).unwrap();
+    connection
+        .execute_batch(
+            "CREATE TABLE metadata (name TEXT, value TEXT);
+             CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+             INSERT INTO metadata VALUES ('minzoom', '2'), ('maxzoom', ' 9 ');
+             INSERT INTO tiles VALUES (2, 1, 3, x'01'), (2, 1, 0, x'02');",
+        )
+        .unwrap();
+    drop(connection);
+
+    let source = MbTilesSource::open(&path).unwrap();
+    assert_eq!(source.min_zoom(), 2);
+    assert_eq!(source.max_zoom(), 9);
+    // TMS row 3 is the northern row, y 0 of XYZ
+    assert_eq!(source.load(&MvtTileKey { x: 1, y: 0, zoom: 2 }), Some(vec![1]));
+    assert_eq!(source.load(&MvtTileKey { x: 1, y: 3, zoom: 2 }), Some(vec![2]));
+    assert_eq!(source.load(&MvtTileKey { x: 0, y: 0, zoom: 2 }), None);
+
+    assert!(MbTilesSource::open(dir.join("missing.mbtiles")).is_err());
+}
@@ -0,0 +1,129 @@
+//! Offline routes over the street grid of the map-matching fixtures.
+//!
+//! `fixtures/matching/roads.json` has "Main Avenue" from west to east, "Side Street" 35m north of it
+//! and "Cross 1..3" from north to south, the avenue and the street are split between two tiles.
+
+mod common;
+
+use common::{DESTINATION, ORIGIN, assert_cross_2_route, fixture};
+use geo_types::{LineString, coord};
+use map::matching::road_network::Road;
+use map::route::RouteCosting;
+use map::route::maneuver::ManeuverKind;
+use map::route::provider::RouteRequest;
+use map::route::road_graph::RoadGraph;
+use map::tiles::mercator;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Deserialize)]
+struct FixtureRoads {
+    tiles: BTreeMap<String, Vec<FixtureRoad>>,
+}
+
+#[derive(Deserialize)]
+struct FixtureRoad {
+    name: String,
+    class: String,
+    coords: Vec<(f64, f64)>,
+}
+
+fn road(name: &str, class: &'static str, coords: &[(f64, f64)]) -> Road {
+    Road {
+        name: Some(name.to_string()),
+        class,
+        line: LineString::from_iter(
+            coords
+                .iter()
+                .map(|(lon, lat)| mercator::lat_lon_to_world(&coord! {x: *lon, y: *lat})),
+        ),
+    }
+}
+
+fn roads() -> Vec<Road> {
+    let path = fixture("matching", "roads.json");
+    let fixture: FixtureRoads = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    fixture
+        .tiles
+        .into_values()
+        .flatten()
+        .map(|fixture_road| {
+            let class = if fixture_road.class == "primary" { "primary" } else { "residential" };
+            road(&fixture_road.name, class, &fixture_road.coords)
+        })
+        .collect()
+}
+
+#[test]
+fn motorbike_route_turns_onto_cross_2() {
+    let route = RoadGraph::new(roads())
+        .route(ORIGIN, DESTINATION, RouteCosting::Motorbike)
+        .unwrap();
+    assert_cross_2_route(&route);
+    assert_eq!(route.maneuvers[0].street_names, ["Main Avenue"]);
+    assert!((route.shape[2].x - 139.757).abs() < 1e-6 && (route.shape[2].y - 35.688).abs() < 1e-6);
+}
+
+#[test]
+fn only_pedestrians_take_the_footway() {
+    let mut roads = roads();
+    // shortcut from the avenue to Side Street at Cross 2
+    roads.push(road("Park Path", "footway", &[(139.754788, 35.688), (139.757, 35.6883144)]));
+    let graph = RoadGraph::new(roads);
+
+    let on_foot = graph.route(ORIGIN, DESTINATION, RouteCosting::Pedestrian).unwrap();
+    let streets: Vec<_> = on_foot.maneuvers.iter().flat_map(|maneuver| maneuver.street_names.clone()).collect();
+    assert_eq!(streets, ["Main Avenue", "Park Path", "Cross 2"]);
+
+    let by_motorbike = graph.route(ORIGIN, DESTINATION, RouteCosting::Motorbike).unwrap();
+    assert!(by_motorbike.maneuvers.iter().all(|maneuver| maneuver.street_names != ["Park Path"]));
+}
+
+#[test]
+fn waypoints_join_the_legs() {
+    // on Side Street between Cross 1 and Cross 2
+    let waypoint = (35.6883144, 139.755);
+    let request = RouteRequest::new(vec![ORIGIN, waypoint, DESTINATION], RouteCosting::Motorbike);
+    let routes = RoadGraph::new(roads()).routes(&request).unwrap();
+    assert_eq!(routes.len(), 1);
+    let route = &routes[0];
+
+    let kinds: Vec<_> = route.maneuvers.iter().map(|maneuver| maneuver.kind).collect();
+    assert_eq!(kinds.iter().filter(|kind| **kind == ManeuverKind::Depart).count(), 1);
+    assert_eq!(kinds.iter().filter(|kind| **kind == ManeuverKind::Arrive).count(), 1);
+    assert_eq!(route.maneuvers.last().unwrap().begin_shape_index, route.shape.len() - 1);
+    assert!(route.distance_from(waypoint) < 1.0);
+    assert!((route.shape.last().unwrap().y - DESTINATION.0).abs() < 1e-6);
+}
+
+#[test]
+fn alternatives_take_other_streets() {
+    let request = RouteRequest::new(vec![ORIGIN, DESTINATION], RouteCosting::Pedestrian).with_alternatives(2);
+    let routes = RoadGraph::new(roads()).routes(&request).unwrap();
+    assert_eq!(routes.len(), 2);
+
+    let streets = |index: usize| -> Vec<String> {
+        routes[index]
+            .maneuvers
+            .iter()
+            .flat_map(|maneuver| maneuver.street_names.clone())
+            .collect()
+    };
+    assert_eq!(streets(0), ["Main Avenue", "Cross 2"]);
+    assert_eq!(streets(1), ["Main Avenue", "Cross 1", "Side Street", "Cross 2"]);
+
+    // a point of Side Street selects the alternative
+    let side_street = (35.6883144, 139.755);
+    assert!(routes[1].distance_from(side_street) < 1.0);
+    assert!(routes[0].distance_from(side_street) > 30.0);
+}
+
+#[test]
+fn locations_away_from_the_roads_have_no_route() {
+    let graph = RoadGraph::new(roads());
+    // ~1km south of the grid
+    let err = graph
+        .route((35.678, 139.753), DESTINATION, RouteCosting::Motorbike)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "No road near the start");
+}
@@ -0,0 +1,117 @@
+//! Picks features of hand made tiles around a point in Tokyo.
+
+use geo_types::{Coord, Geometry, LineString, Point, Polygon, coord};
+use map::feature_processor::ShashlikFeatureProcessor;
+use map::picking::FeatureIndex;
+use map::tiles::shashlik_tiles_provider_v0::ShashlikTilesProviderV0;
+use map::tiles::source::MapTileSource;
+use map::tiles::tile_data::{FeatureKind, TileFeature};
+use map::tiles::tiles_provider::TilesProvider;
+use std::collections::HashSet;
+
+// the tiles are in the world of the default provider
+type Tiles = ShashlikTilesProviderV0<MapTileSource, ShashlikFeatureProcessor>;
+
+const TAP: (f64, f64) = (35.6880, 139.7570);
+
+fn world(lat_lon: (f64, f64)) -> Coord {
+    Tiles::lat_lon_to_world(&coord! {x: lat_lon.1, y: lat_lon.0})
+}
+
+fn feature(kind: FeatureKind, name: Option<&str>, geometry: Geometry) -> TileFeature {
+    TileFeature {
+        kind,
+        name: name.map(str::to_string),
+        geometry,
+    }
+}
+
+// a road 5.5m south of the tap, relative to the origin
+fn road(origin: Coord, from_lon: f64, to_lon: f64) -> Geometry {
+    let lat = TAP.0 - 0.00005;
+    let line: LineString = vec![world((lat, from_lon)) - origin, world((lat, to_lon)) - origin].into();
+    Geometry::LineString(line)
+}
+
+// tile 1 has a building around the tap, a POI 10m north and a part of "Main Avenue",
+// tile 2 has the rest of the avenue and a POI 60m north
+fn index() -> FeatureIndex {
+    let index = FeatureIndex::new();
+    let origin = world((35.69, 139.75));
+    let offset = |lat: f64, lon: f64| world((TAP.0 + lat, TAP.1 + lon)) - origin;
+    let building = Polygon::new(
+        vec![
+            offset(-0.0001, -0.0001),
+            offset(-0.0001, 0.0001),
+            offset(0.0001, 0.0001),
+            offset(0.0001, -0.0001),
+            offset(-0.0001, -0.0001),
+        ]
+        .into(),
+        vec![],
+    );
+    index.insert(
+        "tile_1".to_string(),
+        origin,
+        vec![
+            feature(FeatureKind::Building, None, Geometry::Polygon(building)),
+            feature(FeatureKind::Poi, Some("Cafe"), Geometry::Point(Point(offset(0.00009, 0.0)))),
+            feature(FeatureKind::Road, Some("Main Avenue"), road(origin, 139.7560, 139.7571)),
+        ],
+    );
+    let origin = world((35.69, 139.76));
+    index.insert(
+        "tile_2".to_string(),
+        origin,
+        vec![
+            feature(FeatureKind::Road, Some("Main Avenue"), road(origin, 139.7571, 139.7580)),
+            feature(FeatureKind::Poi, Some("Station"), Geometry::Point(Point(world((TAP.0 + 0.00054, TAP.1)) - origin))),
+        ],
+    );
+    index
+}
+
+#[test]
+fn nearest_features_come_first() {
+    let picked = index().pick::<Tiles>(TAP, 20.0);
+    let kinds: Vec<FeatureKind> = picked.iter().map(|(_, feature)| feature.kind).collect();
+    assert_eq!(kinds, [FeatureKind::Building, FeatureKind::Road, FeatureKind::Poi]);
+    assert!(picked.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+    let (distance, cafe) = &picked[2];
+    assert_eq!(cafe.name.as_deref(), Some("Cafe"));
+    assert_eq!(cafe.group_key, "tile_1");
+    assert!((distance - 10.0).abs() < 1.0, "{distance}");
+    assert!((cafe.lat_lon.0 - (TAP.0 + 0.00009)).abs() < 1e-7);
+    assert!((cafe.lat_lon.1 - TAP.1).abs() < 1e-7);
+
+    // the center of the building, not the tapped point
+    let building = &picked[0].1;
+    assert!((building.lat_lon.0 - TAP.0).abs() < 1e-7);
+    assert!((building.lat_lon.1 - TAP.1).abs() < 1e-7);
+}
+
+#[test]
+fn roads_cut_by_tiles_are_found_once() {
+    let picked = index().pick::<Tiles>(TAP, 20.0);
+    let roads: Vec<_> = picked
+        .iter()
+        .filter(|(_, feature)| feature.kind == FeatureKind::Road)
+        .collect();
+    assert_eq!(roads.len(), 1);
+    assert_eq!(roads[0].1.name.as_deref(), Some("Main Avenue"));
+    assert!((roads[0].0 - 5.5).abs() < 0.5, "{}", roads[0].0);
+}
+
+#[test]
+fn far_and_removed_features_are_not_picked() {
+    let index = index();
+    let names: Vec<Option<String>> = index.pick::<Tiles>(TAP, 100.0).into_iter().map(|(_, feature)| feature.name).collect();
+    assert!(names.contains(&Some("Station".to_string())));
+
+    index.remove(&HashSet::from(["tile_1".to_string()]));
+    let picked = index.pick::<Tiles>(TAP, 20.0);
+    assert_eq!(picked.len(), 1);
+    assert_eq!(picked[0].1.group_key, "tile_2");
+    assert!(index.pick::<Tiles>((TAP.0 + 0.01, TAP.1), 20.0).is_empty());
+}
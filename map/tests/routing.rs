@@ -0,0 +1,98 @@
+//! Routing providers against mock servers.
+//!
+//! `fixtures/guidance/route.json` and `fixtures/routing/osrm_route.json` are the Valhalla and OSRM
+//! responses for the same route along "Main Avenue" with a left turn onto "Cross 2".
+
+mod common;
+
+use common::{DESTINATION, ORIGIN, assert_cross_2_route, fixture, serve_once};
+use map::route::RouteCosting;
+use map::route::provider::{RouteRequest, RoutingProvider};
+use map::route::provider::osrm_provider::OsrmRoutingProvider;
+use map::route::provider::valhalla_provider::ValhallaRoutingProvider;
+use url::Url;
+
+fn fixture_body(dir: &str, name: &str) -> String {
+    std::fs::read_to_string(fixture(dir, name)).unwrap()
+}
+
+fn request(costing: RouteCosting) -> RouteRequest {
+    RouteRequest::new(vec![ORIGIN, DESTINATION], costing)
+}
+
+#[test]
+fn valhalla_route_keeps_the_maneuvers() {
+    let (address, head) = serve_once("200 OK", fixture_body("guidance", "route.json"));
+    let provider = ValhallaRoutingProvider::new(Url::parse(&format!("http://{address}/")).unwrap())
+        .with_header("Authorization", "Bearer token");
+    let routes = provider.route(&request(RouteCosting::Motorbike)).unwrap();
+    assert_eq!(routes.len(), 1);
+    assert_cross_2_route(&routes[0]);
+
+    let head = head.recv().unwrap();
+    assert!(head.contains("Authorization: Bearer token"), "{head}");
+}
+
+#[test]
+fn valhalla_alternates_are_requested() {
+    // the alternate of the saved response is the same trip
+    let mut body: serde_json::Value = serde_json::from_str(&fixture_body("guidance", "route.json")).unwrap();
+    body["alternates"] = serde_json::json!([{"trip": body["trip"].clone()}, {"trip": body["trip"].clone()}]);
+    let (address, head) = serve_once("200 OK", body.to_string());
+    let provider = ValhallaRoutingProvider::new(Url::parse(&format!("http://{address}/")).unwrap());
+    let routes = provider.route(&request(RouteCosting::Pedestrian).with_alternatives(1)).unwrap();
+    assert_eq!(routes.len(), 2);
+    routes.iter().for_each(|route| {
+        assert_cross_2_route(route);
+        assert_eq!(route.costing, RouteCosting::Pedestrian);
+    });
+
+    let head = head.recv().unwrap();
+    assert!(head.starts_with("GET /route?json="), "{head}");
+    // the manifest is percent-encoded
+    assert!(head.contains("%22alternates%22%3A1"), "{head}");
+    assert!(head.contains("%22costing%22%3A%22pedestrian%22"), "{head}");
+}
+
+#[test]
+fn valhalla_errors_are_returned() {
+    let body = r#"{"error_code":171,"error":"No suitable edges near location","status_code":400}"#.to_string();
+    let (address, _) = serve_once("400 Bad Request", body);
+    let provider = ValhallaRoutingProvider::new(Url::parse(&format!("http://{address}/")).unwrap());
+    let err = provider.route(&request(RouteCosting::Motorbike)).unwrap_err();
+    assert!(format!("{err:#}").contains("No suitable edges"), "{err:#}");
+}
+
+#[test]
+fn valhalla_maneuvers_outside_of_the_shape_are_rejected() {
+    let mut body: serde_json::Value = serde_json::from_str(&fixture_body("guidance", "route.json")).unwrap();
+    body["trip"]["legs"][0]["maneuvers"][2]["begin_shape_index"] = 1000.into();
+    let (address, _) = serve_once("200 OK", body.to_string());
+    let provider = ValhallaRoutingProvider::new(Url::parse(&format!("http://{address}/")).unwrap());
+    let err = provider.route(&request(RouteCosting::Motorbike)).unwrap_err();
+    assert!(format!("{err:#}").contains("outside of the route shape"), "{err:#}");
+}
+
+#[test]
+fn osrm_steps_become_maneuvers() {
+    let (address, head) = serve_once("200 OK", fixture_body("routing", "osrm_route.json"));
+    let provider = OsrmRoutingProvider::new(Url::parse(&format!("http://{address}/")).unwrap())
+        .with_header("Authorization", "Bearer token");
+    let routes = provider.route(&request(RouteCosting::Motorbike).with_alternatives(2)).unwrap();
+    assert_cross_2_route(&routes[0]);
+    assert_eq!(routes[0].costing, RouteCosting::Motorbike);
+
+    let head = head.recv().unwrap();
+    assert!(head.starts_with("GET /route/v1/driving/139.753,35.688;139.757,35.6902?"), "{head}");
+    assert!(head.contains("alternatives=2"), "{head}");
+    assert!(head.contains("Authorization: Bearer token"), "{head}");
+}
+
+#[test]
+fn osrm_errors_are_returned() {
+    let body = r#"{"code":"NoRoute","message":"Impossible route between points"}"#.to_string();
+    let (address, _) = serve_once("400 Bad Request", body);
+    let provider = OsrmRoutingProvider::new(Url::parse(&format!("http://{address}/")).unwrap());
+    let err = provider.route(&request(RouteCosting::Pedestrian)).unwrap_err();
+    assert!(format!("{err:#}").contains("NoRoute"), "{err:#}");
+}
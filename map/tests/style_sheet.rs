@@ -0,0 +1,151 @@
+use map::style::style_sheet::{Color, FilterValue, LABEL_STYLE_ID, StyleSheet, ZoomValue};
+use renderer::styles::render_style::RenderStyle;
+use renderer::styles::style_id::StyleId;
+use std::collections::HashMap;
+
+const LABELS: &str = r##"
+[styles.label_station]
+text = true
+fill = "#cc2233"
+halo = "#ffffff"
+halo_width = 2.0
+max_width = 8.0
+
+[[layers]]
+kind = "poi"
+type = "symbol"
+text = { style = "label_station" }
+
+[[layers]]
+kind = "place"
+type = "symbol"
+text = {}
+"##;
+
+#[test]
+fn text_rules_reference_label_styles() {
+    let style_sheet = StyleSheet::from_toml(LABELS).unwrap();
+    let text_styles: Vec<StyleId> = style_sheet
+        .layers
+        .iter()
+        .map(|layer| layer.text.as_ref().unwrap().style_id())
+        .collect();
+    assert_eq!(text_styles, [StyleId("label_station"), LABEL_STYLE_ID]);
+
+    let styles = style_sheet.render_styles();
+    let (_, station) = styles.iter().find(|(id, _)| *id == StyleId("label_station")).unwrap();
+    assert!(station.text);
+    assert_eq!(station.halo_width, Some(2.0));
+}
+
+#[test]
+fn default_sheet_declares_the_label_styles() {
+    let style_sheet = StyleSheet::default_sheet();
+    let styles = style_sheet.render_styles();
+    style_sheet
+        .layers
+        .iter()
+        .filter_map(|layer| layer.text.as_ref())
+        .for_each(|text| {
+            let style_id = text.style_id();
+            assert!(
+                styles.iter().any(|(id, style)| *id == style_id && style.text),
+                "{style_id:?} isn't a text style"
+            );
+        });
+}
+
+#[test]
+fn stations_outrank_the_other_pois() {
+    let style_sheet = StyleSheet::default_sheet();
+    let rank = |class: &str| {
+        style_sheet
+            .layers
+            .iter()
+            .find(|layer| {
+                layer.kind == "poi"
+                    && matches!(layer.filter.get("class"), Some(FilterValue::One(value)) if value == class)
+            })
+            .and_then(|layer| layer.rank)
+    };
+    assert!(rank("train_station") > rank("parking"));
+    assert!(rank("parking") > rank("other"));
+}
+
+const ROADS_JSON: &str = r##"{
+    "styles": {
+        "road": { "fill": [[0, "#ff000080"], [10, [0.0, 0.0, 1.0, 1.0]]], "width": 2.0 }
+    },
+    "layers": [
+        { "kind": "highway", "type": "line", "style": "road", "filter": { "class": ["primary", "trunk"], "!tunnel": "*" },
+          "min_zoom": 2, "max_zoom": 8 }
+    ]
+}"##;
+
+#[test]
+fn json_rules_match_tags_and_zoom() {
+    let style_sheet = StyleSheet::from_json(ROADS_JSON).unwrap();
+    let layer = &style_sheet.layers[0];
+    assert_eq!(layer.style_id(), Some(StyleId("road")));
+
+    let tags = |pairs: &[(&'static str, &str)]| -> HashMap<&'static str, String> {
+        pairs.iter().map(|(key, value)| (*key, value.to_string())).collect()
+    };
+    assert!(layer.matches("highway", &tags(&[("class", "trunk")]), 5));
+    assert!(!layer.matches("highway", &tags(&[("class", "residential")]), 5));
+    assert!(!layer.matches("highway", &tags(&[("class", "trunk"), ("tunnel", "yes")]), 5));
+    assert!(!layer.matches("highway", &tags(&[("class", "trunk")]), 1));
+    assert!(!layer.matches("highway", &tags(&[("class", "trunk")]), 9));
+    assert!(!layer.matches("railway", &tags(&[("class", "trunk")]), 5));
+
+    let styles = style_sheet.render_styles();
+    let fill = styles[0].1.fill.as_ref().unwrap();
+    assert_eq!(fill.eval(0.0), Some(Color([1.0, 0.0, 0.0, 128.0 / 255.0])));
+    assert_eq!(fill.eval(5.0), Some(Color([0.5, 0.0, 0.5, (128.0 / 255.0 + 1.0) / 2.0])));
+    assert_eq!(fill.eval(20.0), Some(Color([0.0, 0.0, 1.0, 1.0])));
+    assert_eq!(styles[0].1.width, Some(ZoomValue::Fixed(2.0)));
+}
+
+#[test]
+fn invalid_documents_are_rejected() {
+    let invalid = [
+        // a line without style
+        "[[layers]]\nkind = \"highway\"\ntype = \"line\"",
+        // a symbol without icon and text
+        "[[layers]]\nkind = \"poi\"\ntype = \"symbol\"",
+        // an icon without style isn't drawn
+        "[[layers]]\nkind = \"poi\"\ntype = \"symbol\"\nicon = \"parking\"",
+        "[styles.road]\nfill = \"#12345\"",
+        "[styles.road]\nfill = \"#gg0000\"",
+        "[styles.road]\ncolour = \"#ff0000\"",
+        "[[layers]]\nkind = \"highway\"\ntype = \"circle\"\nstyle = \"road\"",
+    ];
+    invalid.iter().for_each(|document| {
+        assert!(StyleSheet::from_toml(document).is_err(), "{document} is accepted");
+    });
+    assert!(StyleSheet::from_json("{\"layers\": [{\"kind\": \"poi\"}]}").is_err());
+
+    let with_style = "[[layers]]\nkind = \"poi\"\ntype = \"symbol\"\nicon = \"parking\"\nstyle = \"poi_parking\"";
+    assert!(StyleSheet::from_toml(with_style).is_ok());
+}
+
+#[test]
+fn route_progress_is_kept_when_styles_are_applied_again() {
+    let style_sheet = StyleSheet::from_toml("[styles.route]\nfill = \"#3366ff\"\ntraveled = \"#999999\"").unwrap();
+    let styles = style_sheet.render_styles();
+    let (_, route) = styles.iter().find(|(id, _)| *id == StyleId("route")).unwrap();
+
+    let mut style = RenderStyle::fill([1.0; 4]);
+    route.apply(&mut style);
+    let traveled = style.traveled_color().unwrap();
+    style = style.with_progress(120.0);
+    route.apply(&mut style);
+    assert_eq!(style.progress(), 120.0);
+    assert_eq!(style.traveled_color(), Some(traveled));
+
+    // a style without the traveled color keeps the one the route has
+    let plain = StyleSheet::from_toml("[styles.route]\nfill = \"#3366ff\"").unwrap().render_styles();
+    plain[0].1.apply(&mut style);
+    assert_eq!(style.progress(), 120.0);
+    assert_eq!(style.traveled_color(), Some(traveled));
+}
@@ -0,0 +1,102 @@
+//! Local tile sources: a `{zoom}/{x}/{y}.tile` directory and the pack file exported from it.
+
+use geo_types::{Rect, coord};
+use map::tiles::source::MapTileSource;
+use map::tiles::source::dir_source::DirTileSource;
+use map::tiles::source::pack_export::{export_pack, tile_keys};
+use map::tiles::source::pack_source::PackTileSource;
+use osm::source::TileSource;
+use osm::tiles::TileKey;
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shashlik_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn tile_data(tile_key: &TileKey) -> Vec<u8> {
+    format!("tile {} {} {}", tile_key.zoom_level, tile_key.tile_x, tile_key.tile_y).into_bytes()
+}
+
+fn write_tile(source: &DirTileSource, tile_key: &TileKey) {
+    let path = source.tile_path(tile_key);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, tile_data(tile_key)).unwrap();
+}
+
+#[test]
+fn dir_source_reads_tiles_by_zoom_x_y() {
+    let root = temp_dir("dir_source");
+    let source = DirTileSource::new(&root);
+    let tile_key = TileKey {
+        tile_x: 5,
+        tile_y: 7,
+        zoom_level: 3,
+    };
+    write_tile(&source, &tile_key);
+
+    assert_eq!(source.tile_path(&tile_key), root.join("3").join("5").join("7.tile"));
+    assert_eq!(source.load(&tile_key), Some(tile_data(&tile_key)));
+    assert_eq!(source.load(&TileKey { tile_y: 8, ..tile_key }), None);
+    assert!(matches!(MapTileSource::local(&root), Ok(MapTileSource::Dir(_))));
+}
+
+#[test]
+fn exported_pack_reads_back_the_same_tiles() {
+    let root = temp_dir("pack_export");
+    let source = DirTileSource::new(root.join("tiles"));
+    let area = Rect::new(coord! { x: 139.70, y: 35.65 }, coord! { x: 139.80, y: 35.72 });
+    let keys = tile_keys(&area, 0..=2);
+    // the source has no data for the first tile
+    keys.iter().skip(1).for_each(|tile_key| write_tile(&source, tile_key));
+
+    let pack_path = root.join("tiles.db");
+    let summary = export_pack(&source, &area, 0..=2, &pack_path).unwrap();
+    assert_eq!(summary.exported, keys.len() - 1);
+    assert_eq!(summary.missing, 1);
+
+    let pack = PackTileSource::open(&pack_path).unwrap();
+    assert_eq!(pack.tiles_count(), keys.len() - 1);
+    assert!(!pack.contains(&keys[0]));
+    assert_eq!(pack.load(&keys[0]), None);
+    keys.iter().skip(1).for_each(|tile_key| {
+        assert_eq!(pack.load(tile_key), Some(tile_data(tile_key)));
+    });
+    assert!(matches!(MapTileSource::local(&pack_path), Ok(MapTileSource::Pack(_))));
+}
+
+#[test]
+fn broken_packs_are_rejected() {
+    let root = temp_dir("pack_broken");
+    let not_a_pack = root.join("not_a_pack.db");
+    fs::write(&not_a_pack, b"SQLite format 3\0").unwrap();
+    assert!(PackTileSource::open(&not_a_pack).is_err());
+
+    let source = DirTileSource::new(root.join("tiles"));
+    let area = Rect::new(coord! { x: 139.70, y: 35.65 }, coord! { x: 139.80, y: 35.72 });
+    tile_keys(&area, 0..=0).iter().for_each(|tile_key| write_tile(&source, tile_key));
+    let pack_path = root.join("tiles.db");
+    export_pack(&source, &area, 0..=0, &pack_path).unwrap();
+    let data = fs::read(&pack_path).unwrap();
+    let truncated = root.join("truncated.db");
+    fs::write(&truncated, &data[..data.len() - 4]).unwrap();
+    assert!(PackTileSource::open(&truncated).is_err());
+
+    // offsets that overflow when the bounds are checked
+    let footer = data.len() - 12;
+    let mut huge_index = data.clone();
+    huge_index[footer..footer + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    let huge_index_path = root.join("huge_index.db");
+    fs::write(&huge_index_path, &huge_index).unwrap();
+    assert!(PackTileSource::open(&huge_index_path).is_err());
+
+    let entry = footer - 24;
+    let mut huge_entry = data.clone();
+    huge_entry[entry + 12..entry + 20].copy_from_slice(&(u64::MAX - 1).to_le_bytes());
+    let huge_entry_path = root.join("huge_entry.db");
+    fs::write(&huge_entry_path, &huge_entry).unwrap();
+    assert!(PackTileSource::open(&huge_entry_path).is_err());
+}
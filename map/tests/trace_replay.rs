@@ -0,0 +1,117 @@
+//! Loads and replays the drive along "Main Avenue" of the map-matching fixtures.
+//!
+//! `fixtures/traces/avenue.gpx` and `avenue.nmea` are the same five fixes 5 seconds and ~45m apart
+//! heading east, the NMEA log also has a sentence with a broken checksum which must be skipped.
+
+use map::location::LocationSource;
+use map::location::recorder::TraceRecorder;
+use map::location::replay::TraceReplayer;
+use map::location::trace::Trace;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const START_LON: f64 = 139.7570;
+const LON_STEP: f64 = 0.0005;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("traces")
+        .join(name)
+}
+
+fn assert_avenue(trace: &Trace) {
+    assert_eq!(trace.fixes().len(), 5);
+    assert!((trace.duration() - 20.0).abs() < 1e-6, "Duration {}", trace.duration());
+    trace.fixes().iter().enumerate().for_each(|(i, fix)| {
+        assert!((fix.lat - 35.688).abs() < 1e-6);
+        assert!((fix.lon - (START_LON + LON_STEP * i as f64)).abs() < 1e-6);
+    });
+}
+
+#[test]
+fn gpx_and_nmea_are_the_same_drive() {
+    let gpx = Trace::load(fixture("avenue.gpx")).unwrap();
+    assert_avenue(&gpx);
+    let nmea = Trace::load(fixture("avenue.nmea")).unwrap();
+    assert_avenue(&nmea);
+
+    // 17.5 knots
+    let speed = nmea.fixes()[0].speed.unwrap();
+    assert!((speed - 9.0).abs() < 0.1, "Speed {speed}");
+}
+
+#[test]
+fn corrupted_nmea_lines_are_skipped() {
+    let document = std::fs::read_to_string(fixture("avenue.nmea")).unwrap();
+    // lines without a checksum aren't verified, non-ASCII bytes can't be split by position
+    let corrupted = format!(
+        "$GPRMC,1é000.00,A,3541.280000,N,13945.420000,E,17.5,90.0,010524,,,A\n\
+         $GPRMC,100000.00,A,3541.280000,N,13945.420000,E,17.5,90.0,0é524,,,A\n\
+         $GPGGA,10é00.00,3541.280000,N,13945.420000,E,1,08,0.9,40.0,M,39.0,M,,\n{document}"
+    );
+    assert_avenue(&Trace::from_nmea(&corrupted).unwrap());
+}
+
+#[test]
+fn positions_between_fixes_are_interpolated() {
+    let trace = Trace::load(fixture("avenue.gpx")).unwrap();
+    let fix = trace.interpolate(7.5);
+    assert!((fix.lon - (START_LON + LON_STEP * 1.5)).abs() < 1e-6);
+    // the GPX has no course and speed, they come from the movement
+    assert!((fix.bearing.unwrap() - 90.0).abs() < 0.1);
+    assert!((fix.speed.unwrap() - 9.0).abs() < 0.1);
+}
+
+#[test]
+fn replay_follows_speed_pause_and_seek() {
+    let mut replayer = TraceReplayer::new(Trace::load(fixture("avenue.gpx")).unwrap());
+    let start = Instant::now();
+    let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+
+    let first = replayer.poll(at(0.0)).unwrap();
+    assert!((first.lon - START_LON).abs() < 1e-9);
+    // too soon for the next fix
+    assert!(replayer.poll(at(0.1)).is_none());
+
+    replayer.set_speed(2.0);
+    let fix = replayer.poll(at(2.6)).unwrap();
+    assert!((replayer.position() - 5.1).abs() < 1e-6);
+    assert!((fix.speed.unwrap() - 18.0).abs() < 0.2, "Speed {:?}", fix.speed);
+
+    replayer.set_paused(true);
+    let paused = replayer.poll(at(10.0)).unwrap();
+    assert!((replayer.position() - 5.1).abs() < 1e-6);
+    assert_eq!(paused.speed, Some(0.0));
+
+    replayer.set_paused(false);
+    replayer.seek(19.0);
+    replayer.poll(at(11.0)).unwrap();
+    assert!(replayer.is_finished());
+    assert!((replayer.poll(at(12.0)).unwrap().lon - (START_LON + LON_STEP * 4.0)).abs() < 1e-9);
+}
+
+#[test]
+fn recorded_trace_replays() {
+    let path = std::env::temp_dir().join(format!("shashlik_recorded_{}.nmea", std::process::id()));
+    let original = Trace::load(fixture("avenue.gpx")).unwrap();
+    let mut recorder = TraceRecorder::create(&path).unwrap();
+    original.fixes().iter().for_each(|fix| {
+        let mut fix = *fix;
+        // 2024-05-01T10:00:00Z
+        fix.time += 1714557600.0;
+        fix.bearing = Some(90.0);
+        fix.speed = Some(9.0);
+        recorder.record(&fix).unwrap();
+    });
+    drop(recorder);
+
+    let recorded = Trace::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_avenue(&recorded);
+    recorded.fixes().iter().for_each(|fix| {
+        assert_eq!(fix.bearing, Some(90.0));
+        assert!((fix.speed.unwrap() - 9.0).abs() < 0.01);
+    });
+}
@@ -1,3 +1,4 @@
+use crate::collision_handler::CollisionKey;
 use crate::draw_commands::mesh2d_draw_command::Mesh2dDrawCommand;
 use crate::draw_commands::mesh3d_draw_command::Mesh3dDrawCommand;
 use crate::draw_commands::text_draw_command::TextDrawCommand;
@@ -14,8 +15,10 @@ use lyon::lyon_tessellation::{
     BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeOptions,
     StrokeTessellator, StrokeVertex, VertexBuffers,
 };
-use lyon::path::Path;
+use lyon::path::{Path, Side};
+use rustc_hash::FxHasher;
 use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 use std::mem;
 use std::ops::Range;
 
@@ -23,6 +26,8 @@ use std::ops::Range;
 pub struct ScreenPaths {
     pub positions: Vec<Vector3<f64>>,
     pub with_collision: bool,
+    // the collision key and rank of every position
+    pub(crate) collisions: Vec<(CollisionKey, i32)>,
 }
 
 pub struct CanvasApi {
@@ -33,7 +38,8 @@ pub struct CanvasApi {
     indices_by_layers: BTreeMap<i8, Vec<Range<usize>>>,
     real_layer: usize,
     geometry3d: VertexBuffers<MeshVertex, u32>,
-    text_vec: Vec<TextData>,
+    // with the style indices
+    text_vec: Vec<(TextData, usize)>,
     screen_path_cache: HashMap<&'static str, (VertexBuffers<ShapeVertex, u32>, ScreenPaths)>,
     feature_layer_tag: Option<String>,
 }
@@ -68,6 +74,7 @@ impl CanvasApi {
             .for_each(|(_, (_, screen_paths))| {
                 // keep only buffers, clean positions
                 screen_paths.positions.clear();
+                screen_paths.collisions.clear();
                 screen_paths.with_collision = false
             })
     }
@@ -123,6 +130,7 @@ impl CanvasApi {
             let screen_paths = ScreenPaths {
                 positions: vec![Vector3::new(0.0, 0.0, 0.0)],
                 with_collision: false,
+                collisions: vec![],
             };
             self.mesh2d_with_positions(mesh, flatten_ranges, screen_paths, is_screen);
         }
@@ -216,14 +224,20 @@ impl CanvasApi {
         let initial_index = self.geometry.indices.len();
         match geom_type {
             GeometryType::Polyline(options) => {
+                let width = options.width;
                 self.tessellate_stroke_path(
                     &data.path,
                     options,
                     |vertex| ShapeVertex {
                         position: [vertex.position().x, vertex.position().y, 0.0f32],
-                        normals: [vertex.normal().x, vertex.normal().y, 0.0],
+                        // z is the side of the line for the patterns across it, e.g. chevrons
+                        normals: [vertex.normal().x, vertex.normal().y, match vertex.side() {
+                            Side::Positive => 1.0,
+                            Side::Negative => -1.0,
+                        }],
                         dist: vertex.advancement(),
                         style_index: style_index as u32,
+                        width,
                     },
                 );
             }
@@ -233,6 +247,7 @@ impl CanvasApi {
                     normals: [0.0, 0.0, 0.0],
                     dist: 0.0, // fill doesn't have length
                     style_index: style_index as u32,
+                    width: 0.0,
                 });
             }
         }
@@ -259,10 +274,20 @@ impl CanvasApi {
     }
 
     fn svg(&mut self, data: SvgData) {
+        let collision_key = data.symbol_id.map_or_else(
+            || {
+                let mut hasher = FxHasher::default();
+                data.icon.0.hash(&mut hasher);
+                [data.position.x, data.position.y].map(f64::to_bits).hash(&mut hasher);
+                CollisionKey::Icon(hasher.finish())
+            },
+            CollisionKey::Symbol,
+        );
         self.screen_path_cache
             .entry(data.icon.0)
             .and_modify(|(_, screen_paths)| {
                 screen_paths.positions.push(data.position);
+                screen_paths.collisions.push((collision_key, data.rank));
                 screen_paths.with_collision = data.with_collision
             })
             .or_insert_with(|| {
@@ -273,13 +298,15 @@ impl CanvasApi {
                     ScreenPaths {
                         positions: vec![data.position],
                         with_collision: data.with_collision,
+                        collisions: vec![(collision_key, data.rank)],
                     },
                 )
             });
     }
 
     pub fn text(&mut self, data: TextData) {
-        self.text_vec.push(data);
+        let style_index = self.style_store.get_index(&data.style_id);
+        self.text_vec.push((data, style_index));
     }
 
     pub(crate) fn flush(&mut self) {
@@ -1,17 +1,43 @@
 use geo_types::Point;
 use rstar::primitives::Rectangle;
 use rstar::{Envelope, RTree, RTreeObject, AABB};
+use rustc_hash::{FxHashMap, FxHashSet};
 
+/// Identity of an icon or a label across the frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CollisionKey {
+    /// The icon and the label of a symbol, they are placed or hidden together
+    Symbol(u64),
+    Text(u64),
+    Icon(u64),
+}
+
+/// A collision rectangle and whether it was placed
+pub(crate) type DebugRect = (Rectangle<Point<f32>>, bool);
+
+struct Candidate {
+    rank: i32,
+    rects: Vec<Rectangle<Point<f32>>>,
+}
+
+/// Icons and labels are submitted during the frame and placed together at its end, the higher rank first,
+/// so the result doesn't depend on the order the tiles arrived in. The nodes fade their items with the
+/// result of the previous frame.
 pub struct CollisionHandler {
-    objects: RTree<Rectangle<Point<f32>>>,
+    candidates: FxHashMap<CollisionKey, Candidate>,
+    placed: FxHashSet<CollisionKey>,
     screen_rect: Rectangle<Point<f32>>,
+    // the rectangles of the last placement
+    debug_rects: Option<Vec<DebugRect>>,
 }
 
 impl CollisionHandler {
     pub fn new(width: f32, height: f32) -> Self {
         CollisionHandler {
-            objects: RTree::new(),
+            candidates: FxHashMap::default(),
+            placed: FxHashSet::default(),
             screen_rect: Rectangle::from_corners(Point::new(0.0, 0.0), Point::new(width, height)),
+            debug_rects: None,
         }
     }
 
@@ -27,41 +53,61 @@ impl CollisionHandler {
         self.screen_rect.envelope().intersects(&envelope)
     }
 
-    pub fn insert(&mut self, rectangle: Rectangle<Point<f32>>) -> bool {
-        let envelope = rectangle.envelope();
-        let count = self
-            .objects
-            .locate_in_envelope_intersecting(&envelope)
-            .count();
-        if count > 0 {
-            return false;
-        }
-
-        self.objects.insert(rectangle);
-        true
+    /// Submits the rectangles for the placement at the end of the frame, the rectangles with the same key
+    /// are placed as one. Returns whether they were placed by the last placement.
+    pub(crate) fn insert(
+        &mut self,
+        key: CollisionKey,
+        rank: i32,
+        rectangles: impl IntoIterator<Item = Rectangle<Point<f32>>>,
+    ) -> bool {
+        let candidate = self.candidates.entry(key).or_insert(Candidate {
+            rank,
+            rects: vec![],
+        });
+        candidate.rank = candidate.rank.max(rank);
+        candidate.rects.extend(rectangles);
+        self.placed.contains(&key)
     }
 
-    pub fn insert_rectangles(&mut self, rectangles: Vec<Rectangle<Point<f32>>>) -> bool {
-        let envelopes: Vec<AABB<Point<f32>>> =
-            rectangles.iter().map(|rect| rect.envelope()).collect();
+    /// Places the candidates of the frame, the ones placed before win the ties, so the equal ones don't flicker.
+    pub fn place(&mut self) {
+        let mut candidates: Vec<(CollisionKey, Candidate)> = self.candidates.drain().collect();
+        candidates.sort_by(|(key1, candidate1), (key2, candidate2)| {
+            candidate2
+                .rank
+                .cmp(&candidate1.rank)
+                .then_with(|| self.placed.contains(key2).cmp(&self.placed.contains(key1)))
+                .then_with(|| key1.cmp(key2))
+        });
 
-        for envelope in &envelopes {
-            let count = self
-                .objects
-                .locate_in_envelope_intersecting(envelope)
-                .count();
-            if count > 0 {
-                return false;
+        let mut objects = RTree::new();
+        self.placed.clear();
+        if let Some(debug_rects) = self.debug_rects.as_mut() {
+            debug_rects.clear();
+        }
+        for (key, candidate) in candidates {
+            let envelopes: Vec<AABB<Point<f32>>> =
+                candidate.rects.iter().map(|rect| rect.envelope()).collect();
+            let placed = envelopes
+                .iter()
+                .all(|envelope| objects.locate_in_envelope_intersecting(envelope).next().is_none());
+            if let Some(debug_rects) = self.debug_rects.as_mut() {
+                debug_rects.extend(candidate.rects.iter().map(|rect| (*rect, placed)));
+            }
+            if placed {
+                self.placed.insert(key);
+                candidate.rects.into_iter().for_each(|rect| objects.insert(rect));
             }
         }
+    }
 
-        rectangles.into_iter().for_each(|rect| {
-            self.objects.insert(rect);
-        });
-        true
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug_rects = debug.then(Vec::new);
     }
 
-    pub fn clear(&mut self) {
-        self.objects = RTree::new();
+    /// The rectangles of the last placement and whether they were placed, None unless the debug is on.
+    pub(crate) fn debug_rects(&self) -> Option<&[DebugRect]> {
+        self.debug_rects.as_deref()
     }
 }
@@ -1,2 +1,11 @@
 // TODO Push constant to Shader?
-pub(crate) const STYLE_SHADER_PARAMS_COUNT: usize = 12;
+pub(crate) const STYLE_SHADER_PARAMS_COUNT: usize = 120;
+/// Max stops of every zoom interpolated style property
+pub(crate) const STYLE_MAX_ZOOM_STOPS: usize = 8;
+/// Background of the map until a theme sets another one
+pub(crate) const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.0,
+    g: 0.741,
+    b: 0.961,
+    a: 1.0,
+};
@@ -34,7 +34,9 @@ impl DrawCommand for Mesh2dDrawCommand {
             mem::take(&mut self.screen_paths.positions),
             0.0,
             spatial_rx, self.outlined,
-            self.screen_paths.with_collision,
+            self.screen_paths
+                .with_collision
+                .then(|| mem::take(&mut self.screen_paths.collisions)),
         );
 
         if let Some(feature_layer) = self.feature_layer_tag.as_ref()
@@ -8,7 +8,7 @@ use wgpu::Device;
 
 #[derive(Clone)]
 pub(crate) struct TextDrawCommand {
-    pub data: Vec<TextData>,
+    pub data: Vec<(TextData, usize)>,
 }
 
 impl DrawCommand for TextDrawCommand {
@@ -38,7 +38,11 @@ pub struct SvgData {
     pub position: Vector3<f64>,
     pub size: f32,
     pub style_id: StyleId,
-    pub with_collision: bool
+    pub with_collision: bool,
+    /// Colliding icons and labels with the higher rank are placed first
+    pub rank: i32,
+    /// An icon and a label with the same symbol id are placed or hidden together
+    pub symbol_id: Option<u64>,
 }
 
 #[derive(Clone)]
@@ -47,5 +51,11 @@ pub struct TextData {
     pub text: String,
     pub screen_offset: Vector2<f32>,
     pub size: f32,
-    pub positions: Vec<Vector3<f32>>
+    pub positions: Vec<Vector3<f32>>,
+    /// Text style of the label, a style which isn't one keeps the default look
+    pub style_id: StyleId,
+    /// Colliding icons and labels with the higher rank are placed first
+    pub rank: i32,
+    /// An icon and a label with the same symbol id are placed or hidden together
+    pub symbol_id: Option<u64>,
 }
\ No newline at end of file
@@ -1,12 +1,14 @@
 extern crate core;
 
 use crate::collision_handler::CollisionHandler;
+use crate::consts::{DEFAULT_CLEAR_COLOR, STYLE_SHADER_PARAMS_COUNT};
 use crate::depth_texture::DepthTexture;
 use crate::layers::Layers;
 use crate::messages::RendererMessage;
 use crate::msaa_texture::MultisampledTexture;
 use crate::nodes::SceneNode;
 use crate::nodes::camera_node::CameraNode;
+use crate::nodes::collision_debug_node::CollisionDebugNode;
 use crate::nodes::feature_layers::FeatureLayers;
 use crate::nodes::fps_node::FpsNode;
 use crate::nodes::mesh_layer::MeshLayer;
@@ -32,7 +34,8 @@ use std::sync::mpsc::{Receiver, Sender, channel};
 use std::thread::spawn;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::error::TryRecvError;
-use wgpu::{CompareFunction, DepthStencilState, Face, SurfaceError, TextureFormat, include_wgsl};
+use wgpu::{CompareFunction, DepthStencilState, Face, SurfaceError, Texture, TextureFormat, include_wgsl};
+use wgpu_canvas::texture_canvas::read_rgba;
 use wgpu_canvas::wgpu_canvas::WgpuCanvas;
 
 pub mod canvas_api;
@@ -57,6 +60,8 @@ mod text;
 pub mod vertex_attrs;
 mod view_projection;
 
+pub use text::{TextBackend, TextHalo};
+
 pub const SHADER_STYLE_GROUP_INDEX: u32 = 1;
 
 pub trait Renderer {
@@ -89,11 +94,14 @@ pub struct GlobalContext {
 }
 
 impl GlobalContext {
-    pub fn new(collision_handler: CollisionHandler, device: &wgpu::Device) -> Self {
+    pub fn new(
+        collision_handler: CollisionHandler,
+        style_uniform_rx: tokio::sync::broadcast::Receiver<Vec<[f32; STYLE_SHADER_PARAMS_COUNT]>>,
+    ) -> Self {
         GlobalContext {
             view_projection: ViewProjection::new(),
             collision_handler,
-            text_renderer: TextRenderer::new(device),
+            text_renderer: TextRenderer::new(style_uniform_rx),
         }
     }
 }
@@ -108,6 +116,8 @@ pub struct ShashlikRenderer {
     pub api: Arc<RendererApi>,
     global_context: GlobalContext,
     fps_node: FpsNode, // FIXME FPS should part of text rendering or proper layer system
+    fps_visible: bool,
+    clear_color: wgpu::Color,
 }
 
 impl ShashlikRenderer {
@@ -145,9 +155,11 @@ impl ShashlikRenderer {
             multisample_state.clone(),
         );
 
+        let style_store = StyleStore::new();
+
         let mut global_context = GlobalContext::new(
             CollisionHandler::new(config.width as f32, config.height as f32),
-            device,
+            style_store.subscribe(),
         );
         global_context
             .view_projection
@@ -158,8 +170,6 @@ impl ShashlikRenderer {
             multisample_state.clone(),
         );
 
-        let style_store = StyleStore::new();
-
         let shape_layers = ShapeLayers::new(
             device,
             pipeline_provider.clone(),
@@ -214,7 +224,10 @@ impl ShashlikRenderer {
             .borrow_mut()
             .add_child_with_key(text_layer, "text_layer".to_string());
 
-        camera_node.borrow_mut().add_child_with_key(TextRendererLayer {}, "text_renderer_layer".to_string());
+        camera_node.borrow_mut().add_child_with_key(
+            TextRendererLayer::new(device, pipeline_provider.clone()),
+            "text_renderer_layer".to_string(),
+        );
 
         let feature_layers = FeatureLayers::new(
             feature_tags,
@@ -224,6 +237,12 @@ impl ShashlikRenderer {
             &style_store,
         );
 
+        // on top of everything
+        camera_node.borrow_mut().add_child_with_key(
+            CollisionDebugNode::new(device, pipeline_provider.clone()),
+            "collision_debug".to_string(),
+        );
+
         let mut render_context = RenderContext::default();
         world_tree_node.setup(&mut render_context, &device);
 
@@ -250,6 +269,8 @@ impl ShashlikRenderer {
             api,
             global_context,
             fps_node,
+            fps_visible: true,
+            clear_color: DEFAULT_CLEAR_COLOR,
         })
     }
 
@@ -295,6 +316,9 @@ impl ShashlikRenderer {
                             .send(RendererMessage::ClearGroups(keys))
                             .unwrap();
                     }
+                    RendererApiMsg::Sync => {
+                        renderer_tx.send(RendererMessage::Synced).unwrap();
+                    }
                 }
             }
         });
@@ -306,7 +330,7 @@ impl ShashlikRenderer {
 
     pub fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
-            self.canvas.on_resize();
+            self.canvas.on_resize(width, height);
             let config = self.canvas.config();
             let device = self.canvas.device();
             let queue = self.canvas.queue();
@@ -327,35 +351,115 @@ impl ShashlikRenderer {
         }
     }
 
+    /// Fractional map zoom level, the style zoom stops are interpolated with it on the GPU
+    pub fn set_zoom_level(&mut self, zoom_level: f32) {
+        self.global_context.view_projection.set_zoom_level(zoom_level);
+    }
+
+    /// Background of the map, e.g. for a night theme
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = wgpu::Color {
+            r: color[0] as f64,
+            g: color[1] as f64,
+            b: color[2] as f64,
+            a: color[3] as f64,
+        };
+    }
+
+    pub fn set_text_color(&mut self, color: [f32; 4]) {
+        self.global_context.view_projection.set_text_color(color);
+    }
+
+    /// Adds a TrueType or OpenType font to the end of the fallback chain of the labels,
+    /// e.g. a CJK, Cyrillic or Arabic font for the characters the embedded Latin font doesn't have.
+    pub fn add_font(&mut self, data: Vec<u8>) -> anyhow::Result<()> {
+        self.global_context.text_renderer.add_font(data)
+    }
+
+    /// Switches between the glyph meshes and the signed distance field atlas for the labels.
+    pub fn set_text_backend(&mut self, backend: TextBackend) {
+        self.global_context.text_renderer.set_backend(backend);
+    }
+
+    /// Outline of the labels, only the [TextBackend::Sdf] backend draws it.
+    pub fn set_text_halo(&mut self, halo: TextHalo) {
+        self.global_context.text_renderer.set_halo(halo);
+    }
+
+    /// Outlines the collision rectangles of the labels and icons, the hidden ones in red.
+    pub fn set_collision_debug(&mut self, debug: bool) {
+        self.global_context.collision_handler.set_debug(debug);
+    }
+
+    pub fn set_fps_visible(&mut self, visible: bool) {
+        self.fps_visible = visible;
+    }
+
+    /// Blocks until every render group sent through the api so far is turned into draw commands
+    /// and applies them at once, so the next frame doesn't depend on the background thread timing.
+    pub fn apply_pending_render_groups(&mut self) {
+        self.api.request_sync();
+        while let Ok(message) = self.renderer_rx.recv() {
+            if self.apply_message(message) {
+                break;
+            }
+        }
+    }
+
+    // returns true when the message is a sync marker
+    fn apply_message(&mut self, message: RendererMessage) -> bool {
+        let device = self.canvas.device();
+        match message {
+            RendererMessage::Draw(mut draw_commands) => {
+                draw_commands.execute(&device, &mut self.layers);
+            }
+            RendererMessage::ClearGroups(keys) => {
+                keys.into_iter().for_each(|key| {
+                    self.layers.clear(key);
+                });
+            }
+            RendererMessage::Synced => return true,
+        }
+        false
+    }
+
     fn update(&mut self, view_proj_matrix: Matrix4<f64>, cs_offset: Vector3<f64>) {
         self.global_context.view_projection.update(view_proj_matrix, cs_offset);
-        let device = self.canvas.device();
         if let Ok(message) = self.renderer_rx.try_recv() {
-            match message {
-                RendererMessage::Draw(mut draw_commands) => {
-                    draw_commands.execute(&device, &mut self.layers);
-                }
-                RendererMessage::ClearGroups(keys) => {
-                    keys.into_iter().for_each(|key| {
-                        self.layers.clear(key);
-                    });
-                }
-            }
+            self.apply_message(message);
         }
 
         let queue = self.canvas.queue();
         let device = self.canvas.device();
         let config = self.canvas.config();
+        self.global_context.text_renderer.update_styles();
         self.world_tree_node
             .update(device, queue, config, &mut self.global_context);
 
-        self.global_context.collision_handler.clear();
+        self.global_context.collision_handler.place();
 
-        self.fps_node
-            .update(device, queue, config, &mut self.global_context);
+        if self.fps_visible {
+            self.fps_node
+                .update(device, queue, config, &mut self.global_context);
+        }
     }
 
     fn render(&mut self) -> Result<(), SurfaceError> {
+        self.render_frame(|_, _| ())
+    }
+
+    /// Renders one frame and reads it back as tightly packed RGBA8 rows.
+    /// The canvas texture has to support `COPY_SRC` and be RGBA8/BGRA8, e.g. `TextureCanvas`.
+    pub fn render_to_rgba(&mut self) -> anyhow::Result<Vec<u8>> {
+        self.render_frame(|canvas, texture| read_rgba(canvas.device(), canvas.queue(), texture))?
+            .map_err(anyhow::Error::msg)
+    }
+
+    /// Renders one frame into the canvas texture, `after_render` gets the texture before it's presented.
+    fn render_frame<R>(
+        &mut self,
+        after_render: impl FnOnce(&dyn WgpuCanvas, &Texture) -> R,
+    ) -> Result<R, SurfaceError> {
         self.canvas.on_pre_render();
         // // We can't render unless the surface is configured
         // if !self.is_surface_configured {
@@ -363,16 +467,23 @@ impl ShashlikRenderer {
         // }
 
         let output = self.canvas.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.render_to_texture(output.texture());
+        let result = after_render(self.canvas.as_ref(), output.texture());
+        output.present();
+
+        self.canvas.on_post_render();
+
+        Ok(result)
+    }
+
+    fn render_to_texture(&mut self, texture: &Texture) {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let device = self.canvas.device();
         let queue = self.canvas.queue();
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
-
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -380,12 +491,7 @@ impl ShashlikRenderer {
                     view: &self.msaa_texture.view,
                     resolve_target: Some(&view),
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.741,
-                            b: 0.961,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
@@ -406,16 +512,13 @@ impl ShashlikRenderer {
             self.world_tree_node
                 .render(&mut render_pass, &mut self.global_context);
 
-            self.fps_node
-                .render(&mut render_pass, &mut self.global_context);
+            if self.fps_visible {
+                self.fps_node
+                    .render(&mut render_pass, &mut self.global_context);
+            }
         }
 
         queue.submit(iter::once(encoder.finish()));
-        output.present();
-
-        self.canvas.on_post_render();
-
-        Ok(())
     }
 }
 
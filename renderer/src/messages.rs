@@ -8,11 +8,13 @@ use crate::styles::style_id::StyleId;
 pub(crate) enum RendererMessage {
     Draw(DrawCommands),
     ClearGroups(HashSet<String>),
+    Synced,
 }
 
 pub enum RendererApiMsg {
     RenderGroup((String, usize, SpatialData, Box<dyn RenderGroup>)),
     UpdateStyle((StyleId, Box<dyn FnOnce(&mut RenderStyle) + Send>)),
     UpdateSpatialData((String, Box<dyn FnOnce(&mut SpatialData) + Send>)),
-    ClearGroups(HashSet<String>)
+    ClearGroups(HashSet<String>),
+    Sync,
 }
\ No newline at end of file
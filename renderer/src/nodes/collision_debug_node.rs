@@ -0,0 +1,118 @@
+use crate::GlobalContext;
+use crate::nodes::SceneNode;
+use crate::nodes::scene_tree::RenderContext;
+use crate::pipeline_provider::PipeLineProvider;
+use crate::vertex_attrs::{ColorVertex, VertexAttrib};
+use wgpu::util::DeviceExt;
+use wgpu::{
+    Buffer, CompareFunction, Device, Queue, RenderPass, RenderPipeline, ShaderModule,
+    SurfaceConfiguration, include_wgsl,
+};
+
+/// Outlines the collision rectangles of the last placement, the placed ones in green and the hidden ones in red.
+pub struct CollisionDebugNode {
+    shader_module: ShaderModule,
+    pipeline_provider: PipeLineProvider,
+    render_pipeline: Option<RenderPipeline>,
+    // capacity and the buffer
+    vertex_buffer: Option<(usize, Buffer)>,
+    vertices_count: usize,
+}
+
+impl CollisionDebugNode {
+    const LINE_WIDTH: f32 = 2.0;
+    const PLACED_COLOR: [f32; 4] = [0.1, 0.8, 0.2, 0.9];
+    const HIDDEN_COLOR: [f32; 4] = [0.9, 0.1, 0.1, 0.9];
+
+    pub fn new(device: &Device, pipeline_provider: PipeLineProvider) -> CollisionDebugNode {
+        CollisionDebugNode {
+            shader_module: device
+                .create_shader_module(include_wgsl!("../shaders/collision_debug_shader.wgsl")),
+            pipeline_provider,
+            render_pipeline: None,
+            vertex_buffer: None,
+            vertices_count: 0,
+        }
+    }
+
+    fn push_quad(vertices: &mut Vec<ColorVertex>, min: [f32; 2], max: [f32; 2], color: [f32; 4]) {
+        let corners = [
+            [min[0], min[1]],
+            [max[0], min[1]],
+            [min[0], max[1]],
+            [min[0], max[1]],
+            [max[0], min[1]],
+            [max[0], max[1]],
+        ];
+        vertices.extend(corners.map(|position| ColorVertex { position, color }));
+    }
+}
+
+impl SceneNode for CollisionDebugNode {
+    fn setup(&mut self, render_context: &mut RenderContext, device: &Device) {
+        render_context.depth_compare = CompareFunction::Always;
+        render_context.can_write_depth = false;
+        self.render_pipeline = Some(self.pipeline_provider.create(
+            device,
+            render_context,
+            &[ColorVertex::desc()],
+            &self.shader_module,
+            None,
+        ));
+    }
+
+    fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        _config: &SurfaceConfiguration,
+        global_context: &mut GlobalContext,
+    ) {
+        let Some(rects) = global_context.collision_handler.debug_rects() else {
+            self.vertices_count = 0;
+            return;
+        };
+        let w = Self::LINE_WIDTH;
+        let mut vertices = vec![];
+        for (rect, placed) in rects {
+            let color = if *placed { Self::PLACED_COLOR } else { Self::HIDDEN_COLOR };
+            let (min, max) = (rect.lower(), rect.upper());
+            let (min, max) = ([min.x(), min.y()], [max.x(), max.y()]);
+            Self::push_quad(&mut vertices, min, [max[0], min[1] + w], color);
+            Self::push_quad(&mut vertices, [min[0], max[1] - w], max, color);
+            Self::push_quad(&mut vertices, min, [min[0] + w, max[1]], color);
+            Self::push_quad(&mut vertices, [max[0] - w, min[1]], max, color);
+        }
+
+        self.vertices_count = vertices.len();
+        if vertices.is_empty() {
+            return;
+        }
+        match &self.vertex_buffer {
+            Some((capacity, buffer)) if *capacity >= vertices.len() => {
+                queue.write_buffer(buffer, 0, bytemuck::cast_slice(&vertices));
+            }
+            _ => {
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Collision Debug Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                });
+                self.vertex_buffer = Some((vertices.len(), buffer));
+            }
+        }
+    }
+
+    fn render(&mut self, render_pass: &mut RenderPass, _global_context: &mut GlobalContext) {
+        let (Some(render_pipeline), Some((_, vertex_buffer))) = (&self.render_pipeline, &self.vertex_buffer)
+        else {
+            return;
+        };
+        if self.vertices_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(render_pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertices_count as u32, 0..1);
+    }
+}
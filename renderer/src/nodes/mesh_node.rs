@@ -1,3 +1,4 @@
+use crate::collision_handler::CollisionKey;
 use crate::mesh::mesh::Mesh;
 use crate::modifier::render_modifier::SpatialData;
 use crate::nodes::SceneNode;
@@ -23,7 +24,8 @@ pub struct PositionedMesh {
     is_two_instances: bool,
     spatial_rx: Receiver<SpatialData>,
     original_spatial_data: SpatialData,
-    with_collisions: bool,
+    // the collision key and rank of every position
+    collisions: Option<Vec<(CollisionKey, i32)>>,
     first_render: bool
 }
 
@@ -40,7 +42,7 @@ impl Mesh {
             0.0,
             spatial_rx,
             false,
-            false,
+            None,
         )
     }
     pub fn to_positioned_with_instances(
@@ -50,7 +52,7 @@ impl Mesh {
         yaw: f32,
         spatial_rx: tokio::sync::broadcast::Receiver<SpatialData>,
         is_two_instances: bool,
-        with_collisions: bool,
+        collisions: Option<Vec<(CollisionKey, i32)>>,
     ) -> PositionedMesh {
         PositionedMesh::new(
             device,
@@ -59,7 +61,7 @@ impl Mesh {
             yaw,
             spatial_rx,
             is_two_instances,
-            with_collisions,
+            collisions,
         )
     }
 
@@ -90,7 +92,7 @@ impl PositionedMesh {
         yaw: f32,
         mut spatial_rx: tokio::sync::broadcast::Receiver<SpatialData>,
         is_two_instances: bool,
-        with_collisions: bool,
+        collisions: Option<Vec<(CollisionKey, i32)>>,
     ) -> Self {
         let original_positions_alpha = original_positions.iter().map(|v| (*v, 1.0)).collect();
         let spatial_data = spatial_rx.try_recv().unwrap_or(SpatialData::new());
@@ -121,7 +123,7 @@ impl PositionedMesh {
             is_two_instances,
             spatial_rx,
             original_spatial_data: spatial_data,
-            with_collisions,
+            collisions,
             first_render: true
         }
     }
@@ -177,12 +179,12 @@ impl SceneNode for PositionedMesh {
         config: &wgpu::SurfaceConfiguration,
         global_context: &mut GlobalContext,
     ) {
-        if self.with_collisions {
+        if let Some(collisions) = &self.collisions {
             let screen_position_calculator = global_context
                 .view_projection
                 .screen_position_calculator(&global_context.view_projection.cs_offset, config);
 
-            for item in &mut self.original_positions_alpha {
+            for (item, (key, rank)) in self.original_positions_alpha.iter_mut().zip(collisions) {
                 let screen_pos = screen_position_calculator.screen_position(Vector3::new(
                     item.0.x + self.original_spatial_data.transform.x,
                     item.0.y + self.original_spatial_data.transform.y,
@@ -197,7 +199,7 @@ impl SceneNode for PositionedMesh {
 
                 let within_screen = global_context.collision_handler.within_screen(bounds);
                 if within_screen {
-                    if global_context.collision_handler.insert(bounds) {
+                    if global_context.collision_handler.insert(*key, *rank, [bounds]) {
                         item.1 = clamp(item.1 + 0.05, 0.0, 1.0);
                     } else {
                         if self.first_render {
@@ -214,7 +216,7 @@ impl SceneNode for PositionedMesh {
 
         let cs_offset_updated = global_context.view_projection.cs_offset != self.cs_offset;
         self.cs_offset = global_context.view_projection.cs_offset;
-        let mut update_attrs = self.with_collisions || cs_offset_updated;
+        let mut update_attrs = self.collisions.is_some() || cs_offset_updated;
 
         if let Ok(spatial_data) = self.spatial_rx.no_lagged() {
             self.original_spatial_data = spatial_data;
@@ -3,6 +3,7 @@ use crate::GlobalContext;
 use wgpu::{Device, Queue, RenderPass};
 
 pub mod camera_node;
+pub mod collision_debug_node;
 pub mod fps_node;
 pub mod mesh_layer;
 pub(crate) mod mesh_node;
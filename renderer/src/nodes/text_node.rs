@@ -11,11 +11,11 @@ pub struct TextNode {
 }
 
 impl TextNode {
-    pub fn new(text_data: Vec<TextData>, spatial_data: SpatialData) -> Self {
+    pub fn new(text_data: Vec<(TextData, usize)>, spatial_data: SpatialData) -> Self {
         Self {
             data: text_data
                 .into_iter()
-                .map(|item| TextNodeData {
+                .map(|(item, style_index)| TextNodeData {
                     id: item.id,
                     text: item.text,
                     size: item.size,
@@ -26,7 +26,10 @@ impl TextNode {
                         .map(|pos| pos + spatial_data.transform.cast().unwrap())
                         .collect(),
                     screen_offset: item.screen_offset,
-                    glyph_buffer: None,
+                    style_index,
+                    rank: item.rank,
+                    symbol_id: item.symbol_id,
+                    shaped_text: None,
                 })
                 .collect(),
         }
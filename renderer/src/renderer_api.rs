@@ -58,4 +58,12 @@ impl RendererApi {
             .send(RendererApiMsg::UpdateSpatialData((key, Box::new(updater))))
             .expect("RendererApi update_spatial_data sender failed.");
     }
+
+    /// Asks the background thread to report once every message sent before has been processed,
+    /// see `ShashlikRenderer::apply_pending_render_groups`.
+    pub fn request_sync(&self) {
+        self.renderer_api_tx
+            .send(RendererApiMsg::Sync)
+            .expect("RendererApi request_sync sender failed.");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inv_screen_size: vec2<f32>,
+    zoom_level: f32,
+    text_color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    // in screen pixels, y goes down
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(vertex: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    let clip = vertex.position * 2.0 * camera.inv_screen_size - vec2(1.0, 1.0);
+    out.clip_position = vec4<f32>(clip.x, -clip.y, 0.0, 1.0);
+    out.color = vertex.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
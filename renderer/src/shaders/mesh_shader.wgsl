@@ -3,6 +3,8 @@
 struct CameraUniform {
     view_proj: mat4x4<f32>,
     inv_screen_size: vec2<f32>,
+    zoom_level: f32,
+    text_color: vec4<f32>,
 };
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
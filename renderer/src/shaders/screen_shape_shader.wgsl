@@ -1,9 +1,11 @@
 // Vertex shader
-const PARAMS_COUNT : i32 = 12;
+const PARAMS_COUNT : i32 = 120;
 
 struct CameraUniform {
     view_proj: mat4x4<f32>,
     inv_screen_size: vec2<f32>,
+    zoom_level: f32,
+    text_color: vec4<f32>,
 };
 
 struct StyleUniform {
@@ -0,0 +1,102 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inv_screen_size: vec2<f32>,
+    zoom_level: f32,
+    text_color: vec4<f32>,
+};
+
+struct SdfTextUniform {
+    softness: f32,
+    spread: f32,
+    atlas_size: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+@group(1) @binding(2)
+var<uniform> params: SdfTextUniform;
+
+struct InstanceInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color_alpha: f32,
+    @location(2) model_matrix_0: vec4<f32>,
+    @location(3) model_matrix_1: vec4<f32>,
+    @location(4) model_matrix_2: vec4<f32>,
+    @location(5) model_matrix_3: vec4<f32>,
+    @location(6) rect: vec4<f32>,
+    @location(7) uv: vec4<f32>,
+    @location(8) color: vec4<f32>,
+    @location(9) halo_color: vec4<f32>,
+    // in screen pixels
+    @location(10) halo_width: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color_alpha: f32,
+    @location(1) color: vec4<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) halo_color: vec4<f32>,
+    @location(4) halo_width: f32,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    glyph: InstanceInput
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    // two triangles of the quad, y goes up like the glyph outlines
+    var corners = array<vec2<f32>, 6>(
+        vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+        vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let position = mix(glyph.rect.xy, glyph.rect.zw, corner);
+    out.uv = vec2(mix(glyph.uv.x, glyph.uv.z, corner.x), mix(glyph.uv.w, glyph.uv.y, corner.y));
+
+    let model_matrix = mat4x4<f32>(
+            glyph.model_matrix_0,
+            glyph.model_matrix_1,
+            glyph.model_matrix_2,
+            glyph.model_matrix_3,
+    );
+    let model_position = model_matrix * vec4(position, 0.0, 1.0);
+    let ratio_fixed_modelpos = vec4(model_position.xy * vec2(2.0*camera.inv_screen_size.x, 2.0*camera.inv_screen_size.y), model_position.z, 1.0);
+
+    out.color_alpha = glyph.color_alpha;
+    out.color = glyph.color;
+    out.halo_color = glyph.halo_color;
+    out.halo_width = glyph.halo_width;
+
+    let coord = camera.view_proj * vec4<f32>(glyph.position.xy, 0.0, 1.0);
+
+    out.clip_position = vec4<f32>(ratio_fixed_modelpos.xyz, 0.0) + vec4(coord.xyz/coord.w, 1.0);
+    return out;
+}
+
+// Fragment shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // distance to the outline in the atlas pixels, positive inside
+    let distance = (textureSample(atlas, atlas_sampler, in.uv).r - 0.5) * 2.0 * params.spread;
+    let atlas_uv = in.uv * params.atlas_size;
+    let atlas_pixels = max(length(vec2(length(dpdx(atlas_uv)), length(dpdy(atlas_uv)))) * 0.7071, 0.0001);
+    let pixels = distance / atlas_pixels;
+
+    let edge = 1.0 + params.softness;
+    let fill = clamp(pixels / edge + 0.5, 0.0, 1.0);
+    let halo = max(clamp((pixels + in.halo_width) / edge + 0.5, 0.0, 1.0) - fill, 0.0);
+
+    let fill_alpha = fill * in.color.a;
+    let halo_alpha = halo * in.halo_color.a;
+    let alpha = fill_alpha + halo_alpha;
+    let color = (in.color.rgb * fill_alpha + in.halo_color.rgb * halo_alpha) / max(alpha, 0.0001);
+    return vec4(color, alpha * in.color_alpha);
+}
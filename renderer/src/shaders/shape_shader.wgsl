@@ -1,9 +1,11 @@
 // Vertex shader
-const PARAMS_COUNT : i32 = 12;
+const PARAMS_COUNT : i32 = 120;
 
 struct CameraUniform {
     view_proj: mat4x4<f32>,
     inv_screen_size: vec2<f32>,
+    zoom_level: f32,
+    text_color: vec4<f32>,
 };
 
 struct StyleUniform {
@@ -23,6 +25,7 @@ struct VertexInput {
     @location(1) normal: vec3<f32>,
     @location(2) dist: f32,
     @location(3) style_index: u32,
+    @location(12) width: f32,
 }
 
 struct InstanceInput {
@@ -44,11 +47,88 @@ struct VertexOutput {
     @location(3) vertex_pos_xy: vec2<f32>,
     @location(4) bbox: vec4<f32>,
     @location(5) dist: f32,
+    @location(6) fill_color: vec4<f32>,
+    @location(7) opacity: f32,
+    @location(8) dash_length: f32,
+    // signed distance from the middle of a line and its half width
+    @location(9) across: f32,
+    @location(10) half_width: f32,
 }
 
 // TODO pass as a parameter
 const inflate_factor: f32 = 0.06;
 
+// zoom stops layout of the style params, see render_style.rs
+const COLOR_STOPS_COUNT: u32 = 9u;
+const WIDTH_STOPS_COUNT: u32 = 10u;
+const OPACITY_STOPS_COUNT: u32 = 11u;
+const DASH_STOPS_COUNT: u32 = 12u;
+const COLOR_STOPS: u32 = 16u;
+const WIDTH_STOPS: u32 = 56u;
+const OPACITY_STOPS: u32 = 72u;
+const DASH_STOPS: u32 = 88u;
+const PROGRESS: u32 = 104u;
+const TRAVELED_COLOR_SET: u32 = 105u;
+const TRAVELED_COLOR: u32 = 106u;
+
+// width of the chevron arms in half widths of the line
+const CHEVRON_THICKNESS: f32 = 0.35;
+
+// the length of a dash and a gap which gives the original look
+const DEFAULT_DASH_LENGTH: f32 = 1.25;
+
+fn zoom_scalar(style_index: u32, count_index: u32, stops_index: u32, default_value: f32) -> f32 {
+    let count = u32(styles[style_index].params[count_index]);
+    if(count == 0u) {
+        return default_value;
+    }
+    let zoom = camera.zoom_level;
+    var value = styles[style_index].params[stops_index + 1u];
+    for(var i = 1u; i < count; i++) {
+        let prev_zoom = styles[style_index].params[stops_index + (i - 1u) * 2u];
+        let next_zoom = styles[style_index].params[stops_index + i * 2u];
+        let next_value = styles[style_index].params[stops_index + i * 2u + 1u];
+        if(zoom >= next_zoom) {
+            value = next_value;
+        } else {
+            if(zoom > prev_zoom) {
+                value = mix(value, next_value, (zoom - prev_zoom) / (next_zoom - prev_zoom));
+            }
+            break;
+        }
+    }
+    return value;
+}
+
+fn stop_color(style_index: u32, index: u32) -> vec4<f32> {
+    let offset = COLOR_STOPS + index * 5u + 1u;
+    let params = &styles[style_index].params;
+    return vec4((*params)[offset], (*params)[offset + 1u], (*params)[offset + 2u], (*params)[offset + 3u]);
+}
+
+fn zoom_color(style_index: u32, default_value: vec4<f32>) -> vec4<f32> {
+    let count = u32(styles[style_index].params[COLOR_STOPS_COUNT]);
+    if(count == 0u) {
+        return default_value;
+    }
+    let zoom = camera.zoom_level;
+    var value = stop_color(style_index, 0u);
+    for(var i = 1u; i < count; i++) {
+        let prev_zoom = styles[style_index].params[COLOR_STOPS + (i - 1u) * 5u];
+        let next_zoom = styles[style_index].params[COLOR_STOPS + i * 5u];
+        let next_value = stop_color(style_index, i);
+        if(zoom >= next_zoom) {
+            value = next_value;
+        } else {
+            if(zoom > prev_zoom) {
+                value = mix(value, next_value, (zoom - prev_zoom) / (next_zoom - prev_zoom));
+            }
+            break;
+        }
+    }
+    return value;
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
@@ -70,6 +150,10 @@ fn vs_main(
 
     // only two components for normal
     var normal_scale =  vec3((model.normal.xy * pos.normal_scale) - model.normal.xy, 0.0);
+
+    // stroke vertices are half of the tessellated width away from the path
+    let width_factor = zoom_scalar(model.style_index, WIDTH_STOPS_COUNT, WIDTH_STOPS, 1.0);
+    normal_scale += vec3(model.normal.xy * model.width * 0.5 * (width_factor - 1.0), 0.0);
     if(model.instance_index % 2 == 0) {
         normal_scale += vec3(model.normal.xy * inflate_factor, 0.0);
     }
@@ -79,6 +163,13 @@ fn vs_main(
     out.vertex_pos_xy = pointPos.xy;
     out.bbox = pos.bbox;
     out.dist = model.dist;
+    // z of the line normals is the side of the line
+    out.half_width = model.width * 0.5 * width_factor;
+    out.across = model.normal.z * out.half_width;
+    let params = &styles[model.style_index].params;
+    out.fill_color = zoom_color(model.style_index, vec4((*params)[1], (*params)[2], (*params)[3], (*params)[4]));
+    out.opacity = zoom_scalar(model.style_index, OPACITY_STOPS_COUNT, OPACITY_STOPS, 1.0);
+    out.dash_length = zoom_scalar(model.style_index, DASH_STOPS_COUNT, DASH_STOPS, DEFAULT_DASH_LENGTH);
     out.clip_position = camera.view_proj * vec4<f32>(pointPos, 1.0);
     return out;
 }
@@ -96,52 +187,74 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
             discard;
         }
     }
-    let style_params = styles[in.style_index].params;
+    let params = &styles[in.style_index].params;
+    var fill_color = in.fill_color;
+    // the part of the line before the progress, e.g. the traveled part of a route
+    if((*params)[TRAVELED_COLOR_SET] > 0.0 && in.dist < (*params)[PROGRESS]) {
+        if((*params)[TRAVELED_COLOR + 3u] <= 0.0) {
+            discard;
+        }
+        fill_color = vec4((*params)[TRAVELED_COLOR], (*params)[TRAVELED_COLOR + 1u], (*params)[TRAVELED_COLOR + 2u], (*params)[TRAVELED_COLOR + 3u]);
+    }
     // FIXME Requires better solution for param type
-    let style_type = u32(round(style_params[0]));
+    let style_type = u32(round((*params)[0]));
 
     var res_color = vec4(0.0, 0.0, 0.0, 1.0);
     if(style_type == 0) {
-        res_color = solid_style(in.outline_flag, style_params);
+        res_color = solid_style(in.outline_flag, fill_color);
     } else if(style_type == 1) {
-        res_color = border_style(in.outline_flag, style_params);
+        res_color = border_style(in.outline_flag, fill_color, (*params)[5]);
     } else if(style_type == 2) {
-        res_color = dashed_style(in.outline_flag, in.dist, style_params);
+        res_color = dashed_style(in.outline_flag, in.style_index, in.dist, in.dash_length, fill_color);
+    } else if(style_type == 3) {
+        res_color = chevron_style(in.outline_flag, in.dist, in.across, in.half_width * 2.0 * in.dash_length, in.half_width, fill_color);
     } else {
         res_color = vec4(0.0, 0.0, 0.0, 1.0);
     }
 
-     res_color.a = in.color_alpha;
+     res_color.a = in.color_alpha * in.opacity;
 
      return res_color;
 }
 
-fn solid_style(outline_flag: u32, params: array<f32, PARAMS_COUNT>) -> vec4<f32> {
+fn solid_style(outline_flag: u32, fill_color: vec4<f32>) -> vec4<f32> {
     if(outline_flag == 0) {
         discard;
     }
-    let fill_color = vec4(params[1], params[2], params[3], params[4]);
     return fill_color;
 }
 
-fn border_style(outline_flag: u32, params: array<f32, PARAMS_COUNT>) -> vec4<f32> {
-    let fill_color = vec4(params[1], params[2], params[3], params[4]);
+fn border_style(outline_flag: u32, fill_color: vec4<f32>, koef: f32) -> vec4<f32> {
     if(outline_flag == 0) {
-        let koef = params[5];
         return vec4(fill_color.x * koef, fill_color.y * koef, fill_color.z * koef, 1.0);
     }
     return fill_color;
 }
 
-fn dashed_style(outline_flag: u32, dist: f32, params: array<f32, PARAMS_COUNT>) -> vec4<f32> {
-    let fill_color = vec4(params[1], params[2], params[3], params[4]);
-    let dash_color = vec4(params[5], params[6], params[7], params[8]);
+fn dashed_style(outline_flag: u32, style_index: u32, dist: f32, dash_length: f32, fill_color: vec4<f32>) -> vec4<f32> {
+    let params = &styles[style_index].params;
+    let dash_color = vec4((*params)[5], (*params)[6], (*params)[7], (*params)[8]);
     if(outline_flag == 0) {
         // TODO Border + Dashed later
         discard;
     }
-    
-    return dash(4.0, 0.2*dist, dash_color, fill_color);
+    // without dash stops the lines keep the original look
+    if(u32((*params)[DASH_STOPS_COUNT]) == 0u) {
+        return dash(4.0, 0.2*dist, dash_color, fill_color);
+    }
+    return sharp_dash(4.0, 0.25*dist/dash_length, dash_color, fill_color);
+}
+
+fn chevron_style(outline_flag: u32, dist: f32, across: f32, spacing: f32, half_width: f32, fill_color: vec4<f32>) -> vec4<f32> {
+    if(outline_flag == 0) {
+        discard;
+    }
+    // the tip is in the middle of the line ahead of the arms at its sides
+    let along = dist - spacing * floor(dist / spacing) - spacing * 0.5 + abs(across);
+    if(abs(along) > half_width * CHEVRON_THICKNESS) {
+        discard;
+    }
+    return fill_color;
 }
 
 fn dash(freq: f32, dist: f32, extra_color: vec4f, main_color: vec4f) -> vec4f {
@@ -157,4 +270,14 @@ fn dash(freq: f32, dist: f32, extra_color: vec4f, main_color: vec4f) -> vec4f {
         dashed_color = main_color;
     }
     return dashed_color;
-}
\ No newline at end of file
+}
+
+// the dashes scaled by the dash stops, smoothstep with equal edges is undefined, some drivers never return 0
+fn sharp_dash(freq: f32, dist: f32, extra_color: vec4f, main_color: vec4f) -> vec4f {
+    let koef = 1.0 / freq;
+    let lineUMod = (dist - (koef)*floor(dist/(koef))) * freq;
+    if(step(koef, length(lineUMod-0.5)) <= 0.0) {
+        return extra_color;
+    }
+    return main_color;
+}
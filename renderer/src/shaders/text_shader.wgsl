@@ -1,6 +1,8 @@
 struct CameraUniform {
     view_proj: mat4x4<f32>,
     inv_screen_size: vec2<f32>,
+    zoom_level: f32,
+    text_color: vec4<f32>,
 };
 
 
@@ -26,6 +28,7 @@ struct InstanceInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color_alpha: f32,
+    @location(1) color: vec4<f32>,
 }
 
 @vertex
@@ -45,6 +48,8 @@ fn vs_main(
     let ratio_fixed_modelpos = vec4(model_position.xy * vec2(2.0*camera.inv_screen_size.x, 2.0*camera.inv_screen_size.y), model_position.z, 1.0);
 
     out.color_alpha = pos.color_alpha;
+    // the color of the label
+    out.color = pos.bbox;
 
     let coord = camera.view_proj * vec4<f32>(pos.position.xy, 0.0, 1.0);
 
@@ -55,5 +60,5 @@ fn vs_main(
 // Fragment shader
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4(0.0, 0.0, 0.0, in.color_alpha);
+    return vec4(in.color.rgb, in.color.a * in.color_alpha);
 }
\ No newline at end of file
@@ -1,4 +1,29 @@
-use crate::consts::STYLE_SHADER_PARAMS_COUNT;
+use crate::consts::{STYLE_MAX_ZOOM_STOPS, STYLE_SHADER_PARAMS_COUNT};
+
+// Layout of the style params, it has to be in sync with the shape shaders
+const COLOR_STOPS_COUNT: usize = 9;
+const WIDTH_STOPS_COUNT: usize = 10;
+const OPACITY_STOPS_COUNT: usize = 11;
+const DASH_STOPS_COUNT: usize = 12;
+// zoom + rgba
+const COLOR_STOPS: usize = 16;
+// zoom + value
+const WIDTH_STOPS: usize = COLOR_STOPS + STYLE_MAX_ZOOM_STOPS * 5;
+const OPACITY_STOPS: usize = WIDTH_STOPS + STYLE_MAX_ZOOM_STOPS * 2;
+const DASH_STOPS: usize = OPACITY_STOPS + STYLE_MAX_ZOOM_STOPS * 2;
+// line distance + flag + rgba
+const PROGRESS: usize = DASH_STOPS + STYLE_MAX_ZOOM_STOPS * 2;
+const TRAVELED_COLOR_SET: usize = PROGRESS + 1;
+const TRAVELED_COLOR: usize = PROGRESS + 2;
+// the labels, only read on the CPU
+const TEXT_COLOR_SET: usize = TRAVELED_COLOR + 4;
+const TEXT_HALO_COLOR: usize = TEXT_COLOR_SET + 1;
+const TEXT_HALO_WIDTH: usize = TEXT_HALO_COLOR + 4;
+const TEXT_FONT: usize = TEXT_HALO_WIDTH + 1;
+const TEXT_LETTER_SPACING: usize = TEXT_FONT + 1;
+const TEXT_MAX_WIDTH: usize = TEXT_LETTER_SPACING + 1;
+
+const TEXT_STYLE_TYPE: f32 = 4.0;
 
 #[derive(Clone, Copy, Debug)]
 pub struct RenderStyle {
@@ -45,7 +70,162 @@ impl RenderStyle {
         style
     }
 
+    /// Only chevrons pointing along the line are drawn, the dash length is the distance between them
+    /// in line widths.
+    pub fn chevrons(chevron_color: [f32; 4]) -> RenderStyle {
+        let mut style = RenderStyle::fill(chevron_color);
+
+        style.container[0] = 3.0;
+
+        style
+    }
+
+    /// Label style, the text is drawn in the fill color or, without one, in the text color of the renderer.
+    pub fn text(fill_color: Option<[f32; 4]>) -> RenderStyle {
+        let mut style = Self::empty();
+
+        style.container[0] = TEXT_STYLE_TYPE;
+
+        match fill_color {
+            Some(fill_color) => style.with_fill_color(fill_color),
+            None => style,
+        }
+    }
+
+    /// Replaces the fill color and keeps the kind of the style, e.g. border or dashes.
+    /// The color stops are dropped, the color is used at every zoom level.
+    pub fn with_fill_color(mut self, fill_color: [f32; 4]) -> RenderStyle {
+        self.container[1..fill_color.len() + 1].copy_from_slice(&fill_color);
+        self.container[COLOR_STOPS_COUNT] = 0.0;
+        if self.container[0] == TEXT_STYLE_TYPE {
+            self.container[TEXT_COLOR_SET] = 1.0;
+        }
+        self
+    }
+
+    /// Outline of the labels of a text style, the width is in screen pixels.
+    /// Only the SDF text backend draws it.
+    pub fn with_halo(mut self, halo_color: [f32; 4], width: f32) -> RenderStyle {
+        self.container[TEXT_HALO_COLOR..TEXT_HALO_COLOR + 4].copy_from_slice(&halo_color);
+        self.container[TEXT_HALO_WIDTH] = width;
+        self
+    }
+
+    /// Index of the font in the fallback chain the labels are shaped with first, 0 is the embedded one
+    /// and the fonts added to the renderer follow it, e.g. a bold face.
+    pub fn with_font(mut self, font: usize) -> RenderStyle {
+        self.container[TEXT_FONT] = font as f32;
+        self
+    }
+
+    /// Extra space after every glyph in ems.
+    pub fn with_letter_spacing(mut self, letter_spacing: f32) -> RenderStyle {
+        self.container[TEXT_LETTER_SPACING] = letter_spacing;
+        self
+    }
+
+    /// Point labels wider than it in ems are wrapped at the spaces, 0 disables the wrapping.
+    pub fn with_max_width(mut self, max_width: f32) -> RenderStyle {
+        self.container[TEXT_MAX_WIDTH] = max_width;
+        self
+    }
+
+    /// Fill color over the zoom level, replaces the fill color.
+    /// The zoom level is the map one, fractional while zooming. At most `STYLE_MAX_ZOOM_STOPS` stops are used.
+    pub fn with_color_stops(mut self, stops: &[(f32, [f32; 4])]) -> RenderStyle {
+        let stops = &stops[..stops.len().min(STYLE_MAX_ZOOM_STOPS)];
+        self.container[COLOR_STOPS_COUNT] = stops.len() as f32;
+        stops.iter().enumerate().for_each(|(i, (zoom, color))| {
+            let offset = COLOR_STOPS + i * 5;
+            self.container[offset] = *zoom;
+            self.container[offset + 1..offset + 5].copy_from_slice(color);
+        });
+        self
+    }
+
+    /// Line width factor over the zoom level, lines are widened on the GPU, the tile geometry is kept.
+    pub fn with_width_stops(mut self, stops: &[(f32, f32)]) -> RenderStyle {
+        self.set_scalar_stops(WIDTH_STOPS_COUNT, WIDTH_STOPS, stops);
+        self
+    }
+
+    /// Opacity over the zoom level, multiplies the alpha of the render group.
+    pub fn with_opacity_stops(mut self, stops: &[(f32, f32)]) -> RenderStyle {
+        self.set_scalar_stops(OPACITY_STOPS_COUNT, OPACITY_STOPS, stops);
+        self
+    }
+
+    /// Length of a dash and a gap of dashed lines in world units over the zoom level.
+    pub fn with_dash_stops(mut self, stops: &[(f32, f32)]) -> RenderStyle {
+        self.set_scalar_stops(DASH_STOPS_COUNT, DASH_STOPS, stops);
+        self
+    }
+
+    /// Color of the lines before the progress distance, e.g. the traveled part of a route.
+    /// A transparent color hides that part.
+    pub fn with_traveled_color(mut self, traveled_color: [f32; 4]) -> RenderStyle {
+        self.container[TRAVELED_COLOR_SET] = 1.0;
+        self.container[TRAVELED_COLOR..TRAVELED_COLOR + 4].copy_from_slice(&traveled_color);
+        self
+    }
+
+    /// Distance along the lines where the traveled color ends, in the units the lines are built in.
+    pub fn with_progress(mut self, distance: f32) -> RenderStyle {
+        self.container[PROGRESS] = distance;
+        self
+    }
+
+    /// Distance of [RenderStyle::with_progress], 0 if it isn't set.
+    pub fn progress(&self) -> f32 {
+        self.container[PROGRESS]
+    }
+
+    /// Color of [RenderStyle::with_traveled_color], none if it isn't set.
+    pub fn traveled_color(&self) -> Option<[f32; 4]> {
+        (self.container[TRAVELED_COLOR_SET] == 1.0)
+            .then(|| self.container[TRAVELED_COLOR..TRAVELED_COLOR + 4].try_into().unwrap())
+    }
+
+    fn set_scalar_stops(&mut self, count_index: usize, stops_index: usize, stops: &[(f32, f32)]) {
+        let stops = &stops[..stops.len().min(STYLE_MAX_ZOOM_STOPS)];
+        self.container[count_index] = stops.len() as f32;
+        stops.iter().enumerate().for_each(|(i, (zoom, value))| {
+            self.container[stops_index + i * 2] = *zoom;
+            self.container[stops_index + i * 2 + 1] = *value;
+        });
+    }
+
     pub(crate) fn params(&self) -> [f32; STYLE_SHADER_PARAMS_COUNT] {
         self.container
     }
 }
+
+/// Label properties of a style, the default one for the styles which aren't text styles.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct TextStyle {
+    /// None keeps the text color of the renderer
+    pub color: Option<[f32; 4]>,
+    /// Color and width in screen pixels
+    pub halo: Option<([f32; 4], f32)>,
+    pub font: usize,
+    /// In ems
+    pub letter_spacing: f32,
+    /// In ems
+    pub max_width: Option<f32>,
+}
+
+impl TextStyle {
+    pub(crate) fn from_params(params: &[f32; STYLE_SHADER_PARAMS_COUNT]) -> TextStyle {
+        if params[0] != TEXT_STYLE_TYPE {
+            return TextStyle::default();
+        }
+        let color = |offset: usize| -> [f32; 4] { params[offset..offset + 4].try_into().unwrap() };
+        TextStyle {
+            color: (params[TEXT_COLOR_SET] != 0.0).then(|| color(1)),
+            halo: (params[TEXT_HALO_WIDTH] > 0.0).then(|| (color(TEXT_HALO_COLOR), params[TEXT_HALO_WIDTH])),
+            font: params[TEXT_FONT] as usize,
+            letter_spacing: params[TEXT_LETTER_SPACING],
+            max_width: (params[TEXT_MAX_WIDTH] > 0.0).then_some(params[TEXT_MAX_WIDTH]),
+        }
+    }
+}
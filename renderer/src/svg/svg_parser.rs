@@ -4,6 +4,7 @@ use lyon::lyon_tessellation::{
     StrokeTessellator, StrokeVertex, StrokeVertexConstructor, VertexBuffers,
 };
 use lyon::math::{Point, Vector};
+use log::error;
 use lyon::path::PathEvent;
 use lyon::tessellation;
 use usvg::{tiny_skia_path, Group, Size, Transform};
@@ -21,9 +22,19 @@ pub fn svg_parse(
 
     let opt = usvg::Options::default();
     let db = usvg::fontdb::Database::new();
-    let rtree = usvg::Tree::from_data(&icon, &opt, &db).unwrap();
+    // icons come from the host apps too, a broken one is not drawn
+    let rtree = match usvg::Tree::from_data(icon, &opt, &db) {
+        Ok(rtree) => rtree,
+        Err(e) => {
+            error!("Failed to parse the SVG icon: {e}");
+            return mesh;
+        }
+    };
     let bbox = rtree.view_box().rect;
-    let original_size = Size::from_wh(bbox.width(), bbox.height()).unwrap();
+    let Some(original_size) = Size::from_wh(bbox.width(), bbox.height()) else {
+        error!("SVG icon has an empty view box");
+        return mesh;
+    };
     // let size = Size(width, height);
     let scale = width / original_size.width();
     let mut transforms = Vec::new();
@@ -327,6 +338,8 @@ impl VertexCtor {
             normals: [normal.x, normal.y, 0.0],
             dist: 0.0, // TODO If we want to have dashed style for SVG
             style_index: self.style_index,
+            // icons keep their size on screen
+            width: 0.0,
         }
     }
 }
@@ -0,0 +1,197 @@
+use crate::draw_commands::geometry_to_mesh;
+use crate::mesh::mesh::Mesh;
+use crate::text::glyph_tesselator::GlyphTesselator;
+use anyhow::Context;
+use cgmath::{Matrix4, Vector2};
+use log::error;
+use rustc_hash::FxHashMap;
+use rustybuzz::ttf_parser::GlyphId;
+use lyon::math::Box2D;
+use lyon::path::Path;
+use rustybuzz::{Face, UnicodeBuffer, ttf_parser};
+use wgpu::{Color, Device};
+
+/// Glyph of one of the faces of the collection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub face: usize,
+    pub glyph_id: GlyphId,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShapedGlyph {
+    pub key: GlyphKey,
+    /// In the units of the first face
+    pub x_advance: f32,
+    /// The labels are wrapped at them
+    pub whitespace: bool,
+}
+
+/// Glyphs of a text in the visual order.
+pub struct ShapedText {
+    pub glyphs: Vec<ShapedGlyph>,
+    // the number of the faces when it was shaped, a new fallback face may cover the missing glyphs
+    faces_count: usize,
+    font: usize,
+}
+
+struct FallbackFace {
+    face: Face<'static>,
+    // to the units of the first face
+    units_scale: f32,
+}
+
+/// The embedded Latin face followed by the fallback faces(e.g. CJK, Cyrillic, Arabic) loaded at runtime.
+/// Every character is shaped with the first face which has its glyph, the glyph meshes are
+/// tessellated when they are drawn for the first time.
+pub struct FontCollection {
+    faces: Vec<FallbackFace>,
+    // None for the glyphs without an outline, e.g. the space
+    glyph_meshes: FxHashMap<GlyphKey, Option<Mesh>>,
+    pub glyph_height: f32,
+}
+
+impl FontCollection {
+    // the glyph meshes are in the font units of the first face scaled by it
+    pub const MESH_SCALE: f32 = 0.035;
+
+    pub fn new() -> FontCollection {
+        let face = ttf_parser::Face::parse(include_bytes!("../font.ttf"), 0).unwrap();
+        let face = rustybuzz::Face::from_face(face);
+        let glyph_height = (face.ascender() + face.descender()) as f32;
+
+        FontCollection {
+            faces: vec![FallbackFace {
+                face,
+                units_scale: 1.0,
+            }],
+            glyph_meshes: FxHashMap::default(),
+            glyph_height,
+        }
+    }
+
+    /// Adds a TrueType or OpenType face to the end of the fallback chain.
+    pub fn add_font(&mut self, data: Vec<u8>) -> anyhow::Result<()> {
+        ttf_parser::Face::parse(&data, 0).context("Invalid font")?;
+        // the faces live as long as the renderer
+        let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+        let face = rustybuzz::Face::from_slice(data, 0).context("Invalid font")?;
+        let units_scale = self.faces[0].face.units_per_em() as f32 / face.units_per_em() as f32;
+        self.faces.push(FallbackFace { face, units_scale });
+        Ok(())
+    }
+
+    fn face_index(&self, c: char, font: usize) -> Option<usize> {
+        if self.faces.get(font).is_some_and(|face| face.face.glyph_index(c).is_some()) {
+            return Some(font);
+        }
+        self.faces
+            .iter()
+            .position(|face| face.face.glyph_index(c).is_some())
+    }
+
+    /// Shapes the text with the font first, the characters it doesn't have fall back to the chain.
+    pub fn shape(&self, text: &str, font: usize) -> ShapedText {
+        // runs of the characters of the same face, a character which the face of the current run has
+        // (e.g. a space or a digit) doesn't break the run, so the scripts are shaped as a whole
+        let mut runs: Vec<(usize, String)> = vec![];
+        for c in text.chars() {
+            match runs.last_mut() {
+                Some((face, run)) if self.faces[*face].face.glyph_index(c).is_some() => run.push(c),
+                _ => runs.push((self.face_index(c, font).unwrap_or(0), c.to_string())),
+            }
+        }
+
+        let mut glyphs = vec![];
+        for (face_index, run) in runs {
+            let face = &self.faces[face_index];
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str(&run);
+            buffer.guess_segment_properties();
+            let glyph_buffer = rustybuzz::shape(&face.face, &[], buffer);
+            glyph_buffer
+                .glyph_infos()
+                .iter()
+                .zip(glyph_buffer.glyph_positions())
+                .for_each(|(info, position)| {
+                    glyphs.push(ShapedGlyph {
+                        key: GlyphKey {
+                            face: face_index,
+                            glyph_id: GlyphId(info.glyph_id as u16),
+                        },
+                        x_advance: position.x_advance as f32 * face.units_scale,
+                        whitespace: run[info.cluster as usize..]
+                            .chars()
+                            .next()
+                            .is_some_and(char::is_whitespace),
+                    })
+                });
+        }
+        ShapedText {
+            glyphs,
+            faces_count: self.faces.len(),
+            font,
+        }
+    }
+
+    /// Whether the text was shaped before a fallback face was added or with another font.
+    pub fn is_outdated(&self, shaped_text: &ShapedText, font: usize) -> bool {
+        shaped_text.faces_count != self.faces.len() || shaped_text.font != font
+    }
+
+    fn get_scale_by_font_size(&self, font_size: f32) -> f32 {
+        font_size / self.units_per_em()
+    }
+
+    pub fn get_text_params(&self, font_size: f32) -> (Matrix4<f32>, f32, f32) {
+        let scale = self.get_scale_by_font_size(font_size);
+        let height = self.glyph_height * scale;
+
+        let scale_m = Matrix4::from_scale(scale / Self::MESH_SCALE);
+
+        (scale_m, height, scale)
+    }
+
+    /// Tessellates the glyphs which weren't drawn before.
+    pub fn prepare_glyphs<'a>(&mut self, device: &Device, keys: impl Iterator<Item = &'a GlyphKey>) {
+        keys.for_each(|key| {
+            if self.glyph_meshes.contains_key(key) {
+                return;
+            }
+            let face = &self.faces[key.face];
+            let mut path_builder = GlyphTesselator::new(Self::MESH_SCALE * face.units_scale);
+            let mesh = face
+                .face
+                .outline_glyph(key.glyph_id, &mut path_builder)
+                .and_then(|_| match path_builder.tessellate_fill(Vector2::new(0.0, 0.0f32), Color::RED) {
+                    Some(glyph_buf) => Some(geometry_to_mesh(device, &glyph_buf)),
+                    None => {
+                        error!("Failed to tessellate the glyph {key:?}");
+                        None
+                    }
+                });
+            self.glyph_meshes.insert(*key, mesh);
+        });
+    }
+
+    /// Outline in the units of the first face, None for the glyphs without one.
+    pub fn glyph_outline(&self, key: &GlyphKey) -> Option<(Path, Box2D)> {
+        let face = &self.faces[key.face];
+        let mut path_builder = GlyphTesselator::new(face.units_scale);
+        let rect = face.face.outline_glyph(key.glyph_id, &mut path_builder)?;
+        let bounds = Box2D::new(
+            lyon::geom::point(rect.x_min as f32, rect.y_min as f32),
+            lyon::geom::point(rect.x_max as f32, rect.y_max as f32),
+        )
+        .scale(face.units_scale, face.units_scale);
+        Some((path_builder.build(), bounds))
+    }
+
+    pub fn units_per_em(&self) -> f32 {
+        self.faces[0].face.units_per_em() as f32
+    }
+
+    pub fn glyph_mesh(&self, key: &GlyphKey) -> Option<&Mesh> {
+        self.glyph_meshes.get(key).and_then(Option::as_ref)
+    }
+}
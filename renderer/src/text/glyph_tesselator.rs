@@ -19,7 +19,7 @@ impl GlyphTesselator {
         self,
         offset: Vector2<f32>,
         color: Color,
-    ) -> VertexBuffers<MeshVertex, u32> {
+    ) -> Option<VertexBuffers<MeshVertex, u32>> {
         let mut buffer = VertexBuffers::new();
         let vertex_constructor = GlyphVertexConstructor { offset, color };
         let mut tessellator = FillTessellator::new();
@@ -31,9 +31,9 @@ impl GlyphTesselator {
             )
             .is_ok()
         {
-            buffer
+            Some(buffer)
         } else {
-            panic!("Tessellate failed.");
+            None
         }
     }
 }
@@ -45,6 +45,10 @@ impl GlyphTesselator {
             builder: Path::builder(),
         }
     }
+
+    pub(crate) fn build(self) -> Path {
+        self.builder.build()
+    }
 }
 
 impl OutlineBuilder for GlyphTesselator {
@@ -1,3 +1,36 @@
-mod default_face_wrapper;
+mod font_collection;
 pub mod glyph_tesselator;
+mod sdf_atlas;
+mod sdf_text_pass;
 pub(crate) mod text_renderer;
+
+/// How the glyphs of the labels are drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextBackend {
+    /// A tessellated mesh per glyph, a draw call per distinct glyph
+    #[default]
+    Mesh,
+    /// Quads of a signed distance field atlas in a single instanced draw call, supports the halo
+    Sdf,
+}
+
+/// Outline around the labels drawn by the [TextBackend::Sdf] backend.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextHalo {
+    pub color: [f32; 4],
+    /// In screen pixels, 0 disables the halo. The wider halo is cut by the spread of the atlas
+    /// (about a sixth of the font size).
+    pub width: f32,
+    /// Extra screen pixels the edges are blurred over
+    pub softness: f32,
+}
+
+impl Default for TextHalo {
+    fn default() -> Self {
+        TextHalo {
+            color: [1.0, 1.0, 1.0, 1.0],
+            width: 0.0,
+            softness: 0.0,
+        }
+    }
+}
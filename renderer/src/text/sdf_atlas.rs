@@ -0,0 +1,158 @@
+use crate::text::font_collection::{FontCollection, GlyphKey};
+use cgmath::{InnerSpace, Vector2};
+use log::error;
+use lyon::path::PathEvent;
+use lyon::path::iterator::PathIterator;
+use rustc_hash::FxHashMap;
+
+/// Place of a glyph in the atlas.
+#[derive(Clone, Copy, Debug)]
+pub struct SdfGlyph {
+    /// Quad of the glyph in the space of the glyph meshes: min x, min y, max x, max y
+    pub rect: [f32; 4],
+    /// Texture coordinates of the quad: left, top, right, bottom
+    pub uv: [f32; 4],
+}
+
+/// Signed distance fields of the glyphs packed in shelves of a single channel texture,
+/// 0.5 is the outline, the values grow inside the glyph.
+pub struct SdfAtlas {
+    pixels: Vec<u8>,
+    glyphs: FxHashMap<GlyphKey, Option<SdfGlyph>>,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+    dirty: bool,
+}
+
+impl SdfAtlas {
+    pub const SIZE: u32 = 1024;
+    /// Distance in the atlas pixels which the field covers on each side of the outline
+    pub const SPREAD: f32 = 8.0;
+    // atlas pixels per em
+    const EM_PIXELS: f32 = 48.0;
+    const TOLERANCE_PIXELS: f32 = 0.1;
+
+    pub fn new() -> SdfAtlas {
+        SdfAtlas {
+            pixels: vec![0; (Self::SIZE * Self::SIZE) as usize],
+            glyphs: FxHashMap::default(),
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+            dirty: false,
+        }
+    }
+
+    /// Rasterizes the glyph when it's used for the first time, None for the glyphs without an outline.
+    pub fn glyph(&mut self, fonts: &FontCollection, key: &GlyphKey) -> Option<SdfGlyph> {
+        if let Some(glyph) = self.glyphs.get(key) {
+            return *glyph;
+        }
+        let glyph = self.rasterize(fonts, key);
+        self.glyphs.insert(*key, glyph);
+        glyph
+    }
+
+    /// The pixels if a glyph was added since the last call.
+    pub fn take_dirty(&mut self) -> Option<&[u8]> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+        Some(&self.pixels)
+    }
+
+    fn rasterize(&mut self, fonts: &FontCollection, key: &GlyphKey) -> Option<SdfGlyph> {
+        let (outline, bounds) = fonts.glyph_outline(key)?;
+        let pixels_per_unit = Self::EM_PIXELS / fonts.units_per_em();
+        let spread = Self::SPREAD as i32;
+        let left = (bounds.min.x * pixels_per_unit).floor() as i32 - spread;
+        let top = (bounds.max.y * pixels_per_unit).ceil() as i32 + spread;
+        let width = ((bounds.max.x * pixels_per_unit).ceil() as i32 + spread - left).max(1) as u32;
+        let height = (top - (bounds.min.y * pixels_per_unit).floor() as i32 + spread).max(1) as u32;
+
+        let Some((x, y)) = self.allocate(width, height) else {
+            error!("SDF atlas is full, the glyph {key:?} is not drawn");
+            return None;
+        };
+
+        // the segments in the pixels of the glyph, y grows down like the texture rows
+        let to_pixels = |point: lyon::math::Point| {
+            Vector2::new(point.x * pixels_per_unit - left as f32, top as f32 - point.y * pixels_per_unit)
+        };
+        let mut segments = vec![];
+        for event in outline.iter().flattened(Self::TOLERANCE_PIXELS / pixels_per_unit) {
+            match event {
+                PathEvent::Line { from, to } => segments.push((to_pixels(from), to_pixels(to))),
+                // the outlines are filled with the non-zero rule, so every contour is closed
+                PathEvent::End { last, first, .. } if last != first => {
+                    segments.push((to_pixels(last), to_pixels(first)))
+                }
+                _ => {}
+            }
+        }
+
+        for row in 0..height {
+            for column in 0..width {
+                let center = Vector2::new(column as f32 + 0.5, row as f32 + 0.5);
+                let distance = signed_distance(&segments, center);
+                let value = (0.5 + distance / (2.0 * Self::SPREAD)).clamp(0.0, 1.0);
+                self.pixels[((y + row) * Self::SIZE + x + column) as usize] = (value * 255.0).round() as u8;
+            }
+        }
+        self.dirty = true;
+
+        let mesh_scale = FontCollection::MESH_SCALE / pixels_per_unit;
+        let size = Self::SIZE as f32;
+        Some(SdfGlyph {
+            rect: [
+                left as f32 * mesh_scale,
+                (top - height as i32) as f32 * mesh_scale,
+                (left + width as i32) as f32 * mesh_scale,
+                top as f32 * mesh_scale,
+            ],
+            uv: [
+                x as f32 / size,
+                y as f32 / size,
+                (x + width) as f32 / size,
+                (y + height) as f32 / size,
+            ],
+        })
+    }
+
+    // the glyphs are placed in rows as high as their highest glyph
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.shelf_x + width > Self::SIZE {
+            self.shelf_x = 0;
+            self.shelf_y += self.shelf_height;
+            self.shelf_height = 0;
+        }
+        if width > Self::SIZE || self.shelf_y + height > Self::SIZE {
+            return None;
+        }
+        let position = (self.shelf_x, self.shelf_y);
+        self.shelf_x += width;
+        self.shelf_height = self.shelf_height.max(height);
+        Some(position)
+    }
+}
+
+// positive inside of the non-zero winding outline
+fn signed_distance(segments: &[(Vector2<f32>, Vector2<f32>)], point: Vector2<f32>) -> f32 {
+    let mut min_distance = f32::MAX;
+    let mut winding = 0;
+    for &(from, to) in segments {
+        let segment = to - from;
+        let t = ((point - from).dot(segment) / segment.magnitude2().max(f32::EPSILON)).clamp(0.0, 1.0);
+        min_distance = min_distance.min((from + segment * t - point).magnitude());
+
+        let cross = segment.x * (point.y - from.y) - segment.y * (point.x - from.x);
+        if from.y <= point.y && to.y > point.y && cross > 0.0 {
+            winding += 1;
+        } else if from.y > point.y && to.y <= point.y && cross < 0.0 {
+            winding -= 1;
+        }
+    }
+    if winding != 0 { min_distance } else { -min_distance }
+}
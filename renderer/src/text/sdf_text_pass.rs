@@ -0,0 +1,210 @@
+use crate::nodes::scene_tree::RenderContext;
+use crate::pipeline_provider::PipeLineProvider;
+use crate::text::sdf_atlas::SdfAtlas;
+use crate::vertex_attrs::{SdfGlyphInstance, VertexAttrib};
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, CompareFunction, Device, Queue, RenderPass, RenderPipeline,
+    ShaderModule, Texture, include_wgsl,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SdfTextUniform {
+    softness: f32,
+    spread: f32,
+    atlas_size: f32,
+    _padding: f32,
+}
+
+/// Draws all the glyphs of the frame as quads of the SDF atlas in one instanced draw call.
+pub struct SdfTextPass {
+    shader_module: ShaderModule,
+    pipeline_provider: PipeLineProvider,
+    render_pipeline: Option<RenderPipeline>,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    texture: Texture,
+    uniform_buffer: Buffer,
+    // capacity and the buffer
+    instance_buffer: Option<(usize, Buffer)>,
+    instances_count: usize,
+}
+
+impl SdfTextPass {
+    pub fn new(device: &Device, pipeline_provider: PipeLineProvider) -> SdfTextPass {
+        let shader_module = device.create_shader_module(include_wgsl!("../shaders/sdf_text_shader.wgsl"));
+        let size = wgpu::Extent3d {
+            width: SdfAtlas::SIZE,
+            height: SdfAtlas::SIZE,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("SDF Atlas"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("SDF Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SDF Text Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[Self::uniform(0.0)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("sdf_text_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("sdf_text_bind_group"),
+        });
+
+        SdfTextPass {
+            shader_module,
+            pipeline_provider,
+            render_pipeline: None,
+            bind_group_layout,
+            bind_group,
+            texture,
+            uniform_buffer,
+            instance_buffer: None,
+            instances_count: 0,
+        }
+    }
+
+    fn uniform(softness: f32) -> SdfTextUniform {
+        SdfTextUniform {
+            softness,
+            spread: SdfAtlas::SPREAD,
+            atlas_size: SdfAtlas::SIZE as f32,
+            _padding: 0.0,
+        }
+    }
+
+    pub fn setup(&mut self, render_context: &mut RenderContext, device: &Device) {
+        render_context.depth_compare = CompareFunction::Always;
+        render_context
+            .bind_group_layouts
+            .push(self.bind_group_layout.clone());
+        self.render_pipeline = Some(self.pipeline_provider.create(
+            device,
+            render_context,
+            &[SdfGlyphInstance::desc()],
+            &self.shader_module,
+            None,
+        ));
+    }
+
+    pub fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        atlas_pixels: Option<&[u8]>,
+        instances: &[SdfGlyphInstance],
+        softness: f32,
+    ) {
+        if let Some(pixels) = atlas_pixels {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                pixels,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(SdfAtlas::SIZE),
+                    rows_per_image: Some(SdfAtlas::SIZE),
+                },
+                self.texture.size(),
+            );
+        }
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[Self::uniform(softness)]));
+
+        self.instances_count = instances.len();
+        if instances.is_empty() {
+            return;
+        }
+        match &self.instance_buffer {
+            Some((capacity, buffer)) if *capacity >= instances.len() => {
+                queue.write_buffer(buffer, 0, bytemuck::cast_slice(instances));
+            }
+            _ => {
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("SDF Glyph Instance Buffer"),
+                    contents: bytemuck::cast_slice(instances),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                });
+                self.instance_buffer = Some((instances.len(), buffer));
+            }
+        }
+    }
+
+    pub fn render(&mut self, render_pass: &mut RenderPass) {
+        let (Some(render_pipeline), Some((_, instance_buffer))) = (&self.render_pipeline, &self.instance_buffer)
+        else {
+            return;
+        };
+        if self.instances_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(render_pipeline);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instances_count as u32);
+    }
+}
@@ -1,26 +1,34 @@
-use crate::collision_handler::CollisionHandler;
-use crate::text::default_face_wrapper::DefaultFaceWrapper;
-use crate::vertex_attrs::InstancePos;
+use crate::collision_handler::{CollisionHandler, CollisionKey};
+use crate::consts::STYLE_SHADER_PARAMS_COUNT;
+use crate::styles::render_style::TextStyle;
+use crate::text::font_collection::{FontCollection, GlyphKey, ShapedGlyph, ShapedText};
+use crate::text::sdf_atlas::SdfAtlas;
+use crate::text::sdf_text_pass::SdfTextPass;
+use crate::text::{TextBackend, TextHalo};
+use crate::vertex_attrs::{InstancePos, SdfGlyphInstance};
 use crate::view_projection::ScreenPositionCalculator;
 use cgmath::num_traits::clamp;
 use cgmath::{Deg, InnerSpace, Matrix4, Quaternion, Rotation, Vector2, Vector3};
 use geo_types::{coord, point};
 use rstar::primitives::Rectangle;
 use rustc_hash::FxHashMap;
-use rustybuzz::GlyphBuffer;
-use rustybuzz::ttf_parser::GlyphId;
 use std::collections::HashMap;
+use std::ops::Range;
+use tokio::sync::broadcast::Receiver;
 use wgpu::util::DeviceExt;
 use wgpu::{Buffer, Device, Queue, RenderPass, SurfaceConfiguration};
-use crate::GlobalContext;
+use crate::pipeline_provider::PipeLineProvider;
+use crate::{GlobalContext, ReceiverExt};
 use crate::nodes::SceneNode;
+use crate::nodes::scene_tree::RenderContext;
 
 #[derive(Clone)]
 pub struct GlyphData {
-    pub glyph_id: GlyphId,
+    pub glyph_key: GlyphKey,
     pub position: (f32, f32),
     pub alpha: f32,
     pub matrix: Matrix4<f32>,
+    pub style: TextStyle,
 }
 
 pub struct TextNodeData {
@@ -30,57 +38,132 @@ pub struct TextNodeData {
     pub alpha: f32,
     pub positions: Vec<Vector3<f32>>,
     pub screen_offset: Vector2<f32>,
-    pub glyph_buffer: Option<GlyphBuffer>,
+    pub style_index: usize,
+    pub rank: i32,
+    pub symbol_id: Option<u64>,
+    pub shaped_text: Option<ShapedText>,
 }
 
-pub struct TextRendererLayer {}
+impl TextNodeData {
+    fn collision_key(&self) -> CollisionKey {
+        self.symbol_id.map_or(CollisionKey::Text(self.id), CollisionKey::Symbol)
+    }
+}
+
+/// Draws the glyphs the text nodes inserted with the backend of the [TextRenderer].
+pub struct TextRendererLayer {
+    sdf_pass: SdfTextPass,
+}
+
+impl TextRendererLayer {
+    pub fn new(device: &Device, pipeline_provider: PipeLineProvider) -> TextRendererLayer {
+        TextRendererLayer {
+            sdf_pass: SdfTextPass::new(device, pipeline_provider),
+        }
+    }
+}
 
 impl SceneNode for TextRendererLayer {
+    fn setup(&mut self, render_context: &mut RenderContext, device: &Device) {
+        self.sdf_pass.setup(render_context, device);
+    }
+
     fn update(&mut self, device: &Device, queue: &Queue, _config: &SurfaceConfiguration, global_context: &mut GlobalContext) {
-        global_context.text_renderer.update(queue, device, &global_context.view_projection.cs_offset);
+        let text_renderer = &mut global_context.text_renderer;
+        let view_projection = &global_context.view_projection;
+        text_renderer.update(queue, device, &view_projection.cs_offset, view_projection.text_color());
+        if text_renderer.backend == TextBackend::Sdf {
+            self.sdf_pass.update(
+                device,
+                queue,
+                text_renderer.sdf_atlas.take_dirty(),
+                &text_renderer.sdf_instances,
+                text_renderer.halo.softness,
+            );
+        }
     }
 
     fn render(&mut self, render_pass: &mut RenderPass, global_context: &mut GlobalContext) {
-       global_context.text_renderer.render(render_pass);
+        if global_context.text_renderer.backend == TextBackend::Sdf {
+            self.sdf_pass.render(render_pass);
+        }
+        global_context.text_renderer.render(render_pass);
     }
 }
 
 pub struct TextRenderer {
     id_to_alpha_map: HashMap<u64, f32>,
-    default_face: DefaultFaceWrapper,
-    glyph_data: FxHashMap<GlyphId, Vec<GlyphData>>,
-    instance_buffer_map: FxHashMap<GlyphId, (usize, Buffer)>,
+    fonts: FontCollection,
+    glyph_data: FxHashMap<GlyphKey, Vec<GlyphData>>,
+    instance_buffer_map: FxHashMap<GlyphKey, (usize, Buffer)>,
+    backend: TextBackend,
+    halo: TextHalo,
+    sdf_atlas: SdfAtlas,
+    sdf_instances: Vec<SdfGlyphInstance>,
+    style_uniform_rx: Receiver<Vec<[f32; STYLE_SHADER_PARAMS_COUNT]>>,
+    // by the style indices
+    text_styles: Vec<TextStyle>,
 }
 
 impl TextRenderer {
     const FADE_ANIM_SPEED: f32 = 0.05;
+    // distance between the baselines of the wrapped lines in the label heights
+    const LINE_HEIGHT: f32 = 1.4;
 
-    pub fn new(device: &Device) -> TextRenderer {
-        let default_face = DefaultFaceWrapper::new(device);
-
+    pub fn new(style_uniform_rx: Receiver<Vec<[f32; STYLE_SHADER_PARAMS_COUNT]>>) -> TextRenderer {
         TextRenderer {
             id_to_alpha_map: HashMap::new(),
-            default_face,
+            fonts: FontCollection::new(),
             glyph_data: FxHashMap::default(),
             instance_buffer_map: FxHashMap::default(),
+            backend: TextBackend::default(),
+            halo: TextHalo::default(),
+            sdf_atlas: SdfAtlas::new(),
+            sdf_instances: vec![],
+            style_uniform_rx,
+            text_styles: vec![],
+        }
+    }
+
+    /// Picks up the styles updated since the last frame, before the labels are laid out.
+    pub fn update_styles(&mut self) {
+        if let Ok(uniforms) = self.style_uniform_rx.no_lagged() {
+            self.text_styles = uniforms.iter().map(TextStyle::from_params).collect();
         }
     }
 
+    pub fn add_font(&mut self, data: Vec<u8>) -> anyhow::Result<()> {
+        self.fonts.add_font(data)
+    }
+
+    pub fn set_backend(&mut self, backend: TextBackend) {
+        self.backend = backend;
+    }
+
+    pub fn set_halo(&mut self, halo: TextHalo) {
+        self.halo = halo;
+    }
+
     pub fn insert(
         &mut self,
         data: &mut TextNodeData,
         collision_handler: &mut CollisionHandler,
         screen_position_calculator: &ScreenPositionCalculator,
     ) {
-        let glyph_buffer = data
-            .glyph_buffer
-            .get_or_insert_with(|| self.default_face.shape(data.text.as_str()));
-
-        let glyphs_positions = glyph_buffer.glyph_positions();
-        let glyphs_infos = glyph_buffer.glyph_infos();
+        let style = self.text_styles.get(data.style_index).copied().unwrap_or_default();
+        if data
+            .shaped_text
+            .as_ref()
+            .is_none_or(|shaped_text| self.fonts.is_outdated(shaped_text, style.font))
+        {
+            data.shaped_text = Some(self.fonts.shape(data.text.as_str(), style.font));
+        }
+        let shaped_text = data.shaped_text.as_ref().unwrap();
+        let glyphs = &shaped_text.glyphs;
 
-        let (scale_m, width, height, scale) =
-            self.default_face.get_text_params(&glyph_buffer, data.size);
+        let (scale_m, height, scale) = self.fonts.get_text_params(data.size);
+        let letter_spacing = style.letter_spacing * data.size;
+        let advance = |glyph: &ShapedGlyph| glyph.x_advance * scale + letter_spacing;
 
         let mut glyphs_to_draw = vec![];
 
@@ -100,7 +183,7 @@ impl TextRenderer {
             let middle_point_index = line_positions.len() / 2;
             let mut prev: Option<Vector3<f32>> = None;
             let mut glyph_index = 0;
-            let glyphs_len = glyph_buffer.len();
+            let glyphs_len = glyphs.len();
             let segments_count = line_positions.len();
 
             let mut segments_len = 0.0;
@@ -152,10 +235,9 @@ impl TextRenderer {
                             glyph_index
                         };
 
-                        let position = glyphs_positions[real_glyph_index];
-                        let glyph_info = glyphs_infos[real_glyph_index];
+                        let glyph = glyphs[real_glyph_index];
 
-                        let x_advance = position.x_advance as f32 * scale;
+                        let x_advance = advance(&glyph);
                         let x_advance_vector = Vector3::new(x_advance, 0.0, 0.0);
                         let rotated_glyph_vector = seg_rotation.rotate_vector(x_advance_vector);
 
@@ -179,10 +261,11 @@ impl TextRenderer {
                         segments_vector += rotated_glyph_vector;
 
                         let item = GlyphData {
-                            glyph_id: GlyphId(glyph_info.glyph_id as u16),
+                            glyph_key: glyph.key,
                             alpha: 1.0,
                             position: (initial_position.x as f32, initial_position.y as f32),
                             matrix,
+                            style,
                         };
                         glyphs_to_draw.push((glyph_rect, item));
 
@@ -202,11 +285,8 @@ impl TextRenderer {
                     return;
                 }
 
-                let rects = glyphs_to_draw
-                    .iter()
-                    .map(|(rect, _)| rect.clone())
-                    .collect();
-                if collision_handler.insert_rectangles(rects) {
+                let rects = glyphs_to_draw.iter().map(|(rect, _)| *rect);
+                if collision_handler.insert(data.collision_key(), data.rank, rects) {
                     alpha = clamp(alpha + Self::FADE_ANIM_SPEED, 0.0, 1.0);
                 } else {
                     alpha = clamp(alpha - Self::FADE_ANIM_SPEED, 0.0, 1.0);
@@ -216,13 +296,22 @@ impl TextRenderer {
                 glyphs_to_draw.clear();
             }
         } else {
+            let max_width = style.max_width.map_or(f32::INFINITY, |max_width| max_width * data.size);
+            let lines: Vec<(Range<usize>, f32)> = Self::wrap_lines(glyphs, advance, max_width)
+                .into_iter()
+                .map(|line| {
+                    let width = glyphs[line.clone()].iter().map(advance).sum();
+                    (line, width)
+                })
+                .collect();
+            let width = lines.iter().fold(0.0f32, |width, (_, line_width)| width.max(*line_width));
+            let line_height = height * Self::LINE_HEIGHT;
+            let section_height = height + line_height * (lines.len() - 1) as f32;
             let origin = origin + coord! { x: (-width/2.0) as f64, y: 0.0 };
 
-            let mut glyph_total_x_advance = 0.0;
-            
             let section_rect = Rectangle::from_corners(
                 point! { x: origin.x as f32, y: origin.y as f32 },
-                point! { x: origin.x as f32 + width, y: origin.y as f32 + height },
+                point! { x: origin.x as f32 + width, y: origin.y as f32 + section_height },
             );
 
             let within_screen = collision_handler.within_screen(section_rect);
@@ -234,7 +323,7 @@ impl TextRenderer {
                     return;
                 }
 
-                if collision_handler.insert(section_rect) {
+                if collision_handler.insert(data.collision_key(), data.rank, [section_rect]) {
                     alpha = clamp(alpha + Self::FADE_ANIM_SPEED, 0.0, 1.0);
                 } else {
                     alpha = clamp(alpha - Self::FADE_ANIM_SPEED, 0.0, 1.0);
@@ -244,25 +333,28 @@ impl TextRenderer {
                 if data.alpha > 0.0 {
                     let stub_rect =
                         Rectangle::from_corners(point!(x: 0.0, y: 0.0), point!(x: 0.0, y: 0.0));
-                    for index in 0..glyph_buffer.len() {
-                        let position = glyphs_positions[index];
-                        let glyph_info = glyphs_infos[index];
-
-                        let matrix = Matrix4::from_translation(Vector3::new(
-                            glyph_total_x_advance + data.screen_offset.x + (-width / 2.0),
-                            -height - data.screen_offset.y,
-                            0.0,
-                        )) * scale_m;
-
-                        glyph_total_x_advance += position.x_advance as f32 * scale;
-
-                        let item = GlyphData {
-                            glyph_id: GlyphId(glyph_info.glyph_id as u16),
-                            alpha: data.alpha,
-                            position: (initial_position.x as f32, initial_position.y as f32),
-                            matrix,
-                        };
-                        glyphs_to_draw.push((stub_rect, item));
+                    // every line is centered
+                    for (line_index, (line, line_width)) in lines.iter().enumerate() {
+                        let mut glyph_total_x_advance = 0.0;
+                        let baseline = -height - line_height * line_index as f32;
+                        for glyph in &glyphs[line.clone()] {
+                            let matrix = Matrix4::from_translation(Vector3::new(
+                                glyph_total_x_advance + data.screen_offset.x + (-line_width / 2.0),
+                                baseline - data.screen_offset.y,
+                                0.0,
+                            )) * scale_m;
+
+                            glyph_total_x_advance += advance(glyph);
+
+                            let item = GlyphData {
+                                glyph_key: glyph.key,
+                                alpha: data.alpha,
+                                position: (initial_position.x as f32, initial_position.y as f32),
+                                matrix,
+                                style,
+                            };
+                            glyphs_to_draw.push((stub_rect, item));
+                        }
                     }
                 }
             }
@@ -271,7 +363,7 @@ impl TextRenderer {
         for (_, mut item) in glyphs_to_draw {
             item.alpha = data.alpha;
             self.glyph_data
-                .entry(item.glyph_id)
+                .entry(item.glyph_key)
                 .and_modify(|list| {
                     if data.alpha > 0.0 {
                         list.push(item.clone());
@@ -281,7 +373,36 @@ impl TextRenderer {
         }
     }
 
-    fn update_attrs(&mut self, queue: &Queue, device: &Device, cs_offset: &Vector3<f64>) {
+    // ranges of the glyphs, a line is broken at the last space before it gets wider than the max width
+    fn wrap_lines(
+        glyphs: &[ShapedGlyph],
+        advance: impl Fn(&ShapedGlyph) -> f32,
+        max_width: f32,
+    ) -> Vec<Range<usize>> {
+        let mut lines = vec![];
+        let mut start = 0;
+        let mut width = 0.0;
+        let mut last_space = None;
+        for (index, glyph) in glyphs.iter().enumerate() {
+            if glyph.whitespace {
+                last_space = Some(index);
+            }
+            width += advance(glyph);
+            if let Some(space) = last_space
+                && width > max_width
+                && !glyph.whitespace
+            {
+                lines.push(start..space);
+                start = space + 1;
+                width = glyphs[start..=index].iter().map(&advance).sum();
+                last_space = None;
+            }
+        }
+        lines.push(start..glyphs.len());
+        lines
+    }
+
+    fn update_attrs(&mut self, queue: &Queue, device: &Device, cs_offset: &Vector3<f64>, text_color: [f32; 4]) {
         self.glyph_data.iter().for_each(|(key, list)| {
             let mut attrs = vec![];
             list.iter().for_each(|glyph_data| {
@@ -291,7 +412,8 @@ impl TextRenderer {
                         .into(),
                     color_alpha: glyph_data.alpha,
                     matrix: glyph_data.matrix.cast().unwrap().into(),
-                    bbox: [0.0, 0.0, 0.0, 0.0],
+                    // the text shader takes the color of the label from it
+                    bbox: glyph_data.style.color.unwrap_or(text_color),
                     normal_scale: 1f32
                 };
                 attrs.push(instance_pos);
@@ -323,18 +445,53 @@ impl TextRenderer {
         })
     }
 
-    pub fn update(&mut self, queue: &Queue, device: &wgpu::Device, cs_offset: &Vector3<f64>) {
+    // all the glyphs of the frame as the quads of the atlas
+    fn update_sdf_instances(&mut self, cs_offset: &Vector3<f64>, text_color: [f32; 4]) {
+        self.sdf_instances.clear();
+        for (key, list) in &self.glyph_data {
+            let Some(glyph) = self.sdf_atlas.glyph(&self.fonts, key) else {
+                continue;
+            };
+            let halo = &self.halo;
+            self.sdf_instances.extend(list.iter().map(|glyph_data| {
+                let (halo_color, halo_width) = glyph_data.style.halo.unwrap_or((halo.color, halo.width));
+                SdfGlyphInstance {
+                    position: [
+                        glyph_data.position.0 - cs_offset.x as f32,
+                        glyph_data.position.1 - cs_offset.y as f32,
+                        0.0,
+                    ],
+                    color_alpha: glyph_data.alpha,
+                    matrix: glyph_data.matrix.into(),
+                    rect: glyph.rect,
+                    uv: glyph.uv,
+                    color: glyph_data.style.color.unwrap_or(text_color),
+                    halo_color,
+                    halo_width,
+                }
+            }));
+        }
+    }
+
+    /// The labels without a text color in their style are drawn in the text color.
+    pub fn update(&mut self, queue: &Queue, device: &wgpu::Device, cs_offset: &Vector3<f64>, text_color: [f32; 4]) {
         self.id_to_alpha_map.clear();
-        self.update_attrs(queue, device, cs_offset);
+        match self.backend {
+            TextBackend::Mesh => {
+                self.fonts.prepare_glyphs(device, self.glyph_data.keys());
+                self.update_attrs(queue, device, cs_offset, text_color);
+            }
+            TextBackend::Sdf => self.update_sdf_instances(cs_offset, text_color),
+        }
     }
 
     pub fn render(&mut self, render_pass: &mut RenderPass) {
-        if !self.instance_buffer_map.is_empty() && !self.glyph_data.is_empty() {
-            self.glyph_data.iter().for_each(|(glyph_id, list)| {
-                if let Some(mesh) = self.default_face.glyph_mesh_map.get(glyph_id) {
+        if self.backend == TextBackend::Mesh && !self.instance_buffer_map.is_empty() && !self.glyph_data.is_empty() {
+            self.glyph_data.iter().for_each(|(glyph_key, list)| {
+                if let Some(mesh) = self.fonts.glyph_mesh(glyph_key) {
                     let v_buf = mesh.vertex_buf.get(0).unwrap();
                     let i_buf = mesh.index_buf.get(0).unwrap();
-                    let instance_buffer = self.instance_buffer_map.get(glyph_id).unwrap();
+                    let instance_buffer = self.instance_buffer_map.get(glyph_key).unwrap();
 
                     render_pass.set_vertex_buffer(0, v_buf.slice(..));
                     render_pass.set_index_buffer(i_buf.0.slice(..), wgpu::IndexFormat::Uint32);
@@ -11,12 +11,19 @@ pub struct ShapeVertex {
     pub normals: [f32; 3],
     pub dist: f32,
     pub style_index: u32,
+    /// Tessellated width of lines, 0 for fills
+    pub width: f32,
 }
 
 impl VertexAttrib for ShapeVertex {
     fn desc() -> wgpu::VertexBufferLayout<'static> {
-        const ATTRIBUTES: &[VertexAttribute; 4] =
-            &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32, 3 => Uint32];
+        const ATTRIBUTES: &[VertexAttribute; 5] = &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x3,
+            2 => Float32,
+            3 => Uint32,
+            12 => Float32
+        ];
         use std::mem;
         wgpu::VertexBufferLayout {
             array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
@@ -77,3 +84,62 @@ impl VertexAttrib for InstancePos {
         }
     }
 }
+
+/// Glyph quad of the SDF text, the corners come from the vertex index.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SdfGlyphInstance {
+    pub(crate) position: [f32; 3],
+    pub(crate) color_alpha: f32,
+    pub(crate) matrix: [[f32; 4]; 4],
+    pub(crate) rect: [f32; 4],
+    pub(crate) uv: [f32; 4],
+    pub(crate) color: [f32; 4],
+    pub(crate) halo_color: [f32; 4],
+    pub(crate) halo_width: f32,
+}
+
+impl VertexAttrib for SdfGlyphInstance {
+    fn desc() -> VertexBufferLayout<'static> {
+        const ATTRIBUTES: &[VertexAttribute; 11] = &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32,
+            2 => Float32x4,
+            3 => Float32x4,
+            4 => Float32x4,
+            5 => Float32x4,
+            6 => Float32x4,
+            7 => Float32x4,
+            8 => Float32x4,
+            9 => Float32x4,
+            10 => Float32,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorVertex {
+    /// In screen pixels
+    pub(crate) position: [f32; 2],
+    pub(crate) color: [f32; 4],
+}
+
+impl VertexAttrib for ColorVertex {
+    fn desc() -> VertexBufferLayout<'static> {
+        const ATTRIBUTES: &[VertexAttribute; 2] =
+            &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: ATTRIBUTES,
+        }
+    }
+}
@@ -46,6 +46,9 @@ impl<'a> ScreenPositionCalculator<'a> {
 pub(crate) struct ViewProjUniform {
     view_proj: [[f32; 4]; 4],
     inv_screen_size: [f32; 2],
+    zoom_level: f32,
+    _padding: f32,
+    text_color: [f32; 4],
 }
 
 pub(crate) struct ViewProjection {
@@ -60,6 +63,9 @@ impl ViewProjection {
             uniform: ViewProjUniform {
                 view_proj: Matrix4::identity().into(),
                 inv_screen_size: [0.0, 0.0],
+                zoom_level: 0.0,
+                _padding: 0.0,
+                text_color: [0.0, 0.0, 0.0, 1.0],
             },
             cs_offset: Vector3::new(0.0, 0.0, 0.0),
             inv_view_proj_matrix: Matrix4::identity()
@@ -75,6 +81,18 @@ impl ViewProjection {
         self.inv_view_proj_matrix = view_proj_matrix.inverse_transform().unwrap();
     }
 
+    pub fn set_zoom_level(&mut self, zoom_level: f32) {
+        self.uniform.zoom_level = zoom_level;
+    }
+
+    pub fn set_text_color(&mut self, text_color: [f32; 4]) {
+        self.uniform.text_color = text_color;
+    }
+
+    pub fn text_color(&self) -> [f32; 4] {
+        self.uniform.text_color
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
         self.uniform.inv_screen_size = [1.0 / width as f32, 1.0 / height as f32];
     }
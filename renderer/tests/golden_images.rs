@@ -0,0 +1,439 @@
+mod harness;
+
+use cgmath::{Vector2, Vector3};
+use harness::{GoldenScene, Tolerance};
+use lyon::geom::point;
+use lyon::lyon_tessellation::{LineCap, LineJoin};
+use lyon::path::Path;
+use renderer::draw_commands::{GeometryType, PolylineOptions};
+use renderer::geometry_data::{ExtrudedPolygonData, ShapeData, SvgData, TextData};
+use renderer::styles::render_style::RenderStyle;
+use renderer::styles::style_id::StyleId;
+use renderer::{TextBackend, TextHalo};
+
+const PIN_SVG: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24">
+<circle cx="12" cy="12" r="10" fill="#000000"/>
+<rect x="8" y="8" width="8" height="8" fill="#000000"/>
+</svg>"##;
+
+fn polyline(points: &[(f32, f32)]) -> Path {
+    let mut builder = Path::builder();
+    builder.begin(point(points[0].0, points[0].1));
+    points[1..].iter().for_each(|(x, y)| {
+        builder.line_to(point(*x, *y));
+    });
+    builder.end(false);
+    builder.build()
+}
+
+fn polygon(points: &[(f32, f32)]) -> Path {
+    let mut builder = Path::builder();
+    builder.begin(point(points[0].0, points[0].1));
+    points[1..].iter().for_each(|(x, y)| {
+        builder.line_to(point(*x, *y));
+    });
+    builder.end(true);
+    builder.build()
+}
+
+#[test]
+fn shapes() {
+    GoldenScene::new("shapes")
+        .style(StyleId("golden_water"), RenderStyle::fill([0.2, 0.4, 0.9, 1.0]))
+        .style(StyleId("golden_road"), RenderStyle::border([1.0, 0.8, 0.3, 1.0], 0.5))
+        .style(
+            StyleId("golden_rails"),
+            RenderStyle::dashed([0.2, 0.2, 0.2, 1.0], [0.9, 0.9, 0.9, 1.0]),
+        )
+        .group(0, |canvas| {
+            canvas.path(ShapeData {
+                path: polygon(&[(-40.0, -40.0), (0.0, -40.0), (0.0, 0.0), (-40.0, 0.0)]),
+                geometry_type: GeometryType::Polygon,
+                style_id: StyleId("golden_water"),
+                index_layer_level: -100,
+                is_screen: false,
+            });
+            canvas.path(ShapeData {
+                path: polyline(&[(-45.0, 20.0), (0.0, 30.0), (20.0, -10.0), (45.0, -30.0)]),
+                geometry_type: GeometryType::Polyline(PolylineOptions {
+                    width: 4.0,
+                    line_join: LineJoin::Round,
+                    line_cap: LineCap::Round,
+                    tolerance: 0.05,
+                }),
+                style_id: StyleId("golden_road"),
+                index_layer_level: 0,
+                is_screen: false,
+            });
+            canvas.path(ShapeData {
+                path: polyline(&[(-45.0, 40.0), (45.0, 40.0)]),
+                geometry_type: GeometryType::Polyline(PolylineOptions {
+                    width: 2.0,
+                    ..Default::default()
+                }),
+                style_id: StyleId("golden_rails"),
+                index_layer_level: 1,
+                is_screen: false,
+            });
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn svg_icons() {
+    GoldenScene::new("svg_icons")
+        .style(StyleId("golden_icon"), RenderStyle::fill([0.8, 0.1, 0.3, 1.0]))
+        .group(0, |canvas| {
+            [(-25.0, -25.0), (25.0, 25.0), (25.0, -25.0)]
+                .into_iter()
+                .for_each(|(x, y)| {
+                    canvas.geometry_data(renderer::geometry_data::GeometryData::Svg(SvgData {
+                        icon: ("golden_pin", PIN_SVG),
+                        position: Vector3::new(x, y, 0.0),
+                        size: 32.0,
+                        style_id: StyleId("golden_icon"),
+                        with_collision: false,
+                        rank: 0,
+                        symbol_id: None,
+                    }));
+                });
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn text() {
+    GoldenScene::new("text")
+        .group(0, |canvas| {
+            canvas.text(TextData {
+                id: 1,
+                text: "SHASHLIK".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 30.0,
+                positions: vec![Vector3::new(0.0, 25.0, 0.0)],
+                style_id: StyleId("golden_label"),
+                rank: 0,
+                symbol_id: None,
+            });
+            canvas.text(TextData {
+                id: 2,
+                text: "ROAD-42".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 24.0,
+                positions: vec![
+                    Vector3::new(-40.0, -30.0, 0.0),
+                    Vector3::new(-20.0, -25.0, 0.0),
+                    Vector3::new(0.0, -20.0, 0.0),
+                    Vector3::new(20.0, -15.0, 0.0),
+                    Vector3::new(40.0, -10.0, 0.0),
+                ],
+                style_id: StyleId("golden_label"),
+                rank: 0,
+                symbol_id: None,
+            });
+        })
+        // glyph edges are sensitive to the rasterizer
+        .tolerance(Tolerance {
+            channel: 8,
+            ..Default::default()
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn mixed_case_text() {
+    GoldenScene::new("mixed_case_text")
+        .group(0, |canvas| {
+            canvas.text(TextData {
+                id: 1,
+                text: "Shinjuku Station".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 26.0,
+                positions: vec![Vector3::new(0.0, 0.0, 0.0)],
+                style_id: StyleId("golden_label"),
+                rank: 0,
+                symbol_id: None,
+            });
+        })
+        .tolerance(Tolerance {
+            channel: 8,
+            ..Default::default()
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn sdf_text() {
+    // the same labels as the text test, drawn from the atlas with a dark halo
+    GoldenScene::new("sdf_text")
+        .text_backend(TextBackend::Sdf)
+        .text_halo(TextHalo {
+            color: [0.1, 0.1, 0.2, 1.0],
+            width: 2.0,
+            softness: 0.5,
+        })
+        .text_color([1.0, 0.9, 0.3, 1.0])
+        .group(0, |canvas| {
+            canvas.text(TextData {
+                id: 1,
+                text: "SHASHLIK".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 30.0,
+                positions: vec![Vector3::new(0.0, 25.0, 0.0)],
+                style_id: StyleId("golden_label"),
+                rank: 0,
+                symbol_id: None,
+            });
+            canvas.text(TextData {
+                id: 2,
+                text: "ROAD-42".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 24.0,
+                positions: vec![
+                    Vector3::new(-40.0, -30.0, 0.0),
+                    Vector3::new(-20.0, -25.0, 0.0),
+                    Vector3::new(0.0, -20.0, 0.0),
+                    Vector3::new(20.0, -15.0, 0.0),
+                    Vector3::new(40.0, -10.0, 0.0),
+                ],
+                style_id: StyleId("golden_label"),
+                rank: 0,
+                symbol_id: None,
+            });
+        })
+        .tolerance(Tolerance {
+            channel: 8,
+            ..Default::default()
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn styled_text() {
+    // a wrapped red station name with a halo next to a spaced out road name in the default text color
+    GoldenScene::new("styled_text")
+        .text_backend(TextBackend::Sdf)
+        .style(
+            StyleId("golden_station"),
+            RenderStyle::text(Some([0.8, 0.1, 0.2, 1.0]))
+                .with_halo([1.0, 1.0, 1.0, 1.0], 2.0)
+                .with_max_width(5.0),
+        )
+        .style(StyleId("golden_road_label"), RenderStyle::text(None).with_letter_spacing(0.3))
+        .group(0, |canvas| {
+            canvas.text(TextData {
+                id: 1,
+                text: "SHINJUKU WEST EXIT".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 24.0,
+                positions: vec![Vector3::new(0.0, 30.0, 0.0)],
+                style_id: StyleId("golden_station"),
+                rank: 0,
+                symbol_id: None,
+            });
+            canvas.text(TextData {
+                id: 2,
+                text: "ROAD".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 24.0,
+                positions: vec![Vector3::new(0.0, -30.0, 0.0)],
+                style_id: StyleId("golden_road_label"),
+                rank: 0,
+                symbol_id: None,
+            });
+        })
+        .tolerance(Tolerance {
+            channel: 8,
+            ..Default::default()
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn collision_ranks() {
+    // HIGH wins over the LOW label inserted before it, TOP hides the left pin label together with its icon
+    let label = |id: u64, text: &str, position: (f32, f32), offset: f32, rank: i32, symbol_id: Option<u64>| TextData {
+        id,
+        text: text.to_string(),
+        screen_offset: Vector2::new(0.0, offset),
+        size: 24.0,
+        positions: vec![Vector3::new(position.0, position.1, 0.0)],
+        style_id: StyleId("golden_label"),
+        rank,
+        symbol_id,
+    };
+    GoldenScene::new("collision_ranks")
+        .style(StyleId("golden_icon"), RenderStyle::fill([0.8, 0.1, 0.3, 1.0]))
+        .collision_debug()
+        .group(0, move |canvas| {
+            canvas.text(label(1, "LOW", (0.0, 30.0), 0.0, 0, None));
+            canvas.text(label(2, "HIGH", (5.0, 28.0), 0.0, 10, None));
+            [(7, -25.0), (8, 25.0)].into_iter().for_each(|(symbol_id, x)| {
+                canvas.geometry_data(renderer::geometry_data::GeometryData::Svg(SvgData {
+                    icon: ("golden_pin", PIN_SVG),
+                    position: Vector3::new(x, -20.0, 0.0),
+                    size: 32.0,
+                    style_id: StyleId("golden_icon"),
+                    with_collision: true,
+                    rank: 5,
+                    symbol_id: Some(symbol_id),
+                }));
+                canvas.text(label(symbol_id + 10, "PIN", (x as f32, -20.0), 30.0, 5, Some(symbol_id)));
+            });
+            canvas.text(label(4, "TOP", (-25.0, -8.0), 0.0, 20, None));
+        })
+        .tolerance(Tolerance {
+            channel: 8,
+            ..Default::default()
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn extruded_polygons() {
+    GoldenScene::new("extruded_polygons")
+        .group(0, |canvas| {
+            canvas.extruded_polygon(ExtrudedPolygonData {
+                path: polygon(&[(-30.0, -30.0), (-30.0, 0.0), (0.0, 0.0), (0.0, -30.0)]),
+                height: 10.0,
+            });
+            canvas.extruded_polygon(ExtrudedPolygonData {
+                path: polygon(&[(10.0, 10.0), (10.0, 35.0), (35.0, 35.0), (35.0, 10.0)]),
+                height: 25.0,
+            });
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn zoom_stops() {
+    // at zoom level 2 the roads are twice as wide, purple and the rails have longer dashes
+    GoldenScene::new("zoom_stops")
+        .zoom_level(2.0)
+        .style(
+            StyleId("golden_zoom_road"),
+            RenderStyle::border([1.0, 0.0, 0.0, 1.0], 0.5)
+                .with_color_stops(&[(0.0, [1.0, 0.0, 0.0, 1.0]), (4.0, [0.0, 0.0, 1.0, 1.0])])
+                .with_width_stops(&[(0.0, 1.0), (4.0, 3.0)]),
+        )
+        .style(
+            StyleId("golden_zoom_rails"),
+            RenderStyle::dashed([0.2, 0.2, 0.2, 1.0], [0.9, 0.9, 0.9, 1.0])
+                .with_dash_stops(&[(0.0, 1.25), (4.0, 6.25)]),
+        )
+        .style(
+            StyleId("golden_zoom_water"),
+            RenderStyle::fill([0.2, 0.4, 0.9, 1.0]).with_opacity_stops(&[(1.0, 1.0), (3.0, 0.0)]),
+        )
+        .group(0, |canvas| {
+            canvas.path(ShapeData {
+                path: polygon(&[(-40.0, -40.0), (0.0, -40.0), (0.0, 0.0), (-40.0, 0.0)]),
+                geometry_type: GeometryType::Polygon,
+                style_id: StyleId("golden_zoom_water"),
+                index_layer_level: -100,
+                is_screen: false,
+            });
+            canvas.path(ShapeData {
+                path: polyline(&[(-45.0, 10.0), (45.0, 10.0)]),
+                geometry_type: GeometryType::Polyline(PolylineOptions {
+                    width: 4.0,
+                    ..Default::default()
+                }),
+                style_id: StyleId("golden_zoom_road"),
+                index_layer_level: 0,
+                is_screen: false,
+            });
+            canvas.path(ShapeData {
+                path: polyline(&[(-45.0, 40.0), (45.0, 40.0)]),
+                geometry_type: GeometryType::Polyline(PolylineOptions {
+                    width: 2.0,
+                    ..Default::default()
+                }),
+                style_id: StyleId("golden_zoom_rails"),
+                index_layer_level: 1,
+                is_screen: false,
+            });
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn night_colors() {
+    // the theme color replaces the zoom stops of the water
+    GoldenScene::new("night_colors")
+        .style(
+            StyleId("golden_night_water"),
+            RenderStyle::fill([0.2, 0.4, 0.9, 1.0])
+                .with_color_stops(&[(0.0, [0.2, 0.4, 0.9, 1.0]), (4.0, [0.1, 0.6, 0.3, 1.0])])
+                .with_fill_color([0.01, 0.02, 0.05, 1.0]),
+        )
+        .clear_color([0.005, 0.01, 0.02, 1.0])
+        .text_color([0.9, 0.9, 0.6, 1.0])
+        .group(0, |canvas| {
+            canvas.path(ShapeData {
+                path: polygon(&[(-40.0, -40.0), (0.0, -40.0), (0.0, 0.0), (-40.0, 0.0)]),
+                geometry_type: GeometryType::Polygon,
+                style_id: StyleId("golden_night_water"),
+                index_layer_level: -100,
+                is_screen: false,
+            });
+            canvas.text(TextData {
+                id: 1,
+                text: "NIGHT".to_string(),
+                screen_offset: Vector2::new(0.0, 0.0),
+                size: 30.0,
+                positions: vec![Vector3::new(0.0, 25.0, 0.0)],
+                style_id: StyleId("golden_label"),
+                rank: 0,
+                symbol_id: None,
+            });
+        })
+        .tolerance(Tolerance {
+            channel: 8,
+            ..Default::default()
+        })
+        .assert_matches_golden();
+}
+
+#[test]
+fn route_progress() {
+    // the route is faded before 60 units along it, the chevrons are only after that
+    let route = polyline(&[(-45.0, -20.0), (0.0, -20.0), (0.0, 30.0), (45.0, 30.0)]);
+    let options = PolylineOptions {
+        width: 8.0,
+        line_join: LineJoin::Round,
+        line_cap: LineCap::Round,
+        tolerance: 0.05,
+    };
+    GoldenScene::new("route_progress")
+        .style(
+            StyleId("golden_route"),
+            RenderStyle::border([0.1, 0.4, 1.0, 1.0], 0.5)
+                .with_traveled_color([0.6, 0.6, 0.65, 1.0])
+                .with_progress(60.0),
+        )
+        .style(
+            StyleId("golden_route_chevron"),
+            RenderStyle::chevrons([1.0, 1.0, 1.0, 1.0])
+                .with_dash_stops(&[(0.0, 1.5)])
+                .with_traveled_color([0.0; 4])
+                .with_progress(60.0),
+        )
+        .group(0, move |canvas| {
+            canvas.path(ShapeData {
+                path: route.clone(),
+                geometry_type: GeometryType::Polyline(options),
+                style_id: StyleId("golden_route"),
+                index_layer_level: 0,
+                is_screen: false,
+            });
+            canvas.path(ShapeData {
+                path: route.clone(),
+                geometry_type: GeometryType::Polyline(options),
+                style_id: StyleId("golden_route_chevron"),
+                index_layer_level: 1,
+                is_screen: false,
+            });
+        })
+        .assert_matches_golden();
+}
@@ -0,0 +1,273 @@
+//! Golden-image harness: renders a scripted scene offscreen with a fixed view projection
+//! and compares the frame with a checked-in PNG.
+//!
+//! Run with `SHASHLIK_UPDATE_GOLDEN=1` to (re)write the reference images.
+//! The harness uses wgpu's fallback (software) adapter, so it works without a GPU. When the
+//! platform has no fallback adapter the test is skipped, unless `SHASHLIK_GOLDEN_REQUIRE_ADAPTER=1`.
+
+use cgmath::{Matrix4, Vector3, point3};
+use renderer::canvas_api::CanvasApi;
+use renderer::modifier::render_modifier::SpatialData;
+use renderer::render_group::RenderGroup;
+use renderer::styles::render_style::RenderStyle;
+use renderer::styles::style_id::StyleId;
+use renderer::{Renderer, ShashlikRenderer, TextBackend, TextHalo};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use wgpu_canvas::texture_canvas::TextureCanvas;
+
+pub const WIDTH: u32 = 256;
+pub const HEIGHT: u32 = 256;
+
+// enough frames for fade-in animations of texts and icons to complete
+const WARMUP_FRAMES: usize = 30;
+
+pub struct Tolerance {
+    /// Max difference per color channel for a pixel to be considered equal
+    pub channel: u8,
+    /// Ratio of pixels which are allowed to exceed the channel tolerance, e.g. AA edges
+    pub mismatched_ratio: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            channel: 3,
+            mismatched_ratio: 0.002,
+        }
+    }
+}
+
+pub struct GoldenScene {
+    name: &'static str,
+    styles: Vec<(StyleId, RenderStyle)>,
+    groups: Vec<(String, usize, SpatialData, Box<dyn RenderGroup>)>,
+    tolerance: Tolerance,
+    zoom_level: f32,
+    clear_color: Option<[f32; 4]>,
+    text_color: Option<[f32; 4]>,
+    text_backend: TextBackend,
+    text_halo: Option<TextHalo>,
+    collision_debug: bool,
+}
+
+impl GoldenScene {
+    pub fn new(name: &'static str) -> Self {
+        GoldenScene {
+            name,
+            styles: vec![],
+            groups: vec![],
+            tolerance: Tolerance::default(),
+            zoom_level: 0.0,
+            clear_color: None,
+            text_color: None,
+            text_backend: TextBackend::default(),
+            text_halo: None,
+            collision_debug: false,
+        }
+    }
+
+    pub fn style(mut self, style_id: StyleId, style: RenderStyle) -> Self {
+        self.styles.push((style_id, style));
+        self
+    }
+
+    pub fn group<F: FnMut(&mut CanvasApi) + Send + 'static>(mut self, layer: usize, content: F) -> Self {
+        let key = format!("{}_{}", self.name, self.groups.len());
+        self.groups.push((
+            key,
+            layer,
+            SpatialData::transform(Vector3::new(0.0, 0.0, 0.0)),
+            Box::new(ScriptedGroup { content }),
+        ));
+        self
+    }
+
+    /// Map zoom level used for the style zoom stops
+    pub fn zoom_level(mut self, zoom_level: f32) -> Self {
+        self.zoom_level = zoom_level;
+        self
+    }
+
+    pub fn clear_color(mut self, color: [f32; 4]) -> Self {
+        self.clear_color = Some(color);
+        self
+    }
+
+    pub fn text_color(mut self, color: [f32; 4]) -> Self {
+        self.text_color = Some(color);
+        self
+    }
+
+    pub fn text_backend(mut self, backend: TextBackend) -> Self {
+        self.text_backend = backend;
+        self
+    }
+
+    pub fn text_halo(mut self, halo: TextHalo) -> Self {
+        self.text_halo = Some(halo);
+        self
+    }
+
+    /// Outlines the collision rectangles of the labels and icons
+    pub fn collision_debug(mut self) -> Self {
+        self.collision_debug = true;
+        self
+    }
+
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn assert_matches_golden(mut self) {
+        let name = self.name;
+        let Some(pixels) = self.render() else {
+            return;
+        };
+
+        let golden_path = golden_dir().join(format!("{}.png", name));
+        if std::env::var("SHASHLIK_UPDATE_GOLDEN").is_ok() {
+            write_png(&golden_path, &pixels);
+            return;
+        }
+
+        let expected = read_png(&golden_path).unwrap_or_else(|| {
+            panic!(
+                "Golden image {:?} is missing, run the test with SHASHLIK_UPDATE_GOLDEN=1 to create it",
+                golden_path
+            )
+        });
+
+        let tolerance = &self.tolerance;
+        let mismatched = count_mismatched(&expected, &pixels, tolerance.channel);
+        let allowed = ((WIDTH * HEIGHT) as f32 * tolerance.mismatched_ratio) as usize;
+        if mismatched > allowed {
+            let actual_path =
+                PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.actual.png", name));
+            write_png(&actual_path, &pixels);
+            panic!(
+                "{} pixels differ from the golden image {:?} (allowed {}), actual frame is saved to {:?}",
+                mismatched, golden_path, allowed, actual_path
+            );
+        }
+    }
+
+    fn render(&mut self) -> Option<Vec<u8>> {
+        let name = self.name;
+        let canvas = match pollster::block_on(TextureCanvas::headless(WIDTH, HEIGHT, true)) {
+            Ok(canvas) => canvas,
+            Err(err) => {
+                if std::env::var("SHASHLIK_GOLDEN_REQUIRE_ADAPTER").is_ok() {
+                    panic!("Fallback adapter is required: {err}");
+                }
+                eprintln!("Skipping golden image test {name}: {err}");
+                return None;
+            }
+        };
+
+        let mut renderer = pollster::block_on(ShashlikRenderer::new(&[], Box::new(canvas))).unwrap();
+        renderer.set_fps_visible(false);
+        renderer.set_zoom_level(self.zoom_level);
+        if let Some(color) = self.clear_color {
+            renderer.set_clear_color(color);
+        }
+        if let Some(color) = self.text_color {
+            renderer.set_text_color(color);
+        }
+        renderer.set_text_backend(self.text_backend);
+        if let Some(halo) = self.text_halo {
+            renderer.set_text_halo(halo);
+        }
+        renderer.set_collision_debug(self.collision_debug);
+
+        std::mem::take(&mut self.styles).into_iter().for_each(|(style_id, style)| {
+            renderer.api.update_style(style_id, move |it| *it = style);
+        });
+        std::mem::take(&mut self.groups)
+            .into_iter()
+            .for_each(|(key, layer, spatial_data, group)| {
+                renderer.api.add_render_group(key, layer, spatial_data, group);
+            });
+        renderer.apply_pending_render_groups();
+
+        let view_proj = fixed_view_projection();
+        let cs_offset = Vector3::new(0.0, 0.0, 0.0);
+        for _ in 0..WARMUP_FRAMES {
+            renderer.update(view_proj, cs_offset);
+            renderer.render().unwrap();
+        }
+        renderer.update(view_proj, cs_offset);
+        Some(renderer.render_to_rgba().unwrap())
+    }
+}
+
+struct ScriptedGroup<F: FnMut(&mut CanvasApi) + Send> {
+    content: F,
+}
+
+impl<F: FnMut(&mut CanvasApi) + Send> RenderGroup for ScriptedGroup<F> {
+    fn content(&mut self, canvas: &mut CanvasApi) {
+        (self.content)(canvas);
+    }
+}
+
+/// Top-down camera looking at the world origin, 100x100 world units are visible.
+pub fn fixed_view_projection() -> Matrix4<f64> {
+    let distance = 50.0 / (22.5f64).to_radians().tan();
+    let view = Matrix4::look_at_rh(
+        point3(0.0, 0.0, distance),
+        point3(0.0, 0.0, 0.0),
+        Vector3::unit_y(),
+    );
+    let projection = cgmath::perspective(
+        cgmath::Deg(45.0),
+        WIDTH as f64 / HEIGHT as f64,
+        1.0,
+        2000.0,
+    );
+    projection * view
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+fn count_mismatched(expected: &[u8], actual: &[u8], channel_tolerance: u8) -> usize {
+    if expected.len() != actual.len() {
+        return usize::MAX;
+    }
+    expected
+        .chunks(4)
+        .zip(actual.chunks(4))
+        .filter(|(e, a)| {
+            e.iter()
+                .zip(a.iter())
+                .any(|(e, a)| e.abs_diff(*a) > channel_tolerance)
+        })
+        .count()
+}
+
+fn read_png(path: &PathBuf) -> Option<Vec<u8>> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let mut reader = decoder.read_info().ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).ok()?;
+    if info.width != WIDTH || info.height != HEIGHT || info.color_type != png::ColorType::Rgba {
+        return None;
+    }
+    buf.truncate(info.buffer_size());
+    Some(buf)
+}
+
+fn write_png(path: &PathBuf, pixels: &[u8]) {
+    let file = File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(pixels).unwrap();
+}
@@ -1 +1,2 @@
+pub mod texture_canvas;
 pub mod wgpu_canvas;
\ No newline at end of file
@@ -0,0 +1,225 @@
+use crate::wgpu_canvas::{CanvasTexture, WgpuCanvas};
+use std::sync::mpsc::channel;
+use wgpu::{SurfaceError, TextureFormat};
+
+/// Offscreen [`WgpuCanvas`] backed by a plain texture, e.g. for snapshot tests,
+/// static map images or embedding the map into another UI toolkit.
+pub struct TextureCanvas {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    texture: wgpu::Texture,
+}
+
+impl TextureCanvas {
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue, width: u32, height: u32) -> Self {
+        let config = wgpu::SurfaceConfiguration {
+            usage: Self::usage(),
+            format: Self::FORMAT,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            desired_maximum_frame_latency: 1,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        let texture = Self::create_texture(&device, &config);
+        Self {
+            device,
+            queue,
+            config,
+            texture,
+        }
+    }
+
+    /// Creates a canvas with its own device. `force_fallback_adapter` selects the software
+    /// adapter, so it can be used on machines without a GPU.
+    pub async fn headless(
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+    ) -> Result<Self, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::from_env()
+                    .unwrap_or(wgpu::PowerPreference::HighPerformance),
+                force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await
+            .map_err(|err| format!("No suitable adapter found: {err}"))?;
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("TextureCanvas device"),
+                required_features: adapter.features(),
+                required_limits: adapter.limits(),
+                // enabled since wgpu-text fork uses it
+                experimental_features: unsafe { wgpu::ExperimentalFeatures::enabled() },
+                memory_hints: wgpu::MemoryHints::Performance,
+                trace: wgpu::Trace::Off,
+            })
+            .await
+            .map_err(|err| format!("request_device failed: {err}"))?;
+
+        Ok(Self::new(device, queue, width, height))
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Changes the size of the canvas, the texture is recreated by the following `on_resize`.
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.config.width = width.max(1);
+        self.config.height = height.max(1);
+    }
+
+    /// Reads back the current content of the canvas as tightly packed RGBA8 rows.
+    pub fn read_pixels(&self) -> Result<Vec<u8>, String> {
+        read_rgba(&self.device, &self.queue, &self.texture)
+    }
+
+    fn usage() -> wgpu::TextureUsages {
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING
+    }
+
+    fn create_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("TextureCanvas texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
+        })
+    }
+}
+
+impl WgpuCanvas for TextureCanvas {
+    fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    fn config(&self) -> &wgpu::SurfaceConfiguration {
+        &self.config
+    }
+
+    fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    fn get_current_texture(&self) -> Result<CanvasTexture, SurfaceError> {
+        Ok(CanvasTexture::Texture(self.texture.clone()))
+    }
+
+    fn on_resize(&mut self, width: u32, height: u32) {
+        self.set_size(width, height);
+        let size = self.texture.size();
+        if size.width != self.config.width || size.height != self.config.height {
+            self.texture = Self::create_texture(&self.device, &self.config);
+        }
+    }
+
+    fn on_pre_render(&self) {
+        // not required
+    }
+
+    fn on_post_render(&self) {
+        // not required
+    }
+}
+
+/// Copies a 2D RGBA8/BGRA8 texture into memory as tightly packed RGBA8 rows.
+/// Blocks until the GPU is done with all the submitted work.
+/// Fails for the other formats and for the textures without `COPY_SRC`, e.g. most of the surfaces.
+pub fn read_rgba(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, String> {
+    const BYTES_PER_PIXEL: u32 = 4;
+    if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+        return Err("The texture can't be read back, it has no COPY_SRC usage".to_string());
+    }
+    let is_bgra = match texture.format() {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+        format => return Err(format!("Reading back {format:?} textures isn't supported")),
+    };
+    let size = texture.size();
+    let unpadded_bytes_per_row = size.width * BYTES_PER_PIXEL;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * size.height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(size.height),
+            },
+        },
+        wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (tx, rx) = channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .map_err(|err| format!("Device poll failed during readback: {err}"))?;
+    rx.recv()
+        .map_err(|err| format!("Mapping the readback buffer failed: {err}"))?
+        .map_err(|err| format!("Mapping the readback buffer failed: {err}"))?;
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * size.height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    if is_bgra {
+        pixels.chunks_mut(BYTES_PER_PIXEL as usize).for_each(|pixel| pixel.swap(0, 2));
+    }
+
+    Ok(pixels)
+}
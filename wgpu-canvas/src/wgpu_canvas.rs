@@ -1,12 +1,33 @@
 use wgpu::{SurfaceError, SurfaceTexture};
 
+/// A frame target acquired from a [`WgpuCanvas`].
+pub enum CanvasTexture {
+    Surface(SurfaceTexture),
+    Texture(wgpu::Texture),
+}
+
+impl CanvasTexture {
+    pub fn texture(&self) -> &wgpu::Texture {
+        match self {
+            CanvasTexture::Surface(surface_texture) => &surface_texture.texture,
+            CanvasTexture::Texture(texture) => texture,
+        }
+    }
+
+    pub fn present(self) {
+        if let CanvasTexture::Surface(surface_texture) = self {
+            surface_texture.present();
+        }
+    }
+}
+
 pub trait WgpuCanvas: Send + Sync {
     fn queue(&self) -> &wgpu::Queue;
     fn config(&self) -> &wgpu::SurfaceConfiguration;
     fn device(&self) -> &wgpu::Device;
-    fn get_current_texture(&self) -> Result<SurfaceTexture, SurfaceError>;
+    fn get_current_texture(&self) -> Result<CanvasTexture, SurfaceError>;
 
-    fn on_resize(&mut self);
+    fn on_resize(&mut self, width: u32, height: u32);
     fn on_pre_render(&self);
     fn on_post_render(&self);
 }
@@ -0,0 +1,77 @@
+use geo_types::Rect;
+use map::tiles::source::MapTileSource;
+use map::tiles::source::pack_export::export_pack;
+use std::path::PathBuf;
+use std::process::exit;
+
+const USAGE: &str = "Usage: export_tiles --bbox <min_lon,min_lat,max_lon,max_lat> --zoom <min-max> \
+--out <pack> [--from <tiles dir or pack>]";
+
+struct Args {
+    bbox: Rect,
+    min_zoom: i32,
+    max_zoom: i32,
+    out: PathBuf,
+    from: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let value = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+    };
+
+    let bbox: Vec<f64> = value("--bbox")
+        .ok_or("--bbox is missing")?
+        .split(',')
+        .map(|v| v.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|err| format!("Invalid --bbox: {err}"))?;
+    let [min_lon, min_lat, max_lon, max_lat] = bbox[..] else {
+        return Err("--bbox needs 4 values".to_string());
+    };
+
+    let zoom = value("--zoom").ok_or("--zoom is missing")?;
+    let (min_zoom, max_zoom) = zoom.split_once('-').unwrap_or((&zoom, &zoom));
+    let min_zoom: i32 = min_zoom.parse().map_err(|err| format!("Invalid --zoom: {err}"))?;
+    let max_zoom: i32 = max_zoom.parse().map_err(|err| format!("Invalid --zoom: {err}"))?;
+
+    Ok(Args {
+        bbox: Rect::new((min_lon, min_lat), (max_lon, max_lat)),
+        min_zoom: min_zoom.min(max_zoom),
+        max_zoom: min_zoom.max(max_zoom),
+        out: value("--out").ok_or("--out is missing")?.into(),
+        from: value("--from").map(PathBuf::from),
+    })
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        exit(2);
+    });
+
+    let source = match &args.from {
+        Some(path) => MapTileSource::local(path).unwrap_or_else(|err| {
+            eprintln!("Can't open {:?}: {err}", path);
+            exit(1);
+        }),
+        None => MapTileSource::network(),
+    };
+
+    match export_pack(&source, &args.bbox, args.min_zoom..=args.max_zoom, &args.out) {
+        Ok(summary) => println!(
+            "Exported {} tiles into {:?}, {} tiles had no data",
+            summary.exported, args.out, summary.missing
+        ),
+        Err(err) => {
+            eprintln!("Export failed: {err}");
+            exit(1);
+        }
+    }
+}
@@ -1,18 +1,27 @@
 use app_surface::{AppSurface, SurfaceFrame};
 use i_slint_backend_winit::{CustomApplicationHandler, EventResult};
+use map::location::LocationSource;
+use map::location::replay::TraceReplayer;
 use map::tiles::tiles_provider::TilesProvider;
 use map::ShashlikMap;
 use std::path::PathBuf;
 use std::sync::mpsc::Receiver;
 use std::sync::Arc;
-use wgpu::{Device, Queue, SurfaceConfiguration, SurfaceError, SurfaceTexture};
-use wgpu_canvas::wgpu_canvas::WgpuCanvas;
+use std::time::Instant;
+use wgpu::{Device, Queue, SurfaceConfiguration, SurfaceError};
+use wgpu_canvas::wgpu_canvas::{CanvasTexture, WgpuCanvas};
 use winit::dpi::PhysicalPosition;
 use winit::event::{KeyEvent, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::ActiveEventLoop;
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowId};
 use map::route::RouteCosting;
+use map::route::guidance::GuidanceEvent;
+use map::style::theme::Theme;
+use geo_types::coord;
+use map::annotation::Annotation;
+
+const MARKER_SVG: &[u8] = include_bytes!("../../map/svg/just_circle.svg");
 
 pub struct App<T: TilesProvider> {
     pub receiver: Receiver<CustomUIEvent>,
@@ -20,7 +29,16 @@ pub struct App<T: TilesProvider> {
     pub shashlik_map: Option<ShashlikMap<T>>,
     pub cursor_active: bool,
     pub last_cursor_position: PhysicalPosition<f64>,
-    pub fake_bearing: f32
+    pub fake_bearing: f32,
+    pub style_path: Option<PathBuf>,
+    pub trace_replayer: Option<TraceReplayer>,
+    pub record_path: Option<PathBuf>,
+    pub valhalla_url: Option<String>,
+    pub osrm_url: Option<String>,
+    pub font_paths: Vec<PathBuf>,
+    // (lat, lon) of the next route before its destination
+    pub waypoints: Vec<(f64, f64)>,
+    pub collision_debug: bool,
 }
 
 pub enum CustomUIEvent {
@@ -35,9 +53,53 @@ impl<T: TilesProvider> App<T> {
             shashlik_map: None,
             cursor_active: false,
             last_cursor_position: PhysicalPosition::new(0.0, 0.0),
-            fake_bearing: 0.0
+            fake_bearing: 0.0,
+            style_path: None,
+            trace_replayer: None,
+            record_path: None,
+            valhalla_url: None,
+            osrm_url: None,
+            font_paths: vec![],
+            waypoints: vec![],
+            collision_debug: false,
         }
     }
+
+    /// Style document which is watched and reloaded on change
+    pub fn with_style_path(mut self, style_path: Option<PathBuf>) -> Self {
+        self.style_path = style_path;
+        self
+    }
+
+    /// Drives the puck with a recorded trace instead of the fake position
+    pub fn with_trace_replayer(mut self, trace_replayer: Option<TraceReplayer>) -> Self {
+        self.trace_replayer = trace_replayer;
+        self
+    }
+
+    /// Valhalla server for the routes, e.g. a local one
+    pub fn with_valhalla_url(mut self, valhalla_url: Option<String>) -> Self {
+        self.valhalla_url = valhalla_url;
+        self
+    }
+
+    /// OSRM server for the routes instead of Valhalla
+    pub fn with_osrm_url(mut self, osrm_url: Option<String>) -> Self {
+        self.osrm_url = osrm_url;
+        self
+    }
+
+    /// Fallback fonts of the labels in the order they are tried, e.g. a CJK one
+    pub fn with_font_paths(mut self, font_paths: Vec<PathBuf>) -> Self {
+        self.font_paths = font_paths;
+        self
+    }
+
+    /// NMEA log of the positions the map receives
+    pub fn with_record_path(mut self, record_path: Option<PathBuf>) -> Self {
+        self.record_path = record_path;
+        self
+    }
 }
 
 pub struct WinitAppSurface {
@@ -56,11 +118,14 @@ impl WgpuCanvas for WinitAppSurface {
         &self.app_surface.device
     }
 
-    fn get_current_texture(&self) -> Result<SurfaceTexture, SurfaceError> {
-        self.app_surface.surface.get_current_texture()
+    fn get_current_texture(&self) -> Result<CanvasTexture, SurfaceError> {
+        self.app_surface
+            .surface
+            .get_current_texture()
+            .map(CanvasTexture::Surface)
     }
 
-    fn on_resize(&mut self) {
+    fn on_resize(&mut self, _width: u32, _height: u32) {
         self.app_surface.resize_surface();
     }
 
@@ -92,6 +157,49 @@ impl<T: TilesProvider> CustomApplicationHandler for App<T> {
         ))
         .unwrap();
         self.shashlik_map = Some(wgpu_state);
+        if let (Some(map), Some(style_path)) = (self.shashlik_map.as_mut(), &self.style_path)
+            && let Err(err) = map.watch_style_sheet(style_path)
+        {
+            log::error!("Can't load the style sheet: {err:#}");
+        }
+        if let (Some(map), Some(record_path)) = (self.shashlik_map.as_mut(), &self.record_path)
+            && let Err(err) = map.start_trace_recording(record_path)
+        {
+            log::error!("Can't record the trace: {err:#}");
+        }
+        if let (Some(map), Some(valhalla_url)) = (self.shashlik_map.as_mut(), &self.valhalla_url)
+            && let Err(err) = map.set_valhalla_url(valhalla_url, &[])
+        {
+            log::error!("Invalid Valhalla URL: {err:#}");
+        }
+        if let (Some(map), Some(osrm_url)) = (self.shashlik_map.as_mut(), &self.osrm_url)
+            && let Err(err) = map.set_osrm_url(osrm_url, &[])
+        {
+            log::error!("Invalid OSRM URL: {err:#}");
+        }
+        if let Some(map) = self.shashlik_map.as_mut() {
+            for font_path in &self.font_paths {
+                let result = match std::fs::read(font_path) {
+                    Ok(data) => map.add_font(data),
+                    Err(err) => Err(err.into()),
+                };
+                if let Err(err) = result {
+                    log::error!("Can't load the font {}: {err:#}", font_path.display());
+                }
+            }
+            map.set_guidance_listener(Some(Box::new(|event| match event {
+                GuidanceEvent::UpcomingManeuver { maneuver, distance } => {
+                    log::info!("In {distance:.0}m: {}", maneuver.instruction)
+                }
+                GuidanceEvent::Arrived => log::info!("Arrived"),
+                GuidanceEvent::OffRoute { distance_from_route } => {
+                    log::info!("Off the route by {distance_from_route:.0}m")
+                }
+                GuidanceEvent::Rerouted => log::info!("Rerouted"),
+                GuidanceEvent::RouteFailed { message } => log::info!("No route: {message}"),
+                GuidanceEvent::Progress { .. } => {}
+            })));
+        }
         EventResult::Propagate
     }
 
@@ -112,7 +220,11 @@ impl<T: TilesProvider> CustomApplicationHandler for App<T> {
         if let Ok(event) = self.receiver.try_recv() {
             match event {
                 CustomUIEvent::KMLPath(path) => {
-                    map.load_kml_path(path);
+                    // every file is a layer named after it
+                    let name = path.file_stem().map_or("kml".into(), |stem| stem.to_string_lossy());
+                    if let Err(e) = map.load_kml_layer(&name, &path) {
+                        log::error!("Failed to load KML: {e:#}");
+                    }
                 }
             }
         }
@@ -130,6 +242,9 @@ impl<T: TilesProvider> CustomApplicationHandler for App<T> {
                 }
             }
             WindowEvent::RedrawRequested => {
+                if let Some(fix) = self.trace_replayer.as_mut().and_then(|replayer| replayer.poll(Instant::now())) {
+                    map.set_location(fix.lat, fix.lon, fix.bearing, fix.speed);
+                }
                 map.update_and_render();
             }
             WindowEvent::MouseInput { state, button, .. } => match (button, state.is_pressed()) {
@@ -139,6 +254,14 @@ impl<T: TilesProvider> CustomApplicationHandler for App<T> {
                 (MouseButton::Left, false) => {
                     self.cursor_active = false;
                 }
+                (MouseButton::Right, true) => {
+                    let position = self.last_cursor_position.cast::<f32>();
+                    if !map.select_route_at_screen_point(position.x, position.y) {
+                        map.features_at_screen_point(position.x, position.y, 10.0)
+                            .iter()
+                            .for_each(|feature| log::info!("{feature:?}"));
+                    }
+                }
                 _ => {}
             },
             WindowEvent::CursorMoved { position, .. } => {
@@ -182,7 +305,43 @@ impl<T: TilesProvider> CustomApplicationHandler for App<T> {
                         KeyCode::KeyB => {
                             if is_pressed {
                                 // RouteCosting::Motorbike for winit by default
-                                map.create_route_to_from_screen_center(RouteCosting::Motorbike);
+                                let center = map.clip_to_latlon(&coord! {x: 0.0, y: 0.0}).unwrap();
+                                let mut waypoints = std::mem::take(&mut self.waypoints);
+                                waypoints.push((center.y, center.x));
+                                map.create_route(waypoints, RouteCosting::Motorbike, 2);
+                            }
+                        }
+                        KeyCode::KeyW => {
+                            if is_pressed && let Some(center) = map.clip_to_latlon(&coord! {x: 0.0, y: 0.0}) {
+                                self.waypoints.push((center.y, center.x));
+                                log::info!("Waypoint {}: {:.6}, {:.6}", self.waypoints.len(), center.y, center.x);
+                            }
+                        }
+                        KeyCode::KeyA => {
+                            if is_pressed && let Some(center) = map.clip_to_latlon(&coord! {x: 0.0, y: 0.0}) {
+                                let id = format!("marker_{:.6}_{:.6}", center.y, center.x);
+                                let marker =
+                                    Annotation::marker((center.y, center.x), MARKER_SVG, 40.0).with_clustering(true);
+                                map.add_annotation(&id, marker).unwrap();
+                            }
+                        }
+                        KeyCode::KeyC => {
+                            if is_pressed {
+                                map.clear_annotations();
+                            }
+                        }
+                        KeyCode::KeyT => {
+                            if is_pressed {
+                                let names: Vec<_> = Theme::builtin_names().collect();
+                                let current = names.iter().position(|name| *name == map.theme().name);
+                                let next = names[current.map_or(0, |index| (index + 1) % names.len())];
+                                map.set_theme_by_name(next).unwrap();
+                            }
+                        }
+                        KeyCode::KeyD => {
+                            if is_pressed {
+                                self.collision_debug = !self.collision_debug;
+                                map.set_collision_debug(self.collision_debug);
                             }
                         }
                         KeyCode::KeyM => {
@@ -196,6 +355,25 @@ impl<T: TilesProvider> CustomApplicationHandler for App<T> {
 
                             }
                         }
+                        KeyCode::KeyP | KeyCode::BracketLeft | KeyCode::BracketRight | KeyCode::ArrowLeft
+                        | KeyCode::ArrowRight => {
+                            if is_pressed && let Some(replayer) = self.trace_replayer.as_mut() {
+                                match code {
+                                    KeyCode::KeyP => replayer.set_paused(!replayer.is_paused()),
+                                    KeyCode::BracketLeft => replayer.set_speed(replayer.speed() / 2.0),
+                                    KeyCode::BracketRight => replayer.set_speed(replayer.speed() * 2.0),
+                                    KeyCode::ArrowLeft => replayer.seek(replayer.position() - 10.0),
+                                    _ => replayer.seek(replayer.position() + 10.0),
+                                }
+                                log::info!(
+                                    "Replay {:.0}/{:.0}s x{}{}",
+                                    replayer.position(),
+                                    replayer.trace().duration(),
+                                    replayer.speed(),
+                                    if replayer.is_paused() { " paused" } else { "" }
+                                );
+                            }
+                        }
                         _ => {}
                     }
                 }
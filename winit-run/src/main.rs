@@ -1,22 +1,170 @@
 use map::tiles::shashlik_tiles_provider_v0::ShashlikTilesProviderV0;
 use std::sync::mpsc;
 use native_dialog::DialogBuilder;
-use osm::source::reqwest_source::ReqwestSource;
+use map::tiles::mvt::mvt_tiles_provider::MvtTilesProvider;
+use map::tiles::mvt::openmaptiles_processor::OpenMapTilesFeatureProcessor;
+use map::tiles::mvt::source::{MbTilesSource, MvtDirSource};
+use map::tiles::source::MapTileSource;
+use map::tiles::tiles_provider::{TilesMessage, TilesProvider};
+use map::tiles::mercator;
+use map::matching::road_network::RoadNetwork;
+use map::route::provider::RoutingProvider;
+use map::style::style_sheet::StyleSheet;
+use futures_lite::{Stream, StreamExt};
+use geo_types::{Coord, Polygon, Rect};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::sync::Arc;
 use winit::event_loop::EventLoop;
 use map::feature_processor::ShashlikFeatureProcessor;
+use map::location::replay::TraceReplayer;
+use map::location::trace::Trace;
 use winit_run::{App, CustomUIEvent};
 
 slint::include_modules!();
 
+fn arg_value(name: &str) -> Option<PathBuf> {
+    std::env::args()
+        .skip_while(|arg| arg != name)
+        .nth(1)
+        .map(PathBuf::from)
+}
+
+fn font_paths() -> Vec<PathBuf> {
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2)
+        .filter(|pair| pair[0] == "--font")
+        .map(|pair| PathBuf::from(&pair[1]))
+        .collect()
+}
+
 fn main() {
     env_logger::init();
 
+    // --mvt <dir or .mbtiles> for Mapbox Vector Tiles, --tiles <dir or pack> to run offline,
+    // --style <.toml or .json> to restyle the map, the file is reloaded on change,
+    // --trace <.gpx or .nmea> to replay a drive, --record <.nmea> to record the positions,
+    // --valhalla <url> to calculate the routes with another Valhalla server, --osrm <url> with an OSRM one,
+    // --font <.ttf or .otf> to add a fallback font for the labels, e.g. a CJK one, it can be repeated
+    if let Some(mvt_path) = arg_value("--mvt") {
+        if mvt_path.is_dir() {
+            run(Box::new(move || {
+                MvtTilesProvider::new(
+                    MvtDirSource::new(&mvt_path),
+                    OpenMapTilesFeatureProcessor::new(),
+                    1.0,
+                )
+            }));
+        } else {
+            let source = match MbTilesSource::open(&mvt_path) {
+                Ok(source) => RefCell::new(Some(source)),
+                Err(err) => {
+                    log::error!("Can't open {}: {err:#}", mvt_path.display());
+                    return;
+                }
+            };
+            run(Box::new(move || {
+                // the provider is created again if the window is resumed, so the file is opened again
+                // unless the first opened source wasn't used yet
+                match source.take().map_or_else(|| MbTilesSource::open(&mvt_path), Ok) {
+                    Ok(source) => MbTilesOrDefaultProvider::MbTiles(MvtTilesProvider::new(
+                        source,
+                        OpenMapTilesFeatureProcessor::new(),
+                        1.0,
+                    )),
+                    Err(err) => {
+                        log::error!("Can't open {} again, fallback to the default tiles: {err:#}", mvt_path.display());
+                        MbTilesOrDefaultProvider::Default(default_tiles_provider())
+                    }
+                }
+            }));
+        }
+    } else {
+        run(Box::new(default_tiles_provider));
+    }
+}
+
+fn default_tiles_provider() -> ShashlikTilesProviderV0<MapTileSource, ShashlikFeatureProcessor> {
+    ShashlikTilesProviderV0::new(
+        MapTileSource::local_or_network(arg_value("--tiles").as_deref()),
+        ShashlikFeatureProcessor::new(),
+        1.0,
+    )
+}
+
+/// MBTiles provider which is replaced by the default one if the file can't be opened again
+enum MbTilesOrDefaultProvider {
+    MbTiles(MvtTilesProvider<MbTilesSource, OpenMapTilesFeatureProcessor>),
+    Default(ShashlikTilesProviderV0<MapTileSource, ShashlikFeatureProcessor>),
+}
+
+impl TilesProvider for MbTilesOrDefaultProvider {
+    fn load(&mut self, area_latlon: Rect, area_poly: Polygon<f64>, zoom_level: i32) {
+        match self {
+            MbTilesOrDefaultProvider::MbTiles(provider) => provider.load(area_latlon, area_poly, zoom_level),
+            MbTilesOrDefaultProvider::Default(provider) => provider.load(area_latlon, area_poly, zoom_level),
+        }
+    }
+
+    fn tiles(&mut self) -> impl Stream<Item = TilesMessage> + Send + 'static {
+        match self {
+            MbTilesOrDefaultProvider::MbTiles(provider) => provider.tiles().boxed(),
+            MbTilesOrDefaultProvider::Default(provider) => provider.tiles().boxed(),
+        }
+    }
+
+    fn set_style_sheet(&mut self, style_sheet: Arc<StyleSheet>) {
+        match self {
+            MbTilesOrDefaultProvider::MbTiles(provider) => provider.set_style_sheet(style_sheet),
+            MbTilesOrDefaultProvider::Default(provider) => provider.set_style_sheet(style_sheet),
+        }
+    }
+
+    fn road_network(&self) -> Option<Arc<RoadNetwork>> {
+        match self {
+            MbTilesOrDefaultProvider::MbTiles(provider) => provider.road_network(),
+            MbTilesOrDefaultProvider::Default(provider) => provider.road_network(),
+        }
+    }
+
+    fn offline_routing_provider(&self) -> Option<Arc<dyn RoutingProvider>> {
+        match self {
+            MbTilesOrDefaultProvider::MbTiles(provider) => provider.offline_routing_provider(),
+            MbTilesOrDefaultProvider::Default(provider) => provider.offline_routing_provider(),
+        }
+    }
+
+    // both providers use the web mercator world
+    fn lat_lon_to_world(lat_lon: &Coord<f64>) -> Coord<f64> {
+        mercator::lat_lon_to_world(lat_lon)
+    }
+
+    fn world_to_lat_lon(lat_lon: &Coord<f64>) -> Coord<f64> {
+        mercator::world_to_lat_lon(lat_lon)
+    }
+
+    fn meters_per_world_unit(lat: f64) -> f64 {
+        mercator::meters_per_world_unit(lat)
+    }
+}
+
+fn run<T: TilesProvider + 'static>(get_tiles_provider: Box<dyn Fn() -> T>) {
     let (sender, receiver) = mpsc::channel();
 
-    let app = App::new(
-        Box::new(|| ShashlikTilesProviderV0::new(ReqwestSource::new(), ShashlikFeatureProcessor::new(), 1.0)),
-        receiver,
-    );
+    let trace_replayer = arg_value("--trace").and_then(|path| match Trace::load(&path) {
+        Ok(trace) => Some(TraceReplayer::new(trace)),
+        Err(err) => {
+            log::error!("Can't load the trace: {err:#}");
+            None
+        }
+    });
+    let app = App::new(get_tiles_provider, receiver)
+        .with_style_path(arg_value("--style"))
+        .with_trace_replayer(trace_replayer)
+        .with_record_path(arg_value("--record"))
+        .with_valhalla_url(std::env::args().skip_while(|arg| arg != "--valhalla").nth(1))
+        .with_osrm_url(std::env::args().skip_while(|arg| arg != "--osrm").nth(1))
+        .with_font_paths(font_paths());
     let event_loop = EventLoop::with_user_event();
 
     slint::platform::set_platform(Box::new(
@@ -33,7 +181,7 @@ fn main() {
     ui.on_open_kml_button_click(move || {
         let path = DialogBuilder::file()
             .set_location("~/Desktop")
-            .add_filter("KML", ["kml"])
+            .add_filter("KML", ["kml", "kmz"])
             .open_single_file()
             .show()
             .unwrap();